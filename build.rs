@@ -0,0 +1,64 @@
+/*
+* TimeGuardian Build Script
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* Converts the markdown user guide in docs/guide into structured Rust data,
+* so the TUI can show it offline (see src/tui/docs.rs).
+*/
+
+use std::{env, fs, path::Path};
+
+const GUIDE_DIR: &str = "docs/guide";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", GUIDE_DIR);
+
+    let mut files: Vec<_> = fs::read_dir(GUIDE_DIR)
+        .expect("docs/guide is readable")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    files.sort();
+
+    let mut pages = String::new();
+    for path in &files {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let markdown = fs::read_to_string(path).expect("guide page is readable");
+        pages.push_str(&page(&markdown, path));
+    }
+
+    let out = Path::new(&env::var("OUT_DIR").expect("OUT_DIR is set")).join("guide.rs");
+    fs::write(out, format!("pub const GUIDE: &[DocPage] = &[\n{}];\n", pages))
+        .expect("generated guide is writable");
+}
+
+/// Convert one markdown page into a `DocPage` expression
+fn page(markdown: &str, path: &Path) -> String {
+    let mut title = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let mut lines = String::new();
+    let mut in_code = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_end();
+        let entry = if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        } else if in_code {
+            format!("DocLine::Code({:?})", trimmed)
+        } else if let Some(text) = trimmed.strip_prefix("# ") {
+            title = text.to_string();
+            continue;
+        } else if let Some(text) = trimmed.strip_prefix("## ") {
+            format!("DocLine::Heading({:?})", text)
+        } else if let Some(text) = trimmed.strip_prefix("- ") {
+            format!("DocLine::Bullet({:?})", text)
+        } else if trimmed.is_empty() {
+            "DocLine::Blank".to_string()
+        } else {
+            format!("DocLine::Text({:?})", trimmed)
+        };
+        lines.push_str(&format!("        {},\n", entry));
+    }
+
+    format!("    DocPage {{\n        title: {:?},\n        lines: &[\n{}        ],\n    }},\n", title, lines)
+}
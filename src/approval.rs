@@ -0,0 +1,133 @@
+/*
+* TimeGuardian Approval Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module soft-launches new website lists. Before a list blocks for the
+* first time, a summary shows what the block would break: its domains with
+* open connections right now and those on the safety list. The session only
+* starts once the list is approved, and approvals are kept in the state
+* directory, so every list is checked once. Lists that already show up in
+* the session history count as approved. `soft_launch = false` in the config
+* skips the check.
+*/
+
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use crate::get_state_dir;
+use crate::history::History;
+use crate::inuse::InUse;
+use crate::tui::WebsiteList;
+
+/// File in the state directory holding the approved lists
+pub const APPROVALS_FILE: &str = "approvals.toml";
+
+/// Lists approved for blocking
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Approvals {
+    /// When each list was approved, by lowercase name
+    #[serde(default)]
+    pub lists: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl Approvals {
+    /// Load the approvals from the state directory
+    pub fn load() -> Result<Self> {
+        let path = approvals_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Could not read list approvals: {:?}", path))?;
+        toml::from_str(&content).wrap_err("Could not parse list approvals")
+    }
+
+    /// Save the approvals to the state directory
+    pub fn save(&self) -> Result<()> {
+        let path = approvals_path()?;
+        let content = toml::to_string(self).wrap_err("Could not serialize list approvals")?;
+        fs::write(&path, content).wrap_err_with(|| format!("Could not save list approvals: {:?}", path))
+    }
+}
+
+/// What the first block of new lists would break
+#[derive(Clone, Debug)]
+pub struct Impact {
+    /// Names of the new lists
+    pub lists: Vec<String>,
+    /// Number of entries in the new lists
+    pub entries: usize,
+    /// Domains of the lists with open connections right now
+    pub in_use: Vec<InUse>,
+    /// Entries of the lists on the safety list
+    pub protected: Vec<String>,
+}
+
+impl Impact {
+    /// The summary, one line per finding
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "First block of {} ({} entr{}).",
+            self.lists.iter().map(|list| format!("'{}'", list)).collect::<Vec<_>>().join(", "),
+            self.entries,
+            if self.entries == 1 { "y" } else { "ies" }
+        )];
+        if self.in_use.is_empty() {
+            lines.push("No open connections to these domains right now.".to_string());
+        }
+        for in_use in &self.in_use {
+            lines.push(format!(
+                "In use: {} has {} open connection(s) that will break.",
+                in_use.domain, in_use.connections
+            ));
+        }
+        if !self.protected.is_empty() {
+            lines.push(format!("On the safety list: {}.", self.protected.join(", ")));
+        }
+        lines
+    }
+}
+
+/// The lists among `names` (all enabled lists if empty) that were never
+/// approved or blocked before
+pub fn pending<'a>(lists: &'a [WebsiteList], names: &[String]) -> Result<Vec<&'a WebsiteList>> {
+    let approvals = Approvals::load()?;
+    let history = History::load()?;
+    let used = |name: &str| {
+        approvals.lists.contains_key(&name.to_lowercase())
+            || history
+                .sessions
+                .iter()
+                .any(|record| record.lists.iter().any(|list| list.eq_ignore_ascii_case(name)))
+    };
+
+    Ok(lists
+        .iter()
+        .filter(|list| {
+            if names.is_empty() {
+                list.enabled
+            } else {
+                names.iter().any(|name| name.eq_ignore_ascii_case(&list.name))
+            }
+        })
+        // System lists are the administrator's decision
+        .filter(|list| !list.system && !used(&list.name))
+        .collect())
+}
+
+/// Record the approval of `lists`
+pub fn approve(lists: &[String]) -> Result<()> {
+    let mut approvals = Approvals::load()?;
+    for list in lists {
+        approvals.lists.insert(list.to_lowercase(), Utc::now());
+    }
+    approvals.save()
+}
+
+/// Path of the approvals file
+fn approvals_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(APPROVALS_FILE))
+}
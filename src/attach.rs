@@ -0,0 +1,289 @@
+/*
+* TimeGuardian Attach Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module reaches sessions running in other processes over their IPC
+* sockets. `timeguardian stop` and `attach-cli` end or follow one of them,
+* picked with `--session` or `--list` when several run, and `status --watch`
+* prints the status line for status bars. `--detach` and `ipc start` hand a
+* session to a background process in the first place.
+*/
+
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use crossterm::{
+    event::{Event, KeyCode},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use std::{
+    env,
+    path::PathBuf,
+    process::Command,
+    time::{Duration, Instant},
+};
+use timeguardian::format;
+
+use crate::{check_and_get_permissions, history, hosts, ipc, is_interactive_terminal, live, SessionTarget};
+
+/// The running session `target` addresses, with the socket it serves: the
+/// only one running without `--session` or `--list`. Several matching
+/// sessions are listed to pick from.
+fn target_session(target: &SessionTarget) -> Result<Option<(PathBuf, ipc::SessionStatus)>> {
+    let sessions = ipc::sessions();
+    // Hosts of older versions only serve the shared socket
+    if sessions.is_empty() && target.session.is_none() && target.list.is_none() {
+        let status = ipc::query(&ipc::Request::Status)?.and_then(|response| response.status);
+        return Ok(status.filter(|status| status.active).map(|status| (ipc::socket_path(), status)));
+    }
+
+    let matching: Vec<(PathBuf, ipc::SessionStatus)> = sessions
+        .into_iter()
+        .filter(|(_, status)| status.active)
+        .filter(|(_, status)| {
+            target.session.as_deref().is_none_or(|id| {
+                status.session_id.as_deref().is_some_and(|session_id| {
+                    session_id.to_lowercase().starts_with(&id.to_lowercase())
+                })
+            })
+        })
+        .filter(|(_, status)| {
+            target.list.as_deref().is_none_or(|list| status.lists.iter().any(|name| name.eq_ignore_ascii_case(list)))
+        })
+        .collect();
+    if matching.len() <= 1 {
+        return Ok(matching.into_iter().next());
+    }
+
+    println!("{} sessions are running:", matching.len());
+    for (_, status) in &matching {
+        println!(
+            "  {} {} ({}), {} left",
+            status.session_id.as_deref().unwrap_or("(no session ID)"),
+            status.task.as_deref().unwrap_or("Focus session"),
+            if status.lists.is_empty() { "all enabled lists".to_string() } else { status.lists.join(", ") },
+            format::spoken(Duration::from_secs(status.remaining_secs.unwrap_or(0)))
+        );
+    }
+    Err(eyre!("Pick one with --session <ID> or --list <name>"))
+}
+
+/// End the running session `target` addresses, as if it was stopped with 'q'
+pub fn stop_session(target: &SessionTarget) -> Result<()> {
+    let Some((socket, _)) = target_session(target)? else {
+        let sessions = hosts::sessions_in_hosts_file()?;
+        if target.session.is_some() || target.list.is_some() {
+            println!("No running session matches; see 'timeguardian status'.");
+        } else if sessions.is_empty() {
+            println!("No session is running.");
+        } else {
+            println!(
+                "No running session serves the {} block(s) in the hosts file; 'timeguardian reset' removes them.",
+                sessions.len()
+            );
+        }
+        return Ok(());
+    };
+    let Some(response) = ipc::query_at(&socket, &ipc::Request::Stop)? else {
+        println!("The session already ended.");
+        return Ok(());
+    };
+    let Some(stopped) = response.status.filter(|_| response.ok) else {
+        println!("Could not stop the session: {}", response.error.unwrap_or_default());
+        return Ok(());
+    };
+
+    // The session releases its blocks and records the abort at its next check
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let confirmed = loop {
+        let status = ipc::query_at(&socket, &ipc::Request::Status)?.and_then(|response| response.status);
+        if !status.is_some_and(|status| status.active && status.started_at == stopped.started_at) {
+            break true;
+        }
+        if Instant::now() >= deadline {
+            break false;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+    let task = stopped.task.as_deref().unwrap_or("the session");
+    if !confirmed {
+        return Err(eyre!("Stopping {} was not confirmed within 5 seconds; see 'timeguardian status'", task));
+    }
+    println!("Stopped {}.", task);
+
+    let remaining = hosts::sessions_in_hosts_file()?.len();
+    if remaining > 0 {
+        println!("{} other session(s) keep blocking; see 'timeguardian status'.", remaining);
+    }
+    Ok(())
+}
+
+/// Run a CLI session with the given arguments as a detached background process.
+///
+/// Returns `true` once the new process has started its session and the
+/// IPC socket is served (by it, or by a session that was already running).
+pub fn spawn_background_session(args: &[String]) -> Result<bool> {
+    let last_session = || history::History::load().ok().and_then(|history| history.last().map(|record| record.id));
+    let before = last_session();
+
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .args(args)
+        // This process already counted the session for telemetry
+        .env("TIMEGUARDIAN_NO_TELEMETRY", "1")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+
+    #[cfg(unix)]
+    {
+        // Keep the session alive when the calling terminal closes
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let mut child = command.spawn().wrap_err("Could not start the session process")?;
+
+    // The child records its session once it claimed its websites, or right
+    // away when it only runs the timer
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if last_session() > before {
+            return Ok(ipc::wait_for_host(deadline.saturating_duration_since(Instant::now())));
+        }
+        if child.try_wait()?.is_some() {
+            return Ok(false);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    Ok(false)
+}
+
+/// Hand the session described by the command line to a background process
+pub fn detach_session(timer_only: bool) -> Result<()> {
+    // The background process can't ask for permissions
+    if !timer_only && !check_and_get_permissions()? {
+        println!("Blocking disabled (no permissions): the background session only runs the timer.");
+    }
+    let first = !ipc::host_running();
+
+    let args: Vec<String> = env::args().skip(1).filter(|arg| arg != "--detach").collect();
+    if spawn_background_session(&args)? {
+        println!("Session running in the background.");
+        if first {
+            println!("Use 'timeguardian attach-cli' to follow it or 'timeguardian stop' to end it.");
+        } else {
+            println!("It runs alongside the other sessions until it ends; 'timeguardian attach-cli --session <ID>'");
+            println!("follows it and 'timeguardian stop --session <ID>' ends it (IDs in 'timeguardian status').");
+        }
+    } else {
+        println!("The background session could not be started.");
+    }
+
+    Ok(())
+}
+
+/// Print the status of the running session every `interval` until
+/// interrupted, over an IPC subscription; idle while no session runs
+pub fn watch_status(interval: Duration, json: bool) -> Result<()> {
+    let interval = interval.max(ipc::SUBSCRIBE_INTERVAL);
+    let print = |status: &ipc::SessionStatus| -> Result<bool> {
+        if json {
+            ipc::print_status(status)?;
+        } else if status.active {
+            println!(
+                "{}: {} left",
+                status.task.as_deref().unwrap_or("Focus session"),
+                format::clock(Duration::from_secs(status.remaining_secs.unwrap_or(0)))
+            );
+        } else {
+            println!("No session");
+        }
+        Ok(true)
+    };
+
+    // Without a host, look for one again after every idle line
+    loop {
+        if !ipc::watch(interval, print)? {
+            print(&ipc::SessionStatus::default())?;
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// Follow the running session `target` addresses until it ends or the
+/// user detaches
+pub fn attach_session(target: &SessionTarget, accessible: bool) -> Result<()> {
+    let Some((socket, _)) = target_session(target)? else {
+        println!("No session is running.");
+        return Ok(());
+    };
+    let interactive = is_interactive_terminal() && !accessible;
+    let mut line = live::LiveLine::default();
+    let mut last_report: Option<Instant> = None;
+    let mut finished = false;
+
+    if interactive {
+        enable_raw_mode()?;
+    }
+
+    let attached = ipc::watch_at(&socket, ipc::SUBSCRIBE_INTERVAL, |status| {
+        if !status.active {
+            finished = true;
+            return Ok(false);
+        }
+
+        let remaining = status.remaining_secs.unwrap_or(0);
+        let message = if accessible {
+            format!(
+                "{}: {} remaining",
+                status.task.as_deref().unwrap_or("Focus session"),
+                format::spoken(Duration::from_secs(remaining))
+            )
+        } else {
+            format!(
+                "{}: {} remaining",
+                status.task.as_deref().unwrap_or("Focus session"),
+                format::clock(Duration::from_secs(remaining))
+            )
+        };
+
+        if !interactive {
+            // One line a minute is enough for logs, every five for screen readers
+            let interval = Duration::from_secs(if accessible { 300 } else { 60 });
+            if last_report.is_none_or(|at| at.elapsed() >= interval) {
+                println!("{}", message);
+                last_report = Some(Instant::now());
+            }
+            return Ok(true);
+        }
+
+        // Detaching leaves the session running
+        if crossterm::event::poll(Duration::ZERO)? {
+            let event = crossterm::event::read()?;
+            if matches!(event, Event::Key(key) if key.code == KeyCode::Esc || key.code == KeyCode::Char('q')) {
+                return Ok(false);
+            }
+        }
+
+        line.draw(&format!("{} (q to detach)", message))?;
+        Ok(true)
+    });
+
+    line.finish()?;
+    if interactive {
+        disable_raw_mode()?;
+    }
+
+    if !attached? {
+        println!("No session is running.");
+    } else if finished {
+        println!("Session finished");
+    } else {
+        println!("Detached; the session keeps running.");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,311 @@
+/*
+* TimeGuardian Backup Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module implements `timeguardian backup push/pull`.
+* The configuration files and the session history are bundled, encrypted
+* with a passphrase (PBKDF2-SHA256 + ChaCha20-Poly1305) and stored in a
+* configurable target. The history is restored as is, so its hash chain
+* still verifies on the other machine.
+*
+* Targets are a local directory (which can be a synced Dropbox, Nextcloud or
+* S3-mounted folder) or a WebDAV collection. The age format and native S3
+* and Dropbox targets are not supported: on top of ring and ureq they would
+* mean implementing age's scrypt recipients, AWS request signing and the
+* Dropbox OAuth flow in TimeGuardian itself.
+*/
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use ring::{
+    aead::{self, Aad, LessSafeKey, Nonce, UnboundKey},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, fs, num::NonZeroU32, path::PathBuf};
+
+use crate::history::{HISTORY_FILE, HISTORY_HEAD_FILE};
+use crate::{
+    config_is_read_only, config_path, get_config_dir, get_state_dir, inlined_config, load_config, lock_config, progress,
+    save_config, BackupAction, Config,
+};
+
+/// Magic header identifying an encrypted TimeGuardian backup
+const BACKUP_MAGIC: &[u8] = b"TGBACKUP1";
+/// Name of the backup file in the target
+const BACKUP_FILE_NAME: &str = "timeguardian-backup.bin";
+/// Environment variable that can provide the passphrase non-interactively
+const PASSPHRASE_ENV: &str = "TIMEGUARDIAN_BACKUP_PASSPHRASE";
+/// Files of the config directory that are part of the bundle
+const BUNDLED_FILES: &[&str] = &["config.toml"];
+/// Files of the state directory that are part of the bundle
+const BUNDLED_STATE_FILES: &[&str] = &[HISTORY_FILE, HISTORY_HEAD_FILE];
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const PBKDF2_ITERATIONS: u32 = 200_000;
+
+/// Where backups are stored
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BackupTarget {
+    /// A local directory, e.g. a Dropbox or Nextcloud synced folder
+    Directory { path: String },
+    /// A WebDAV collection URL
+    Webdav {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+    },
+}
+
+/// Plain content of a backup
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct BackupBundle {
+    version: u32,
+    files: BTreeMap<String, String>,
+}
+
+/// Encrypt the bundled files and upload them to the target
+pub fn push(target: &BackupTarget) -> Result<()> {
+    let config_dir = get_config_dir()?;
+
+    let mut bundle = BackupBundle {
+        version: 1,
+        ..Default::default()
+    };
+    for name in BUNDLED_FILES {
+        let path = config_dir.join(name);
+        if path.exists() {
+            // The config is bundled with its lists inline
+            let content = if *name == "config.toml" {
+                inlined_config(&path)?
+            } else {
+                fs::read_to_string(&path).wrap_err_with(|| format!("Could not read {:?}", path))?
+            };
+            bundle.files.insert(name.to_string(), content);
+        }
+    }
+    let state_dir = get_state_dir()?;
+    for name in BUNDLED_STATE_FILES {
+        let path = state_dir.join(name);
+        if path.exists() {
+            let content = fs::read_to_string(&path).wrap_err_with(|| format!("Could not read {:?}", path))?;
+            bundle.files.insert(name.to_string(), content);
+        }
+    }
+
+    if bundle.files.is_empty() {
+        return Err(eyre!("Nothing to back up yet. Run 'timeguardian setup' first."));
+    }
+
+    let plaintext = toml::to_string(&bundle).wrap_err("Could not serialize backup")?;
+    let encrypted = encrypt(plaintext.as_bytes(), &read_passphrase()?)?;
+    upload(target, &encrypted)?;
+
+    println!("Backed up {} file(s).", bundle.files.len());
+    Ok(())
+}
+
+/// Download the backup from the target, decrypt it and restore the files
+pub fn pull(target: &BackupTarget) -> Result<()> {
+    let encrypted = download(target)?;
+    let plaintext = decrypt(&encrypted, &read_passphrase()?)?;
+
+    let bundle: BackupBundle = toml::from_str(
+        std::str::from_utf8(&plaintext).wrap_err("Backup is not valid UTF-8")?,
+    )
+    .wrap_err("Could not parse backup")?;
+
+    let config = bundle
+        .files
+        .get("config.toml")
+        .map(|content| toml::from_str::<Config>(content).wrap_err("Could not parse the config in the backup"))
+        .transpose()?;
+
+    // The history and its head are written next to their files first and
+    // renamed together, so an interrupted pull never leaves a history that
+    // doesn't match its head (which 'stats verify' reports as tampered)
+    let state_dir = get_state_dir()?;
+    let mut restored = Vec::new();
+    for name in BUNDLED_STATE_FILES {
+        // Only known files are restored, never a name taken from the backup
+        let Some(content) = bundle.files.get(*name) else {
+            continue;
+        };
+        let path = state_dir.join(name);
+        let temp_path = state_dir.join(format!("{}.tmp", name));
+        fs::write(&temp_path, content).wrap_err_with(|| format!("Could not restore {:?}", path))?;
+        restored.push((name, temp_path, path));
+    }
+
+    let _lock = lock_config()?;
+    if let Some(config) = &config {
+        // Saved like any config change: backed up first, lists split into files
+        save_config(config)?;
+        println!("Restored config.toml");
+    }
+    for (name, temp_path, path) in restored {
+        fs::rename(&temp_path, &path).wrap_err_with(|| format!("Could not restore {:?}", path))?;
+        println!("Restored {}", name);
+    }
+
+    Ok(())
+}
+
+/// Ask for the backup passphrase unless it is provided via the environment
+fn read_passphrase() -> Result<String> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+
+    let passphrase =
+        rpassword::prompt_password("Backup passphrase: ").wrap_err("Could not read passphrase")?;
+    if passphrase.is_empty() {
+        return Err(eyre!("The passphrase must not be empty"));
+    }
+    Ok(passphrase)
+}
+
+/// Derive the encryption key from the passphrase and salt
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<LessSafeKey> {
+    let mut key_bytes = [0u8; 32];
+    let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).expect("iterations are non-zero");
+    let bar = progress::spinner("Deriving key from passphrase");
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key_bytes,
+    );
+    bar.finish_and_clear();
+
+    let key = UnboundKey::new(&aead::CHACHA20_POLY1305, &key_bytes)
+        .map_err(|_| eyre!("Could not create encryption key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Encrypt data; the output is `magic | salt | nonce | ciphertext+tag`
+fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| eyre!("Could not generate random data"))?;
+
+    let key = derive_key(passphrase, &salt)?;
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+        .map_err(|_| eyre!("Could not encrypt backup"))?;
+
+    let mut output = Vec::with_capacity(BACKUP_MAGIC.len() + SALT_LEN + NONCE_LEN + in_out.len());
+    output.extend_from_slice(BACKUP_MAGIC);
+    output.extend_from_slice(&salt);
+    output.extend_from_slice(&nonce);
+    output.extend_from_slice(&in_out);
+    Ok(output)
+}
+
+/// Decrypt data produced by `encrypt`
+fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let header_len = BACKUP_MAGIC.len() + SALT_LEN + NONCE_LEN;
+    if data.len() < header_len || !data.starts_with(BACKUP_MAGIC) {
+        return Err(eyre!("Not a TimeGuardian backup"));
+    }
+
+    let salt = &data[BACKUP_MAGIC.len()..BACKUP_MAGIC.len() + SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(&data[BACKUP_MAGIC.len() + SALT_LEN..header_len]);
+
+    let key = derive_key(passphrase, salt)?;
+    let mut in_out = data[header_len..].to_vec();
+    let plaintext = key
+        .open_in_place(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+        .map_err(|_| eyre!("Could not decrypt backup (wrong passphrase?)"))?;
+    Ok(plaintext.to_vec())
+}
+
+/// Store the encrypted backup in the target
+fn upload(target: &BackupTarget, data: &[u8]) -> Result<()> {
+    match target {
+        BackupTarget::Directory { path } => {
+            let dir = PathBuf::from(path);
+            fs::create_dir_all(&dir)
+                .wrap_err_with(|| format!("Could not create backup directory: {:?}", dir))?;
+            fs::write(dir.join(BACKUP_FILE_NAME), data)
+                .wrap_err_with(|| format!("Could not write backup to {:?}", dir))
+        }
+        BackupTarget::Webdav { url, username, password } => {
+            let request = ureq::put(&webdav_file_url(url));
+            let bar = progress::spinner("Uploading backup");
+            let result = with_basic_auth(request, username, password).send_bytes(data);
+            bar.finish_and_clear();
+            result.wrap_err("Could not upload backup")?;
+            Ok(())
+        }
+    }
+}
+
+/// Fetch the encrypted backup from the target
+fn download(target: &BackupTarget) -> Result<Vec<u8>> {
+    match target {
+        BackupTarget::Directory { path } => {
+            let file = PathBuf::from(path).join(BACKUP_FILE_NAME);
+            fs::read(&file).wrap_err_with(|| format!("Could not read backup: {:?}", file))
+        }
+        BackupTarget::Webdav { url, username, password } => {
+            let request = ureq::get(&webdav_file_url(url));
+            let response = with_basic_auth(request, username, password)
+                .call()
+                .wrap_err("Could not download backup")?;
+            progress::read_response(response, "Downloading backup")
+        }
+    }
+}
+
+/// URL of the backup file inside a WebDAV collection
+fn webdav_file_url(url: &str) -> String {
+    format!("{}/{}", url.trim_end_matches('/'), BACKUP_FILE_NAME)
+}
+
+/// Add HTTP basic authentication to a request if credentials are configured
+fn with_basic_auth(
+    request: ureq::Request,
+    username: &Option<String>,
+    password: &Option<String>,
+) -> ureq::Request {
+    match username {
+        Some(username) => {
+            let credentials = format!("{}:{}", username, password.as_deref().unwrap_or_default());
+            request.set("Authorization", &format!("Basic {}", STANDARD.encode(credentials)))
+        }
+        None => request,
+    }
+}
+
+/// Run `timeguardian backup push` or `pull` against the configured target
+pub fn run_command(action: &BackupAction) -> Result<()> {
+    let config = load_config()?;
+    let Some(target) = &config.backup else {
+        println!("No backup target configured.");
+        println!("Add a [backup] section to {:?}, e.g.:", config_path()?);
+        println!("  kind = \"directory\"");
+        println!("  path = \"/home/me/Dropbox/timeguardian\"");
+        return Ok(());
+    };
+
+    match action {
+        _ if config_is_read_only() => {
+            println!("The configuration is managed declaratively; back it up with your dotfiles instead.");
+        }
+        BackupAction::Push => push(target)?,
+        BackupAction::Pull => pull(target)?,
+    }
+    Ok(())
+}
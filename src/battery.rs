@@ -0,0 +1,199 @@
+/*
+* TimeGuardian Battery Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module watches the battery of laptops during a session. When the
+* charge drops below a threshold while discharging, it warns or pauses the
+* session clock until the machine is plugged in again.
+*
+* While paused, the session end and the hosts claim move along with every
+* check, so the claim doesn't expire during a long pause. If the battery
+* dies, the block ends at the planned time plus the pause so far and is
+* removed by the next run.
+*/
+
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::{hosts, ipc::SessionHandle};
+
+/// How often the battery is read
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Battery settings
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BatteryConfig {
+    /// Charge in percent below which the battery counts as low
+    #[serde(default = "default_low_percent")]
+    pub low_percent: u8,
+    /// What happens when the battery is low
+    #[serde(default)]
+    pub behavior: BatteryBehavior,
+}
+
+fn default_low_percent() -> u8 {
+    10
+}
+
+/// Reaction to a low battery
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BatteryBehavior {
+    /// Only warn (default)
+    #[default]
+    Warn,
+    /// Pause the session clock until the machine is charging again
+    Pause,
+}
+
+/// Charge and power state of the battery
+#[derive(Clone, Copy, Debug)]
+pub struct BatteryStatus {
+    pub percent: u8,
+    pub discharging: bool,
+}
+
+/// A change worth telling the user about
+pub enum BatteryEvent {
+    /// The battery is low (percent); the session keeps running
+    Low(u8),
+    /// The battery is low (percent); the session clock is paused
+    Paused(u8),
+    /// Power is back after the session was paused for the given time
+    Resumed(Duration),
+}
+
+/// Watches the battery during a session
+pub struct BatteryWatch {
+    config: Option<BatteryConfig>,
+    source: Option<String>,
+    last_check: Option<Instant>,
+    warned: bool,
+    paused: Option<Duration>,
+}
+
+impl BatteryWatch {
+    /// Watch the session with the given hosts claim source (`None` for
+    /// sessions that don't block); inactive without config
+    pub fn new(config: Option<BatteryConfig>, source: Option<&str>) -> Self {
+        Self {
+            config,
+            source: source.map(str::to_string),
+            last_check: None,
+            warned: false,
+            paused: None,
+        }
+    }
+
+    /// Read the battery (every 30 seconds) and move the session end and its
+    /// claim while paused
+    pub fn poll(&mut self, end_time: &mut DateTime<Local>, session: &SessionHandle) -> Result<Option<BatteryEvent>> {
+        let Some(config) = &self.config else {
+            return Ok(None);
+        };
+        let since_check = match self.last_check {
+            Some(at) if at.elapsed() < CHECK_INTERVAL => return Ok(None),
+            Some(at) => at.elapsed(),
+            None => Duration::ZERO,
+        };
+        self.last_check = Some(Instant::now());
+
+        // While paused, the time since the last check doesn't count
+        if let Some(paused) = self.paused.as_mut() {
+            *paused += since_check;
+            *end_time += chrono::Duration::from_std(since_check)?;
+            if let Some(source) = &self.source {
+                hosts::extend(source, since_check)?;
+            }
+            session.extend(since_check);
+        }
+
+        let low = status().filter(|battery| battery.discharging && battery.percent <= config.low_percent);
+        match (low, self.paused) {
+            (Some(battery), None) if config.behavior == BatteryBehavior::Pause => {
+                self.paused = Some(Duration::ZERO);
+                Ok(Some(BatteryEvent::Paused(battery.percent)))
+            }
+            (Some(battery), None) if !self.warned => {
+                self.warned = true;
+                Ok(Some(BatteryEvent::Low(battery.percent)))
+            }
+            (None, Some(paused)) => {
+                self.paused = None;
+                Ok(Some(BatteryEvent::Resumed(paused)))
+            }
+            (None, None) => {
+                self.warned = false;
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Start watching afresh (while no session is running)
+    pub fn reset(&mut self) {
+        self.last_check = None;
+        self.warned = false;
+        self.paused = None;
+    }
+}
+
+/// Current battery state, if the machine has a battery
+pub fn status() -> Option<BatteryStatus> {
+    #[cfg(target_os = "linux")]
+    {
+        let batteries = std::fs::read_dir("/sys/class/power_supply").ok()?;
+        batteries.flatten().find_map(|entry| {
+            let path = entry.path();
+            let kind = std::fs::read_to_string(path.join("type")).ok()?;
+            if kind.trim() != "Battery" {
+                return None;
+            }
+            let percent = std::fs::read_to_string(path.join("capacity")).ok()?.trim().parse().ok()?;
+            let state = std::fs::read_to_string(path.join("status")).unwrap_or_default();
+            Some(BatteryStatus {
+                percent,
+                discharging: state.trim() == "Discharging",
+            })
+        })
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // "-InternalBattery-0 (id=...)	42%; discharging; 2:10 remaining ..."
+        let output = std::process::Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let line = text.lines().find(|line| line.contains("InternalBattery"))?;
+        let (_, rest) = line.split_once('\t')?;
+        let percent = rest.split('%').next()?.trim().parse().ok()?;
+        Some(BatteryStatus {
+            percent,
+            discharging: rest.contains("discharging"),
+        })
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // BatteryStatus 1 means discharging
+        let output = std::process::Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Get-CimInstance Win32_Battery | ForEach-Object { \"$($_.EstimatedChargeRemaining) $($_.BatteryStatus)\" }",
+            ])
+            .output()
+            .ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let mut values = text.split_whitespace();
+        let percent = values.next()?.parse().ok()?;
+        Some(BatteryStatus {
+            percent,
+            discharging: values.next() == Some("1"),
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    None
+}
@@ -0,0 +1,176 @@
+/*
+* TimeGuardian GUI
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* A minimal graphical frontend built with egui (`cargo run --features gui
+* --bin timeguardian-gui`). Like every frontend it only talks to the backend
+* over the IPC socket through `timeguardian::client`: it shows the running
+* session and stops it, or starts a new one. Without a running backend a
+* session is started with `timeguardian ipc start`, which runs it as a
+* background CLI process.
+*/
+
+use eframe::egui;
+use std::{
+    path::PathBuf,
+    process::Command,
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+use timeguardian::{client, format};
+
+/// How often the status is asked for
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+fn main() -> eframe::Result<()> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("TimeGuardian")
+            .with_inner_size([360.0, 220.0]),
+        ..Default::default()
+    };
+    eframe::run_native("TimeGuardian", options, Box::new(|_| Ok(Box::new(Gui::default()))))
+}
+
+struct Gui {
+    status: client::Status,
+    polled: Option<Instant>,
+    duration: String,
+    task: String,
+    message: Option<String>,
+    /// Result of a `timeguardian ipc start` still running in the background
+    starting: Option<Receiver<Result<(), String>>>,
+}
+
+impl Default for Gui {
+    fn default() -> Self {
+        Self {
+            status: client::Status::default(),
+            polled: None,
+            duration: "25m".to_string(),
+            task: String::new(),
+            message: None,
+            starting: None,
+        }
+    }
+}
+
+impl Gui {
+    fn poll(&mut self) {
+        if self.polled.is_some_and(|polled| polled.elapsed() < POLL_INTERVAL) {
+            return;
+        }
+        self.polled = Some(Instant::now());
+        match client::status() {
+            Ok(status) => self.status = status,
+            Err(error) => self.message = Some(format!("Could not read the status: {}", error)),
+        }
+
+        if let Some(result) = self.starting.as_ref().and_then(|starting| starting.try_recv().ok()) {
+            self.starting = None;
+            self.message = result.err();
+        }
+    }
+
+    fn start(&mut self) {
+        let task = Some(self.task.trim()).filter(|task| !task.is_empty());
+        match client::start(self.duration.trim(), task) {
+            Ok(true) => self.message = None,
+            Ok(false) => {
+                let (sender, receiver) = mpsc::channel();
+                let args = start_args(self.duration.trim(), task);
+                std::thread::spawn(move || {
+                    let _ = sender.send(run_cli(&args));
+                });
+                self.starting = Some(receiver);
+                self.message = Some("Starting the session...".to_string());
+            }
+            Err(error) => self.message = Some(error.to_string()),
+        }
+        self.polled = None;
+    }
+
+    fn stop(&mut self) {
+        self.message = client::stop().err().map(|error| error.to_string());
+        self.polled = None;
+    }
+}
+
+impl eframe::App for Gui {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll();
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if self.status.active {
+                let task = self.status.task.as_deref().unwrap_or("Focus session");
+                ui.heading(task);
+                let remaining = Duration::from_secs(self.status.remaining_secs.unwrap_or(0));
+                ui.label(format!("{} left", format::clock(remaining)));
+                ui.add(egui::ProgressBar::new(self.status.progress() as f32));
+                if let Some(priority) = &self.status.priority {
+                    ui.label(format!("Priority: {}", priority));
+                }
+                if ui.button("Stop").clicked() {
+                    self.stop();
+                }
+            } else {
+                ui.heading("No session is running");
+                egui::Grid::new("start").num_columns(2).show(ui, |ui| {
+                    ui.label("Duration");
+                    ui.text_edit_singleline(&mut self.duration);
+                    ui.end_row();
+                    ui.label("Task");
+                    ui.text_edit_singleline(&mut self.task);
+                    ui.end_row();
+                });
+                let idle = self.starting.is_none();
+                if ui.add_enabled(idle, egui::Button::new("Start")).clicked() {
+                    self.start();
+                }
+            }
+
+            if let Some(message) = &self.message {
+                ui.separator();
+                ui.label(message);
+            }
+        });
+
+        ctx.request_repaint_after(POLL_INTERVAL);
+    }
+}
+
+/// Arguments of `timeguardian ipc start`
+fn start_args(duration: &str, task: Option<&str>) -> Vec<String> {
+    let mut args = vec!["ipc".to_string(), "start".to_string(), "--duration".to_string(), duration.to_string()];
+    if let Some(task) = task {
+        args.extend(["--task".to_string(), task.to_string()]);
+    }
+    args
+}
+
+/// Run the TimeGuardian CLI and turn an error line it prints into an error
+fn run_cli(args: &[String]) -> Result<(), String> {
+    let output = Command::new(cli_path())
+        .args(args)
+        .output()
+        .map_err(|error| format!("Could not run timeguardian: {}", error))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let error = stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .find_map(|response| response["error"].as_str().map(str::to_string));
+    match error {
+        Some(error) => Err(error),
+        None if output.status.success() => Ok(()),
+        None => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+/// The CLI next to this executable, or the one on the `PATH`
+fn cli_path() -> PathBuf {
+    let sibling = std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(format!("TimeGuardian{}", std::env::consts::EXE_SUFFIX)));
+    sibling.filter(|path| path.exists()).unwrap_or_else(|| PathBuf::from("timeguardian"))
+}
@@ -0,0 +1,109 @@
+/*
+* TimeGuardian Blocker Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module describes the blocking backends and what each of them can do,
+* so the interface can explain why a feature is unavailable instead of
+* silently ignoring it. The hosts file is always used; a configured firewall
+* backend additionally blocks the addresses of the blocked domains. The
+* blocking itself goes through the claims in `hosts`. The `Blocker` trait
+* and `Capabilities` are part of the library.
+*/
+
+pub use timeguardian::{Blocker, Capabilities};
+
+use crate::firewall::FirewallBackend;
+use crate::{hosts, load_config};
+
+/// Blocking through the system hosts file
+pub struct HostsBlocker;
+
+impl Blocker for HostsBlocker {
+    fn name(&self) -> &'static str {
+        "hosts"
+    }
+
+    fn description(&self) -> String {
+        let (v4, v6) = hosts::sinkhole_pair(hosts::sinkhole());
+        format!("Points blocked domains to {} and {} in {}", v4, v6, hosts::get_hosts_path().display())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            wildcards: false,
+            per_path: false,
+            attempt_logging: false,
+            bypass_resistant: false,
+            per_user: false,
+            needs_root: true,
+        }
+    }
+}
+
+/// Blocking through the hosts file and the addresses in a firewall
+pub struct FirewallBlocker {
+    /// Firewall holding the address rules
+    pub backend: FirewallBackend,
+}
+
+impl Blocker for FirewallBlocker {
+    fn name(&self) -> &'static str {
+        match self.backend {
+            FirewallBackend::Pf => "hosts+pf",
+            FirewallBackend::Nftables => "hosts+nftables",
+            FirewallBackend::Iptables => "hosts+iptables",
+            FirewallBackend::Windows => "hosts+windows-firewall",
+        }
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "{}, and blocks their addresses in {}",
+            HostsBlocker.description(),
+            self.backend.location()
+        )
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            bypass_resistant: true,
+            per_user: matches!(self.backend, FirewallBackend::Nftables | FirewallBackend::Iptables),
+            ..HostsBlocker.capabilities()
+        }
+    }
+}
+
+/// No blocking: the hosts file is not writable, sessions only run the timer
+pub struct DisabledBlocker;
+
+impl Blocker for DisabledBlocker {
+    fn name(&self) -> &'static str {
+        "blocking disabled (no permissions)"
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "Nothing is blocked: {} is not writable. Sessions run the timer and are recorded as unenforced.",
+            hosts::get_hosts_path().display()
+        )
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            wildcards: false,
+            per_path: false,
+            attempt_logging: false,
+            bypass_resistant: false,
+            per_user: false,
+            needs_root: false,
+        }
+    }
+}
+
+/// The backend blocking websites on this machine
+pub fn active() -> Box<dyn Blocker> {
+    match load_config().ok().and_then(|config| config.firewall) {
+        Some(firewall) => Box::new(FirewallBlocker { backend: firewall.backend.available() }),
+        None => Box::new(HostsBlocker),
+    }
+}
@@ -0,0 +1,82 @@
+/*
+* TimeGuardian Blocking Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module defines what a way of blocking websites looks like to the rest
+* of TimeGuardian: a name, a description and its capabilities, so interfaces
+* can explain why a feature is unavailable instead of silently ignoring it.
+* The backends themselves (hosts file, firewalls) live in the binary.
+*/
+
+/// What a blocking backend supports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Block every subdomain with one entry (e.g. "*.example.com")
+    pub wildcards: bool,
+    /// Block single paths of a site (e.g. "reddit.com/r/all")
+    pub per_path: bool,
+    /// Record attempts to open blocked sites
+    pub attempt_logging: bool,
+    /// Also stop apps using DNS over HTTPS or hard-coded addresses
+    pub bypass_resistant: bool,
+    /// Restrict the block to some users of the machine
+    pub per_user: bool,
+    /// Root or administrator rights are needed
+    pub needs_root: bool,
+}
+
+impl Capabilities {
+    /// Label and support of every capability, for listings
+    pub fn rows(&self) -> Vec<(&'static str, bool)> {
+        vec![
+            ("Wildcard domains (*.example.com)", self.wildcards),
+            ("Per-path rules (example.com/path)", self.per_path),
+            ("Logging of blocked attempts", self.attempt_logging),
+            ("Blocks DNS over HTTPS and hard-coded IPs", self.bypass_resistant),
+            ("Blocking only some users", self.per_user),
+        ]
+    }
+}
+
+/// A way of blocking websites
+///
+/// ```
+/// use timeguardian::{Blocker, Capabilities};
+///
+/// struct DryRun;
+///
+/// impl Blocker for DryRun {
+///     fn name(&self) -> &'static str {
+///         "dry-run"
+///     }
+///
+///     fn description(&self) -> String {
+///         "Prints what would be blocked".to_string()
+///     }
+///
+///     fn capabilities(&self) -> Capabilities {
+///         Capabilities {
+///             wildcards: true,
+///             per_path: false,
+///             attempt_logging: false,
+///             bypass_resistant: false,
+///             per_user: false,
+///             needs_root: false,
+///         }
+///     }
+/// }
+///
+/// let rows = DryRun.capabilities().rows();
+/// let supported: Vec<&str> = rows.iter().filter(|(_, on)| *on).map(|(label, _)| *label).collect();
+/// assert_eq!(supported, ["Wildcard domains (*.example.com)"]);
+/// ```
+pub trait Blocker {
+    /// Short name for the status bar
+    fn name(&self) -> &'static str;
+
+    /// One-line description of how the backend blocks
+    fn description(&self) -> String;
+
+    /// What the backend supports
+    fn capabilities(&self) -> Capabilities;
+}
@@ -0,0 +1,175 @@
+/*
+* TimeGuardian Block Page Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module serves a small page on 127.0.0.1 and ::1 while a session
+* blocks, so an opened blocked site says "You're in a focus session" with the
+* task and the remaining time instead of failing with a connection error:
+*
+*   [block_page]
+*   port = 80
+*
+* Blocked domains point to the loopback addresses then instead of 0.0.0.0,
+* unless `sinkhole` names another address. The page is read from the live
+* session on every request. Only plain HTTP reaches it: for HTTPS the
+* browser still shows an error, since the certificate can't match the
+* blocked site.
+*/
+
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Read, Write},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use timeguardian::format;
+
+use crate::ipc::{SessionHandle, SessionStatus};
+use crate::{idn, load_config, newtab};
+
+/// Port browsers use for plain HTTP
+const DEFAULT_PORT: u16 = 80;
+
+/// How often the listeners are checked for connections
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a client may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Where the block page is served
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BlockPageConfig {
+    /// Port of the server; other ports than 80 only help behind a port forward
+    pub port: Option<u16>,
+}
+
+/// Serves the block page in a background thread until it is dropped
+pub struct BlockPage {
+    stop: Arc<AtomicBool>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for BlockPage {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Serve the block page of `[block_page]` for the session of `handle`;
+/// `None` without `[block_page]`. Fails if the port can't be bound, e.g.
+/// without root for port 80 or while another session serves it.
+pub fn serve(handle: SessionHandle) -> Result<Option<BlockPage>> {
+    let Some(config) = load_config()?.block_page else {
+        return Ok(None);
+    };
+    let port = config.port.unwrap_or(DEFAULT_PORT);
+
+    // Either address is enough, e.g. on machines without IPv6
+    let mut error = None;
+    let listeners: Vec<TcpListener> = [Ipv4Addr::LOCALHOST.into(), Ipv6Addr::LOCALHOST.into()]
+        .into_iter()
+        .filter_map(|address| {
+            TcpListener::bind(SocketAddr::new(address, port)).map_err(|e| error.get_or_insert(e)).ok()
+        })
+        .collect();
+    if let (true, Some(error)) = (listeners.is_empty(), error) {
+        return Err(eyre!("Could not serve the block page on port {}: {}", port, error));
+    }
+    for listener in &listeners {
+        listener.set_nonblocking(true)?;
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    let worker = thread::spawn(move || {
+        while !stopped.load(Ordering::Relaxed) {
+            for listener in &listeners {
+                // One request at a time; a blocked site only loads a page or two
+                while let Ok((stream, _)) = listener.accept() {
+                    let _ = answer(stream, &handle.status());
+                }
+            }
+            thread::sleep(ACCEPT_INTERVAL);
+        }
+    });
+
+    Ok(Some(BlockPage { stop, worker: Some(worker) }))
+}
+
+/// Read the request on `stream` and send the page for its host
+fn answer(mut stream: TcpStream, status: &SessionStatus) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 16 * 1024 {
+        match stream.read(&mut buffer)? {
+            0 => break,
+            read => request.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let host = request
+        .lines()
+        .find_map(|line| line.split_once(':').filter(|(name, _)| name.eq_ignore_ascii_case("host")))
+        .map(|(_, host)| host.trim().split(':').next().unwrap_or_default().to_string())
+        .filter(|host| !host.is_empty());
+
+    let body = render(host.as_deref(), status);
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// The HTML of the page
+fn render(host: Option<&str>, status: &SessionStatus) -> String {
+    let site = newtab::escape(&host.map(idn::to_unicode).unwrap_or_else(|| "This site".to_string()));
+    let session = match (status.active, status.remaining_secs) {
+        (true, Some(remaining)) => format!(
+            r#"<h1>You're in a focus session</h1>
+    <p>{site} is blocked while you work on <strong>{task}</strong>.</p>
+    <p class="remaining">{remaining} remaining</p>"#,
+            site = site,
+            task = newtab::escape(status.task.as_deref().unwrap_or("your task")),
+            remaining = format::spoken(Duration::from_secs(remaining)),
+        ),
+        _ => format!("<h1>{} is blocked</h1>\n    <p>The session blocking it is ending.</p>", site),
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta http-equiv="refresh" content="60">
+  <title>Blocked by TimeGuardian</title>
+  <style>
+    body {{ margin: 0; min-height: 100vh; display: flex; align-items: center; justify-content: center;
+           background: #1e1e2e; color: #cdd6f4; font-family: system-ui, sans-serif; text-align: center; }}
+    .remaining {{ font-size: 2rem; color: #a6e3a1; }}
+  </style>
+</head>
+<body>
+  <main>
+    {session}
+  </main>
+</body>
+</html>
+"#,
+        session = session
+    )
+}
@@ -0,0 +1,182 @@
+/*
+* TimeGuardian Client Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module is the client side of the IPC protocol (docs/ipc.md) for
+* frontends. The backend is the process running a session, the CLI timer or
+* the TUI: it owns the hosts file, the firewall and the history. A frontend
+* only ever reads the status and asks for a start or a stop through the
+* socket, so it needs no root and no access to the config or the state.
+*/
+
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{io, path::PathBuf};
+
+use crate::session::{Priority, Session};
+
+/// File name of the IPC socket in the runtime directory
+const SOCKET_NAME: &str = "timeguardian.sock";
+
+/// Status of the session served by the backend
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Status {
+    /// Whether a blocking session is running
+    pub active: bool,
+    /// Task of the session
+    pub task: Option<String>,
+    /// ULID of the session in the history
+    pub session_id: Option<String>,
+    /// Priority of the session ("low", "normal", "high" or "critical")
+    pub priority: Option<String>,
+    /// When the session started
+    pub started_at: Option<DateTime<Local>>,
+    /// When the session ends
+    pub ends_at: Option<DateTime<Local>>,
+    /// Seconds until the session ends
+    pub remaining_secs: Option<u64>,
+}
+
+impl Status {
+    /// Share of the session that has passed, from 0.0 to 1.0
+    pub fn progress(&self) -> f64 {
+        let (Some(started_at), Some(ends_at), Some(remaining)) = (self.started_at, self.ends_at, self.remaining_secs)
+        else {
+            return 0.0;
+        };
+        let total = (ends_at - started_at).num_seconds();
+        if total <= 0 {
+            return 1.0;
+        }
+        (1.0 - remaining as f64 / total as f64).clamp(0.0, 1.0)
+    }
+
+    /// The running session, if there is one
+    pub fn session(&self) -> Option<Session> {
+        if !self.active {
+            return None;
+        }
+        let priority = self.priority.as_deref().and_then(|name| Priority::from_str(name, true).ok());
+        Some(Session {
+            id: self.session_id.clone(),
+            task: self.task.clone(),
+            priority: priority.unwrap_or_default(),
+            started_at: self.started_at?,
+            ends_at: self.ends_at?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct Response {
+    ok: bool,
+    status: Option<Status>,
+    error: Option<String>,
+}
+
+/// Path of the IPC socket served by the backend: in the runtime directory of
+/// [`socket_owner`], so other users of the machine can't reach the session
+pub fn socket_path() -> PathBuf {
+    runtime_dir().join(SOCKET_NAME)
+}
+
+/// User the socket belongs to: the one who called sudo when running as root
+/// through it, else the current one
+#[cfg(unix)]
+pub fn socket_owner() -> u32 {
+    let euid = unsafe { libc::geteuid() };
+    let sudo_uid = std::env::var("SUDO_UID").ok().and_then(|uid| uid.parse().ok());
+    match sudo_uid {
+        Some(uid) if euid == 0 => uid,
+        _ => euid,
+    }
+}
+
+/// `$XDG_RUNTIME_DIR` or `/run/user/<uid>` of the socket owner; without one,
+/// a directory of the owner in the temp directory
+#[cfg(unix)]
+fn runtime_dir() -> PathBuf {
+    use std::os::unix::fs::MetadataExt;
+
+    let owner = socket_owner();
+    // Under sudo, $XDG_RUNTIME_DIR is usually unset or root's
+    let candidates = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain([PathBuf::from(format!("/run/user/{}", owner))]);
+    for dir in candidates {
+        if std::fs::symlink_metadata(&dir).is_ok_and(|metadata| metadata.is_dir() && metadata.uid() == owner) {
+            return dir;
+        }
+    }
+    std::env::temp_dir().join(format!("timeguardian-{}", owner))
+}
+
+/// The socket is not available on Windows
+#[cfg(not(unix))]
+fn runtime_dir() -> PathBuf {
+    std::env::temp_dir()
+}
+
+/// Whether a backend is serving the socket
+pub fn backend_running() -> bool {
+    #[cfg(unix)]
+    {
+        std::os::unix::net::UnixStream::connect(socket_path()).is_ok()
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// The status of the running session; idle if no backend is running
+pub fn status() -> io::Result<Status> {
+    Ok(request(r#"{"cmd":"status"}"#)?.unwrap_or_default())
+}
+
+/// End the running session early; fails with the reason given by the
+/// backend, e.g. for strict sessions
+pub fn stop() -> io::Result<()> {
+    match request(r#"{"cmd":"stop"}"#)? {
+        Some(_) => Ok(()),
+        None => Err(io::Error::other("no session is running")),
+    }
+}
+
+/// Start a session in an idle backend (the TUI). Returns `Ok(false)` if no
+/// backend is running; a frontend can then run `timeguardian ipc start`,
+/// which starts one.
+pub fn start(duration: &str, task: Option<&str>) -> io::Result<bool> {
+    let line = serde_json::json!({ "cmd": "start", "duration": duration, "task": task }).to_string();
+    request(&line).map(|status| status.is_some())
+}
+
+/// Send one request line and return the status of the response, or `None`
+/// if no backend is running
+#[cfg(unix)]
+fn request(line: &str) -> io::Result<Option<Status>> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let Ok(mut stream) = UnixStream::connect(socket_path()) else {
+        return Ok(None);
+    };
+    writeln!(stream, "{}", line)?;
+    stream.flush()?;
+
+    let mut answer = String::new();
+    BufReader::new(stream).read_line(&mut answer)?;
+    let response: Response = serde_json::from_str(&answer).map_err(io::Error::other)?;
+    if !response.ok {
+        return Err(io::Error::other(response.error.unwrap_or_else(|| "request refused".to_string())));
+    }
+    Ok(Some(response.status.unwrap_or_default()))
+}
+
+/// The socket is not available on Windows, so no backend can be reached
+#[cfg(not(unix))]
+fn request(_line: &str) -> io::Result<Option<Status>> {
+    Ok(None)
+}
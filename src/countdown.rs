@@ -0,0 +1,210 @@
+/*
+* TimeGuardian Countdown Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module holds the length of the next session as it is picked in the
+* Timer tab: a value and a unit, changed in steps.
+*/
+
+use std::time::Duration;
+
+/// Time unit of the session length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    /// Minutes (default)
+    Minutes,
+    /// Hours
+    Hours,
+    /// Seconds
+    Seconds,
+}
+
+impl TimeUnit {
+    /// Name of the unit for display
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimeUnit::Minutes => "minutes",
+            TimeUnit::Hours => "hours",
+            TimeUnit::Seconds => "seconds",
+        }
+    }
+
+    /// Length of one unit in seconds
+    fn seconds(&self) -> u64 {
+        match self {
+            TimeUnit::Minutes => 60,
+            TimeUnit::Hours => 3600,
+            TimeUnit::Seconds => 1,
+        }
+    }
+
+    /// Step, smallest and largest value reachable with the arrow keys
+    fn range(&self) -> (u64, u64, u64) {
+        match self {
+            TimeUnit::Minutes => (5, 1, 120),
+            TimeUnit::Hours => (1, 1, 8),
+            TimeUnit::Seconds => (5, 5, 60),
+        }
+    }
+
+    /// Value selected when switching to the unit
+    fn default_value(&self) -> u64 {
+        match self {
+            TimeUnit::Minutes => 25,
+            TimeUnit::Hours => 1,
+            TimeUnit::Seconds => 30,
+        }
+    }
+}
+
+/// Length of the next session as a value in a unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Countdown {
+    /// Unit of the value
+    pub unit: TimeUnit,
+    /// Number of units
+    pub value: u64,
+}
+
+impl Default for Countdown {
+    fn default() -> Self {
+        Self { unit: TimeUnit::Minutes, value: TimeUnit::Minutes.default_value() }
+    }
+}
+
+impl Countdown {
+    /// Go to the next step (e.g. 1 → 5 → 10 minutes), up to the largest value.
+    /// Values above it, set by a macro or preset, are kept.
+    pub fn increase(&mut self) {
+        let (step, _, max) = self.unit.range();
+        let next = (self.value / step + 1) * step;
+        self.value = next.min(max).max(self.value);
+    }
+
+    /// Go to the previous step (e.g. 10 → 5 → 1 minutes), down to the smallest value
+    pub fn decrease(&mut self) {
+        let (step, min, _) = self.unit.range();
+        let previous = self.value.saturating_sub(1) / step * step;
+        self.value = previous.max(min).min(self.value);
+    }
+
+    /// Switch to the next unit with its default value
+    pub fn cycle_unit(&mut self) {
+        self.unit = match self.unit {
+            TimeUnit::Minutes => TimeUnit::Hours,
+            TimeUnit::Hours => TimeUnit::Seconds,
+            TimeUnit::Seconds => TimeUnit::Minutes,
+        };
+        self.value = self.unit.default_value();
+    }
+
+    /// Set the length, shown in the largest whole unit
+    pub fn set(&mut self, duration: Duration) {
+        let secs = duration.as_secs().max(1);
+        (self.unit, self.value) = if secs.is_multiple_of(3600) {
+            (TimeUnit::Hours, secs / 3600)
+        } else if secs.is_multiple_of(60) {
+            (TimeUnit::Minutes, secs / 60)
+        } else {
+            (TimeUnit::Seconds, secs)
+        };
+    }
+
+    /// The selected length
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.value * self.unit.seconds())
+    }
+
+    /// The selected length for display, e.g. "25 minutes"
+    pub fn label(&self) -> String {
+        format!("{} {}", self.value, self.unit.label())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(unit: TimeUnit, value: u64) -> Countdown {
+        Countdown { unit, value }
+    }
+
+    #[test]
+    fn increase_goes_to_the_next_step_up_to_the_largest_value() {
+        let cases = [
+            (TimeUnit::Minutes, 1, 5),
+            (TimeUnit::Minutes, 7, 10),
+            (TimeUnit::Minutes, 118, 120),
+            (TimeUnit::Minutes, 120, 120),
+            (TimeUnit::Minutes, 180, 180),
+            (TimeUnit::Hours, 7, 8),
+            (TimeUnit::Hours, 8, 8),
+            (TimeUnit::Seconds, 55, 60),
+            (TimeUnit::Seconds, 60, 60),
+        ];
+        for (unit, value, expected) in cases {
+            let mut countdown = at(unit, value);
+            countdown.increase();
+            assert_eq!(countdown.value, expected, "{} {}", value, unit.label());
+        }
+    }
+
+    #[test]
+    fn decrease_goes_to_the_previous_step_down_to_the_smallest_value() {
+        let cases = [
+            (TimeUnit::Minutes, 10, 5),
+            (TimeUnit::Minutes, 7, 5),
+            (TimeUnit::Minutes, 5, 1),
+            (TimeUnit::Minutes, 1, 1),
+            (TimeUnit::Hours, 2, 1),
+            (TimeUnit::Hours, 1, 1),
+            (TimeUnit::Seconds, 10, 5),
+            (TimeUnit::Seconds, 5, 5),
+            (TimeUnit::Seconds, 3, 3),
+        ];
+        for (unit, value, expected) in cases {
+            let mut countdown = at(unit, value);
+            countdown.decrease();
+            assert_eq!(countdown.value, expected, "{} {}", value, unit.label());
+        }
+    }
+
+    #[test]
+    fn cycle_unit_wraps_around_with_default_values() {
+        let mut countdown = at(TimeUnit::Minutes, 45);
+        countdown.cycle_unit();
+        assert_eq!(countdown, at(TimeUnit::Hours, 1));
+        countdown.cycle_unit();
+        assert_eq!(countdown, at(TimeUnit::Seconds, 30));
+        countdown.cycle_unit();
+        assert_eq!(countdown, Countdown::default());
+    }
+
+    #[test]
+    fn set_picks_the_largest_whole_unit() {
+        let cases = [
+            (0, TimeUnit::Seconds, 1),
+            (90, TimeUnit::Seconds, 90),
+            (25 * 60, TimeUnit::Minutes, 25),
+            (90 * 60, TimeUnit::Minutes, 90),
+            (3600, TimeUnit::Hours, 1),
+            (26 * 3600, TimeUnit::Hours, 26),
+        ];
+        for (secs, unit, value) in cases {
+            let mut countdown = Countdown::default();
+            countdown.set(Duration::from_secs(secs));
+            assert_eq!(countdown, at(unit, value), "{}s", secs);
+        }
+    }
+
+    #[test]
+    fn duration_is_value_times_unit() {
+        assert_eq!(Countdown::default().duration(), Duration::from_secs(25 * 60));
+        assert_eq!(at(TimeUnit::Hours, 2).duration(), Duration::from_secs(7200));
+        assert_eq!(at(TimeUnit::Seconds, 45).duration(), Duration::from_secs(45));
+
+        let mut countdown = Countdown::default();
+        countdown.set(Duration::from_secs(5400));
+        assert_eq!(countdown.duration(), Duration::from_secs(5400));
+    }
+}
@@ -0,0 +1,139 @@
+/*
+* TimeGuardian Demo Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module backs `timeguardian demo`: the TUI runs against a throwaway
+* directory holding generated website lists, a fake session history and a
+* hosts file of its own, with a simulated session already running. Nothing
+* touches the real configuration or /etc/hosts, so the demo needs no root
+* and is safe for screenshots, onboarding and UI work.
+*/
+
+use chrono::{Duration as ChronoDuration, Local};
+use color_eyre::{eyre::Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use crate::history::{Achievement, EndReason, Enforcement, History, SessionRecord};
+use crate::hosts::{self, Priority};
+use crate::ulid;
+
+/// Directory of the running demo; set once at startup
+static DEMO_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Configuration of the demo
+const DEMO_CONFIG: &str = r#"website_list_path = "websites.txt"
+intent_prompt = true
+
+[[website_lists]]
+name = "Social"
+websites = ["reddit.com", "twitter.com", "instagram.com", "facebook.com"]
+
+[[website_lists]]
+name = "Video"
+websites = ["youtube.com @19:00-21:00", "twitch.tv", "netflix.com"]
+
+[[website_lists]]
+name = "News"
+websites = ["news.ycombinator.com", "theguardian.com", "spiegel.de"]
+enabled = false
+
+[[schedules]]
+name = "Morning focus"
+days = ["Mon", "Tue", "Wed", "Thu", "Fri"]
+start = "09:00"
+end = "12:00"
+lists = ["Social", "Video"]
+"#;
+
+/// Hosts file the demo starts with
+const DEMO_HOSTS: &str = "127.0.0.1\tlocalhost\n::1\tlocalhost\n";
+
+/// Task, list and outcome of the generated past sessions
+const DEMO_SESSIONS: &[(&str, &str, u64, Option<bool>)] = &[
+    ("Write thesis chapter", "Social", 50, Some(true)),
+    ("Review pull requests", "Social", 25, Some(true)),
+    ("Prepare slides", "Video", 45, Some(false)),
+    ("Inbox zero", "Social", 25, Some(true)),
+    ("Write thesis chapter", "Video", 90, Some(true)),
+    ("Study for exam", "Social", 60, Some(true)),
+    ("Refactor parser", "Social", 50, Some(false)),
+    ("Write thesis chapter", "Social", 50, Some(true)),
+];
+
+/// The demo directory, while the demo runs
+pub fn dir() -> Option<&'static Path> {
+    DEMO_DIR.get().map(PathBuf::as_path)
+}
+
+/// Create the demo directory with its config, history and hosts file and
+/// start the simulated session
+pub fn prepare() -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("timeguardian-demo-{}", std::process::id()));
+    fs::create_dir_all(&dir).wrap_err_with(|| format!("Could not create demo directory: {:?}", dir))?;
+    let _ = DEMO_DIR.set(dir.clone());
+
+    fs::write(dir.join("config.toml"), DEMO_CONFIG).wrap_err("Could not write demo configuration")?;
+    fs::write(dir.join("hosts"), DEMO_HOSTS).wrap_err("Could not write demo hosts file")?;
+    fake_history().save()?;
+
+    // A session of another process, as started with `timeguardian start -d 50m`
+    hosts::claim(
+        "cli-demo",
+        Priority::High,
+        Some("Write thesis chapter"),
+        None,
+        &["Social".to_string()],
+        &["reddit.com".to_string(), "twitter.com".to_string()],
+        Duration::from_secs(32 * 60),
+    )?;
+
+    Ok(dir)
+}
+
+/// Remove the demo directory
+pub fn cleanup(dir: &Path) {
+    let _ = fs::remove_dir_all(dir);
+}
+
+/// History of the last days, one or two sessions a day
+fn fake_history() -> History {
+    let now = Local::now();
+    let sessions = DEMO_SESSIONS
+        .iter()
+        .enumerate()
+        .map(|(index, (task, list, minutes, completed))| {
+            let days_ago = (DEMO_SESSIONS.len() - index) as i64 / 2 + 1;
+            let achieved = match (index % 3, completed) {
+                (0, Some(true)) => Some(Achievement::Yes),
+                (0, _) => Some(Achievement::No),
+                (1, _) => Some(Achievement::Partial),
+                _ => None,
+            };
+            SessionRecord {
+                id: index as u64 + 1,
+                started_at: now - ChronoDuration::days(days_ago) - ChronoDuration::hours(index as i64 % 2 * 3),
+                duration_secs: minutes * 60,
+                task: task.to_string(),
+                lists: vec![list.to_string()],
+                priority: Priority::Normal,
+                completed: *completed,
+                ended_by: completed.map(|completed| if completed { EndReason::Expired } else { EndReason::Stopped }),
+                intent: achieved.map(|_| format!("Finish: {}", task.to_lowercase())),
+                achieved,
+                enforcement: Enforcement::Blocked,
+                session_id: Some(ulid::new()),
+                checklist: Vec::new(),
+                hash: None,
+            }
+        });
+    let mut history = History::default();
+    for record in sessions {
+        history.push(record);
+    }
+    history
+}
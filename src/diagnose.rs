@@ -0,0 +1,460 @@
+/*
+* TimeGuardian Diagnose Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module implements `timeguardian why <domain>`, which explains why a
+* website still loads: it follows the domain from the website lists through
+* the active claims and the hosts file to the system resolver, and checks
+* browsers for DNS over HTTPS, which bypasses the hosts file. The other way
+* round, it traces every blocked domain back to the sessions and lists
+* blocking it (`timeguardian status --verbose`, `:blocked` in the TUI).
+*/
+
+use chrono::{Local, Utc};
+use color_eyre::{eyre::Context, Result};
+use directories::BaseDirs;
+use std::{
+    fs,
+    net::{IpAddr, ToSocketAddrs},
+    path::{Path, PathBuf},
+};
+use timeguardian::format;
+
+use crate::history::{self, Enforcement};
+use crate::hosts::{self, BlockClaim, BlockState};
+use crate::{firewall, idn, load_config, time_of_day};
+use crate::services::{self, Service};
+use crate::tui::WebsiteList;
+
+/// Why a domain is blocked right now
+#[derive(Clone, Debug)]
+pub struct Provenance {
+    pub domain: String,
+    /// Claims blocking the domain with the lists it comes from, the owner first
+    pub sources: Vec<(BlockClaim, Vec<String>)>,
+}
+
+impl Provenance {
+    /// One line: the domain, its owner and the other claims
+    pub fn line(&self) -> String {
+        let sources: Vec<String> = self
+            .sources
+            .iter()
+            .map(|(claim, lists)| {
+                let task = claim.task.as_deref().map(|task| format!(" \"{}\"", task)).unwrap_or_default();
+                let lists = match lists.as_slice() {
+                    [] => "no current list".to_string(),
+                    lists => format!("list {}", lists.join(", ")),
+                };
+                format!("{}{} [{}] via {}", claim.source, task, claim.priority.name(), lists)
+            })
+            .collect();
+        format!("{}: {}", self.domain, sources.join("; also "))
+    }
+}
+
+/// A browser with DNS over HTTPS turned on
+struct DohBrowser {
+    name: &'static str,
+    /// Whether DoH is enforced rather than used opportunistically
+    strict: bool,
+}
+
+/// Explain why `domain` is (not) blocked and suggest fixes
+pub fn why(domain: &str, lists: &[WebsiteList], custom_services: &[Service]) -> Result<()> {
+    let Some(domain) = hosts::expand_website(domain).into_iter().next() else {
+        println!("Please pass a domain, e.g. 'timeguardian why example.com'.");
+        return Ok(());
+    };
+    match idn::to_unicode(&domain) {
+        unicode if unicode != domain => println!("Checking {} ({})\n", unicode, domain),
+        _ => println!("Checking {}\n", domain),
+    }
+
+    // 1. Website lists
+    let listed: Vec<&str> = lists
+        .iter()
+        .filter(|list| {
+            services::expand(&list.websites, custom_services)
+                .iter()
+                .any(|entry| hosts::expand_website(entry).contains(&domain))
+        })
+        .map(|list| list.name.as_str())
+        .collect();
+    let parents: Vec<String> = lists
+        .iter()
+        .flat_map(|list| list.websites.iter())
+        .map(|entry| idn::key(entry))
+        .filter(|entry| domain.ends_with(&format!(".{}", entry)))
+        .collect();
+    if listed.is_empty() {
+        println!("- Lists: not in any website list");
+        if !parents.is_empty() {
+            let parents: Vec<String> = parents.iter().map(|parent| idn::to_unicode(parent)).collect();
+            println!("  ({} is listed, but the hosts file can't block its subdomains)", parents.join(", "));
+        }
+    } else {
+        println!("- Lists: in {}", listed.join(", "));
+    }
+    let allowed: Vec<&str> = lists
+        .iter()
+        .filter(|list| list.websites.iter().any(|entry| hosts::allowed_domain(entry).as_ref() == Some(&domain)))
+        .map(|list| list.name.as_str())
+        .collect();
+    if !allowed.is_empty() {
+        println!(
+            "  (let through by an allow: entry in {}; sessions blocking that list leave it out)",
+            allowed.join(", ")
+        );
+    }
+    let with_path = lists
+        .iter()
+        .flat_map(|list| list.websites.iter())
+        .filter(|entry| hosts::allowed_domain(entry).is_none())
+        .filter_map(|entry| entry.strip_prefix(hosts::ALLOW_PREFIX))
+        .any(|website| hosts::expand_website(website).first() == Some(&domain));
+    if with_path {
+        println!("  (allow: entries with a path are ignored; the hosts file can only allow whole domains)");
+    }
+
+    // 2. Active sessions and allowed windows
+    let now = Utc::now();
+    let state = BlockState::load()?;
+    let claims: Vec<_> = state
+        .claims
+        .iter()
+        .filter(|claim| claim.expires_at > now && claim.domains.contains(&domain))
+        .collect();
+    let allowed_now = !claims.is_empty()
+        && claims.iter().all(|claim| claim.allows(&domain, Local::now().time()));
+    match claims.as_slice() {
+        [] => println!("- Sessions: no running session blocks it"),
+        claims => {
+            for claim in claims {
+                println!(
+                    "- Sessions: claimed by {} [{}] until {}",
+                    claim.source,
+                    claim.priority.name(),
+                    crate::time_of_day(&claim.expires_at.with_timezone(&Local))
+                );
+            }
+            if allowed_now {
+                println!("  (an allowed window is open right now)");
+            }
+        }
+    }
+
+    // 3. Hosts file
+    let in_hosts_file = hosts::sessions_in_hosts_file()?
+        .iter()
+        .any(|session| session.domains.contains(&domain));
+    let sinkhole = hosts::sinkhole();
+    let (sinkhole_v4, sinkhole_v6) = hosts::sinkhole_pair(sinkhole);
+    if in_hosts_file {
+        println!("- Hosts file: redirected to {} and {}", sinkhole_v4, sinkhole_v6);
+    } else {
+        println!("- Hosts file: no entry (blocked domains point to {} and {})", sinkhole_v4, sinkhole_v6);
+    }
+    let block_page = crate::load_config().is_ok_and(|config| config.block_page.is_some());
+    if block_page {
+        println!("  (the block page of [block_page] answers for blocked domains over plain HTTP)");
+    } else if sinkhole.is_loopback() {
+        println!("  (a local web server on port 80 answers for blocked domains; sinkhole = \"0.0.0.0\" avoids that)");
+    }
+
+    // 4. System resolver
+    let addresses: Vec<IpAddr> = (domain.as_str(), 80)
+        .to_socket_addrs()
+        .map(|addresses| addresses.map(|address| address.ip()).collect())
+        .unwrap_or_default();
+    let resolves_blocked = !addresses.is_empty()
+        && addresses
+            .iter()
+            .all(|ip| ip.is_loopback() || ip.is_unspecified() || *ip == sinkhole_v4 || *ip == sinkhole_v6);
+    match addresses.first() {
+        None => println!("- Resolver: does not resolve"),
+        Some(ip) if resolves_blocked => println!("- Resolver: {} (blocked)", ip),
+        Some(ip) => println!("- Resolver: {} (reachable)", ip),
+    }
+
+    // 5. Browsers bypassing the system resolver
+    let doh = doh_browsers();
+    if doh.is_empty() {
+        println!("- Browsers: no DNS over HTTPS found");
+    }
+    for browser in &doh {
+        println!(
+            "- Browsers: {} uses DNS over HTTPS{}",
+            browser.name,
+            if browser.strict { "" } else { " when available" }
+        );
+    }
+
+    // The first broken link explains the behavior
+    println!();
+    if listed.is_empty() && claims.is_empty() {
+        println!("Reason: {} is not blocked by any list.", domain);
+        println!("Fix: add it to a list (TUI Website Lists tab) and start a session.");
+    } else if claims.is_empty() {
+        println!("Reason: no running session includes {}.", domain);
+        println!("Fix: start a session with one of its lists, e.g. 'timeguardian start -d 25m -t work -l {}'.", listed[0]);
+    } else if allowed_now {
+        println!("Reason: an allowed window of the list entry is open right now.");
+        println!("Fix: remove or change the '@HH:MM-HH:MM' part of the entry.");
+    } else if !in_hosts_file {
+        println!("Reason: the session claims {} but the hosts file has no entry.", domain);
+        println!("Fix: check 'timeguardian permissions' and run any command as root to repair the hosts file.");
+    } else if !resolves_blocked {
+        println!("Reason: the system resolver does not use the hosts file entry yet.");
+        println!("Fix: flush the DNS cache (it happens on every block change) or check /etc/nsswitch.conf.");
+    } else if !doh.is_empty() {
+        println!("Reason: the browser resolves names over HTTPS and skips the hosts file.");
+        println!("Fix: turn off \"secure DNS\" / \"DNS over HTTPS\" in the browser settings.");
+    } else {
+        println!("Reason: {} is blocked system-wide; the browser still has an open connection or cached answer.", domain);
+        println!("Fix: restart the browser or wait a minute.");
+    }
+
+    Ok(())
+}
+
+/// Every domain blocked right now with the claims and lists blocking it
+pub fn provenance(lists: &[WebsiteList], custom_services: &[Service]) -> Result<Vec<Provenance>> {
+    let now = Utc::now();
+    let mut state = BlockState::load()?;
+    state.claims.retain(|claim| claim.expires_at > now);
+
+    let listed: Vec<(&WebsiteList, Vec<String>)> = lists
+        .iter()
+        .map(|list| {
+            let domains = services::expand(&list.websites, custom_services)
+                .iter()
+                .flat_map(|entry| hosts::expand_website(entry))
+                .collect();
+            (list, domains)
+        })
+        .collect();
+
+    let provenance = state
+        .claimants()
+        .into_iter()
+        .map(|(domain, claims)| {
+            let sources = claims
+                .into_iter()
+                .map(|claim| {
+                    // Claims without lists blocked all enabled lists, and
+                    // every claim the enabled system lists
+                    let from = listed
+                        .iter()
+                        .filter(|(list, domains)| {
+                            let claimed = if claim.lists.is_empty() || list.system {
+                                list.enabled
+                            } else {
+                                claim.lists.iter().any(|name| name.eq_ignore_ascii_case(&list.name))
+                            };
+                            claimed && domains.iter().any(|listed| listed == domain)
+                        })
+                        .map(|(list, _)| {
+                            if list.system {
+                                format!("{} (system)", list.name)
+                            } else {
+                                list.name.clone()
+                            }
+                        })
+                        .collect();
+                    (claim.clone(), from)
+                })
+                .collect();
+            Provenance { domain: domain.to_string(), sources }
+        })
+        .collect();
+    Ok(provenance)
+}
+
+/// Browsers whose settings turn on DNS over HTTPS
+fn doh_browsers() -> Vec<DohBrowser> {
+    let Some(dirs) = BaseDirs::new() else {
+        return Vec::new();
+    };
+    let mut browsers = Vec::new();
+
+    // Firefox: network.trr.mode 2 = DoH first, 3 = DoH only
+    let firefox_profiles = if cfg!(target_os = "linux") {
+        dirs.home_dir().join(".mozilla/firefox")
+    } else {
+        dirs.config_dir().join("Mozilla/Firefox/Profiles")
+    };
+    for prefs in profile_files(&firefox_profiles, "prefs.js") {
+        let content = fs::read_to_string(prefs).unwrap_or_default();
+        if content.contains("\"network.trr.mode\", 3") {
+            browsers.push(DohBrowser { name: "Firefox", strict: true });
+        } else if content.contains("\"network.trr.mode\", 2") {
+            browsers.push(DohBrowser { name: "Firefox", strict: false });
+        }
+    }
+
+    // Chromium-based browsers keep the setting in "Local State"
+    let chromium = [
+        ("Chrome", "google-chrome", "Google/Chrome"),
+        ("Chromium", "chromium", "Chromium"),
+        ("Edge", "microsoft-edge", "Microsoft/Edge"),
+        ("Brave", "BraveSoftware/Brave-Browser", "BraveSoftware/Brave-Browser"),
+    ];
+    for (name, linux_dir, other_dir) in chromium {
+        let local_state = if cfg!(target_os = "linux") {
+            dirs.config_dir().join(linux_dir).join("Local State")
+        } else if cfg!(target_os = "windows") {
+            dirs.data_local_dir().join(other_dir).join("User Data/Local State")
+        } else {
+            dirs.config_dir().join(other_dir).join("Local State")
+        };
+        let Ok(content) = fs::read_to_string(local_state) else {
+            continue;
+        };
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) else {
+            continue;
+        };
+        match json["dns_over_https"]["mode"].as_str() {
+            Some("secure") => browsers.push(DohBrowser { name, strict: true }),
+            Some("automatic") => browsers.push(DohBrowser { name, strict: false }),
+            _ => {}
+        }
+    }
+
+    browsers.dedup_by(|a, b| a.name == b.name);
+    browsers
+}
+
+/// `file` in every profile directory below `dir`
+fn profile_files(dir: &Path, file: &str) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path().join(file))
+                .filter(|path| path.exists())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Print `timeguardian status`: the running sessions and, with `verbose`,
+/// the claims and firewall behind every blocked domain
+pub fn status(qr: bool, verbose: bool) -> Result<()> {
+    print_block_status(qr)?;
+    if !verbose {
+        return Ok(());
+    }
+
+    let config = load_config()?;
+    let provenance = provenance(
+        config.website_lists.as_deref().unwrap_or_default(),
+        config.services.as_deref().unwrap_or_default(),
+    )?;
+    if !provenance.is_empty() {
+        println!("\nBlocked domains (owner first):");
+    }
+    for domain in provenance {
+        println!("  {}", domain.line());
+    }
+
+    let lines = firewall::status_lines()?;
+    if !lines.is_empty() {
+        println!();
+    }
+    for line in lines {
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+/// Print the sessions behind the TimeGuardian section of the hosts file
+fn print_block_status(qr: bool) -> Result<()> {
+    // Sessions that run without blocking have no claim in the hosts file
+    let history = history::History::load()?;
+    for record in history.sessions.iter().filter(|record| record.running() && record.enforcement != Enforcement::Blocked) {
+        let status = match record.enforcement {
+            Enforcement::Unenforced => "blocking disabled (no permissions)",
+            _ => "timer only",
+        };
+        println!("{}: {}, started {}", record.task, status, time_of_day(&record.started_at));
+    }
+
+    let sessions = hosts::sessions_in_hosts_file()?;
+    if sessions.is_empty() {
+        println!("No websites are blocked.");
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let state = BlockState::load()?;
+    println!("{} session(s) blocking websites:", sessions.len());
+    for session in &sessions {
+        let until = if session.expires_at == chrono::DateTime::<Utc>::MIN_UTC {
+            "no readable expiry (expired)".to_string()
+        } else if session.expires_at <= now {
+            let expires = session.expires_at.with_timezone(&Local);
+            format!("until {} (expired)", expires.format("%Y-%m-%d %H:%M"))
+        } else {
+            let expires = session.expires_at.with_timezone(&Local);
+            let left = (session.expires_at - now).to_std().unwrap_or_default();
+            format!("until {}, {} left", expires.format("%Y-%m-%d %H:%M"), format::spoken(left))
+        };
+        println!(
+            "{} [{}] {}: {} domains, {}",
+            session.source,
+            session.priority.name(),
+            session.task.as_deref().unwrap_or("no task"),
+            session.domains.len(),
+            until
+        );
+        if session.unreadable_expiry && session.expires_at != chrono::DateTime::<Utc>::MIN_UTC {
+            println!("  expiry unreadable in the hosts file; assumed the default length from its start");
+        }
+        if let Some(session_id) = &session.session_id {
+            println!("  session {}", session_id);
+        }
+        if let Some(claim) = state.claims.iter().find(|claim| claim.source == session.source)
+            && !claim.taken_over.is_empty()
+        {
+            println!("  took over {} until it ends", claim.taken_over.join(", "));
+        }
+    }
+
+    // The session that ends last decides when everything is unblocked
+    if qr {
+        let last = sessions
+            .iter()
+            .filter(|session| session.expires_at > now && !session.unreadable_expiry)
+            .max_by_key(|session| session.expires_at);
+        match last {
+            Some(session) => print_session_qr(session)?,
+            None => println!("\nNo running session with a known end time for a QR code."),
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a QR code with the end time and task of `session`
+fn print_session_qr(session: &BlockClaim) -> Result<()> {
+    let ends_at = session.expires_at.with_timezone(&Local);
+    let text = format!(
+        "TimeGuardian: {} until {} ({})",
+        session.task.as_deref().unwrap_or("focus session"),
+        time_of_day(&ends_at),
+        ends_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+    );
+    let code = qrcode::QrCode::new(text.as_bytes()).wrap_err("Could not encode the QR code")?;
+
+    // Inverted, so the code scans on dark terminal backgrounds
+    let image = code
+        .render::<qrcode::render::unicode::Dense1x2>()
+        .dark_color(qrcode::render::unicode::Dense1x2::Light)
+        .light_color(qrcode::render::unicode::Dense1x2::Dark)
+        .build();
+    println!("\n{}\n{}", image, text);
+    Ok(())
+}
+
@@ -0,0 +1,865 @@
+/*
+* TimeGuardian Firewall Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module also blocks the addresses of blocked domains in the system
+* firewall, for apps that ignore the hosts file (DNS over HTTPS, hard-coded
+* addresses, long-lived DNS caches). The hosts file stays the primary block;
+* the firewall follows its domains on every reconciliation:
+*
+*   [firewall]
+*   backend = "nftables"    # Linux ("iptables" without nft); "pf" on macOS;
+*                           # "windows" for the Windows Firewall
+*   resolver = "9.9.9.9"    # optional, otherwise the servers from resolv.conf
+*   enable = true           # turn the firewall on for the block if it is off
+*   users = ["jannis"]      # optional, Linux only: block only these users
+*
+* While the block lasts, every domain is looked up again when the TTL of
+* its records runs out (between a minute and an hour). New addresses are
+* added; old ones stay blocked until the block ends, since sites and CDNs
+* rotate between them. `status --verbose` shows the lookup statistics.
+*
+* nftables: everything lives in the table "inet timeguardian", with one set
+* of IPv4 and one of IPv6 addresses that an output chain rejects. Addresses
+* are added to the sets without reloading the rules, and removing the block
+* is a single delete of the table, which leaves all other rules alone.
+* With `users`, the rules only match packets of those users (`meta skuid`),
+* so other accounts on the machine are not blocked. The hosts file applies
+* to everyone, so in that case its section only records the block and the
+* firewall alone enforces it.
+*
+* iptables: for systems without nft, and used instead of nftables when `nft`
+* is not installed. The addresses are rejected in the chain
+* TIMEGUARDIAN-BLOCK, which OUTPUT reaches through the chain TIMEGUARDIAN
+* (matching `users` with `-m owner`), in iptables and ip6tables alike.
+* Loading replaces both chains at once with `iptables-restore --noflush`;
+* removing the block deletes the jump and the chains.
+*
+* pf: the rules live in the anchor "com.apple/timeguardian", which the
+* default pf.conf of macOS already evaluates, so pf.conf is never edited. The
+* rules are written to a temporary anchor file, loaded with `pfctl` and
+* flushed when the block ends. If a reload of pf.conf drops them, they are
+* loaded again with the next refresh (about once a minute). A disabled pf is
+* enabled with a reference (`pfctl -E`) that is released when the block ends,
+* which leaves pf as it was.
+*
+* Windows Firewall: outbound block rules named "TimeGuardian", created with
+* `netsh advfirewall`, with up to 100 addresses each (netsh has a limit on
+* the length of a command). New addresses get a rule of their own, and
+* removing the block deletes every rule of that name. The firewall catches
+* connections that the hosts file misses when Defender keeps it from being
+* edited, or when the DNS cache still holds the old answer. Profiles
+* (Domain, Private, Public) the firewall is off for are turned on, and only
+* those are turned off again when the block ends.
+*
+* Sites behind a CDN share addresses with others, which are blocked as well
+* while the block lasts. Domains let through by "allow:" entries are looked
+* up the same way, and their addresses are kept out of the block.
+*/
+
+use chrono::{DateTime, Local, Utc};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    net::IpAddr,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use crate::{get_state_dir, journal, load_config, resolve};
+
+/// File in the state directory remembering what the firewall blocks
+pub const FIREWALL_STATE_FILE: &str = "firewall.toml";
+
+/// pf anchor holding the rules, below the anchor evaluated by macOS' pf.conf
+const PF_ANCHOR: &str = "com.apple/timeguardian";
+
+/// nftables table holding the sets and the chain
+const NFT_TABLE: &str = "inet timeguardian";
+
+/// iptables chain jumped to from OUTPUT, selecting the blocked users
+const IPT_CHAIN: &str = "TIMEGUARDIAN";
+
+/// iptables chain rejecting the blocked addresses
+const IPT_BLOCK_CHAIN: &str = "TIMEGUARDIAN-BLOCK";
+
+/// Name of the Windows Firewall rules
+const WINDOWS_RULE: &str = "TimeGuardian";
+
+/// Addresses per Windows Firewall rule
+const WINDOWS_RULE_ADDRESSES: usize = 100;
+
+/// Domains resolved at the same time
+const RESOLVE_PARALLELISM: usize = 16;
+
+/// Shortest time before a domain is looked up again, whatever its TTL
+const MIN_REFRESH: Duration = Duration::from_secs(60);
+
+/// Longest time before a domain is looked up again
+const MAX_REFRESH: Duration = Duration::from_secs(60 * 60);
+
+/// Addresses kept per domain; CDNs hand out a few new ones with every answer
+const MAX_ADDRESSES_PER_DOMAIN: usize = 256;
+
+/// Number of addresses from which a domain is reported as a CDN
+const MANY_ADDRESSES: usize = 16;
+
+/// Firewall blocking configured in `[firewall]`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FirewallConfig {
+    /// Firewall to use
+    pub backend: FirewallBackend,
+    /// DNS server resolving the blocked domains, instead of the system's
+    #[serde(default)]
+    pub resolver: Option<IpAddr>,
+    /// Enable the firewall for the block if it is disabled
+    #[serde(default = "enabled_by_default")]
+    pub enable: bool,
+    /// Users (names or UIDs) whose traffic is blocked; everyone if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Supported firewalls
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallBackend {
+    /// The packet filter of macOS
+    Pf,
+    /// nftables on Linux
+    Nftables,
+    /// iptables and ip6tables on Linux
+    Iptables,
+    /// The Windows Firewall, through netsh
+    Windows,
+}
+
+/// What the firewall blocks right now
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct FirewallState {
+    /// Firewall holding the rules
+    backend: Option<FirewallBackend>,
+    /// Reference from `pfctl -E`, if TimeGuardian enabled pf
+    pf_token: Option<String>,
+    /// Windows Firewall profiles TimeGuardian turned on ("Domain", "Private", "Public")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    windows_profiles: Vec<String>,
+    /// UIDs the rules are limited to; everyone if empty
+    #[serde(default)]
+    uids: Vec<u32>,
+    /// Lookup counters
+    #[serde(default)]
+    stats: ResolverStats,
+    /// Blocked domains and their addresses
+    #[serde(default)]
+    domains: BTreeMap<String, DomainRecord>,
+    /// Allowed domains and their addresses, which are never blocked
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    allowed: BTreeMap<String, DomainRecord>,
+}
+
+/// Addresses of a blocked domain
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct DomainRecord {
+    /// Addresses seen for the domain, oldest first
+    #[serde(default)]
+    addresses: Vec<IpAddr>,
+    /// When the domain is looked up again (its TTL)
+    refresh_at: DateTime<Utc>,
+    /// Why the last lookup failed
+    error: Option<String>,
+}
+
+impl Default for DomainRecord {
+    fn default() -> Self {
+        Self { addresses: Vec::new(), refresh_at: Utc::now(), error: None }
+    }
+}
+
+/// Counters of the DNS lookups
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct ResolverStats {
+    /// Domains looked up
+    lookups: u64,
+    /// Lookups without an answer
+    failures: u64,
+    /// When domains were last looked up
+    last_refresh: Option<DateTime<Utc>>,
+}
+
+impl FirewallState {
+    fn load() -> Result<Self> {
+        let path = state_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Could not read firewall state: {:?}", path))?;
+        toml::from_str(&content).wrap_err("Could not parse firewall state")
+    }
+
+    /// All blocked addresses, without those of allowed domains
+    fn addresses(&self) -> Vec<IpAddr> {
+        let allowed: Vec<&IpAddr> = self.allowed.values().flat_map(|record| &record.addresses).collect();
+        let mut addresses: Vec<IpAddr> = self
+            .domains
+            .values()
+            .flat_map(|record| record.addresses.clone())
+            .filter(|address| !allowed.contains(&address))
+            .collect();
+        addresses.sort();
+        addresses.dedup();
+        addresses
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = state_path()?;
+        let content = toml::to_string(self).wrap_err("Could not serialize firewall state")?;
+        fs::write(&path, content).wrap_err_with(|| format!("Could not save firewall state: {:?}", path))
+    }
+}
+
+impl FirewallBackend {
+    /// Where the rules are kept, for descriptions
+    pub fn location(&self) -> String {
+        match self {
+            FirewallBackend::Pf => format!("the pf anchor {}", PF_ANCHOR),
+            FirewallBackend::Nftables => format!("the nftables table {}", NFT_TABLE),
+            FirewallBackend::Iptables => format!("the iptables chains {} and {}", IPT_CHAIN, IPT_BLOCK_CHAIN),
+            FirewallBackend::Windows => format!("the Windows Firewall rules {}", WINDOWS_RULE),
+        }
+    }
+
+    /// The backend to use for this config: nftables falls back to iptables
+    /// where `nft` is not installed
+    pub fn available(self) -> Self {
+        if self == FirewallBackend::Nftables && !installed("nft") && installed("iptables") {
+            return FirewallBackend::Iptables;
+        }
+        self
+    }
+
+    /// Fail early on systems without this firewall
+    fn check_platform(&self) -> Result<()> {
+        match self {
+            FirewallBackend::Pf if !cfg!(target_os = "macos") => {
+                Err(eyre!("The pf firewall backend is only available on macOS"))
+            }
+            FirewallBackend::Nftables if !cfg!(target_os = "linux") => {
+                Err(eyre!("The nftables firewall backend is only available on Linux"))
+            }
+            FirewallBackend::Iptables if !cfg!(target_os = "linux") => {
+                Err(eyre!("The iptables firewall backend is only available on Linux"))
+            }
+            FirewallBackend::Windows if !cfg!(windows) => {
+                Err(eyre!("The Windows Firewall backend is only available on Windows"))
+            }
+            FirewallBackend::Pf | FirewallBackend::Nftables | FirewallBackend::Iptables | FirewallBackend::Windows => {
+                Ok(())
+            }
+        }
+    }
+
+    /// Make sure the firewall filters packets, enabling it if allowed
+    fn ensure_enabled(&self, config: &FirewallConfig, state: &mut FirewallState) -> Result<()> {
+        match self {
+            FirewallBackend::Pf => {
+                let info = run("pfctl", &["-s", "info"])?;
+                if info.contains("Status: Enabled") {
+                    return Ok(());
+                }
+                if !config.enable {
+                    return Err(eyre!(
+                        "pf is disabled. Enable it with `sudo pfctl -E` or set enable = true in [firewall]."
+                    ));
+                }
+                // pfctl reports the reference on stderr
+                let output = Command::new("pfctl").arg("-E").output().wrap_err("Could not run pfctl")?;
+                let text = String::from_utf8_lossy(&output.stderr);
+                state.pf_token = text
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Token : "))
+                    .map(|token| token.trim().to_string());
+                if !output.status.success() {
+                    return Err(eyre!("Could not enable pf: {}", text.trim()));
+                }
+                Ok(())
+            }
+            FirewallBackend::Windows => {
+                let off = windows_profiles_off()?;
+                if off.is_empty() {
+                    return Ok(());
+                }
+                if !config.enable {
+                    return Err(eyre!(
+                        "The Windows Firewall is off for the {} profile(s). Turn it on or set enable = true in [firewall].",
+                        off.join(", ")
+                    ));
+                }
+                // Only the profiles that were off, so that flushing leaves the others on
+                for profile in off {
+                    run("netsh", &["advfirewall", "set", &windows_profile_arg(&profile), "state", "on"])?;
+                    if !state.windows_profiles.contains(&profile) {
+                        state.windows_profiles.push(profile);
+                    }
+                }
+                Ok(())
+            }
+            // The rules hook into the kernel directly, nothing to enable
+            FirewallBackend::Nftables | FirewallBackend::Iptables => Ok(()),
+        }
+    }
+
+    /// Replace the rules with a block of `addresses` for the users `uids`
+    /// (everyone if empty)
+    fn load(&self, addresses: &[IpAddr], uids: &[u32]) -> Result<()> {
+        match self {
+            FirewallBackend::Pf => {
+                let list: Vec<String> = addresses.iter().map(IpAddr::to_string).collect();
+                let rules = format!(
+                    "table <timeguardian> persist {{ {} }}\nblock return out quick to <timeguardian>\n",
+                    list.join(", ")
+                );
+                let anchor_file = get_state_dir()?.join("timeguardian.pf");
+                fs::write(&anchor_file, rules)
+                    .wrap_err_with(|| format!("Could not write pf anchor file: {:?}", anchor_file))?;
+                let loaded = run("pfctl", &["-a", PF_ANCHOR, "-f", &anchor_file.to_string_lossy()]);
+                let _ = fs::remove_file(&anchor_file);
+                loaded.map(|_| ())
+            }
+            FirewallBackend::Nftables => {
+                let (v4, v6) = nft_elements(addresses);
+                let scope = if uids.is_empty() {
+                    String::new()
+                } else {
+                    let uids: Vec<String> = uids.iter().map(u32::to_string).collect();
+                    format!("meta skuid {{ {} }} ", uids.join(", "))
+                };
+                // Creating the table first lets the delete succeed on the first load
+                let script = format!(
+                    "table {table} {{}}\n\
+                     delete table {table}\n\
+                     table {table} {{\n\
+                     \tset blocked4 {{ type ipv4_addr;{v4} }}\n\
+                     \tset blocked6 {{ type ipv6_addr;{v6} }}\n\
+                     \tchain output {{\n\
+                     \t\ttype filter hook output priority filter; policy accept;\n\
+                     \t\t{scope}ip daddr @blocked4 reject\n\
+                     \t\t{scope}ip6 daddr @blocked6 reject\n\
+                     \t}}\n\
+                     }}\n",
+                    table = NFT_TABLE,
+                    v4 = v4.map(|elements| format!(" elements = {}", elements)).unwrap_or_default(),
+                    v6 = v6.map(|elements| format!(" elements = {}", elements)).unwrap_or_default(),
+                );
+                run_with_input("nft", &["-f", "-"], &script)
+            }
+            FirewallBackend::Iptables => {
+                let families = [(false, "iptables", "iptables-restore"), (true, "ip6tables", "ip6tables-restore")];
+                for (v6, tool, restore) in families {
+                    let mut script = format!("*filter\n:{} - [0:0]\n:{} - [0:0]\n", IPT_CHAIN, IPT_BLOCK_CHAIN);
+                    if uids.is_empty() {
+                        script.push_str(&format!("-A {} -j {}\n", IPT_CHAIN, IPT_BLOCK_CHAIN));
+                    }
+                    for uid in uids {
+                        script.push_str(&format!(
+                            "-A {} -m owner --uid-owner {} -j {}\n",
+                            IPT_CHAIN, uid, IPT_BLOCK_CHAIN
+                        ));
+                    }
+                    for address in addresses.iter().filter(|address| address.is_ipv6() == v6) {
+                        script.push_str(&format!("-A {} -d {} -j REJECT\n", IPT_BLOCK_CHAIN, address));
+                    }
+                    script.push_str("COMMIT\n");
+                    // Declaring the chains empties them; other chains are left alone
+                    run_with_input(restore, &["--noflush"], &script)?;
+                    if run(tool, &["-C", "OUTPUT", "-j", IPT_CHAIN]).is_err() {
+                        run(tool, &["-I", "OUTPUT", "-j", IPT_CHAIN])?;
+                    }
+                }
+                Ok(())
+            }
+            FirewallBackend::Windows => {
+                if self.loaded() {
+                    run("netsh", &["advfirewall", "firewall", "delete", "rule", &format!("name={}", WINDOWS_RULE)])?;
+                }
+                self.add(addresses)
+            }
+        }
+    }
+
+    /// Add `addresses` to the rules in place
+    fn add(&self, addresses: &[IpAddr]) -> Result<()> {
+        if addresses.is_empty() {
+            return Ok(());
+        }
+        match self {
+            FirewallBackend::Pf => {
+                let mut args = vec!["-a", PF_ANCHOR, "-t", "timeguardian", "-T", "add"];
+                let list: Vec<String> = addresses.iter().map(IpAddr::to_string).collect();
+                args.extend(list.iter().map(String::as_str));
+                run("pfctl", &args).map(|_| ())
+            }
+            FirewallBackend::Nftables => {
+                let (v4, v6) = nft_elements(addresses);
+                let script: String = [("blocked4", v4), ("blocked6", v6)]
+                    .into_iter()
+                    .filter_map(|(set, elements)| Some(format!("add element {} {} {}\n", NFT_TABLE, set, elements?)))
+                    .collect();
+                run_with_input("nft", &["-f", "-"], &script)
+            }
+            FirewallBackend::Iptables => {
+                for address in addresses {
+                    let tool = if address.is_ipv6() { "ip6tables" } else { "iptables" };
+                    run(tool, &["-A", IPT_BLOCK_CHAIN, "-d", &address.to_string(), "-j", "REJECT"])?;
+                }
+                Ok(())
+            }
+            FirewallBackend::Windows => {
+                for chunk in addresses.chunks(WINDOWS_RULE_ADDRESSES) {
+                    let list: Vec<String> = chunk.iter().map(IpAddr::to_string).collect();
+                    run(
+                        "netsh",
+                        &[
+                            "advfirewall",
+                            "firewall",
+                            "add",
+                            "rule",
+                            &format!("name={}", WINDOWS_RULE),
+                            "dir=out",
+                            "action=block",
+                            &format!("remoteip={}", list.join(",")),
+                        ],
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the rules are still in place
+    fn loaded(&self) -> bool {
+        match self {
+            FirewallBackend::Pf => {
+                run("pfctl", &["-a", PF_ANCHOR, "-s", "rules"]).is_ok_and(|rules| !rules.trim().is_empty())
+            }
+            FirewallBackend::Nftables => run("nft", &["list", "table", "inet", "timeguardian"]).is_ok(),
+            // Both families are loaded together; with one missing the rules are loaded again
+            FirewallBackend::Iptables => ["iptables", "ip6tables"]
+                .iter()
+                .all(|tool| run(tool, &["-C", "OUTPUT", "-j", IPT_CHAIN]).is_ok()),
+            // netsh fails when no rule has the name
+            FirewallBackend::Windows => {
+                run("netsh", &["advfirewall", "firewall", "show", "rule", &format!("name={}", WINDOWS_RULE)]).is_ok()
+            }
+        }
+    }
+
+    /// Whether any of the rules are left, even if only some of them
+    fn leftover(&self) -> bool {
+        match self {
+            // A crash or a reboot can leave the chain of one family behind
+            FirewallBackend::Iptables => ["iptables", "ip6tables"]
+                .iter()
+                .any(|tool| run(tool, &["-n", "-L", IPT_CHAIN]).is_ok()),
+            FirewallBackend::Pf | FirewallBackend::Nftables | FirewallBackend::Windows => self.loaded(),
+        }
+    }
+
+    /// Remove the rules and undo enabling the firewall
+    fn flush(&self, state: &FirewallState) -> Result<()> {
+        match self {
+            FirewallBackend::Pf => {
+                run("pfctl", &["-a", PF_ANCHOR, "-F", "all"])?;
+                if let Some(token) = &state.pf_token {
+                    run("pfctl", &["-X", token])?;
+                }
+                Ok(())
+            }
+            FirewallBackend::Nftables => {
+                if self.loaded() {
+                    run("nft", &["delete", "table", "inet", "timeguardian"])?;
+                }
+                Ok(())
+            }
+            FirewallBackend::Iptables => {
+                for tool in ["iptables", "ip6tables"] {
+                    // Chains that don't exist (any more) fail; nothing to remove then
+                    while run(tool, &["-D", "OUTPUT", "-j", IPT_CHAIN]).is_ok() {}
+                    for chain in [IPT_CHAIN, IPT_BLOCK_CHAIN] {
+                        let _ = run(tool, &["-F", chain]);
+                    }
+                    for chain in [IPT_CHAIN, IPT_BLOCK_CHAIN] {
+                        let _ = run(tool, &["-X", chain]);
+                    }
+                }
+                Ok(())
+            }
+            FirewallBackend::Windows => {
+                if self.loaded() {
+                    run("netsh", &["advfirewall", "firewall", "delete", "rule", &format!("name={}", WINDOWS_RULE)])?;
+                }
+                for profile in &state.windows_profiles {
+                    run("netsh", &["advfirewall", "set", &windows_profile_arg(profile), "state", "off"])?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Block the addresses of `domains` in the configured firewall, except those
+/// of the `allowed` domains, or remove the block if `domains` is empty or no
+/// firewall is configured.
+///
+/// Domains whose records expired are resolved again; their new addresses
+/// are added to the block.
+pub fn apply(domains: &[String], allowed: &[String]) -> Result<()> {
+    let config = load_config()?.firewall;
+    let mut state = FirewallState::load()?;
+
+    // Remove the rules of a firewall that is no longer used
+    if let Some(active) = state.backend
+        && (domains.is_empty() || config.as_ref().is_none_or(|config| config.backend.available() != active))
+    {
+        journal::change_firewall("flush", active, || active.flush(&state))?;
+        state = FirewallState { stats: state.stats, ..FirewallState::default() };
+        state.save()?;
+    }
+    let Some(config) = config else {
+        return Ok(());
+    };
+    if domains.is_empty() {
+        return Ok(());
+    }
+
+    let backend = config.backend.available();
+    if !config.users.is_empty() && matches!(backend, FirewallBackend::Pf | FirewallBackend::Windows) {
+        return Err(eyre!("users in [firewall] needs the nftables or iptables backend"));
+    }
+    let uids = config
+        .users
+        .iter()
+        .map(|user| uid_of(user).ok_or_else(|| eyre!("Unknown user '{}' in [firewall] users", user)))
+        .collect::<Result<Vec<u32>>>()?;
+    let now = Utc::now();
+    let unchanged = state.backend == Some(backend)
+        && state.uids == uids
+        && state.domains.keys().eq(domains.iter())
+        && state.allowed.keys().eq(allowed.iter());
+    let loaded = unchanged && backend.loaded();
+    let due = |records: &BTreeMap<String, DomainRecord>, domains: &[String]| -> Vec<String> {
+        domains
+            .iter()
+            .filter(|domain| records.get(*domain).is_none_or(|record| record.refresh_at <= now))
+            .cloned()
+            .collect()
+    };
+    let (due_blocked, due_allowed) = (due(&state.domains, domains), due(&state.allowed, allowed));
+    if loaded && due_blocked.is_empty() && due_allowed.is_empty() {
+        return Ok(());
+    }
+    backend.check_platform()?;
+
+    let before = state.addresses();
+    state.domains.retain(|domain, _| domains.contains(domain));
+    state.allowed.retain(|domain, _| allowed.contains(domain));
+    refresh(&mut state.domains, &mut state.stats, &due_blocked, config.resolver)?;
+    refresh(&mut state.allowed, &mut state.stats, &due_allowed, config.resolver)?;
+    let after = state.addresses();
+    // Addresses an allowed domain newly resolved to can only leave the block by reloading it
+    if loaded && before.iter().all(|address| after.contains(address)) {
+        let added: Vec<IpAddr> = after.into_iter().filter(|address| !before.contains(address)).collect();
+        journal::change_firewall("add", backend, || backend.add(&added))?;
+    } else {
+        journal::change_firewall("load", backend, || {
+            backend.ensure_enabled(&config, &mut state)?;
+            state.backend = Some(backend);
+            state.uids = uids;
+            state.save()?;
+            backend.load(&state.addresses(), &state.uids)
+        })?;
+    }
+    state.save()
+}
+
+/// Flush the rules of `backend` after a change to them was interrupted,
+/// and forget them, so that the next `apply` loads the block from scratch
+pub fn roll_back(backend: FirewallBackend) -> Result<()> {
+    let state = FirewallState::load().unwrap_or_default();
+    if backend.check_platform().is_ok() {
+        backend.flush(&state)?;
+    }
+    FirewallState { stats: state.stats, ..FirewallState::default() }.save()
+}
+
+/// Remove the rules of every firewall of this platform, without looking at
+/// the config (`timeguardian rescue`); returns what was done
+pub fn rescue() -> Vec<String> {
+    let state = FirewallState::load().unwrap_or_default();
+    let mut done = Vec::new();
+    let backends = [FirewallBackend::Pf, FirewallBackend::Nftables, FirewallBackend::Iptables, FirewallBackend::Windows];
+    for backend in backends {
+        if backend.check_platform().is_err() || !backend.leftover() {
+            continue;
+        }
+        match backend.flush(&state) {
+            Ok(()) => done.push(format!("Removed {}.", backend.location())),
+            Err(error) => done.push(format!("Could not remove {}: {}", backend.location(), error)),
+        }
+    }
+    if let Ok(path) = state_path()
+        && path.exists()
+        && fs::remove_file(&path).is_ok()
+    {
+        done.push("Cleared the firewall state.".to_string());
+    }
+    done
+}
+
+/// Whether the firewall alone enforces blocks: it is limited to `users`,
+/// which the hosts file can't be
+pub fn firewall_only() -> bool {
+    load_config()
+        .ok()
+        .and_then(|config| config.firewall)
+        .is_some_and(|firewall| !firewall.users.is_empty())
+}
+
+/// Resolver statistics for `status --verbose`, if a firewall is configured
+pub fn status_lines() -> Result<Vec<String>> {
+    let Some(config) = load_config()?.firewall else {
+        return Ok(Vec::new());
+    };
+    let state = FirewallState::load()?;
+    let servers = resolve::upstream_servers(config.resolver);
+    let servers: Vec<String> = servers.iter().map(IpAddr::to_string).collect();
+    let stats = &state.stats;
+
+    let mut lines = vec![
+        format!("Firewall: {}", config.backend.available().location()),
+        format!(
+            "  {} domain(s), {} address(es) blocked",
+            state.domains.len(),
+            state.addresses().len()
+        ),
+        format!(
+            "  DNS servers: {}",
+            if servers.is_empty() { "none found".to_string() } else { servers.join(", ") }
+        ),
+        format!("  Lookups: {} ({} failed)", stats.lookups, stats.failures),
+    ];
+    if !state.allowed.is_empty() {
+        lines.push(format!("  {} allowed domain(s) keep their addresses reachable", state.allowed.len()));
+    }
+    if !config.users.is_empty() {
+        lines.push(format!("  Only blocks the users: {}", config.users.join(", ")));
+    }
+    if let Some(at) = stats.last_refresh {
+        lines.push(format!("  Last refresh: {}", at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")));
+    }
+    if let Some(next) = state.domains.values().map(|record| record.refresh_at).min() {
+        lines.push(format!("  Next refresh: {}", next.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")));
+    }
+    for (domain, record) in &state.domains {
+        if record.addresses.len() >= MANY_ADDRESSES {
+            lines.push(format!("  {} has {} addresses (CDN)", domain, record.addresses.len()));
+        }
+        if let Some(error) = &record.error {
+            lines.push(format!("  {}: {}", domain, error));
+        }
+    }
+    Ok(lines)
+}
+
+/// Look up the `due` domains again and record their addresses in `records`.
+///
+/// Addresses of earlier answers are kept up to a limit, since sites rotate
+/// between them; a failed lookup keeps the old addresses and is retried soon.
+fn refresh(
+    records: &mut BTreeMap<String, DomainRecord>,
+    stats: &mut ResolverStats,
+    due: &[String],
+    configured: Option<IpAddr>,
+) -> Result<()> {
+    if due.is_empty() {
+        return Ok(());
+    }
+    let servers = resolve::upstream_servers(configured);
+    if servers.is_empty() {
+        return Err(eyre!("No DNS server found to resolve the blocked domains; set resolver in [firewall]"));
+    }
+
+    let now = Utc::now();
+    for chunk in due.chunks(RESOLVE_PARALLELISM) {
+        let answers: Vec<(&String, Result<resolve::Resolved>)> = thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|domain| (domain, scope.spawn(|| resolve::resolve(domain, &servers))))
+                .collect();
+            handles
+                .into_iter()
+                .map(|(domain, handle)| {
+                    let answer = handle.join().unwrap_or_else(|_| Err(eyre!("The lookup crashed")));
+                    (domain, answer)
+                })
+                .collect()
+        });
+
+        for (domain, answer) in answers {
+            let record = records.entry(domain.clone()).or_default();
+            stats.lookups += 1;
+            match answer {
+                Ok(resolved) => {
+                    // Never cut off the machine from the network
+                    let fresh = resolved
+                        .addresses
+                        .into_iter()
+                        .filter(|address| !address.is_loopback() && !address.is_unspecified());
+                    for address in fresh {
+                        record.addresses.retain(|known| *known != address);
+                        record.addresses.push(address);
+                    }
+                    // The oldest addresses go first
+                    let excess = record.addresses.len().saturating_sub(MAX_ADDRESSES_PER_DOMAIN);
+                    record.addresses.drain(..excess);
+                    let ttl = resolved.ttl.clamp(MIN_REFRESH, MAX_REFRESH);
+                    record.refresh_at = now + chrono::Duration::from_std(ttl).unwrap_or_default();
+                    record.error = None;
+                }
+                Err(error) => {
+                    stats.failures += 1;
+                    record.refresh_at = now + chrono::Duration::from_std(MIN_REFRESH).unwrap_or_default();
+                    record.error = Some(error.to_string());
+                }
+            }
+        }
+    }
+    stats.last_refresh = Some(now);
+    Ok(())
+}
+
+/// UID of a user given by name or number
+#[cfg(unix)]
+fn uid_of(user: &str) -> Option<u32> {
+    if let Ok(uid) = user.parse() {
+        return Some(uid);
+    }
+    let name = std::ffi::CString::new(user).ok()?;
+    let entry = unsafe { libc::getpwnam(name.as_ptr()) };
+    (!entry.is_null()).then(|| unsafe { (*entry).pw_uid })
+}
+
+/// Without a user database only UIDs are understood
+#[cfg(not(unix))]
+fn uid_of(user: &str) -> Option<u32> {
+    user.parse().ok()
+}
+
+/// Addresses for nftables sets: the IPv4 and the IPv6 ones as "{ a, b }",
+/// `None` where there are none
+fn nft_elements(addresses: &[IpAddr]) -> (Option<String>, Option<String>) {
+    let elements = |v6: bool| {
+        let list: Vec<String> = addresses
+            .iter()
+            .filter(|address| address.is_ipv6() == v6)
+            .map(IpAddr::to_string)
+            .collect();
+        (!list.is_empty()).then(|| format!("{{ {} }}", list.join(", ")))
+    };
+    (elements(false), elements(true))
+}
+
+/// Windows Firewall profiles that are turned off, by name ("Domain",
+/// "Private", "Public").
+///
+/// Asks PowerShell, whose profile names and flags don't depend on the
+/// language of Windows, unlike the output of netsh.
+fn windows_profiles_off() -> Result<Vec<String>> {
+    let output = run(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "Get-NetFirewallProfile | ForEach-Object { \"$($_.Name) $([int]$_.Enabled)\" }",
+        ],
+    )?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(_, enabled)| enabled.trim() == "0")
+        .map(|(name, _)| name.trim().to_string())
+        .collect())
+}
+
+/// netsh name of a Windows Firewall profile, e.g. "domainprofile"
+fn windows_profile_arg(profile: &str) -> String {
+    format!("{}profile", profile.to_lowercase())
+}
+
+/// Whether a firewall tool is installed
+fn installed(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Run a firewall tool with `input` on stdin
+fn run_with_input(program: &str, args: &[&str], input: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .wrap_err_with(|| format!("Could not run {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Run a firewall tool, returning its output
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .wrap_err_with(|| format!("Could not run {}", program))?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(FIREWALL_STATE_FILE))
+}
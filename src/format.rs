@@ -0,0 +1,169 @@
+/*
+* TimeGuardian Format Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module formats durations and times of day for the TUI, the CLI
+* countdown, `attach`, the session widget, notifications and reports. Times
+* of day follow the 12- or 24-hour clock of the locale unless the config
+* picks one.
+*/
+
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 12- or 24-hour clock for times of day
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClockStyle {
+    /// "14:30"
+    #[default]
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+    /// "2:30 PM"
+    #[serde(rename = "12h")]
+    TwelveHour,
+}
+
+impl ClockStyle {
+    /// The clock of the locale in `LC_ALL`, `LC_TIME` or `LANG`
+    pub fn from_locale() -> Self {
+        let locale = ["LC_ALL", "LC_TIME", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        Self::for_locale(&locale)
+    }
+
+    /// The clock of a locale like "en_US.UTF-8"
+    pub fn for_locale(locale: &str) -> Self {
+        // Regions that write times of day on a 12-hour clock
+        const TWELVE_HOUR: &[&str] = &["US", "CA", "AU", "NZ", "PH", "IN", "PK", "EG", "SA"];
+        let region = locale.split(['.', '@']).next().unwrap_or_default().split(['_', '-']).nth(1);
+        match region {
+            Some(region) if TWELVE_HOUR.contains(&region) => Self::TwelveHour,
+            _ => Self::TwentyFourHour,
+        }
+    }
+}
+
+/// A time of day, e.g. "14:30" or "2:30 PM"
+pub fn time_of_day(time: &impl Timelike, style: ClockStyle) -> String {
+    match style {
+        ClockStyle::TwentyFourHour => format!("{:02}:{:02}", time.hour(), time.minute()),
+        ClockStyle::TwelveHour => {
+            let (pm, hour) = time.hour12();
+            format!("{}:{:02} {}", hour, time.minute(), if pm { "PM" } else { "AM" })
+        }
+    }
+}
+
+/// Remaining time as "HH:MM:SS"
+pub fn clock(remaining: Duration) -> String {
+    let secs = remaining.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
+
+/// A duration in the largest units, e.g. "1h 05m 00s" or "45s"
+pub fn human(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// A duration in its largest whole unit, as accepted by `-d` (e.g. "25m", "2h", "90s")
+pub fn short(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs > 0 && secs.is_multiple_of(3600) {
+        format!("{}h", secs / 3600)
+    } else if secs > 0 && secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// A duration in hours and minutes for totals, e.g. "12h 30m"
+pub fn hours_minutes(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+/// Duration in words for screen readers (e.g. "1 hour 5 minutes")
+pub fn spoken(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let plural = |n: u64, unit: &str| format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" });
+
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    match (hours, minutes) {
+        (0, 0) => plural(seconds, "second"),
+        (0, _) => plural(minutes, "minute"),
+        (_, 0) => plural(hours, "hour"),
+        _ => format!("{} {}", plural(hours, "hour"), plural(minutes, "minute")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_formatted_in_every_style() {
+        // Seconds, then clock, human, short, hours_minutes and spoken
+        let cases = [
+            (0, "00:00:00", "0s", "0s", "0h 00m", "0 seconds"),
+            (1, "00:00:01", "1s", "1s", "0h 00m", "1 second"),
+            (59, "00:00:59", "59s", "59s", "0h 00m", "59 seconds"),
+            (25 * 60, "00:25:00", "25m 00s", "25m", "0h 25m", "25 minutes"),
+            (3600, "01:00:00", "1h 00m 00s", "1h", "1h 00m", "1 hour"),
+            (3600 + 5 * 60, "01:05:00", "1h 05m 00s", "65m", "1h 05m", "1 hour 5 minutes"),
+            (25 * 3600 + 61, "25:01:01", "25h 01m 01s", "90061s", "25h 01m", "25 hours 1 minute"),
+            (48 * 3600, "48:00:00", "48h 00m 00s", "48h", "48h 00m", "48 hours"),
+        ];
+        for (secs, clock, human, short, hours_minutes, spoken) in cases {
+            let duration = Duration::from_secs(secs);
+            assert_eq!(super::clock(duration), clock, "{}s", secs);
+            assert_eq!(super::human(duration), human, "{}s", secs);
+            assert_eq!(super::short(duration), short, "{}s", secs);
+            assert_eq!(super::hours_minutes(duration), hours_minutes, "{}s", secs);
+            assert_eq!(super::spoken(duration), spoken, "{}s", secs);
+        }
+    }
+
+    #[test]
+    fn times_of_day_follow_the_clock_style() {
+        let cases = [
+            ((0, 5), "00:05", "12:05 AM"),
+            ((9, 30), "09:30", "9:30 AM"),
+            ((12, 0), "12:00", "12:00 PM"),
+            ((23, 59), "23:59", "11:59 PM"),
+        ];
+        for ((hour, minute), twenty_four, twelve) in cases {
+            let time = chrono::NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+            assert_eq!(time_of_day(&time, ClockStyle::TwentyFourHour), twenty_four);
+            assert_eq!(time_of_day(&time, ClockStyle::TwelveHour), twelve);
+        }
+    }
+
+    #[test]
+    fn locales_pick_their_clock() {
+        let cases = [
+            ("en_US.UTF-8", ClockStyle::TwelveHour),
+            ("en-CA", ClockStyle::TwelveHour),
+            ("hi_IN@latin", ClockStyle::TwelveHour),
+            ("en_GB.UTF-8", ClockStyle::TwentyFourHour),
+            ("de_DE.UTF-8", ClockStyle::TwentyFourHour),
+            ("C.UTF-8", ClockStyle::TwentyFourHour),
+            ("", ClockStyle::TwentyFourHour),
+        ];
+        for (locale, style) in cases {
+            assert_eq!(ClockStyle::for_locale(locale), style, "{:?}", locale);
+        }
+    }
+}
@@ -0,0 +1,507 @@
+/*
+* TimeGuardian History Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module records finished and running sessions in the state directory,
+* so a previous session configuration can be repeated (`timeguardian again`).
+* Each record says how the session ended, planned or not: it ran out, was
+* stopped in place or remotely, was reset, or its process crashed.
+*
+* The records form a hash chain: each one stores the SHA-256 of its content
+* and the hash of the record before it. Editing or deleting a record by hand
+* breaks the chain from there on, which `timeguardian stats verify` reports.
+* The hash of the newest record is also kept in a file of its own, so
+* removing the last sessions is noticed as well.
+* TimeGuardian re-seals records it changes itself (e.g. when a session ends),
+* but never records whose seal was already broken. Records written by older
+* versions have no hash and are reported as not covered.
+*/
+
+use chrono::{DateTime, Local};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf, time::Duration};
+use timeguardian::format;
+
+use crate::get_state_dir;
+use crate::hosts::Priority;
+
+/// File in the state directory holding the session history
+pub const HISTORY_FILE: &str = "history.toml";
+/// File in the state directory holding the id and hash of the newest record
+pub const HISTORY_HEAD_FILE: &str = "history.head";
+
+/// A session as it was started
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionRecord {
+    /// Identifier used by `again --from <id>`
+    pub id: u64,
+    /// When the session started
+    pub started_at: DateTime<Local>,
+    /// Length of the session in seconds
+    pub duration_secs: u64,
+    /// Task of the session
+    pub task: String,
+    /// Names of the blocked website lists; all lists if empty
+    #[serde(default)]
+    pub lists: Vec<String>,
+    /// Priority of the session
+    #[serde(default)]
+    pub priority: Priority,
+    /// Whether the session ran until the end; unset while it is running
+    pub completed: Option<bool>,
+    /// What success looks like, as entered at the start
+    pub intent: Option<String>,
+    /// Whether the intent was achieved, as answered at the end
+    pub achieved: Option<Achievement>,
+    /// Whether the websites were actually blocked
+    #[serde(default)]
+    pub enforcement: Enforcement,
+    /// ULID of the session, also written to the hosts file and the IPC
+    /// status; unset for sessions recorded by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Checklist items ticked off before the session started
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checklist: Vec<String>,
+    /// How the session ended; unset while it is running and for sessions
+    /// recorded by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_by: Option<EndReason>,
+    /// Seal over the record and the seal of the record before it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// Whether a session blocked its websites
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Enforcement {
+    /// The websites were blocked
+    #[default]
+    Blocked,
+    /// Only the timer ran, as requested (`--timer-only`)
+    TimerOnly,
+    /// The websites should have been blocked, but the hosts file was not writable
+    Unenforced,
+}
+
+impl Enforcement {
+    /// Note for listings; empty for blocked sessions
+    pub fn note(&self) -> &'static str {
+        match self {
+            Self::Blocked => "",
+            Self::TimerOnly => "timer only",
+            Self::Unenforced => "unenforced, no permissions",
+        }
+    }
+}
+
+/// How a session ended, in the order of reports
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum EndReason {
+    /// The session ran until its end
+    Expired,
+    /// Stopped early where it ran: a key in the CLI timer or the TUI
+    Stopped,
+    /// Stopped early over IPC: `timeguardian stop`, another TUI, an editor plugin
+    StoppedRemotely,
+    /// Ended by `timeguardian reset`
+    Reset,
+    /// Its process went away without ending it; the block was removed after it expired
+    Crashed,
+}
+
+impl EndReason {
+    /// Short description for listings
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Expired => "completed",
+            Self::Stopped => "stopped early",
+            Self::StoppedRemotely => "stopped remotely",
+            Self::Reset => "ended by reset",
+            Self::Crashed => "crashed",
+        }
+    }
+}
+
+/// How well the intent of a session was achieved
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Achievement {
+    Yes,
+    Partial,
+    No,
+}
+
+impl Achievement {
+    /// Parse an answer like "y", "partial" or "no"
+    pub fn from_answer(answer: &str) -> Option<Self> {
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => Some(Self::Yes),
+            "p" | "partial" | "partly" => Some(Self::Partial),
+            "n" | "no" => Some(Self::No),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Yes => "achieved",
+            Self::Partial => "partly achieved",
+            Self::No => "not achieved",
+        }
+    }
+}
+
+impl SessionRecord {
+    /// Whether the session is still running, going by its planned length
+    pub fn running(&self) -> bool {
+        self.completed.is_none() && Local::now() < self.started_at + chrono::Duration::seconds(self.duration_secs as i64)
+    }
+
+    /// Length of the session
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration_secs)
+    }
+
+    /// Length of the session in the `-d` format (e.g. "25m")
+    pub fn duration_text(&self) -> String {
+        format::short(self.duration())
+    }
+
+    /// Short outcome description for listings
+    pub fn outcome(&self) -> &'static str {
+        if let Some(ended_by) = self.ended_by {
+            return ended_by.label();
+        }
+        match self.completed {
+            Some(true) => "completed",
+            Some(false) => "stopped early",
+            None => "running",
+        }
+    }
+}
+
+/// Result of checking the hash chain of the history
+#[derive(Clone, Debug, Default)]
+pub struct Verification {
+    /// Records covered by the chain
+    pub sealed: usize,
+    /// Leading records written before the chain existed
+    pub unsealed: usize,
+    /// Records whose seal doesn't match: edited, or a record before them was removed
+    pub broken: Vec<u64>,
+    /// The newest records were removed: the last record is not the one sealed last
+    pub truncated: bool,
+}
+
+/// All recorded sessions, oldest first
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct History {
+    #[serde(default)]
+    pub sessions: Vec<SessionRecord>,
+}
+
+impl History {
+    /// Load the history from the state directory
+    pub fn load() -> Result<Self> {
+        let path = history_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Could not read session history: {:?}", path))?;
+        toml::from_str(&content).wrap_err("Could not parse session history")
+    }
+
+    /// Add a session at the end of the chain
+    pub fn push(&mut self, mut record: SessionRecord) {
+        let previous = self.sessions.last().and_then(|last| last.hash.as_deref()).unwrap_or_default();
+        record.hash = Some(seal(previous, &record));
+        self.sessions.push(record);
+    }
+
+    /// Change the session `id` and re-seal the chain after it up to the next
+    /// record that was already broken; a broken record stays broken
+    fn update(&mut self, id: u64, change: impl FnOnce(&mut SessionRecord)) -> bool {
+        let Some(index) = self.sessions.iter().position(|record| record.id == id) else {
+            return false;
+        };
+        let broken = self.broken_indices();
+        change(&mut self.sessions[index]);
+
+        let unsealed = self.unsealed();
+        if index < unsealed || broken.contains(&index) {
+            return true;
+        }
+        let end = broken.iter().copied().find(|broken| *broken > index).unwrap_or(self.sessions.len());
+        for index in index..end {
+            let previous = self.previous_hash(index, unsealed).to_string();
+            self.sessions[index].hash = Some(seal(&previous, &self.sessions[index]));
+        }
+        true
+    }
+
+    /// Check the hash chain
+    pub fn verify(&self) -> Verification {
+        let unsealed = self.unsealed();
+        let broken: Vec<u64> = self.broken_indices().into_iter().map(|index| self.sessions[index].id).collect();
+        let last = self.sessions.last().and_then(|last| Some(head_line(last.id, last.hash.as_deref()?)));
+        let head = fs::read_to_string(head_path().unwrap_or_default()).ok();
+        Verification {
+            sealed: self.sessions.len() - unsealed,
+            unsealed,
+            broken,
+            truncated: match head {
+                Some(head) => Some(head.trim()) != last.as_deref(),
+                None => last.is_some(),
+            },
+        }
+    }
+
+    /// Number of leading records without a hash, from before the chain existed
+    fn unsealed(&self) -> usize {
+        self.sessions.iter().take_while(|record| record.hash.is_none()).count()
+    }
+
+    /// Hash the record at `index` is chained to; the chain starts after the unsealed records
+    fn previous_hash(&self, index: usize, unsealed: usize) -> &str {
+        if index <= unsealed {
+            return "";
+        }
+        self.sessions[index - 1].hash.as_deref().unwrap_or_default()
+    }
+
+    /// Indices of the sealed records whose hash doesn't match
+    fn broken_indices(&self) -> Vec<usize> {
+        let unsealed = self.unsealed();
+        (unsealed..self.sessions.len())
+            .filter(|index| {
+                let record = &self.sessions[*index];
+                record.hash.as_deref() != Some(seal(self.previous_hash(*index, unsealed), record).as_str())
+            })
+            .collect()
+    }
+
+    /// Save the history to the state directory
+    pub fn save(&self) -> Result<()> {
+        let path = history_path()?;
+        let content = toml::to_string(self).wrap_err("Could not serialize session history")?;
+        fs::write(&path, content).wrap_err_with(|| format!("Could not save session history: {:?}", path))?;
+
+        if let Some(last) = self.sessions.last()
+            && let Some(hash) = &last.hash
+        {
+            let path = head_path()?;
+            fs::write(&path, head_line(last.id, hash)).wrap_err_with(|| format!("Could not save {:?}", path))?;
+        }
+        Ok(())
+    }
+
+    /// The most recent session
+    pub fn last(&self) -> Option<&SessionRecord> {
+        self.sessions.last()
+    }
+
+    /// Number of ended sessions per reason; sessions of older versions are
+    /// counted as expired or stopped
+    pub fn end_counts(&self) -> BTreeMap<EndReason, usize> {
+        let mut counts = BTreeMap::new();
+        for record in &self.sessions {
+            let ended_by = record.ended_by.or(match record.completed {
+                Some(true) => Some(EndReason::Expired),
+                Some(false) => Some(EndReason::Stopped),
+                None => None,
+            });
+            if let Some(ended_by) = ended_by {
+                *counts.entry(ended_by).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Number of reflected sessions per answer: (yes, partial, no)
+    pub fn achievement_counts(&self) -> (usize, usize, usize) {
+        let count = |answer| {
+            self.sessions
+                .iter()
+                .filter(|record| record.achieved == Some(answer))
+                .count()
+        };
+        (count(Achievement::Yes), count(Achievement::Partial), count(Achievement::No))
+    }
+
+    /// Find a session by its identifier
+    pub fn find(&self, id: u64) -> Result<&SessionRecord> {
+        self.sessions
+            .iter()
+            .find(|record| record.id == id)
+            .ok_or_else(|| eyre!("No session with id {} in the history", id))
+    }
+
+    /// Find a session by its history id ("54" or "#54") or by its ULID or
+    /// an unambiguous start of it
+    pub fn find_by_reference(&self, reference: &str) -> Result<&SessionRecord> {
+        let reference = reference.trim();
+        if let Ok(id) = reference.trim_start_matches('#').parse() {
+            return self.find(id);
+        }
+        if !crate::ulid::is_prefix(reference) {
+            return Err(eyre!("'{}' is neither a history id nor a session ID", reference));
+        }
+
+        let matches: Vec<&SessionRecord> = self
+            .sessions
+            .iter()
+            .filter(|record| {
+                record
+                    .session_id
+                    .as_deref()
+                    .is_some_and(|session_id| session_id.starts_with(&reference.to_uppercase()))
+            })
+            .collect();
+        match matches.as_slice() {
+            [record] => Ok(record),
+            [] => Err(eyre!("No session with ID {} in the history", reference)),
+            _ => Err(eyre!("{} sessions have IDs starting with {}; give more characters", matches.len(), reference)),
+        }
+    }
+}
+
+/// Path of the history file
+fn history_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(HISTORY_FILE))
+}
+
+/// Path of the file with the newest seal
+fn head_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(HISTORY_HEAD_FILE))
+}
+
+/// Content of the head file for the newest record
+fn head_line(id: u64, hash: &str) -> String {
+    format!("{} {}", id, hash)
+}
+
+/// Record a session that just started and return its identifier
+pub fn record(
+    session_id: &str,
+    task: &str,
+    lists: &[String],
+    priority: Priority,
+    duration: Duration,
+    intent: Option<&str>,
+    enforcement: Enforcement,
+) -> Result<u64> {
+    let mut history = History::load()?;
+    let id = history.sessions.iter().map(|record| record.id).max().unwrap_or(0) + 1;
+
+    history.push(SessionRecord {
+        id,
+        started_at: Local::now(),
+        duration_secs: duration.as_secs(),
+        task: task.to_string(),
+        lists: lists.to_vec(),
+        priority,
+        completed: None,
+        intent: intent.map(str::to_string),
+        achieved: None,
+        enforcement,
+        session_id: Some(session_id.to_string()),
+        checklist: Vec::new(),
+        ended_by: None,
+        hash: None,
+    });
+    history.save()?;
+
+    Ok(id)
+}
+
+/// Store how a recorded session ended. A session ended by `reset` keeps
+/// that, even if its process only notices the end later.
+pub fn finish(id: u64, ended_by: EndReason) -> Result<()> {
+    close(id, ended_by, None)
+}
+
+/// Store how a recorded session ended and how long it actually ran, for
+/// sessions whose length isn't known up front (`timeguardian wrap`)
+pub fn finish_after(id: u64, ended_by: EndReason, duration: Duration) -> Result<()> {
+    close(id, ended_by, Some(duration))
+}
+
+fn close(id: u64, ended_by: EndReason, duration: Option<Duration>) -> Result<()> {
+    let mut history = History::load()?;
+    if history.find(id).is_ok_and(|record| record.ended_by == Some(EndReason::Reset)) {
+        return Ok(());
+    }
+    let changed = history.update(id, |record| {
+        end(record, ended_by);
+        if let Some(duration) = duration {
+            record.duration_secs = duration.as_secs();
+        }
+    });
+    if changed {
+        history.save()?;
+    }
+    Ok(())
+}
+
+/// Store how the sessions with the given session IDs ended, unless they
+/// already did (e.g. expired just before being healed); returns the number
+/// of sessions ended
+pub fn end_sessions(session_ids: &[String], ended_by: EndReason) -> Result<usize> {
+    let mut history = History::load()?;
+    let ids: Vec<u64> = history
+        .sessions
+        .iter()
+        .filter(|record| record.completed.is_none() && record.ended_by.is_none())
+        .filter(|record| record.session_id.as_ref().is_some_and(|session_id| session_ids.contains(session_id)))
+        .map(|record| record.id)
+        .collect();
+    for id in &ids {
+        history.update(*id, |record| end(record, ended_by));
+    }
+    if !ids.is_empty() {
+        history.save()?;
+    }
+    Ok(ids.len())
+}
+
+fn end(record: &mut SessionRecord, ended_by: EndReason) {
+    record.completed = Some(ended_by == EndReason::Expired);
+    record.ended_by = Some(ended_by);
+}
+
+/// Store the checklist items ticked off before a recorded session
+pub fn tick_off(id: u64, checklist: &[String]) -> Result<()> {
+    let mut history = History::load()?;
+    if history.update(id, |record| record.checklist = checklist.to_vec()) {
+        history.save()?;
+    }
+    Ok(())
+}
+
+/// Store whether the intent of a recorded session was achieved
+pub fn reflect(id: u64, achieved: Achievement) -> Result<()> {
+    let mut history = History::load()?;
+    if history.update(id, |record| record.achieved = Some(achieved)) {
+        history.save()?;
+    }
+    Ok(())
+}
+
+/// SHA-256 over the hash of the previous record and the content of `record`
+fn seal(previous: &str, record: &SessionRecord) -> String {
+    let content = SessionRecord { hash: None, ..record.clone() };
+    // JSON keeps the field order of the struct, so the bytes are stable
+    let json = serde_json::to_string(&content).unwrap_or_default();
+    let hash = digest(&SHA256, format!("{}\n{}", previous, json).as_bytes());
+    hash.as_ref().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,1297 @@
+/*
+* TimeGuardian Hosts Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module owns the TimeGuardian section of the system hosts file.
+* Every blocking source (CLI session, TUI session, ...) registers a claim on a set
+* of domains, and the reconciler rewrites the marker block from the union of all
+* active claims. Claims carry a priority, so a lower-priority source releasing its
+* block never unblocks domains that a higher-priority source still owns. A
+* claim sharing a domain with a lower-priority claim that ends earlier takes
+* that claim over: it blocks all of its domains until it ends itself, so a
+* critical deadline extends an ordinary scheduled block instead of only
+* outranking it.
+*
+* Each claim is written as a metadata comment followed by the domains it owns,
+* so the sessions behind a block can be reconstructed from the hosts file alone
+* (e.g. after a crash):
+*
+*   # tg-session source=cli-1234 priority=normal started=<RFC 3339> expires=<RFC 3339> task=Thesis
+*   0.0.0.0	example.com
+*   ::	example.com
+*
+* Blocked domains point to 0.0.0.0, which fails immediately instead of
+* reaching a local web server on port 80. `sinkhole = "127.0.0.1"` or any
+* other address in the config changes that. Every domain gets an IPv6 line
+* as well, or dual-stack systems would still load it over its AAAA records:
+* `::` for 0.0.0.0, `::1` for a loopback sinkhole.
+*
+* A list entry can carry daily windows in local time during which the domain
+* stays reachable even while the list is blocked, e.g. "youtube.com @19:00-21:00".
+*
+* An "allow:" entry lets one of the variants a block entry expands to through,
+* e.g. "allow:m.reddit.com" next to "reddit.com" keeps the mobile site
+* reachable while reddit.com, www.reddit.com and app.reddit.com stay blocked.
+* Only those exact variants can be allowed; other subdomains such as
+* mail.google.com next to "google.com" are never blocked to begin with.
+* Within a session an allow entry wins over the block entries of every list
+* it blocks; it never unblocks a domain that another session claims. The
+* firewall keeps the addresses of allowed domains out of its block. Entries
+* with a path ("allow:old.reddit.com/r/selfhosted") are ignored, as the hosts
+* file can't tell paths apart.
+*
+* The hosts file and the sessions parsed from it are cached per process and
+* read again only when its modification time or size changes, so the TUI
+* polling the sessions and the reconciler don't re-read it every time.
+*/
+
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::PathBuf,
+    process::Command,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime},
+};
+
+pub use timeguardian::Priority;
+
+use crate::{demo, firewall, get_state_dir, idn, journal, load_config, metrics, mirror, HOSTS_BACKUP};
+
+/// Marker line opening the TimeGuardian section of the hosts file
+pub const TEMP_HOSTS_MARKER: &str = "# ===== TimeGuardian Temporary Hosts =====";
+/// Marker line closing the TimeGuardian section of the hosts file
+pub const TEMP_HOSTS_END_MARKER: &str = "# ===== End Temporary Hosts =====";
+/// File in the state directory holding the active claims
+pub const BLOCK_STATE_FILE: &str = "blocks.toml";
+/// File in the state directory locked while the claims change
+const BLOCK_LOCK_FILE: &str = "blocks.lock";
+/// Prefix of the metadata comment preceding the domains of a claim
+const SESSION_COMMENT: &str = "# tg-session ";
+/// Prefix of domains only the firewall blocks (`users` in `[firewall]`)
+const FIREWALL_ONLY: &str = "# firewall-only ";
+/// Prefix of list entries that let a domain through
+pub const ALLOW_PREFIX: &str = "allow:";
+/// Time after its expiry before a claim counts as forgotten, so sessions
+/// that are just ending (or moving their end) aren't healed as crashed
+const HEAL_GRACE: chrono::Duration = chrono::Duration::minutes(1);
+/// Length assumed for a claim whose expiry can't be read from the hosts
+/// file: the default session length, counted from its start
+const UNREADABLE_EXPIRY_LENGTH: chrono::Duration = chrono::Duration::minutes(25);
+/// Address blocked domains point to unless `sinkhole` is configured
+pub const DEFAULT_SINKHOLE: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+/// Last read of the hosts file
+static HOSTS_CACHE: Mutex<Option<CachedHosts>> = Mutex::new(None);
+
+/// Content of the hosts file and the sessions parsed from it
+struct CachedHosts {
+    /// Path the content was read from
+    path: PathBuf,
+    /// Modification time when it was read
+    modified: SystemTime,
+    /// Size when it was read
+    len: u64,
+    /// Content of the file
+    content: String,
+    /// Sessions in the TimeGuardian section
+    sessions: Vec<BlockClaim>,
+}
+
+/// A set of domains blocked on behalf of a single source
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlockClaim {
+    /// Identifier of the source owning this claim (e.g. "cli-1234")
+    pub source: String,
+    /// Priority of the source
+    pub priority: Priority,
+    /// Task of the session, if any
+    #[serde(default)]
+    pub task: Option<String>,
+    /// ULID of the session in the history; unset for schedules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Website lists the domains come from; all enabled lists if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lists: Vec<String>,
+    /// Fully expanded domains to block
+    pub domains: Vec<String>,
+    /// Daily windows ("19:00-21:00") during which a domain is allowed
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub allowed_windows: BTreeMap<String, Vec<String>>,
+    /// Variants of the blocked websites let through by "allow:" entries
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<String>,
+    /// Sources of the lower-priority claims this claim took over
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub taken_over: Vec<String>,
+    /// When the claim was made
+    pub started_at: DateTime<Utc>,
+    /// Time after which the claim is stale
+    pub expires_at: DateTime<Utc>,
+    /// Whether the expiry in the hosts file could not be read, so
+    /// `expires_at` is assumed from the start (or the claim counts as expired)
+    #[serde(skip)]
+    pub unreadable_expiry: bool,
+}
+
+impl BlockClaim {
+    /// Whether an allowed window of `domain` contains `time`
+    pub fn allows(&self, domain: &str, time: NaiveTime) -> bool {
+        self.allowed_windows
+            .get(domain)
+            .is_some_and(|windows| windows.iter().any(|window| window_contains(window, time)))
+    }
+}
+
+/// Reconciler state persisted between invocations
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BlockState {
+    #[serde(default)]
+    pub claims: Vec<BlockClaim>,
+}
+
+impl BlockState {
+    /// Load the reconciler state from the state directory.
+    ///
+    /// A file that can't be parsed (e.g. written by an older version that
+    /// crashed while saving it) is rebuilt from the sessions in the hosts
+    /// file, so the next save replaces it instead of every command failing.
+    pub fn load() -> Result<Self> {
+        let path = state_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Could not read block state: {:?}", path))?;
+        match toml::from_str(&content) {
+            Ok(state) => Ok(state),
+            Err(_) => Ok(Self {
+                claims: sessions_in_hosts_file()?
+                    .into_iter()
+                    .filter(|claim| claim.source != "unknown")
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Save the reconciler state to the state directory
+    pub fn save(&self) -> Result<()> {
+        let path = state_path()?;
+        let content = toml::to_string(self).wrap_err("Could not serialize block state")?;
+
+        // Write to a temporary file first so a crash never leaves a truncated state
+        let temp_path = path.with_extension("toml.tmp");
+        fs::write(&temp_path, content)
+            .wrap_err_with(|| format!("Could not save block state: {:?}", temp_path))?;
+        fs::rename(&temp_path, &path).wrap_err_with(|| format!("Could not save block state: {:?}", path))
+    }
+
+    /// Drop claims whose expiry time has passed
+    fn prune_expired(&mut self) {
+        let now = Utc::now();
+        self.claims.retain(|claim| claim.expires_at > now);
+    }
+
+    /// Drop the claim of `source` and return its domains that another claim
+    /// still blocks
+    fn release(&mut self, source: &str) -> Vec<String> {
+        let released: Vec<String> = self
+            .claims
+            .iter()
+            .filter(|claim| claim.source == source)
+            .flat_map(|claim| claim.domains.clone())
+            .collect();
+        self.claims.retain(|claim| claim.source != source);
+
+        let owners = self.owners();
+        released
+            .into_iter()
+            .filter(|domain| owners.contains_key(domain.as_str()))
+            .collect()
+    }
+
+    /// Drop the claims that expired by `cutoff` and adopt the live sessions
+    /// only `in_hosts_file` knows about.
+    ///
+    /// Returns the sources of the expired claims, in the state or the hosts
+    /// file, with their session IDs.
+    fn heal(&mut self, in_hosts_file: Vec<BlockClaim>, cutoff: DateTime<Utc>) -> BTreeMap<String, Option<String>> {
+        let mut expired: BTreeMap<String, Option<String>> = self
+            .claims
+            .iter()
+            .filter(|claim| claim.expires_at <= cutoff)
+            .map(|claim| (claim.source.clone(), claim.session_id.clone()))
+            .collect();
+        self.claims.retain(|claim| claim.expires_at > cutoff);
+
+        for claim in in_hosts_file {
+            if claim.expires_at <= cutoff {
+                let session_id = expired.remove(&claim.source).flatten().or(claim.session_id);
+                expired.insert(claim.source, session_id);
+            } else if claim.source != "unknown" && !self.claims.iter().any(|c| c.source == claim.source) {
+                // Keep live sessions that are only known from the hosts file
+                self.claims.push(claim);
+            }
+        }
+        expired
+    }
+
+    /// Let every claim take over the lower-priority claims that share a
+    /// domain with it and end before it: it adds their domains (except the
+    /// ones it allows) and their allowed windows, and records their sources
+    fn take_over_lower_claims(&mut self) {
+        let original = self.claims.clone();
+        for (index, claim) in self.claims.iter_mut().enumerate() {
+            let own_domains = &original[index].domains;
+            for lower in &original {
+                if lower.priority >= claim.priority
+                    || lower.expires_at >= claim.expires_at
+                    || !lower.domains.iter().any(|domain| own_domains.contains(domain))
+                {
+                    continue;
+                }
+                for domain in &lower.domains {
+                    if claim.domains.contains(domain) || claim.allowed.contains(domain) {
+                        continue;
+                    }
+                    claim.domains.push(domain.clone());
+                    if let Some(windows) = lower.allowed_windows.get(domain) {
+                        claim.allowed_windows.insert(domain.clone(), windows.clone());
+                    }
+                }
+                claim.domains.sort();
+                if !claim.taken_over.contains(&lower.source) {
+                    claim.taken_over.push(lower.source.clone());
+                }
+            }
+        }
+    }
+
+    /// Map every currently blocked domain to all claims blocking it, the
+    /// highest-priority claim owning it first
+    pub fn claimants(&self) -> BTreeMap<&str, Vec<&BlockClaim>> {
+        let now = Local::now().time();
+        let mut claimants: BTreeMap<&str, Vec<&BlockClaim>> = BTreeMap::new();
+        for claim in &self.claims {
+            for domain in &claim.domains {
+                if !claim.allows(domain, now) {
+                    claimants.entry(domain.as_str()).or_default().push(claim);
+                }
+            }
+        }
+        // Stable, so the earliest claim owns the domain among equal priorities
+        for claims in claimants.values_mut() {
+            claims.sort_by_key(|claim| std::cmp::Reverse(claim.priority));
+        }
+        claimants
+    }
+
+    /// Map every currently blocked domain to the highest-priority claim owning it
+    pub fn owners(&self) -> BTreeMap<&str, &BlockClaim> {
+        self.claimants()
+            .into_iter()
+            .map(|(domain, claims)| (domain, claims[0]))
+            .collect()
+    }
+
+    /// Domains blocked right now
+    pub fn blocked_domains(&self) -> Vec<String> {
+        self.owners().into_keys().map(str::to_string).collect()
+    }
+
+    /// Domains let through by "allow:" entries that no other claim blocks
+    pub fn allowed_domains(&self) -> Vec<String> {
+        let owners = self.owners();
+        let mut allowed: Vec<String> = self
+            .claims
+            .iter()
+            .flat_map(|claim| claim.allowed.iter())
+            .filter(|domain| !owners.contains_key(domain.as_str()))
+            .cloned()
+            .collect();
+        allowed.sort();
+        allowed.dedup();
+        allowed
+    }
+}
+
+/// Get the path to the hosts file based on the operating system
+pub fn get_hosts_path() -> PathBuf {
+    if let Some(dir) = demo::dir() {
+        return dir.join("hosts");
+    }
+    if cfg!(target_os = "linux") || cfg!(target_os = "macos") {
+        PathBuf::from("/etc/hosts")
+    } else if cfg!(target_os = "windows") {
+        PathBuf::from(r"C:\Windows\System32\drivers\etc\hosts")
+    } else {
+        panic!("Unsupported operating system")
+    }
+}
+
+/// Path of the reconciler state file
+fn state_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(BLOCK_STATE_FILE))
+}
+
+/// Acquire the exclusive claims lock, held from loading the claims until
+/// they are saved again, so concurrent sources (sessions, the TUI, a
+/// scheduled `schedule apply`) never overwrite each other's claims; it is
+/// released when the returned file is dropped
+pub fn lock_claims() -> Result<fs::File> {
+    let lock_path = get_state_dir()?.join(BLOCK_LOCK_FILE);
+    let lock = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .wrap_err_with(|| format!("Could not open claims lock: {:?}", lock_path))?;
+    lock.lock().wrap_err("Could not lock the blocking claims")?;
+    Ok(lock)
+}
+
+/// Split a list entry into the website and its allowed windows
+/// ("youtube.com @19:00-21:00" -> "youtube.com", ["19:00-21:00"])
+pub fn parse_entry(entry: &str) -> (&str, Vec<String>) {
+    let mut parts = entry.split_whitespace();
+    let website = parts.next().unwrap_or_default();
+    let windows = parts
+        .filter_map(|part| part.strip_prefix('@'))
+        .filter(|window| parse_window(window).is_some())
+        .map(str::to_string)
+        .collect();
+    (website, windows)
+}
+
+/// Parse a daily window "HH:MM-HH:MM"
+fn parse_window(window: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (start, end) = window.split_once('-')?;
+    Some((
+        NaiveTime::parse_from_str(start, "%H:%M").ok()?,
+        NaiveTime::parse_from_str(end, "%H:%M").ok()?,
+    ))
+}
+
+/// Whether a daily window contains `time`; windows may run over midnight
+fn window_contains(window: &str, time: NaiveTime) -> bool {
+    match parse_window(window) {
+        Some((start, end)) if start <= end => start <= time && time < end,
+        Some((start, end)) => time >= start || time < end,
+        None => false,
+    }
+}
+
+/// The domain an "allow:" entry lets through; `None` for other entries and
+/// for allow entries with a path
+pub fn allowed_domain(entry: &str) -> Option<String> {
+    let website = parse_entry(entry).0.strip_prefix(ALLOW_PREFIX)?.trim().to_lowercase();
+    let website = website
+        .strip_prefix("http://")
+        .or_else(|| website.strip_prefix("https://"))
+        .unwrap_or(&website);
+    let domain = website.strip_suffix('/').unwrap_or(website);
+    (!domain.is_empty() && !domain.contains('/')).then(|| idn::to_ascii(domain))
+}
+
+/// Expand a website entry into the domain variants that have to be blocked;
+/// "allow:" entries block nothing
+pub fn expand_website(website: &str) -> Vec<String> {
+    let (website, _) = parse_entry(website);
+    if website.starts_with(ALLOW_PREFIX) {
+        return Vec::new();
+    }
+    let website = website.trim().to_lowercase();
+
+    // Remove any protocol prefixes if present
+    let clean_website = website
+        .strip_prefix("http://")
+        .or_else(|| website.strip_prefix("https://"))
+        .unwrap_or(&website);
+
+    // Remove any trailing path components
+    let domain = clean_website.split('/').next().unwrap_or(clean_website);
+    if domain.is_empty() {
+        return Vec::new();
+    }
+    // The hosts file and the firewall only know the punycode form
+    let domain = idn::to_ascii(domain);
+    let domain = domain.as_str();
+
+    let mut domains = vec![domain.to_string()];
+
+    // Block common subdomains
+    if !domain.starts_with("www.") {
+        domains.push(format!("www.{}", domain));
+    }
+
+    // Block mobile version and app subdomain
+    domains.push(format!("m.{}", domain));
+    domains.push(format!("app.{}", domain));
+
+    domains
+}
+
+/// Block the given websites on behalf of `source` for `duration`.
+///
+/// An existing claim of the same source is replaced. Lower-priority claims
+/// sharing a domain with it are taken over, and it is taken over by
+/// higher-priority ones.
+pub fn claim(
+    source: &str,
+    priority: Priority,
+    task: Option<&str>,
+    session_id: Option<&str>,
+    lists: &[String],
+    websites: &[String],
+    duration: Duration,
+) -> Result<()> {
+    // Allow entries win over the block entries of the same claim
+    let allow_entries: Vec<String> = websites.iter().filter_map(|w| allowed_domain(w)).collect();
+    let (mut allowed, mut domains): (Vec<String>, Vec<String>) = websites
+        .iter()
+        .flat_map(|w| expand_website(w))
+        .partition(|domain| allow_entries.contains(domain));
+    domains.sort();
+    domains.dedup();
+    allowed.sort();
+    allowed.dedup();
+
+    let mut allowed_windows: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for website in websites {
+        let (_, windows) = parse_entry(website);
+        if windows.is_empty() {
+            continue;
+        }
+        for domain in expand_website(website) {
+            allowed_windows.entry(domain).or_default().extend(windows.iter().cloned());
+        }
+    }
+
+    let started_at = Utc::now();
+    let expires_at = started_at
+        + chrono::Duration::from_std(duration).wrap_err("Blocking duration is too long")?;
+
+    let _lock = lock_claims()?;
+    let mut state = BlockState::load()?;
+    state.prune_expired();
+    state.claims.retain(|claim| claim.source != source);
+    state.claims.push(BlockClaim {
+        source: source.to_string(),
+        priority,
+        task: task.map(|task| task.replace(['\n', '\r'], " ")),
+        session_id: session_id.map(str::to_string),
+        lists: lists.to_vec(),
+        domains,
+        allowed_windows,
+        allowed,
+        taken_over: Vec::new(),
+        started_at,
+        expires_at,
+        unreadable_expiry: false,
+    });
+    state.take_over_lower_claims();
+
+    if let Err(error) = reconcile(&state) {
+        // Leave nothing behind for a session that could not start
+        state.claims.retain(|claim| claim.source != source);
+        let _ = reconcile(&state);
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Release the claim of `source`.
+///
+/// Returns the domains of the released claim that stay blocked because
+/// another source still claims them.
+pub fn release(source: &str) -> Result<Vec<String>> {
+    let _lock = lock_claims()?;
+    let mut state = BlockState::load()?;
+    // Sessions that never blocked (timer only) have nothing to release
+    if !state.claims.iter().any(|claim| claim.source == source) {
+        return Ok(Vec::new());
+    }
+    state.prune_expired();
+    let retained = state.release(source);
+
+    reconcile(&state)?;
+    Ok(retained)
+}
+
+/// Release every claim with a priority up to `priority` (`reset` without
+/// `--force`) if a higher-priority claim remains; callers hold the claims
+/// lock.
+///
+/// Returns the released claims and the number of claims kept. Without a
+/// higher-priority claim nothing changes and no claim counts as kept, so
+/// the caller can reset everything.
+pub fn release_up_to(priority: Priority) -> Result<(Vec<BlockClaim>, usize)> {
+    let mut state = BlockState::load()?;
+    state.prune_expired();
+    // Sessions only the hosts file knows about (e.g. crashed before saving)
+    let now = Utc::now();
+    for claim in sessions_in_hosts_file()? {
+        if claim.source != "unknown"
+            && claim.expires_at > now
+            && !state.claims.iter().any(|c| c.source == claim.source)
+        {
+            state.claims.push(claim);
+        }
+    }
+
+    let (released, kept): (Vec<BlockClaim>, Vec<BlockClaim>) =
+        state.claims.into_iter().partition(|claim| claim.priority <= priority);
+    if kept.is_empty() {
+        return Ok((released, 0));
+    }
+
+    let count = kept.len();
+    reconcile(&BlockState { claims: kept })?;
+    Ok((released, count))
+}
+
+/// Remove sessions whose expiry time has passed for a while (`HEAL_GRACE`)
+/// from the claim state and the hosts file, so forgotten blocks heal on the
+/// next run.
+///
+/// Returns the number of removed sessions and the session IDs among them.
+pub fn heal_expired() -> Result<(usize, Vec<String>)> {
+    let _lock = lock_claims()?;
+    let in_hosts_file = sessions_in_hosts_file()?;
+    let cutoff = Utc::now() - HEAL_GRACE;
+
+    let mut state = BlockState::load()?;
+    let expired = state.heal(in_hosts_file, cutoff);
+
+    // Allowed windows open and close over the day
+    let has_windows = state.claims.iter().any(|claim| !claim.allowed_windows.is_empty());
+    if expired.is_empty() {
+        if has_windows {
+            reconcile(&state)?;
+        }
+        return Ok((0, Vec::new()));
+    }
+
+    reconcile(&state)?;
+    Ok((expired.len(), expired.into_values().flatten().collect()))
+}
+
+/// Move the expiry of the claim of `source` back by `by` (e.g. after a suspend);
+/// fails if the claim is gone, e.g. healed after it expired or reset
+pub fn extend(source: &str, by: Duration) -> Result<()> {
+    let by = chrono::Duration::from_std(by).wrap_err("Extension is too long")?;
+
+    let _lock = lock_claims()?;
+    let mut state = BlockState::load()?;
+    if !state.claims.iter().any(|claim| claim.source == source) {
+        return Err(eyre!("The block of this session is gone (expired or reset); nothing is blocked anymore"));
+    }
+    for claim in state.claims.iter_mut().filter(|claim| claim.source == source) {
+        claim.expires_at += by;
+    }
+
+    reconcile(&state)
+}
+
+/// Rewrite the hosts file for the current time of day (allowed windows)
+pub fn refresh() -> Result<()> {
+    let _lock = lock_claims()?;
+    let mut state = BlockState::load()?;
+    state.prune_expired();
+    if state.claims.iter().any(|claim| !claim.allowed_windows.is_empty()) {
+        reconcile(&state)
+    } else {
+        // Reload firewall rules dropped by someone else
+        firewall::apply(&state.blocked_domains(), &state.allowed_domains())
+    }
+}
+
+/// Address blocked domains point to (`sinkhole` in the config)
+pub fn sinkhole() -> IpAddr {
+    let Ok(config) = load_config() else {
+        return DEFAULT_SINKHOLE;
+    };
+    let sinkhole = config.sinkhole.unwrap_or(DEFAULT_SINKHOLE);
+    // The block page is served on the loopback addresses
+    if config.block_page.is_some() && sinkhole.is_unspecified() {
+        return IpAddr::V4(Ipv4Addr::LOCALHOST);
+    }
+    sinkhole
+}
+
+/// The sinkhole and its counterpart in the other address family: a loopback
+/// sinkhole pairs with the other loopback address, any other address with
+/// the unspecified one, which fails right away
+pub fn sinkhole_pair(sinkhole: IpAddr) -> (IpAddr, IpAddr) {
+    match sinkhole {
+        IpAddr::V4(v4) if v4.is_loopback() => (sinkhole, IpAddr::V6(Ipv6Addr::LOCALHOST)),
+        IpAddr::V4(_) => (sinkhole, IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        IpAddr::V6(v6) if v6.is_loopback() => (IpAddr::V4(Ipv4Addr::LOCALHOST), sinkhole),
+        IpAddr::V6(_) => (IpAddr::V4(Ipv4Addr::UNSPECIFIED), sinkhole),
+    }
+}
+
+/// Forget every claim without touching the hosts file (used by `reset`;
+/// callers hold the claims lock)
+pub fn clear_claims() -> Result<()> {
+    BlockState::default().save()
+}
+
+/// Rewrite the TimeGuardian section of the hosts file from the active claims,
+/// save the claims and block their domains in the firewall.
+///
+/// The claims are saved before the firewall is touched, so a failing
+/// firewall never leaves a hosts block that no claim accounts for. Callers
+/// hold the claims lock.
+fn reconcile(state: &BlockState) -> Result<()> {
+    let started = Instant::now();
+    let hosts_path = get_hosts_path();
+    let hosts_content = with_hosts_file(|cached| cached.content.clone())?;
+
+    // Keep a backup of the hosts file as it was before TimeGuardian touched it
+    if !hosts_content.contains(TEMP_HOSTS_MARKER) {
+        let backup_path = get_state_dir()?.join(HOSTS_BACKUP);
+        fs::write(&backup_path, &hosts_content)
+            .wrap_err_with(|| format!("Could not create hosts file backup: {:?}", backup_path))?;
+    }
+
+    // Blocks limited to some users are only recorded here
+    let prefix = if firewall::firewall_only() { FIREWALL_ONLY } else { "" };
+    let block = marker_block(state, sinkhole(), prefix);
+
+    let mut new_hosts_content = strip_marker_block(&hosts_content);
+    if !block.is_empty() {
+        new_hosts_content.push('\n');
+        new_hosts_content.push_str(&block);
+    }
+
+    let rewrite = new_hosts_content != hosts_content;
+    if rewrite {
+        let sessions: Vec<String> = state.claims.iter().filter_map(|claim| claim.session_id.clone()).collect();
+        journal::write("reconcile", &sessions, &hosts_path, &hosts_content, &new_hosts_content)
+            .wrap_err_with(|| format!("Could not update hosts file: {:?}", hosts_path))?;
+        flush_dns_cache();
+        mirror::apply(&block);
+    }
+    metrics::record_reconcile(started.elapsed(), rewrite);
+    state.save()?;
+
+    firewall::apply(&state.blocked_domains(), &state.allowed_domains())
+}
+
+/// The TimeGuardian section of the hosts file for `state`: every blocked
+/// domain under the comment of the claim owning it, pointing to `sinkhole`
+/// and its counterpart. Empty if nothing is blocked.
+fn marker_block(state: &BlockState, sinkhole: IpAddr, prefix: &str) -> String {
+    let (sinkhole_v4, sinkhole_v6) = sinkhole_pair(sinkhole);
+    let owners = state.owners();
+    if owners.is_empty() {
+        return String::new();
+    }
+
+    let mut block = format!("{}\n", TEMP_HOSTS_MARKER);
+    for claim in &state.claims {
+        let owned: Vec<&str> = owners
+            .iter()
+            .filter(|(_, owner)| owner.source == claim.source)
+            .map(|(domain, _)| *domain)
+            .collect();
+        if owned.is_empty() {
+            continue;
+        }
+
+        block.push_str(&session_comment(claim));
+        block.push('\n');
+        for domain in owned {
+            block.push_str(&format!("{}{}\t{}\n", prefix, sinkhole_v4, domain));
+            block.push_str(&format!("{}{}\t{}\n", prefix, sinkhole_v6, domain));
+        }
+    }
+    block.push_str(TEMP_HOSTS_END_MARKER);
+    block.push('\n');
+    block
+}
+
+/// Metadata comment describing a claim in the hosts file
+fn session_comment(claim: &BlockClaim) -> String {
+    let mut comment = format!(
+        "{}source={} priority={} started={} expires={}",
+        SESSION_COMMENT,
+        claim.source,
+        claim.priority.name(),
+        claim.started_at.to_rfc3339(),
+        claim.expires_at.to_rfc3339()
+    );
+    if let Some(session_id) = &claim.session_id {
+        comment.push_str(&format!(" session={}", session_id));
+    }
+    // The task may contain spaces, so it always comes last
+    if let Some(task) = &claim.task {
+        comment.push_str(&format!(" task={}", task));
+    }
+    comment
+}
+
+/// Parse a metadata comment written by `session_comment`.
+///
+/// Only the source is required. A missing or garbled expiry never makes a
+/// block permanent: the claim runs for the default session length from its
+/// start, or counts as expired without a readable start, so it heals like
+/// any other. A missing start falls back to the expiry; an unknown priority
+/// counts as normal.
+fn parse_session_comment(line: &str) -> Option<BlockClaim> {
+    let fields = line.strip_prefix(SESSION_COMMENT)?;
+    let (fields, task) = match fields.split_once(" task=") {
+        Some((fields, task)) => (fields, Some(task.to_string())),
+        None => (fields, None),
+    };
+
+    let mut source = None;
+    let mut priority = Priority::Normal;
+    let mut started_at = None;
+    let mut expires_at = None;
+    let mut session_id = None;
+    for field in fields.split_whitespace() {
+        match field.split_once('=') {
+            Some(("source", value)) => source = Some(value.to_string()),
+            Some(("priority", value)) => {
+                priority = clap::ValueEnum::from_str(value, true).unwrap_or_default();
+            }
+            Some(("started", value)) => started_at = DateTime::parse_from_rfc3339(value).ok(),
+            Some(("expires", value)) => expires_at = DateTime::parse_from_rfc3339(value).ok(),
+            Some(("session", value)) => session_id = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    let started_at = started_at.map(|at| at.with_timezone(&Utc));
+    let unreadable_expiry = expires_at.is_none();
+    let expires_at = match (expires_at, started_at) {
+        (Some(expires_at), _) => expires_at.with_timezone(&Utc),
+        (None, Some(started_at)) => started_at + UNREADABLE_EXPIRY_LENGTH,
+        (None, None) => DateTime::<Utc>::MIN_UTC,
+    };
+
+    Some(BlockClaim {
+        source: source?,
+        priority,
+        task,
+        session_id,
+        lists: Vec::new(),
+        domains: Vec::new(),
+        allowed_windows: BTreeMap::new(),
+        allowed: Vec::new(),
+        taken_over: Vec::new(),
+        started_at: started_at.unwrap_or(expires_at),
+        expires_at,
+        unreadable_expiry,
+    })
+}
+
+/// Reconstruct the blocking sessions from the TimeGuardian section of the
+/// hosts file.
+///
+/// Domains written without a metadata comment (by older versions) are
+/// collected in a claim with the source "unknown". It has no expiry to
+/// read, so it counts as expired and is healed on the next run.
+pub fn sessions_in_hosts_file() -> Result<Vec<BlockClaim>> {
+    with_hosts_file(|cached| cached.sessions.clone())
+}
+
+/// Run `f` on the hosts file, reading it again only if it changed since the
+/// last read
+fn with_hosts_file<T>(f: impl FnOnce(&CachedHosts) -> T) -> Result<T> {
+    let hosts_path = get_hosts_path();
+    let metadata = fs::metadata(&hosts_path)
+        .wrap_err_with(|| format!("Could not read hosts file: {:?}", hosts_path))?;
+    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+    let mut cache = HOSTS_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(cached) = cache.as_ref()
+        && cached.path == hosts_path
+        && cached.modified == modified
+        && cached.len == metadata.len()
+    {
+        return Ok(f(cached));
+    }
+
+    let content = fs::read_to_string(&hosts_path)
+        .wrap_err_with(|| format!("Could not read hosts file: {:?}", hosts_path))?;
+    let cached = cache.insert(CachedHosts {
+        path: hosts_path,
+        modified,
+        len: metadata.len(),
+        sessions: parse_marker_block(&content),
+        content,
+    });
+    Ok(f(cached))
+}
+
+/// Forget the cached hosts file after writing it; the modification time may
+/// not change within the timestamp resolution of the file system
+pub fn invalidate_cache() {
+    *HOSTS_CACHE.lock().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Parse the claims from the TimeGuardian section of hosts file content
+fn parse_marker_block(content: &str) -> Vec<BlockClaim> {
+    let mut claims: Vec<BlockClaim> = Vec::new();
+    let mut in_block = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line == TEMP_HOSTS_MARKER {
+            in_block = true;
+            continue;
+        }
+        if line == TEMP_HOSTS_END_MARKER {
+            in_block = false;
+            continue;
+        }
+        if !in_block {
+            continue;
+        }
+
+        if let Some(claim) = parse_session_comment(line) {
+            claims.push(claim);
+            continue;
+        }
+        let line = line.strip_prefix(FIREWALL_ONLY).unwrap_or(line);
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some(domain) = line.split_whitespace().nth(1) else {
+            continue;
+        };
+
+        if claims.is_empty() {
+            claims.push(BlockClaim {
+                source: "unknown".to_string(),
+                priority: Priority::Normal,
+                task: None,
+                session_id: None,
+                lists: Vec::new(),
+                domains: Vec::new(),
+                allowed_windows: BTreeMap::new(),
+                allowed: Vec::new(),
+                taken_over: Vec::new(),
+                started_at: DateTime::<Utc>::MIN_UTC,
+                expires_at: DateTime::<Utc>::MIN_UTC,
+                unreadable_expiry: true,
+            });
+        }
+        // The IPv4 and the IPv6 line of a domain follow each other
+        if let Some(claim) = claims.last_mut()
+            && claim.domains.last().is_none_or(|last| last != domain)
+        {
+            claim.domains.push(domain.to_string());
+        }
+    }
+
+    claims
+}
+
+/// Remove the TimeGuardian section from the given hosts file content
+pub fn strip_marker_block(content: &str) -> String {
+    let Some(start) = content.find(TEMP_HOSTS_MARKER) else {
+        return content.to_string();
+    };
+
+    let end = content[start..]
+        .find(TEMP_HOSTS_END_MARKER)
+        .map(|end| start + end + TEMP_HOSTS_END_MARKER.len())
+        .unwrap_or(content.len());
+
+    let before = content[..start].trim_end_matches('\n');
+    let after = content[end..].trim_start_matches('\n');
+
+    let mut stripped = before.to_string();
+    stripped.push('\n');
+    stripped.push_str(after);
+    stripped
+}
+
+/// Flush the resolver cache of the operating system, so a changed hosts
+/// file takes effect right away instead of after the cached answers expire
+pub fn flush_dns_cache() {
+    // The demo hosts file is not used by the resolver
+    if demo::dir().is_some() {
+        return;
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // For Windows
+        let _ = Command::new("ipconfig")
+            .args(["/flushdns"])
+            .output();
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // For macOS
+        let _ = Command::new("dscacheutil")
+            .args(["-flushcache"])
+            .output();
+        let _ = Command::new("killall")
+            .args(["-HUP", "mDNSResponder"])
+            .output();
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // systemd-resolved; systemd-resolve is its name before systemd 239
+        let flushed = Command::new("resolvectl")
+            .args(["flush-caches"])
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !flushed {
+            let _ = Command::new("systemd-resolve")
+                .args(["--flush-caches"])
+                .output();
+        }
+
+        // nscd caches hosts lookups of glibc
+        let _ = Command::new("nscd")
+            .args(["--invalidate", "hosts"])
+            .output();
+
+        // dnsmasq (also the one started by NetworkManager) clears its cache on SIGHUP
+        let _ = Command::new("pkill")
+            .args(["-HUP", "-x", "dnsmasq"])
+            .output();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(task: Option<&str>, session_id: Option<&str>) -> BlockClaim {
+        BlockClaim {
+            source: "cli-1234".to_string(),
+            priority: Priority::High,
+            task: task.map(str::to_string),
+            session_id: session_id.map(str::to_string),
+            lists: Vec::new(),
+            domains: Vec::new(),
+            allowed_windows: BTreeMap::new(),
+            allowed: Vec::new(),
+            taken_over: Vec::new(),
+            started_at: "2026-10-16T09:00:00Z".parse().unwrap(),
+            expires_at: "2026-10-16T09:25:00Z".parse().unwrap(),
+            unreadable_expiry: false,
+        }
+    }
+
+    /// A claim of `source` on `domains` that expires at 09:00 plus `minutes`
+    fn claim_on(source: &str, priority: Priority, domains: &[&str], minutes: i64) -> BlockClaim {
+        let mut claim = claim(None, None);
+        claim.source = source.to_string();
+        claim.priority = priority;
+        claim.domains = domains.iter().map(|domain| domain.to_string()).collect();
+        claim.expires_at = claim.started_at + chrono::Duration::minutes(minutes);
+        claim
+    }
+
+    /// The fields of a claim that the comment records
+    fn fields(claim: &BlockClaim) -> String {
+        format!(
+            "{} {:?} {:?} {:?} {} {}",
+            claim.source, claim.priority, claim.task, claim.session_id, claim.started_at, claim.expires_at
+        )
+    }
+
+    #[test]
+    fn session_comment_round_trips() {
+        let cases = [
+            claim(None, None),
+            claim(Some("Write thesis chapter"), None),
+            claim(None, Some("01JA0000000000000000000000")),
+            claim(Some("Read task=notes"), Some("01JA0000000000000000000000")),
+        ];
+        for original in cases {
+            let comment = session_comment(&original);
+            let parsed = parse_session_comment(&comment).unwrap_or_else(|| panic!("{} not parsed", comment));
+            assert_eq!(fields(&parsed), fields(&original), "{}", comment);
+        }
+    }
+
+    #[test]
+    fn session_comment_falls_back_for_missing_or_garbled_fields() {
+        let parsed = parse_session_comment("# tg-session source=cli-1 priority=urgent expires=2026-10-16T09:25:00Z")
+            .unwrap();
+        assert_eq!(parsed.source, "cli-1");
+        assert_eq!(parsed.priority, Priority::Normal);
+        assert_eq!(parsed.started_at, parsed.expires_at);
+        assert!(!parsed.unreadable_expiry);
+    }
+
+    #[test]
+    fn session_comment_without_readable_expiry_never_blocks_for_good() {
+        // The default length from a readable start
+        for comment in [
+            "# tg-session source=cli-1 started=2026-10-16T09:00:00Z",
+            "# tg-session source=cli-1 started=2026-10-16T09:00:00Z expires=2026-13-40T00:00:00Z",
+        ] {
+            let parsed = parse_session_comment(comment).unwrap();
+            assert_eq!(parsed.expires_at, "2026-10-16T09:25:00Z".parse::<DateTime<Utc>>().unwrap(), "{}", comment);
+            assert!(parsed.unreadable_expiry, "{}", comment);
+        }
+
+        // Expired without one
+        for comment in ["# tg-session source=cli-1", "# tg-session source=cli-1 started=yesterday expires=never"] {
+            let parsed = parse_session_comment(comment).unwrap();
+            assert_eq!(parsed.expires_at, DateTime::<Utc>::MIN_UTC, "{}", comment);
+            assert!(parsed.expires_at <= Utc::now() - HEAL_GRACE, "{}", comment);
+            assert!(parsed.unreadable_expiry, "{}", comment);
+        }
+    }
+
+    #[test]
+    fn session_comment_needs_a_source_and_the_prefix() {
+        assert!(parse_session_comment("# tg-session priority=high expires=2026-10-16T09:25:00Z").is_none());
+        assert!(parse_session_comment("# some other comment source=cli-1").is_none());
+        assert!(parse_session_comment("127.0.0.1\treddit.com").is_none());
+    }
+
+    #[test]
+    fn marker_block_assigns_domains_to_their_claims() {
+        let first = claim(Some("Write"), Some("01JA0000000000000000000000"));
+        let mut second = claim(None, None);
+        second.source = "schedule-evening".to_string();
+        let content = format!(
+            "127.0.0.1\tlocalhost\n{}\n{}\n0.0.0.0\treddit.com\n::\treddit.com\n0.0.0.0\tnews.ycombinator.com\n\
+             {}\n{}0.0.0.0\tyoutube.com\n{}\n0.0.0.0\toutside.com\n",
+            TEMP_HOSTS_MARKER,
+            session_comment(&first),
+            session_comment(&second),
+            FIREWALL_ONLY,
+            TEMP_HOSTS_END_MARKER,
+        );
+
+        let claims = parse_marker_block(&content);
+        assert_eq!(claims.len(), 2);
+        assert_eq!(fields(&claims[0]), fields(&first));
+        assert_eq!(claims[0].domains, ["reddit.com", "news.ycombinator.com"]);
+        assert_eq!(claims[1].source, "schedule-evening");
+        assert_eq!(claims[1].domains, ["youtube.com"]);
+    }
+
+    #[test]
+    fn marker_block_without_comments_is_one_unknown_claim() {
+        let content = format!("{}\n127.0.0.1\treddit.com\n{}\n", TEMP_HOSTS_MARKER, TEMP_HOSTS_END_MARKER);
+        let claims = parse_marker_block(&content);
+        assert_eq!(claims.len(), 1);
+        assert_eq!(claims[0].source, "unknown");
+        assert_eq!(claims[0].domains, ["reddit.com"]);
+        assert_eq!(claims[0].expires_at, DateTime::<Utc>::MIN_UTC);
+        assert!(claims[0].unreadable_expiry);
+
+        assert!(parse_marker_block("127.0.0.1\treddit.com\n").is_empty());
+    }
+
+    #[test]
+    fn highest_priority_claim_owns_shared_domains() {
+        let state = BlockState {
+            claims: vec![
+                claim_on("schedule-work", Priority::Normal, &["news.com", "reddit.com"], 60),
+                claim_on("cli-1", Priority::High, &["reddit.com"], 30),
+                claim_on("cli-2", Priority::Normal, &["reddit.com", "youtube.com"], 30),
+            ],
+        };
+        let owners = state.owners();
+        assert_eq!(owners["reddit.com"].source, "cli-1");
+        // Among equal priorities the earliest claim owns the domain
+        assert_eq!(owners["news.com"].source, "schedule-work");
+        assert_eq!(owners["youtube.com"].source, "cli-2");
+
+        let claimants: Vec<&str> = state.claimants()["reddit.com"].iter().map(|claim| claim.source.as_str()).collect();
+        assert_eq!(claimants, ["cli-1", "schedule-work", "cli-2"]);
+    }
+
+    #[test]
+    fn higher_priority_claim_takes_over_lower_claims_ending_earlier() {
+        let mut deadline = claim_on("cli-1", Priority::Critical, &["reddit.com"], 180);
+        deadline.allowed = vec!["m.youtube.com".to_string()];
+        let mut state = BlockState {
+            claims: vec![
+                claim_on("schedule-work", Priority::Normal, &["m.youtube.com", "news.com", "reddit.com"], 60),
+                deadline,
+            ],
+        };
+        state.claims[0].allowed_windows.insert("news.com".to_string(), vec!["12:00-13:00".to_string()]);
+        state.take_over_lower_claims();
+
+        let deadline = &state.claims[1];
+        assert_eq!(deadline.domains, ["news.com", "reddit.com"]);
+        assert_eq!(deadline.taken_over, ["schedule-work"]);
+        assert_eq!(deadline.allowed_windows["news.com"], ["12:00-13:00"]);
+        // The lower claim keeps its own domains
+        assert_eq!(state.claims[0].domains.len(), 3);
+        assert!(state.claims[0].taken_over.is_empty());
+    }
+
+    #[test]
+    fn claims_only_take_over_overlapping_lower_claims_ending_earlier() {
+        let cases = [
+            // Ends later than the higher claim
+            claim_on("schedule-work", Priority::Normal, &["news.com", "reddit.com"], 240),
+            // Shares no domain
+            claim_on("schedule-work", Priority::Normal, &["news.com"], 60),
+            // Same priority
+            claim_on("schedule-work", Priority::Critical, &["news.com", "reddit.com"], 60),
+        ];
+        for other in cases {
+            let mut state = BlockState {
+                claims: vec![other, claim_on("cli-1", Priority::Critical, &["reddit.com"], 180)],
+            };
+            state.take_over_lower_claims();
+            assert_eq!(state.claims[1].domains, ["reddit.com"]);
+            assert!(state.claims[1].taken_over.is_empty());
+        }
+    }
+
+    #[test]
+    fn marker_block_lists_shared_domains_only_under_their_owner() {
+        let state = BlockState {
+            claims: vec![
+                claim_on("schedule-work", Priority::Normal, &["news.com", "reddit.com"], 60),
+                claim_on("cli-1", Priority::High, &["reddit.com"], 30),
+            ],
+        };
+        let block = marker_block(&state, DEFAULT_SINKHOLE, "");
+        assert_eq!(
+            block,
+            format!(
+                "{}\n{}\n0.0.0.0\tnews.com\n::\tnews.com\n{}\n0.0.0.0\treddit.com\n::\treddit.com\n{}\n",
+                TEMP_HOSTS_MARKER,
+                session_comment(&state.claims[0]),
+                session_comment(&state.claims[1]),
+                TEMP_HOSTS_END_MARKER,
+            )
+        );
+
+        // A claim owning nothing gets no comment, and nothing owned no section
+        let state = BlockState { claims: vec![state.claims[1].clone()] };
+        assert!(!marker_block(&state, DEFAULT_SINKHOLE, "").contains("schedule-work"));
+        assert!(marker_block(&BlockState::default(), DEFAULT_SINKHOLE, "").is_empty());
+    }
+
+    #[test]
+    fn marker_block_points_to_the_sinkhole_pair_and_marks_firewall_only_blocks() {
+        let state = BlockState { claims: vec![claim_on("cli-1", Priority::Normal, &["reddit.com"], 30)] };
+        let block = marker_block(&state, IpAddr::V4(Ipv4Addr::LOCALHOST), FIREWALL_ONLY);
+        assert!(block.contains(&format!("{}127.0.0.1\treddit.com\n", FIREWALL_ONLY)));
+        assert!(block.contains(&format!("{}::1\treddit.com\n", FIREWALL_ONLY)));
+
+        // What a reconcile writes is read back as the same claim
+        let claims = parse_marker_block(&block);
+        assert_eq!(claims.len(), 1);
+        assert_eq!(fields(&claims[0]), fields(&state.claims[0]));
+        assert_eq!(claims[0].domains, ["reddit.com"]);
+    }
+
+    /// Two sessions of the same priority blocking different lists
+    fn concurrent_sessions() -> BlockState {
+        let mut work = claim_on("cli-1", Priority::Normal, &["news.com", "reddit.com"], 25);
+        work.lists = vec!["Work".to_string()];
+        let mut social = claim_on("cli-2", Priority::Normal, &["reddit.com", "youtube.com"], 50);
+        social.lists = vec!["Social".to_string()];
+        BlockState { claims: vec![work, social] }
+    }
+
+    #[test]
+    fn concurrent_sessions_block_side_by_side() {
+        let mut state = concurrent_sessions();
+        // Equal priorities never take each other over
+        state.take_over_lower_claims();
+        assert_eq!(state.claims[0].domains, ["news.com", "reddit.com"]);
+        assert_eq!(state.claims[1].domains, ["reddit.com", "youtube.com"]);
+        assert!(state.claims.iter().all(|claim| claim.taken_over.is_empty()));
+        assert_eq!(state.blocked_domains(), ["news.com", "reddit.com", "youtube.com"]);
+
+        // One section holds both sessions, the shared domain under the first
+        let claims = parse_marker_block(&marker_block(&state, DEFAULT_SINKHOLE, ""));
+        let sessions: Vec<(&str, &[String])> =
+            claims.iter().map(|claim| (claim.source.as_str(), claim.domains.as_slice())).collect();
+        assert_eq!(
+            sessions,
+            [
+                ("cli-1", &["news.com".to_string(), "reddit.com".to_string()][..]),
+                ("cli-2", &["youtube.com".to_string()][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn releasing_a_session_keeps_what_another_session_blocks() {
+        let mut state = concurrent_sessions();
+        assert_eq!(state.release("cli-1"), ["reddit.com"]);
+        assert_eq!(state.claims.len(), 1);
+        // The shared domain moves to the remaining session
+        assert_eq!(state.owners()["reddit.com"].source, "cli-2");
+        assert_eq!(state.blocked_domains(), ["reddit.com", "youtube.com"]);
+
+        assert!(state.release("cli-2").is_empty());
+        assert!(state.blocked_domains().is_empty());
+        // Releasing an unknown source changes nothing
+        assert!(concurrent_sessions().release("cli-3").is_empty());
+    }
+
+    #[test]
+    fn healing_drops_claims_expired_past_the_grace_period() {
+        let mut expired = claim_on("cli-1", Priority::Normal, &["reddit.com"], 25);
+        expired.session_id = Some("01JA0000000000000000000001".to_string());
+        let mut state = BlockState {
+            claims: vec![
+                expired,
+                claim_on("cli-2", Priority::Normal, &["news.com"], 60),
+                claim_on("schedule-work", Priority::Normal, &["youtube.com"], 30),
+            ],
+        };
+        // 09:30 minus the grace period: cli-2 runs, the schedule just ended
+        let now: DateTime<Utc> = "2026-10-16T09:30:00Z".parse().unwrap();
+        let healed = state.heal(Vec::new(), now - HEAL_GRACE);
+
+        assert_eq!(healed.len(), 1);
+        assert_eq!(healed["cli-1"].as_deref(), Some("01JA0000000000000000000001"));
+        let kept: Vec<&str> = state.claims.iter().map(|claim| claim.source.as_str()).collect();
+        assert_eq!(kept, ["cli-2", "schedule-work"]);
+    }
+
+    #[test]
+    fn healing_covers_sessions_only_the_hosts_file_knows() {
+        let mut expired = claim_on("cli-1", Priority::Normal, &["reddit.com"], 25);
+        expired.session_id = Some("01JA0000000000000000000001".to_string());
+        let mut state = BlockState { claims: vec![expired] };
+        let mut crashed = claim_on("cli-2", Priority::Normal, &["news.com"], 10);
+        crashed.session_id = Some("01JA0000000000000000000002".to_string());
+        let in_hosts_file = vec![
+            // The expired claim again, without its session ID
+            claim_on("cli-1", Priority::Normal, &["reddit.com"], 25),
+            crashed,
+            claim_on("cli-3", Priority::High, &["youtube.com"], 60),
+            claim_on("unknown", Priority::Normal, &["twitter.com"], 60),
+        ];
+        let cutoff = "2026-10-16T09:30:00Z".parse().unwrap();
+        let healed = state.heal(in_hosts_file, cutoff);
+
+        // The session ID of the state isn't lost to the hosts file
+        assert_eq!(healed["cli-1"].as_deref(), Some("01JA0000000000000000000001"));
+        assert_eq!(healed["cli-2"].as_deref(), Some("01JA0000000000000000000002"));
+        assert_eq!(healed.len(), 2);
+        // Live sessions are adopted, entries of no session aren't
+        let kept: Vec<&str> = state.claims.iter().map(|claim| claim.source.as_str()).collect();
+        assert_eq!(kept, ["cli-3"]);
+    }
+}
@@ -0,0 +1,42 @@
+/*
+* TimeGuardian IDN Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module handles internationalized domain names. Lists may hold either
+* form of a domain, "bücher.de" or "xn--bcher-kva.de". The hosts file and the
+* firewall only know the ASCII (punycode) form, so domains are converted to it
+* before they are blocked, and lists are compared in it, so both forms are the
+* same entry. The TUI shows the unicode form, which is what users type.
+*/
+
+use crate::hosts;
+
+/// The ASCII (punycode) form of `domain`, lowercase; domains idna rejects
+/// are only lowercased
+pub fn to_ascii(domain: &str) -> String {
+    idna::domain_to_ascii(domain).unwrap_or_else(|_| domain.to_lowercase())
+}
+
+/// The unicode form of `domain`, for display
+pub fn to_unicode(domain: &str) -> String {
+    match idna::domain_to_unicode(domain) {
+        (unicode, Ok(())) => unicode,
+        _ => domain.to_string(),
+    }
+}
+
+/// A list entry with its website in the unicode form; allowed windows stay
+/// ("xn--bcher-kva.de @19:00-21:00" -> "bücher.de @19:00-21:00")
+pub fn display(entry: &str) -> String {
+    let (website, _) = hosts::parse_entry(entry);
+    let unicode = to_unicode(website);
+    if unicode == website {
+        return entry.to_string();
+    }
+    entry.replacen(website, &unicode, 1)
+}
+
+/// The website of a list entry in the ASCII form, to compare entries
+pub fn key(entry: &str) -> String {
+    to_ascii(hosts::parse_entry(entry).0)
+}
@@ -0,0 +1,172 @@
+/*
+* TimeGuardian In-Use Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module warns before blocking a domain that something on the machine
+* is talking to right now, e.g. meet.google.com during a call or github.com
+* during a push. The addresses of the domains are looked up like for the
+* firewall (bypassing the hosts file) and compared with the remote addresses
+* of the open connections: /proc/net on Linux, lsof on macOS and netstat on
+* Windows. Lookups run in parallel and are capped, so huge lists only have
+* their first domains checked.
+*/
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::IpAddr,
+    thread,
+};
+
+use crate::{hosts, resolve};
+
+/// Most domains looked up per check
+const MAX_CHECKED_DOMAINS: usize = 256;
+/// Lookups running at the same time
+const PARALLEL_LOOKUPS: usize = 32;
+
+/// A domain with open connections to its addresses
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InUse {
+    /// The domain as it would be blocked
+    pub domain: String,
+    /// Open connections to its addresses
+    pub connections: usize,
+}
+
+/// Domains of `websites` that open connections currently go to
+pub fn domains_in_use(websites: &[String], resolver: Option<IpAddr>) -> Vec<InUse> {
+    let connected = remote_addresses();
+    if connected.is_empty() {
+        return Vec::new();
+    }
+    let servers = resolve::upstream_servers(resolver);
+    if servers.is_empty() {
+        return Vec::new();
+    }
+
+    let domains: BTreeSet<String> = websites
+        .iter()
+        .filter_map(|website| hosts::expand_website(website).into_iter().next())
+        .take(MAX_CHECKED_DOMAINS)
+        .collect();
+    let domains: Vec<String> = domains.into_iter().collect();
+
+    let mut in_use = Vec::new();
+    for chunk in domains.chunks(PARALLEL_LOOKUPS) {
+        let lookups: Vec<_> = chunk
+            .iter()
+            .map(|domain| {
+                let (domain, servers) = (domain.clone(), servers.clone());
+                thread::spawn(move || {
+                    let addresses = resolve::resolve(&domain, &servers).map(|resolved| resolved.addresses);
+                    (domain, addresses.unwrap_or_default())
+                })
+            })
+            .collect();
+        for lookup in lookups {
+            let Ok((domain, addresses)) = lookup.join() else {
+                continue;
+            };
+            let connections: usize = addresses.iter().filter_map(|address| connected.get(address)).sum();
+            if connections > 0 {
+                in_use.push(InUse { domain, connections });
+            }
+        }
+    }
+    in_use
+}
+
+/// Remote addresses of the open connections and how many go to each
+fn remote_addresses() -> HashMap<IpAddr, usize> {
+    let mut counts = HashMap::new();
+    for address in connection_peers() {
+        if !address.is_unspecified() {
+            *counts.entry(address).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Peers of the established TCP connections and connected UDP sockets
+#[cfg(target_os = "linux")]
+fn connection_peers() -> Vec<IpAddr> {
+    // Established TCP connections and connected UDP sockets both have state 01
+    const CONNECTED: &str = "01";
+    let mut peers = Vec::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6", "/proc/net/udp", "/proc/net/udp6"] {
+        let Ok(content) = std::fs::read_to_string(table) else {
+            continue;
+        };
+        for line in content.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(3) != Some(&CONNECTED) {
+                continue;
+            }
+            if let Some(address) = fields.get(2).and_then(|field| field.split(':').next()).and_then(proc_address) {
+                peers.push(address);
+            }
+        }
+    }
+    peers
+}
+
+/// Address in /proc/net notation: 32-bit words in hex, each in host byte order
+#[cfg(target_os = "linux")]
+fn proc_address(hex: &str) -> Option<IpAddr> {
+    let words: Vec<u32> = (0..hex.len() / 8)
+        .map(|i| u32::from_str_radix(hex.get(i * 8..i * 8 + 8)?, 16).ok())
+        .collect::<Option<_>>()?;
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_ne_bytes()).collect();
+    match bytes.len() {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+        16 => {
+            let address = std::net::Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?);
+            // IPv4 connections on IPv6 sockets
+            Some(address.to_ipv4_mapped().map_or(IpAddr::V6(address), IpAddr::V4))
+        }
+        _ => None,
+    }
+}
+
+/// Peers of the open internet connections, from `lsof`
+#[cfg(target_os = "macos")]
+fn connection_peers() -> Vec<IpAddr> {
+    let Ok(output) = std::process::Command::new("lsof").args(["-nP", "-i", "-F", "n"]).output() else {
+        return Vec::new();
+    };
+    // Lines like "n192.168.1.2:50123->140.82.112.3:443" or "n[::1]:5000->[2001:db8::1]:443"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix('n')?.split_once("->"))
+        .filter_map(|(_, peer)| peer_address(peer))
+        .collect()
+}
+
+/// Peers of the open connections, from `netstat`
+#[cfg(windows)]
+fn connection_peers() -> Vec<IpAddr> {
+    let Ok(output) = std::process::Command::new("netstat").arg("-n").output() else {
+        return Vec::new();
+    };
+    // Lines like "  TCP    192.168.1.2:50123    140.82.112.3:443    ESTABLISHED"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.contains("ESTABLISHED"))
+        .filter_map(|line| line.split_whitespace().nth(2))
+        .filter_map(peer_address)
+        .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn connection_peers() -> Vec<IpAddr> {
+    Vec::new()
+}
+
+/// Address of "host:port" or "[v6 host]:port"
+#[cfg(any(target_os = "macos", windows))]
+fn peer_address(peer: &str) -> Option<IpAddr> {
+    let (host, _port) = peer.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    // Drop the zone of link-local addresses ("fe80::1%en0")
+    host.split('%').next()?.parse().ok()
+}
@@ -0,0 +1,785 @@
+/*
+* TimeGuardian IPC Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module implements the line-delimited JSON protocol spoken over the
+* TimeGuardian IPC socket (see docs/ipc.md). The process running a session
+* (CLI timer or TUI) serves the socket; editor plugins and scripts talk to it
+* through `timeguardian ipc`.
+*
+* Only one process can serve the shared socket, so every process running a
+* session also serves a socket of its own, named after its process ID. Those
+* sockets are the registry of all running sessions: `stop` and `attach-cli`
+* find a session among them by its ID or list, even while another session
+* serves the shared socket.
+*/
+
+use chrono::{DateTime, Local};
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    thread,
+};
+
+use crate::hosts::Priority;
+#[cfg(unix)]
+use crate::metrics;
+use crate::{attach, parse_duration, IpcCommand};
+
+/// File name of the IPC socket in the demo directory
+const SOCKET_NAME: &str = "timeguardian.sock";
+/// Prefix of the socket every process running a session serves for itself,
+/// followed by its process ID
+const HOST_SOCKET_PREFIX: &str = "timeguardian-";
+/// Interval between status lines of a subscription, unless it asks for a longer one
+pub const SUBSCRIBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A request sent by a client, one JSON object per line
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum Request {
+    /// Return the current session status
+    Status,
+    /// End the running session
+    Stop,
+    /// Stream the status once per second, or every `interval_secs`, until
+    /// the connection is closed
+    Subscribe {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        interval_secs: Option<u64>,
+    },
+    /// Start a session in an idle host
+    Start {
+        duration: String,
+        task: Option<String>,
+    },
+}
+
+impl Request {
+    /// The `cmd` of the request
+    pub fn name(&self) -> &'static str {
+        match self {
+            Request::Status => "status",
+            Request::Stop => "stop",
+            Request::Subscribe { .. } => "subscribe",
+            Request::Start { .. } => "start",
+        }
+    }
+}
+
+/// Status of the session served by a host
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SessionStatus {
+    /// Whether a blocking session is running
+    pub active: bool,
+    /// Task of the session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// ULID of the session in the history
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Website lists the session blocks; all enabled lists if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lists: Vec<String>,
+    /// Priority of the session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// When the session started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Local>>,
+    /// When the session ends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<DateTime<Local>>,
+    /// Seconds until the session ends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_secs: Option<u64>,
+}
+
+/// A response line sent by the host
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<SessionStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    fn status(status: SessionStatus) -> Self {
+        Self { ok: true, status: Some(status), error: None }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self { ok: false, status: None, error: Some(message.into()) }
+    }
+}
+
+/// A start request waiting to be picked up by the host
+#[derive(Clone, Debug)]
+pub struct StartRequest {
+    pub duration: String,
+    pub task: Option<String>,
+}
+
+#[derive(Default)]
+struct HostState {
+    status: SessionStatus,
+    stop_requested: bool,
+    strict: bool,
+    accepts_start: bool,
+    pending_start: Option<StartRequest>,
+}
+
+/// Session state shared between the host and the IPC server thread
+#[derive(Clone, Default)]
+pub struct SessionHandle(Arc<Mutex<HostState>>);
+
+impl SessionHandle {
+    /// Create a handle; `accepts_start` marks hosts able to start sessions on request
+    pub fn new(accepts_start: bool) -> Self {
+        let handle = Self::default();
+        handle.lock().accepts_start = accepts_start;
+        handle
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HostState> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Publish a running session
+    pub fn set_session(
+        &self,
+        task: Option<String>,
+        priority: Priority,
+        started_at: DateTime<Local>,
+        ends_at: DateTime<Local>,
+    ) {
+        let mut state = self.lock();
+        state.status = SessionStatus {
+            active: true,
+            task,
+            session_id: None,
+            lists: Vec::new(),
+            priority: Some(priority),
+            started_at: Some(started_at),
+            ends_at: Some(ends_at),
+            remaining_secs: None,
+        };
+        state.stop_requested = false;
+    }
+
+    /// Publish that no session is running
+    pub fn clear_session(&self) {
+        let mut state = self.lock();
+        state.status = SessionStatus::default();
+        state.stop_requested = false;
+        state.strict = false;
+    }
+
+    /// Publish the ULID of the running session
+    pub fn set_session_id(&self, session_id: &str) {
+        self.lock().status.session_id = Some(session_id.to_string());
+    }
+
+    /// Publish the website lists the running session blocks
+    pub fn set_lists(&self, lists: &[String]) {
+        self.lock().status.lists = lists.to_vec();
+    }
+
+    /// Refuse stop requests until the running session ends (`start --strict`)
+    pub fn set_strict(&self) {
+        self.lock().strict = true;
+    }
+
+    /// Move the end of the running session back by `by`
+    pub fn extend(&self, by: Duration) {
+        let mut state = self.lock();
+        if let (Some(ends_at), Ok(by)) = (state.status.ends_at, chrono::Duration::from_std(by)) {
+            state.status.ends_at = Some(ends_at + by);
+        }
+    }
+
+    /// Whether a client asked to stop the session (resets the request)
+    pub fn take_stop_request(&self) -> bool {
+        std::mem::take(&mut self.lock().stop_requested)
+    }
+
+    /// A start request sent by a client, if any
+    pub fn take_start_request(&self) -> Option<StartRequest> {
+        self.lock().pending_start.take()
+    }
+
+    /// Current status with the remaining time filled in
+    pub fn status(&self) -> SessionStatus {
+        let mut status = self.lock().status.clone();
+        if let Some(ends_at) = status.ends_at {
+            status.remaining_secs = Some((ends_at - Local::now()).num_seconds().max(0) as u64);
+        }
+        status
+    }
+
+    /// Handle a single request
+    fn handle(&self, request: &Request) -> Response {
+        match request {
+            Request::Status | Request::Subscribe { .. } => Response::status(self.status()),
+            Request::Stop => {
+                let mut state = self.lock();
+                if !state.status.active {
+                    return Response::error("no session is running");
+                }
+                if state.strict {
+                    return Response::error("the session is strict and can't be stopped early");
+                }
+                state.stop_requested = true;
+                drop(state);
+                Response::status(self.status())
+            }
+            Request::Start { duration, task } => {
+                let mut state = self.lock();
+                if state.status.active {
+                    return Response::error("a session is already running");
+                }
+                if !state.accepts_start {
+                    return Response::error("this host cannot start sessions");
+                }
+                state.pending_start = Some(StartRequest {
+                    duration: duration.clone(),
+                    task: task.clone(),
+                });
+                drop(state);
+                Response::status(self.status())
+            }
+        }
+    }
+}
+
+/// Path of the IPC socket
+pub fn socket_path() -> PathBuf {
+    // The demo must not take over the socket of a real session
+    match crate::demo::dir() {
+        Some(dir) => dir.join(SOCKET_NAME),
+        None => timeguardian::client::socket_path(),
+    }
+}
+
+/// Path of the socket the process `pid` serves for itself
+fn host_socket_path(pid: u32) -> PathBuf {
+    socket_path().with_file_name(format!("{}{}.sock", HOST_SOCKET_PREFIX, pid))
+}
+
+/// Running IPC server; removes the socket file when dropped
+pub struct IpcServer {
+    #[cfg(unix)]
+    path: PathBuf,
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Serve the IPC socket for `handle` in a background thread.
+///
+/// Returns `None` if another process already serves the socket, or if the
+/// socket or its directory belong to another user.
+#[cfg(unix)]
+pub fn serve(handle: SessionHandle) -> Option<IpcServer> {
+    let path = socket_path();
+    prepare_socket(&path)?;
+    if path.exists() {
+        // A reachable socket belongs to another live host
+        if UnixStream::connect(&path).is_ok() {
+            return None;
+        }
+        let _ = fs::remove_file(&path);
+    }
+    listen(path, handle)
+}
+
+/// Serve the socket of this process for `handle` in a background thread,
+/// so the session can be found by `sessions` whoever serves the shared one.
+///
+/// Returns `None` if the socket or its directory belong to another user.
+#[cfg(unix)]
+pub fn register(handle: SessionHandle) -> Option<IpcServer> {
+    let path = host_socket_path(std::process::id());
+    prepare_socket(&path)?;
+    // Left behind by an earlier process with the same ID
+    let _ = fs::remove_file(&path);
+    listen(path, handle)
+}
+
+/// Create the directory of the socket at `path` for its owner; `None` if
+/// the directory or the socket belong to another user
+#[cfg(unix)]
+fn prepare_socket(path: &std::path::Path) -> Option<()> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let owner = timeguardian::client::socket_owner();
+    let dir = path.parent()?;
+    if !dir.exists() {
+        fs::create_dir_all(dir).ok()?;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700)).ok()?;
+        std::os::unix::fs::chown(dir, Some(owner), None).ok()?;
+    }
+    // Never reuse a directory or socket someone else could have prepared
+    let foreign = |path: &std::path::Path| {
+        fs::symlink_metadata(path).is_ok_and(|metadata| metadata.uid() != owner && metadata.uid() != 0)
+    };
+    (!foreign(dir) && !foreign(path)).then_some(())
+}
+
+/// Bind the socket at `path` and answer its clients in a background thread
+#[cfg(unix)]
+fn listen(path: PathBuf, handle: SessionHandle) -> Option<IpcServer> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let listener = UnixListener::bind(&path).ok()?;
+    // Only the owner reaches it; a host running as root through sudo hands it
+    // to the user who called sudo, whose editor plugins talk to it
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).ok()?;
+    std::os::unix::fs::chown(&path, Some(timeguardian::client::socket_owner()), None).ok()?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let handle = handle.clone();
+            thread::spawn(move || serve_client(stream, handle));
+        }
+    });
+
+    Some(IpcServer { path })
+}
+
+/// IPC is only available on Unix platforms
+#[cfg(not(unix))]
+pub fn serve(_handle: SessionHandle) -> Option<IpcServer> {
+    None
+}
+
+/// IPC is only available on Unix platforms
+#[cfg(not(unix))]
+pub fn register(_handle: SessionHandle) -> Option<IpcServer> {
+    None
+}
+
+/// The sockets of all processes running a session with their status,
+/// oldest process first; sockets nobody answers on are skipped
+#[cfg(unix)]
+pub fn sessions() -> Vec<(PathBuf, SessionStatus)> {
+    let Some(dir) = socket_path().parent().map(std::path::Path::to_path_buf) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut pids: Vec<u32> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            name.strip_prefix(HOST_SOCKET_PREFIX)?.strip_suffix(".sock")?.parse().ok()
+        })
+        .collect();
+    pids.sort();
+
+    pids.into_iter()
+        .map(host_socket_path)
+        .filter_map(|path| {
+            let status = query_at(&path, &Request::Status).ok()??.status?;
+            Some((path, status))
+        })
+        .collect()
+}
+
+/// IPC is only available on Unix platforms
+#[cfg(not(unix))]
+pub fn sessions() -> Vec<(PathBuf, SessionStatus)> {
+    Vec::new()
+}
+
+/// Answer the requests of a single client connection
+#[cfg(unix)]
+fn serve_client(stream: UnixStream, handle: SessionHandle) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                if write_response(&mut writer, &Response::error(format!("invalid request: {}", e))).is_err() {
+                    return;
+                }
+                continue;
+            }
+        };
+        metrics::record_ipc_request(request.name());
+
+        if let Request::Subscribe { interval_secs } = request {
+            let interval = interval_secs
+                .map_or(SUBSCRIBE_INTERVAL, |secs| Duration::from_secs(secs).max(SUBSCRIBE_INTERVAL));
+            // Stream until the client goes away
+            while write_response(&mut writer, &handle.handle(&request)).is_ok() {
+                thread::sleep(interval);
+            }
+            return;
+        }
+
+        if write_response(&mut writer, &handle.handle(&request)).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(unix)]
+fn write_response(writer: &mut impl Write, response: &Response) -> std::io::Result<()> {
+    let line = serde_json::to_string(response).map_err(std::io::Error::other)?;
+    writeln!(writer, "{}", line)?;
+    writer.flush()
+}
+
+/// Send a request to the running host and print every response line.
+///
+/// Returns `Ok(false)` if no host is running.
+#[cfg(unix)]
+pub fn send(request: &Request) -> Result<bool> {
+    let Ok(mut stream) = UnixStream::connect(socket_path()) else {
+        return Ok(false);
+    };
+
+    let line = serde_json::to_string(request)?;
+    writeln!(stream, "{}", line)?;
+    stream.flush()?;
+
+    let subscribe = matches!(request, Request::Subscribe { .. });
+    for line in BufReader::new(stream).lines() {
+        println!("{}", line?);
+        if !subscribe {
+            break;
+        }
+    }
+
+    Ok(true)
+}
+
+/// IPC is only available on Unix platforms
+#[cfg(not(unix))]
+pub fn send(_request: &Request) -> Result<bool> {
+    Err(eyre!("The IPC socket is only available on Unix platforms"))
+}
+
+/// Send a request to the running host and return its response.
+///
+/// Returns `Ok(None)` if no host is running.
+pub fn query(request: &Request) -> Result<Option<Response>> {
+    query_at(&socket_path(), request)
+}
+
+/// Send a request to the host serving the socket at `path` and return its
+/// response.
+///
+/// Returns `Ok(None)` if no host serves it.
+#[cfg(unix)]
+pub fn query_at(path: &std::path::Path, request: &Request) -> Result<Option<Response>> {
+    let Ok(mut stream) = UnixStream::connect(path) else {
+        return Ok(None);
+    };
+
+    writeln!(stream, "{}", serde_json::to_string(request)?)?;
+    stream.flush()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+/// Without IPC no other host can be reached
+#[cfg(not(unix))]
+pub fn query_at(_path: &std::path::Path, _request: &Request) -> Result<Option<Response>> {
+    Ok(None)
+}
+
+/// Subscribe to the running host and pass every status, one per `interval`,
+/// to `on_status` until it returns `false`. When the host goes away, an idle
+/// status is passed last.
+///
+/// Returns `Ok(false)` if no host is running.
+pub fn watch(interval: Duration, on_status: impl FnMut(&SessionStatus) -> Result<bool>) -> Result<bool> {
+    watch_at(&socket_path(), interval, on_status)
+}
+
+/// Like `watch`, for the host serving the socket at `path`
+#[cfg(unix)]
+pub fn watch_at(
+    path: &std::path::Path,
+    interval: Duration,
+    mut on_status: impl FnMut(&SessionStatus) -> Result<bool>,
+) -> Result<bool> {
+    let Ok(mut stream) = UnixStream::connect(path) else {
+        return Ok(false);
+    };
+
+    let request = Request::Subscribe { interval_secs: Some(interval.as_secs()) };
+    writeln!(stream, "{}", serde_json::to_string(&request)?)?;
+    stream.flush()?;
+
+    for line in BufReader::new(stream).lines() {
+        let response: Response = serde_json::from_str(&line?)?;
+        let Some(status) = response.status else {
+            break;
+        };
+        if !on_status(&status)? {
+            return Ok(true);
+        }
+    }
+
+    on_status(&SessionStatus::default())?;
+    Ok(true)
+}
+
+/// IPC is only available on Unix platforms
+#[cfg(not(unix))]
+pub fn watch_at(
+    _path: &std::path::Path,
+    _interval: Duration,
+    _on_status: impl FnMut(&SessionStatus) -> Result<bool>,
+) -> Result<bool> {
+    Err(eyre!("The IPC socket is only available on Unix platforms"))
+}
+
+/// Print the status line of an idle system (no host running)
+pub fn print_idle_status() -> Result<()> {
+    print_status(&SessionStatus::default())
+}
+
+/// Print a status as the response line a host would send
+pub fn print_status(status: &SessionStatus) -> Result<()> {
+    let response = Response::status(status.clone());
+    println!("{}", serde_json::to_string(&response).map_err(|e| eyre!(e))?);
+    Ok(())
+}
+
+/// Print an error response line
+pub fn print_error(message: &str) -> Result<()> {
+    println!("{}", serde_json::to_string(&Response::error(message)).map_err(|e| eyre!(e))?);
+    Ok(())
+}
+
+/// Whether a host is serving the IPC socket
+pub fn host_running() -> bool {
+    #[cfg(unix)]
+    {
+        UnixStream::connect(socket_path()).is_ok()
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Wait until a host serves the socket or the timeout expires
+pub fn wait_for_host(timeout: Duration) -> bool {
+    let start = std::time::Instant::now();
+    while start.elapsed() < timeout {
+        if host_running() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+/// Handle `timeguardian ipc` requests
+pub fn run_command(command: &IpcCommand) -> Result<()> {
+    let request = match command {
+        IpcCommand::Status => Request::Status,
+        IpcCommand::Stop => Request::Stop,
+        IpcCommand::Subscribe => Request::Subscribe { interval_secs: None },
+        IpcCommand::Start { duration, task } => {
+            parse_duration(duration)?;
+            Request::Start {
+                duration: duration.clone(),
+                task: task.clone(),
+            }
+        }
+    };
+
+    if send(&request)? {
+        return Ok(());
+    }
+
+    // No host is running
+    match command {
+        IpcCommand::Status => print_idle_status(),
+        IpcCommand::Stop => print_error("no session is running"),
+        IpcCommand::Subscribe => {
+            // Keep reporting idle until a host appears
+            while !host_running() {
+                print_idle_status()?;
+                std::thread::sleep(Duration::from_secs(1));
+            }
+            send(&request).map(|_| ())
+        }
+        IpcCommand::Start { duration, task } => {
+            // Run the session as a background CLI process
+            let args = [
+                "start".to_string(),
+                "--duration".to_string(),
+                duration.clone(),
+                "--task".to_string(),
+                task.clone().unwrap_or_else(|| "Focus session".to_string()),
+            ];
+
+            if attach::spawn_background_session(&args)? {
+                send(&Request::Status).map(|_| ())
+            } else {
+                print_error("the session could not be started (missing permissions?)")
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A host whose session runs for another 25 minutes
+    fn running(accepts_start: bool) -> SessionHandle {
+        let handle = SessionHandle::new(accepts_start);
+        let now = Local::now();
+        handle.set_session(Some("Thesis".to_string()), Priority::High, now, now + chrono::Duration::minutes(25));
+        handle.set_session_id("01JS2Q8W6X3T9V7M5K4N2P1R0H");
+        handle
+    }
+
+    fn json(response: &Response) -> serde_json::Value {
+        serde_json::to_value(response).unwrap()
+    }
+
+    #[test]
+    fn requests_parse_from_the_documented_lines() {
+        let parse = |line| serde_json::from_str::<Request>(line).unwrap();
+        assert!(matches!(parse(r#"{"cmd":"status"}"#), Request::Status));
+        assert!(matches!(parse(r#"{"cmd":"stop"}"#), Request::Stop));
+        assert!(matches!(parse(r#"{"cmd":"subscribe"}"#), Request::Subscribe { interval_secs: None }));
+        assert!(matches!(
+            parse(r#"{"cmd":"subscribe","interval_secs":5}"#),
+            Request::Subscribe { interval_secs: Some(5) }
+        ));
+        let Request::Start { duration, task } = parse(r#"{"cmd":"start","duration":"25m","task":"Thesis"}"#) else {
+            panic!("not a start request");
+        };
+        assert_eq!((duration.as_str(), task.as_deref()), ("25m", Some("Thesis")));
+        assert!(matches!(parse(r#"{"cmd":"start","duration":"25m"}"#), Request::Start { task: None, .. }));
+
+        for line in [r#"{"cmd":"pause"}"#, r#"{"cmd":"start"}"#, r#"{"command":"status"}"#, "status"] {
+            assert!(serde_json::from_str::<Request>(line).is_err(), "{}", line);
+        }
+        // Clients write the same lines
+        let subscribe = Request::Subscribe { interval_secs: None };
+        assert_eq!(serde_json::to_string(&subscribe).unwrap(), r#"{"cmd":"subscribe"}"#);
+    }
+
+    #[test]
+    fn status_lists_only_the_fields_of_a_running_session() {
+        let idle = SessionHandle::new(false);
+        assert_eq!(
+            serde_json::to_string(&idle.handle(&Request::Status)).unwrap(),
+            r#"{"ok":true,"status":{"active":false}}"#
+        );
+
+        let handle = running(false);
+        let status = json(&handle.handle(&Request::Status))["status"].clone();
+        assert_eq!(status["active"], true);
+        assert_eq!(status["task"], "Thesis");
+        assert_eq!(status["session_id"], "01JS2Q8W6X3T9V7M5K4N2P1R0H");
+        assert_eq!(status["priority"], "high");
+        assert!((1490..=1500).contains(&status["remaining_secs"].as_u64().unwrap()));
+        // All enabled lists unless the session names some
+        assert!(status.get("lists").is_none());
+        handle.set_lists(&["Social".to_string()]);
+        assert_eq!(json(&handle.handle(&Request::Status))["status"]["lists"], serde_json::json!(["Social"]));
+
+        handle.clear_session();
+        assert_eq!(json(&handle.handle(&Request::Status))["status"], serde_json::json!({"active": false}));
+    }
+
+    #[test]
+    fn stop_needs_a_running_session_that_isnt_strict() {
+        let idle = SessionHandle::new(false);
+        assert_eq!(
+            serde_json::to_string(&idle.handle(&Request::Stop)).unwrap(),
+            r#"{"ok":false,"error":"no session is running"}"#
+        );
+        assert!(!idle.take_stop_request());
+
+        let handle = running(false);
+        assert!(handle.handle(&Request::Stop).ok);
+        // The host takes the request once
+        assert!(handle.take_stop_request());
+        assert!(!handle.take_stop_request());
+
+        handle.set_strict();
+        assert!(!handle.handle(&Request::Stop).ok);
+        assert!(!handle.take_stop_request());
+        // Strictness ends with the session
+        handle.clear_session();
+        let now = Local::now();
+        handle.set_session(None, Priority::Normal, now, now + chrono::Duration::minutes(5));
+        assert!(handle.handle(&Request::Stop).ok);
+    }
+
+    #[test]
+    fn start_needs_an_idle_host_that_accepts_it() {
+        let start = Request::Start { duration: "25m".to_string(), task: Some("Thesis".to_string()) };
+
+        let handle = SessionHandle::new(true);
+        assert!(handle.handle(&start).ok);
+        let pending = handle.take_start_request().unwrap();
+        assert_eq!((pending.duration.as_str(), pending.task.as_deref()), ("25m", Some("Thesis")));
+        assert!(handle.take_start_request().is_none());
+
+        for (handle, error) in [
+            (SessionHandle::new(false), "this host cannot start sessions"),
+            (running(true), "a session is already running"),
+        ] {
+            let response = handle.handle(&start);
+            assert_eq!(response.error.as_deref(), Some(error));
+            assert!(handle.take_start_request().is_none());
+        }
+    }
+
+    #[test]
+    fn extending_moves_the_end_of_the_session() {
+        let handle = running(false);
+        let ends_at = handle.status().ends_at.unwrap();
+        handle.extend(Duration::from_secs(5 * 60));
+        assert_eq!(handle.status().ends_at.unwrap(), ends_at + chrono::Duration::minutes(5));
+
+        // Nothing to extend without a session
+        let idle = SessionHandle::new(false);
+        idle.extend(Duration::from_secs(60));
+        assert!(idle.status().ends_at.is_none());
+    }
+}
@@ -0,0 +1,276 @@
+/*
+* TimeGuardian Journal Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module makes hosts file writes and firewall changes recoverable.
+* Before the hosts file is rewritten, the old and new content are stored in
+* a write-ahead journal in the state directory; the entry is removed once
+* the write succeeded. If TimeGuardian crashes mid-write, the next run finds
+* the entry and resolves the half-applied operation:
+*
+* - the file has the new content: the write completed, nothing to do
+* - the file still has the old content: the write never started
+* - the file holds a torn prefix of the new content: the write is completed
+* - anything else was changed by another tool and is left alone
+*
+* Firewall changes (loading, extending and flushing the rules) take several
+* commands of nft, iptables, pfctl or netsh, so their progress can't be
+* read back like a file. They get an entry of their own naming the firewall;
+* after a crash its rules are flushed and the block of the sessions still
+* claimed is loaded again from scratch.
+*/
+
+use chrono::{DateTime, Local, Utc};
+use color_eyre::{eyre::Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::firewall::{self, FirewallBackend};
+use crate::{get_state_dir, hosts};
+
+/// File in the state directory holding the pending operation
+pub const JOURNAL_FILE: &str = "journal.toml";
+
+/// File in the state directory holding the pending firewall change
+pub const FIREWALL_JOURNAL_FILE: &str = "firewall-journal.toml";
+
+/// A file write that has not been confirmed yet
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Entry {
+    /// What the write does (e.g. "reconcile", "reset")
+    operation: String,
+    /// ULIDs of the sessions blocked by the new content
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sessions: Vec<String>,
+    /// The written file
+    path: PathBuf,
+    /// Content before the write
+    before: String,
+    /// Content after the write
+    after: String,
+    /// When the write started
+    started_at: DateTime<Utc>,
+}
+
+/// A firewall change that has not been confirmed yet
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct FirewallEntry {
+    /// What the change does ("load", "add", "flush")
+    operation: String,
+    /// Firewall whose rules are changed
+    backend: FirewallBackend,
+    /// When the change started
+    started_at: DateTime<Utc>,
+}
+
+/// How an interrupted file write was resolved
+#[derive(Debug, PartialEq, Eq)]
+enum Resolution {
+    /// The file has the new content
+    Completed,
+    /// The file still has the old content
+    NeverStarted,
+    /// The file held a torn prefix of the new content, which was completed
+    Repaired,
+    /// The file was changed by something else and left alone
+    Changed,
+}
+
+/// Replace the content of `path` (currently `before`) with `after`,
+/// journaling the operation until it is complete
+pub fn write(operation: &str, sessions: &[String], path: &Path, before: &str, after: &str) -> Result<()> {
+    let entry = Entry {
+        operation: operation.to_string(),
+        sessions: sessions.to_vec(),
+        path: path.to_path_buf(),
+        before: before.to_string(),
+        after: after.to_string(),
+        started_at: Utc::now(),
+    };
+
+    // The entry has to be on disk before the file is touched
+    let journal_path = journal_path(JOURNAL_FILE)?;
+    store(&journal_path, &toml::to_string(&entry).wrap_err("Could not serialize journal entry")?)?;
+
+    let written = fs::write(path, after);
+    hosts::invalidate_cache();
+    written.wrap_err_with(|| format!("Could not update {:?}", path))?;
+    fs::remove_file(&journal_path).wrap_err_with(|| format!("Could not clear journal: {:?}", journal_path))
+}
+
+/// Run `change` on the rules of `backend`, journaling it until it succeeded.
+///
+/// A failing change keeps its entry as well, since it may have run some of
+/// its commands; the next run cleans up after it like after a crash.
+pub fn change_firewall(operation: &str, backend: FirewallBackend, change: impl FnOnce() -> Result<()>) -> Result<()> {
+    let entry = FirewallEntry { operation: operation.to_string(), backend, started_at: Utc::now() };
+    let journal_path = journal_path(FIREWALL_JOURNAL_FILE)?;
+    store(&journal_path, &toml::to_string(&entry).wrap_err("Could not serialize journal entry")?)?;
+
+    change()?;
+    fs::remove_file(&journal_path).wrap_err_with(|| format!("Could not clear journal: {:?}", journal_path))
+}
+
+/// Resolve the operations left behind by a crash: first a hosts file
+/// write, then a firewall change.
+///
+/// Returns a description of what was done, if there were pending entries.
+pub fn recover() -> Result<Option<String>> {
+    let messages: Vec<String> = [recover_write()?, recover_firewall()?].into_iter().flatten().collect();
+    Ok((!messages.is_empty()).then(|| messages.join("\n")))
+}
+
+/// Resolve a pending hosts file write
+fn recover_write() -> Result<Option<String>> {
+    let journal_path = journal_path(JOURNAL_FILE)?;
+    let Ok(content) = fs::read_to_string(&journal_path) else {
+        return Ok(None);
+    };
+
+    let message = match toml::from_str::<Entry>(&content) {
+        Ok(entry) => {
+            let mut started = started(entry.started_at);
+            if !entry.sessions.is_empty() {
+                let noun = if entry.sessions.len() == 1 { "session" } else { "sessions" };
+                started.push_str(&format!(" ({} {})", noun, entry.sessions.join(", ")));
+            }
+            match resolve(&entry)? {
+                Resolution::Completed => {
+                    format!("The interrupted {} of {} completed before the crash.", entry.operation, started)
+                }
+                Resolution::NeverStarted => {
+                    format!("The interrupted {} of {} never started; nothing changed.", entry.operation, started)
+                }
+                Resolution::Repaired => {
+                    hosts::flush_dns_cache();
+                    format!("Completed the {} of {} that was interrupted mid-write.", entry.operation, started)
+                }
+                Resolution::Changed => format!(
+                    "The interrupted {} of {} was not repaired: {:?} was changed since.",
+                    entry.operation, started, entry.path
+                ),
+            }
+        }
+        Err(_) => "Discarded an unreadable journal entry.".to_string(),
+    };
+
+    fs::remove_file(&journal_path).wrap_err_with(|| format!("Could not clear journal: {:?}", journal_path))?;
+    Ok(Some(message))
+}
+
+/// Compare the written file with the entry, completing a torn write
+fn resolve(entry: &Entry) -> Result<Resolution> {
+    let current = fs::read_to_string(&entry.path).unwrap_or_default();
+    if current == entry.after {
+        Ok(Resolution::Completed)
+    } else if current == entry.before {
+        Ok(Resolution::NeverStarted)
+    } else if entry.after.starts_with(&current) {
+        let written = fs::write(&entry.path, &entry.after);
+        hosts::invalidate_cache();
+        written.wrap_err_with(|| format!("Could not repair {:?}", entry.path))?;
+        Ok(Resolution::Repaired)
+    } else {
+        Ok(Resolution::Changed)
+    }
+}
+
+/// Undo a pending firewall change and load the block of the claimed
+/// sessions again
+fn recover_firewall() -> Result<Option<String>> {
+    let journal_path = journal_path(FIREWALL_JOURNAL_FILE)?;
+    let Ok(content) = fs::read_to_string(&journal_path) else {
+        return Ok(None);
+    };
+
+    let message = match toml::from_str::<FirewallEntry>(&content) {
+        Ok(entry) => {
+            firewall::roll_back(entry.backend)?;
+            hosts::refresh()?;
+            format!(
+                "Flushed {} after the firewall {} of {} was interrupted; the running sessions are blocked again.",
+                entry.backend.location(),
+                entry.operation,
+                started(entry.started_at)
+            )
+        }
+        Err(_) => "Discarded an unreadable firewall journal entry.".to_string(),
+    };
+
+    fs::remove_file(&journal_path).wrap_err_with(|| format!("Could not clear journal: {:?}", journal_path))?;
+    Ok(Some(message))
+}
+
+/// Start of an operation for messages
+fn started(at: DateTime<Utc>) -> String {
+    at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Write a journal entry and make sure it is on disk
+fn store(path: &Path, content: &str) -> Result<()> {
+    let mut journal = File::create(path).wrap_err_with(|| format!("Could not create journal: {:?}", path))?;
+    journal.write_all(content.as_bytes())?;
+    journal.sync_all()?;
+    Ok(())
+}
+
+/// Path of a journal file
+fn journal_path(file: &str) -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An entry rewriting a file in the temp directory that currently holds `current`
+    fn entry(name: &str, current: &str) -> Entry {
+        let path = std::env::temp_dir().join(format!("timeguardian-journal-{}-{}", std::process::id(), name));
+        fs::write(&path, current).unwrap();
+        Entry {
+            operation: "reconcile".to_string(),
+            sessions: Vec::new(),
+            path,
+            before: "127.0.0.1\tlocalhost\n".to_string(),
+            after: "127.0.0.1\tlocalhost\n0.0.0.0\treddit.com\n".to_string(),
+            started_at: Utc::now(),
+        }
+    }
+
+    /// Resolve the entry and return the resolution with the file afterwards
+    fn resolved(entry: &Entry) -> (Resolution, String) {
+        let resolution = resolve(entry).unwrap();
+        let content = fs::read_to_string(&entry.path).unwrap();
+        fs::remove_file(&entry.path).unwrap();
+        (resolution, content)
+    }
+
+    #[test]
+    fn finished_write_is_left_alone() {
+        let entry = entry("after", "127.0.0.1\tlocalhost\n0.0.0.0\treddit.com\n");
+        assert_eq!(resolved(&entry), (Resolution::Completed, entry.after.clone()));
+    }
+
+    #[test]
+    fn write_that_never_started_is_left_alone() {
+        let entry = entry("before", "127.0.0.1\tlocalhost\n");
+        assert_eq!(resolved(&entry), (Resolution::NeverStarted, entry.before.clone()));
+    }
+
+    #[test]
+    fn torn_write_is_completed() {
+        let entry = entry("torn", "127.0.0.1\tlocalhost\n0.0.0.0\tred");
+        assert_eq!(resolved(&entry), (Resolution::Repaired, entry.after.clone()));
+    }
+
+    #[test]
+    fn file_changed_by_another_tool_is_left_alone() {
+        let changed = "127.0.0.1\tlocalhost\n10.0.0.2\tprinter.local\n";
+        let entry = entry("changed", changed);
+        assert_eq!(resolved(&entry), (Resolution::Changed, changed.to_string()));
+    }
+}
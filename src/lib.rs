@@ -0,0 +1,80 @@
+/*
+* TimeGuardian Library
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* Reusable pieces of TimeGuardian for other applications: the core types of
+* sessions, blocking backends, website lists and schedules, the ratatui
+* session widget for dashboards, and the IPC client used by graphical
+* frontends and bots.
+*/
+
+//! Reusable parts of TimeGuardian for other applications.
+//!
+//! The stable API is what the crate root re-exports:
+//!
+//! - [`Session`] and [`Priority`]: a running focus session as the backend
+//!   reports it, from [`client::Status::session`]
+//! - [`Blocker`] and [`Capabilities`]: a way of blocking websites and what it
+//!   supports
+//! - [`ListStore`] and [`WebsiteList`]: the website lists in their files below
+//!   the config directory, and the read-only system lists
+//! - [`Schedule`] and [`Occurrence`]: recurring blocks and their concrete
+//!   windows
+//! - [`SessionWidget`] and [`SessionState`] to show a session in a ratatui
+//!   interface, and [`Countdown`] and [`TimeUnit`] for picking its length
+//!
+//! It follows semver: removing or changing any of them, or a public method or
+//! field of them, needs a new major version (a new minor one before 1.0). The
+//! modules they live in, and [`client`], which speaks the IPC protocol (see
+//! `docs/ipc.md`), follow semver as well. ratatui is re-exported, since a new
+//! major version of it is a breaking change for the widget as well.
+//!
+//! Blocking itself stays in the binary, the backend: it owns the hosts file,
+//! the firewall and the history, and frontends reach a running session only
+//! over its IPC socket.
+//!
+//! ```
+//! use std::time::Duration;
+//! use timeguardian::ratatui::{buffer::Buffer, layout::Rect, widgets::StatefulWidget};
+//! use timeguardian::{SessionState, SessionWidget};
+//!
+//! let mut state = SessionState::new(
+//!     Some("Write report".to_string()),
+//!     Duration::from_secs(25 * 60),
+//!     Duration::from_secs(10 * 60),
+//! );
+//! assert_eq!(state.progress(), 0.6);
+//!
+//! let area = Rect::new(0, 0, 40, 4);
+//! let mut buffer = Buffer::empty(area);
+//! SessionWidget::new().render(area, &mut buffer, &mut state);
+//! ```
+
+#![warn(missing_docs)]
+
+/// Ways of blocking websites and their capabilities
+pub mod blocking;
+/// Status, start and stop of the running session for frontends, over IPC
+pub mod client;
+/// Session lengths picked in steps
+pub mod countdown;
+/// Duration and time-of-day formatting shared with the binary; not part of
+/// the stable API
+#[doc(hidden)]
+pub mod format;
+/// Website lists and their files
+pub mod lists;
+/// Recurring blocking schedules
+pub mod schedule;
+/// Focus sessions and their priority
+pub mod session;
+/// The ratatui widget of a running session
+pub mod widget;
+
+pub use blocking::{Blocker, Capabilities};
+pub use countdown::{Countdown, TimeUnit};
+pub use lists::{ListStore, WebsiteList};
+pub use ratatui;
+pub use schedule::{Occurrence, Schedule};
+pub use session::{Priority, Session};
+pub use widget::{SessionState, SessionWidget};
@@ -0,0 +1,210 @@
+/*
+* TimeGuardian Lists Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module holds the website lists and keeps them in files of their own under `lists/` in the
+* config directory, referenced from config.toml by `list_files`. Huge lists
+* don't bloat the config, diffs of a config kept in git stay readable and a
+* list can be shared by copying one file. A list file holds one list as TOML
+* or JSON:
+*
+*   name = "Work"
+*   websites = ["reddit.com", "news.ycombinator.com"]
+*
+* Lists written inline in config.toml keep working; they are moved to files
+* on the next save.
+*
+* Administrators can put list files into a system directory
+* (/etc/timeguardian/lists, or %ProgramData%\TimeGuardian\lists on Windows).
+* These lists are read-only for users and merged into every session that
+* blocks, on top of the lists the user picked, as a shared baseline.
+*/
+
+use color_eyre::{eyre::Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Directory of the list files in the config directory
+pub const LIST_DIR: &str = "lists";
+
+/// A named list of websites blocked together
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WebsiteList {
+    /// Name of the list, unique regardless of case
+    pub name: String,
+    /// Domains and entries like "*.example.com" or "allow:docs.example.com"
+    pub websites: Vec<String>,
+    /// Disabled lists are left out of sessions and schedules that block all lists
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Read-only list of the system list directory, blocked in every session
+    #[serde(skip)]
+    pub system: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// The list files of a config directory
+///
+/// ```
+/// use timeguardian::{ListStore, WebsiteList};
+///
+/// let dir = std::env::temp_dir().join(format!("timeguardian-doc-lists-{}", std::process::id()));
+/// let store = ListStore::new(&dir);
+/// let work = WebsiteList {
+///     name: "Deep Work".to_string(),
+///     websites: vec!["reddit.com".to_string(), "news.ycombinator.com".to_string()],
+///     enabled: true,
+///     system: false,
+/// };
+///
+/// // The references go into `list_files` of config.toml
+/// let files = store.save(&[work.clone()], &[])?;
+/// assert_eq!(files, ["lists/deep-work.toml"]);
+/// assert_eq!(store.load(&files)?, [work]);
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), color_eyre::Report>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListStore {
+    config_dir: PathBuf,
+}
+
+impl ListStore {
+    /// The list files below `config_dir`
+    pub fn new(config_dir: impl Into<PathBuf>) -> Self {
+        Self { config_dir: config_dir.into() }
+    }
+
+    /// Directory of the system lists, shared by all users
+    pub fn system_dir() -> PathBuf {
+        if cfg!(target_os = "windows") {
+            let program_data = std::env::var_os("ProgramData").unwrap_or_else(|| "C:\\ProgramData".into());
+            PathBuf::from(program_data).join("TimeGuardian").join(LIST_DIR)
+        } else {
+            PathBuf::from("/etc/timeguardian").join(LIST_DIR)
+        }
+    }
+
+    /// The lists of the system directory, in file name order; none if it doesn't exist
+    pub fn system_lists() -> Result<Vec<WebsiteList>> {
+        let dir = Self::system_dir();
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return Ok(Vec::new());
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml" || ext == "json"))
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| {
+                let mut list = read(path)?;
+                list.system = true;
+                Ok(list)
+            })
+            .collect()
+    }
+
+    /// Read the lists referenced by `files`, relative to the config directory
+    pub fn load(&self, files: &[String]) -> Result<Vec<WebsiteList>> {
+        files.iter().map(|file| read(&self.config_dir.join(file))).collect()
+    }
+
+    /// Write every list to its file and remove the files of deleted lists.
+    ///
+    /// Lists keep the file they were loaded from (`previous`); new lists get
+    /// `lists/<name>.toml`. Unchanged files are not touched. Returns the
+    /// references to store in the config.
+    pub fn save(&self, lists: &[WebsiteList], previous: &[String]) -> Result<Vec<String>> {
+        let config_dir = &self.config_dir;
+        // Lists of a broken or missing file are written again
+        let mut existing: Vec<(String, Option<WebsiteList>)> = previous
+            .iter()
+            .map(|file| (file.clone(), read(&config_dir.join(file)).ok()))
+            .collect();
+
+        let mut files = Vec::new();
+        for list in lists {
+            let position = existing
+                .iter()
+                .position(|(_, old)| old.as_ref().is_some_and(|old| old.name.eq_ignore_ascii_case(&list.name)));
+            let (file, old) = match position {
+                Some(index) => existing.remove(index),
+                None => (new_file(config_dir, &list.name, &files), None),
+            };
+            if old.as_ref() != Some(list) {
+                write(&config_dir.join(&file), list)?;
+            }
+            files.push(file);
+        }
+
+        // Only files in the list directory are ours to delete
+        for (file, _) in existing {
+            if Path::new(&file).starts_with(LIST_DIR) && !files.contains(&file) {
+                let _ = fs::remove_file(config_dir.join(&file));
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Read one list file
+fn read(path: &Path) -> Result<WebsiteList> {
+    let content = fs::read_to_string(path).wrap_err_with(|| format!("Could not read list file {:?}", path))?;
+    if is_json(path) {
+        serde_json::from_str(&content).wrap_err_with(|| format!("Could not parse list file {:?}", path))
+    } else {
+        toml::from_str(&content).wrap_err_with(|| format!("Could not parse list file {:?}", path))
+    }
+}
+
+/// Write one list file in the format of its extension
+fn write(path: &Path, list: &WebsiteList) -> Result<()> {
+    let content = if is_json(path) {
+        serde_json::to_string_pretty(list).wrap_err("Could not serialize list")?
+    } else {
+        toml::to_string(list).wrap_err("Could not serialize list")?
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).wrap_err_with(|| format!("Could not create {:?}", parent))?;
+    }
+
+    // Replaced atomically like the config itself
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+    fs::write(&temp_path, content).wrap_err_with(|| format!("Could not save list file {:?}", temp_path))?;
+    fs::rename(&temp_path, path).wrap_err_with(|| format!("Could not save list file {:?}", path))
+}
+
+/// A free file name for a new list, e.g. "lists/deep-work.toml"
+fn new_file(config_dir: &Path, name: &str, taken: &[String]) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let slug = if slug.is_empty() { "list".to_string() } else { slug };
+
+    (1..)
+        .map(|n| match n {
+            1 => format!("{}/{}.toml", LIST_DIR, slug),
+            n => format!("{}/{}-{}.toml", LIST_DIR, slug, n),
+        })
+        .find(|file| !taken.contains(file) && !config_dir.join(file).exists())
+        .unwrap_or_default()
+}
+
+/// Whether a list file is JSON rather than TOML
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
@@ -0,0 +1,84 @@
+/*
+* TimeGuardian Live Line Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module draws the CLI countdown as a single line that is updated in
+* place with crossterm cursor control, instead of printing a new spinner on
+* every tick.
+*/
+
+use crossterm::{
+    cursor::{Hide, MoveToColumn, Show},
+    style::Print,
+    terminal::{self, Clear, ClearType},
+    QueueableCommand,
+};
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+/// Frames of the activity indicator in front of the line
+const SPINNER_FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+/// Width of the progress bar in characters
+const BAR_WIDTH: usize = 20;
+
+/// A line of terminal output that is redrawn in place
+#[derive(Default)]
+pub struct LiveLine {
+    frame: usize,
+    visible: bool,
+}
+
+impl LiveLine {
+    /// Replace the content of the line
+    pub fn draw(&mut self, text: &str) -> io::Result<()> {
+        let mut out = io::stdout();
+        let spinner = SPINNER_FRAMES[self.frame % SPINNER_FRAMES.len()];
+        self.frame += 1;
+
+        // Never wrap, otherwise the next redraw can't overwrite the line
+        let width = terminal::size().map(|(cols, _)| cols as usize).unwrap_or(80);
+        let line: String = format!("{} {}", spinner, text).chars().take(width.saturating_sub(1)).collect();
+
+        if !self.visible {
+            out.queue(Hide)?;
+            self.visible = true;
+        }
+        out.queue(MoveToColumn(0))?
+            .queue(Clear(ClearType::CurrentLine))?
+            .queue(Print(line))?;
+        out.flush()
+    }
+
+    /// Clear the line and give the cursor back
+    pub fn finish(&mut self) -> io::Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.visible = false;
+
+        let mut out = io::stdout();
+        out.queue(MoveToColumn(0))?
+            .queue(Clear(ClearType::CurrentLine))?
+            .queue(Show)?;
+        out.flush()
+    }
+}
+
+impl Drop for LiveLine {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+/// Text progress bar for the elapsed share of a session, e.g. "[=====     ]"
+pub fn progress_bar(elapsed: Duration, total: Duration) -> String {
+    let ratio = if total.is_zero() {
+        1.0
+    } else {
+        (elapsed.as_secs_f64() / total.as_secs_f64()).clamp(0.0, 1.0)
+    };
+    let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+    format!("[{}{}]", "=".repeat(filled), " ".repeat(BAR_WIDTH - filled))
+}
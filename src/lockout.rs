@@ -0,0 +1,254 @@
+/*
+* TimeGuardian Lockout Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module takes configured launcher shortcuts away during strict
+* sessions, as friction on top of the network block. It is opt-in:
+*
+*   [lockout]
+*   shortcuts = ["<Super>b", "<Primary><Alt>t"]
+*   message = "The browser stays closed until the session ends."
+*
+* On GNOME the shortcuts are rebound through gsettings: built-in media keys
+* and custom shortcuts using them are disabled, and a shortcut of our own
+* runs `timeguardian remind` instead, which shows the remaining time. The
+* original bindings are kept in the state directory and put back when the
+* session ends, or by `timeguardian reset` after a crash. Other desktops
+* and platforms get a note that the lockout is not available.
+*/
+
+use color_eyre::{eyre::Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf, process::Command};
+
+use crate::{get_state_dir, ipc, load_config};
+
+/// File in the state directory holding the original bindings
+pub const LOCKOUT_FILE: &str = "lockout.toml";
+
+/// Schema of the GNOME media keys and the list of custom shortcuts
+const MEDIA_KEYS: &str = "org.gnome.settings-daemon.plugins.media-keys";
+
+/// Schema of one custom shortcut, relocated to its path
+const CUSTOM_KEYBINDING: &str = "org.gnome.settings-daemon.plugins.media-keys.custom-keybinding";
+
+/// Where our own custom shortcuts live
+const CUSTOM_PATH: &str = "/org/gnome/settings-daemon/plugins/media-keys/custom-keybindings/timeguardian";
+
+/// Shortcut lockout settings; nothing is locked without shortcuts
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LockoutConfig {
+    /// Accelerators to take over, in GTK notation like "<Super>b"
+    #[serde(default)]
+    pub shortcuts: Vec<String>,
+    /// Shown along with the remaining time when a shortcut is pressed
+    pub message: Option<String>,
+}
+
+/// The settings changed by a lockout, with their original values
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct Saved {
+    /// The list of custom shortcuts before ours were added
+    custom_keybindings: String,
+    /// Paths of the shortcuts we added
+    #[serde(default)]
+    added: Vec<String>,
+    /// Disabled settings as (schema, key, original value)
+    #[serde(default)]
+    disabled: Vec<(String, String, String)>,
+}
+
+/// Take over the configured shortcuts until `release`; returns a note for
+/// the user about what happened
+pub fn engage(config: &LockoutConfig) -> Result<String> {
+    if !supported() {
+        return Ok("Shortcut lockout is only available on GNOME; shortcuts stay as they are.".to_string());
+    }
+    // Bindings left over from a crashed session go back first
+    release()?;
+
+    let exe = std::env::current_exe().wrap_err("Could not find the TimeGuardian executable")?;
+    let mut saved = Saved {
+        custom_keybindings: gsettings_get(MEDIA_KEYS, "custom-keybindings")?,
+        ..Saved::default()
+    };
+    let taken = |value: &str| config.shortcuts.iter().any(|shortcut| binds(value, shortcut));
+
+    // Built-in launchers like "www" or "terminal"
+    for (key, value) in media_keys()? {
+        if taken(&value) {
+            saved.disabled.push((MEDIA_KEYS.to_string(), key, value));
+        }
+    }
+    // Custom shortcuts of the user
+    let existing = string_array(&saved.custom_keybindings);
+    for path in &existing {
+        let schema = format!("{}:{}", CUSTOM_KEYBINDING, path);
+        let value = gsettings_get(&schema, "binding")?;
+        if taken(&value) {
+            saved.disabled.push((schema, "binding".to_string(), value));
+        }
+    }
+    // Save before touching anything, so a crash can always be undone
+    saved.added = (0..config.shortcuts.len()).map(|index| format!("{}{}/", CUSTOM_PATH, index)).collect();
+    saved.save()?;
+
+    for (schema, key, value) in &saved.disabled {
+        let empty = if value.starts_with('[') || value.starts_with('@') { "[]" } else { "''" };
+        gsettings_set(schema, key, empty)?;
+    }
+    let command = format!("'{}' remind", exe.display().to_string().replace('\'', "'\\''"));
+    for (path, shortcut) in saved.added.iter().zip(&config.shortcuts) {
+        let schema = format!("{}:{}", CUSTOM_KEYBINDING, path);
+        gsettings_set(&schema, "name", &quote("TimeGuardian lockout"))?;
+        gsettings_set(&schema, "command", &quote(&command))?;
+        gsettings_set(&schema, "binding", &quote(shortcut))?;
+    }
+    let paths: Vec<String> = existing.iter().chain(&saved.added).map(|path| quote(path)).collect();
+    gsettings_set(MEDIA_KEYS, "custom-keybindings", &format!("[{}]", paths.join(", ")))?;
+
+    Ok(format!("Locked shortcuts until the session ends: {}", config.shortcuts.join(", ")))
+}
+
+/// Put back the bindings of the last lockout, if one is active
+pub fn release() -> Result<()> {
+    let Some(saved) = Saved::load()? else {
+        return Ok(());
+    };
+
+    gsettings_set(MEDIA_KEYS, "custom-keybindings", &saved.custom_keybindings)?;
+    for path in &saved.added {
+        let schema = format!("{}:{}", CUSTOM_KEYBINDING, path);
+        for key in ["name", "command", "binding"] {
+            gsettings(&["reset", &schema, key])?;
+        }
+    }
+    for (schema, key, value) in &saved.disabled {
+        gsettings_set(schema, key, value)?;
+    }
+
+    let path = lockout_path()?;
+    fs::remove_file(&path).wrap_err_with(|| format!("Could not remove {:?}", path))
+}
+
+/// Show `message` as a desktop notification, or print it without one
+pub fn remind(message: &str) {
+    #[cfg(target_os = "linux")]
+    let shown = Command::new("notify-send")
+        .args(["--app-name=TimeGuardian", "TimeGuardian", message])
+        .status()
+        .is_ok_and(|status| status.success());
+
+    #[cfg(not(target_os = "linux"))]
+    let shown = false;
+
+    if !shown {
+        println!("{}", message);
+    }
+}
+
+impl Saved {
+    /// Load the saved bindings, if a lockout is active
+    fn load() -> Result<Option<Self>> {
+        let path = lockout_path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Could not read saved shortcuts: {:?}", path))?;
+        toml::from_str(&content).map(Some).wrap_err("Could not parse saved shortcuts")
+    }
+
+    /// Save the bindings to the state directory
+    fn save(&self) -> Result<()> {
+        let path = lockout_path()?;
+        let content = toml::to_string(self).wrap_err("Could not serialize saved shortcuts")?;
+        fs::write(&path, content).wrap_err_with(|| format!("Could not save shortcuts: {:?}", path))
+    }
+}
+
+/// Whether the shortcuts can be rebound: a GNOME session with gsettings
+fn supported() -> bool {
+    cfg!(target_os = "linux")
+        && std::env::var("XDG_CURRENT_DESKTOP").is_ok_and(|desktop| desktop.to_lowercase().contains("gnome"))
+        && gsettings(&["list-keys", MEDIA_KEYS]).is_ok()
+}
+
+/// Keys of the media keys schema with their values
+fn media_keys() -> Result<Vec<(String, String)>> {
+    Ok(gsettings(&["list-recursively", MEDIA_KEYS])?
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            let (_, key, value) = (parts.next()?, parts.next()?, parts.next()?);
+            // The list of custom shortcuts is handled separately
+            (key != "custom-keybindings").then(|| (key.to_string(), value.to_string()))
+        })
+        .collect())
+}
+
+/// Whether a binding value (a string or a list of strings) contains `shortcut`
+fn binds(value: &str, shortcut: &str) -> bool {
+    string_array(value).iter().any(|binding| binding.eq_ignore_ascii_case(shortcut.trim()))
+}
+
+/// The quoted strings of a value printed by gsettings, like "['a', 'b']"
+fn string_array(value: &str) -> Vec<String> {
+    value
+        .split('\'')
+        .skip(1)
+        .step_by(2)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// A string in GVariant notation
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn gsettings_get(schema: &str, key: &str) -> Result<String> {
+    gsettings(&["get", schema, key]).map(|value| value.trim().to_string())
+}
+
+fn gsettings_set(schema: &str, key: &str, value: &str) -> Result<()> {
+    gsettings(&["set", schema, key, value]).map(|_| ())
+}
+
+/// Run gsettings and return its output
+fn gsettings(args: &[&str]) -> Result<String> {
+    let output = Command::new("gsettings").args(args).output().wrap_err("Could not run gsettings")?;
+    if !output.status.success() {
+        return Err(color_eyre::eyre::eyre!(
+            "gsettings {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Path of the saved bindings
+fn lockout_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(LOCKOUT_FILE))
+}
+
+/// Remind of the running strict session, e.g. from a locked-out shortcut
+pub fn run_command() -> Result<()> {
+    let status = ipc::query(&ipc::Request::Status)?.and_then(|response| response.status);
+    let mut message = match status.filter(|status| status.active) {
+        Some(status) => format!(
+            "Strict session: {} min left for {}.",
+            status.remaining_secs.unwrap_or_default().div_ceil(60),
+            status.task.unwrap_or_else(|| "your task".to_string())
+        ),
+        None => "No session is running.".to_string(),
+    };
+    if let Some(extra) = load_config()?.lockout.and_then(|lockout| lockout.message) {
+        message = format!("{} {}", message, extra);
+    }
+    remind(&message);
+    Ok(())
+}
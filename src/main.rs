@@ -6,36 +6,104 @@
 * It modifies the hosts file to redirect specified websites to localhost during focus sessions.
 */
 
+mod approval;
+mod attach;
+mod backup;
+mod battery;
+mod blocker;
+mod blockpage;
+mod demo;
+mod diagnose;
+mod firewall;
+mod history;
+mod hosts;
+mod idn;
+mod inuse;
+mod ipc;
+mod journal;
+mod live;
+mod lockout;
+mod merge;
+mod metrics;
+mod mirror;
+mod newtab;
+mod notes;
+mod plan;
+mod preset;
+mod profile;
+mod progress;
+mod rescue;
+mod resolve;
+mod safety;
+mod services;
+mod setup;
+mod snooze;
+mod snapshot;
+mod state;
+mod stats;
+mod sudo;
+mod suspend;
+mod telemetry;
+mod timer;
 mod tui;
+mod ulid;
+mod wrap;
 
-use clap::{Parser, Subcommand};
+use chrono::Local;
+use clap::{Args, Parser, Subcommand};
 use color_eyre::{eyre::Context, Result};
-use crossterm::{
-    event::{Event, KeyCode},
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-    ExecutableCommand,
-};
 use directories::BaseDirs;
-use ratatui::Terminal;
 use serde::{Deserialize, Serialize};
-use spinners::{Spinner, Spinners};
 use std::{
     env,
     fs::{self, OpenOptions},
-    io::{self, stdout, Write},
-    path::{Path, PathBuf},
-    process::Command,
+    io::{self, stdout, IsTerminal},
+    path::PathBuf,
+    sync::OnceLock,
     time::{Duration, Instant},
 };
-use tui_input::{backend::crossterm::EventHandler, Input};
+use timeguardian::{format, schedule, ListStore};
 
-// Local imports for our TUI module
-use crate::tui::{App, TuiMode};
+use crate::history::EndReason;
+use crate::hosts::{get_hosts_path, Priority};
+use crate::preset::{EndBehavior, Preset};
 
 // Constants for file paths and configurations
 const APP_NAME: &str = "timeguardian";
 const HOSTS_BACKUP: &str = "hosts.backup";
-const TEMP_HOSTS_MARKER: &str = "# ===== TimeGuardian Temporary Hosts =====";
+const CONFIG_BACKUP_DIR: &str = "config-backups";
+const CONFIG_BACKUPS_KEPT: usize = 10;
+const HOSTS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_DURATION: &str = "25m";
+const CONFIG_ENV: &str = "TIMEGUARDIAN_CONFIG";
+const CONFIG_DIR_ENV: &str = "TIMEGUARDIAN_CONFIG_DIR";
+
+/// Files kept in the state directory; older versions kept them in the
+/// config directory
+const STATE_FILES: &[&str] = &[
+    HOSTS_BACKUP,
+    CONFIG_BACKUP_DIR,
+    history::HISTORY_FILE,
+    history::HISTORY_HEAD_FILE,
+    hosts::BLOCK_STATE_FILE,
+    journal::JOURNAL_FILE,
+    metrics::METRICS_FILE,
+    firewall::FIREWALL_STATE_FILE,
+    snooze::SNOOZE_FILE,
+    telemetry::TELEMETRY_FILE,
+    approval::APPROVALS_FILE,
+    lockout::LOCKOUT_FILE,
+];
+
+/// Clock for times of day, read from the config on first use
+static CLOCK_STYLE: OnceLock<format::ClockStyle> = OnceLock::new();
+
+/// Read-only config file given with `--config` or `TIMEGUARDIAN_CONFIG`
+static DECLARATIVE_CONFIG: OnceLock<PathBuf> = OnceLock::new();
+
+/// Directory for config and state given with `--config-dir` or
+/// `TIMEGUARDIAN_CONFIG_DIR`
+static CONFIG_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
 
 /// TimeGuardian: A modern, user-friendly CLI application to block distracting websites 
 /// and improve productivity by creating focused work sessions.
@@ -50,9 +118,50 @@ struct Cli {
     #[arg(long = "duration", short = 'd')]
     duration: Option<String>,
 
-    /// Task name or reason for the focus session
-    #[arg(long = "task", short = 't')]
-    task: Option<String>,
+    /// Start a session from a preset defined in the configuration
+    #[arg(long = "preset")]
+    preset: Option<String>,
+    
+    #[command(flatten)]
+    session: SessionOptions,
+
+    /// Refuse to stop or pause the session early (set by `start --strict`)
+    #[arg(skip)]
+    strict: bool,
+    
+    /// The session was started with `start` rather than the bare flags
+    #[arg(skip)]
+    start: bool,
+    
+    /// Use this profile instead of detecting it from the network
+    #[arg(long = "profile", global = true)]
+    profile: Option<String>,
+    
+    /// Run the session in the background and return immediately
+    #[arg(long = "detach")]
+    detach: bool,
+    
+    /// Screen-reader-friendly output: no animations, plain status lines
+    #[arg(long = "accessible", global = true)]
+    accessible: bool,
+    
+    /// Allow blocking critical domains (localhost, package mirrors, safe domains)
+    #[arg(long = "i-know-what-im-doing", global = true)]
+    i_know_what_im_doing: bool,
+    
+    /// Color theme of the TUI; overrides the configuration
+    #[arg(long = "theme", global = true, value_enum)]
+    theme: Option<tui::theme::ThemeName>,
+    
+    /// Read lists, presets and schedules from this file and never write it
+    /// (also TIMEGUARDIAN_CONFIG); runtime state stays in the state directory
+    #[arg(long = "config", global = true)]
+    config: Option<PathBuf>,
+    
+    /// Keep the configuration and all state in this directory
+    /// (also TIMEGUARDIAN_CONFIG_DIR)
+    #[arg(long = "config-dir", global = true)]
+    config_dir: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -60,6 +169,43 @@ enum Commands {
     /// Start the TUI (text user interface)
     Tui,
     
+    /// Start a focus session
+    Start {
+        /// Session length with units (e.g., 25m, 1h); `default_duration` in the config, else 25m
+        #[arg(long = "duration", short = 'd')]
+        duration: Option<String>,
+        
+        /// Start the session from a preset defined in the configuration
+        #[arg(long = "preset")]
+        preset: Option<String>,
+        
+        #[command(flatten)]
+        session: SessionOptions,
+        
+        /// Don't allow stopping or pausing the session early
+        #[arg(long = "strict")]
+        strict: bool,
+        
+        /// Run the session in the background and return immediately
+        #[arg(long = "detach")]
+        detach: bool,
+    },
+    
+    /// Set up a new machine from a shared config or website list
+    Init {
+        /// Path or http(s) URL of a config.toml or a plain website list
+        #[arg(long = "from")]
+        from: String,
+        
+        /// Replace an existing configuration (a backup is kept)
+        #[arg(long)]
+        force: bool,
+        
+        /// Afterwards restore the synced backup configured in the imported config
+        #[arg(long)]
+        pull: bool,
+    },
+    
     /// Set up the application with a website list
     Setup {
         /// Path to the file containing websites to block
@@ -67,12 +213,404 @@ enum Commands {
         list_path: String,
     },
     
-    /// Reset hosts file to its original state
-    Reset,
+    /// End the running session early, removing only its blocks
+    Stop {
+        #[command(flatten)]
+        target: SessionTarget,
+    },
+    
+    /// Restore the hosts file from its backup and end all sessions (for recovery)
+    Reset {
+        /// Priority of this reset; without --force, sessions above it keep running
+        #[arg(long = "priority", short = 'p', value_enum, default_value_t = Priority::Normal)]
+        priority: Priority,
+        
+        /// Also end sessions with a priority above --priority
+        #[arg(long)]
+        force: bool,
+    },
+    
+    /// Remove every trace of a block without reading the config or state
+    /// (when everything else fails)
+    Rescue,
     
     /// Request sudo access and set up permissions
     #[command(alias = "perms")]
     Permissions,
+    
+    /// Encrypted backup of the configuration and session history
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+    
+    /// Move the configuration and all state to another machine
+    State {
+        #[command(subcommand)]
+        action: StateAction,
+    },
+    
+    /// Show the detected network and the active profile
+    Profile,
+    
+    /// Print the timeline of a preset or a queue of sessions without starting it
+    Plan {
+        /// Preset whose lists, length and chained presets are planned
+        #[arg(long = "preset")]
+        preset: Option<String>,
+        
+        /// Lengths of alternating focus sessions and breaks (e.g. "50m,10m,50m")
+        #[arg(long = "queue")]
+        queue: Option<String>,
+        
+        /// Session length without a preset or queue; `default_duration` in the config, else 25m
+        #[arg(long = "duration", short = 'd')]
+        duration: Option<String>,
+        
+        #[command(flatten)]
+        session: SessionOptions,
+        
+        /// Start of the plan ("HH:MM"); now if unset
+        #[arg(long = "at")]
+        at: Option<String>,
+    },
+    
+    /// Show the blocking sessions recorded in the hosts file
+    Status {
+        /// Also show a QR code with the end time and task, to set a matching phone timer
+        #[arg(long)]
+        qr: bool,
+        
+        /// Also show which sessions and lists block each domain, the firewall and its DNS lookups
+        #[arg(long, short = 'v')]
+        verbose: bool,
+        
+        /// Print a status line of the running session until interrupted (for status bars)
+        #[arg(long, conflicts_with_all = ["qr", "verbose"])]
+        watch: bool,
+        
+        /// Time between the lines of `--watch` (e.g. 1s, 1m); at least a second
+        #[arg(long, default_value = "1s", requires = "watch")]
+        interval: String,
+        
+        /// Print the lines of `--watch` as IPC JSON responses
+        #[arg(long, requires = "watch")]
+        json: bool,
+    },
+    
+    /// Show the services that lists can name instead of domains
+    Services,
+    
+    /// Explain why a website is still reachable
+    Why {
+        /// Domain that still loads (e.g. example.com)
+        domain: String,
+    },
+    
+    /// Manage the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    
+    /// Compare and restore earlier versions of a website list
+    List {
+        #[command(subcommand)]
+        action: ListAction,
+    },
+    
+    /// Recurring blocking schedules
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+    
+    /// Follow the running session with a read-only countdown
+    #[command(name = "attach-cli", alias = "attach")]
+    AttachCli {
+        #[command(flatten)]
+        target: SessionTarget,
+    },
+    
+    /// Repeat the most recent session (lists, duration and task)
+    Again {
+        /// Repeat the session with this history id instead
+        #[arg(long = "from")]
+        from: Option<u64>,
+    },
+    
+    /// List recent sessions with their history ids
+    History {
+        /// Number of sessions to show
+        #[arg(long, short = 'n', default_value_t = 10)]
+        limit: usize,
+    },
+    
+    /// Shareable summaries of the session history
+    Stats {
+        #[command(subcommand)]
+        action: StatsAction,
+    },
+    
+    /// Show counters about hosts file reconciliation and IPC requests
+    Metrics {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = metrics::MetricsFormat::Text)]
+        format: metrics::MetricsFormat,
+    },
+    
+    /// Try the TUI with generated lists and history, without root or real blocking
+    Demo,
+    
+    /// Opt-in anonymous usage counters (off unless enabled)
+    Telemetry {
+        #[command(subcommand)]
+        action: TelemetryAction,
+    },
+    
+    /// Talk to the running session over the IPC socket (for editor plugins)
+    Ipc {
+        #[command(subcommand)]
+        request: IpcCommand,
+    },
+    
+    /// Write the new tab page of `[new_tab]` now and print its path
+    NewTab,
+    
+    /// Block while a command runs, e.g. `timeguardian wrap -- nvim thesis.tex`
+    ///
+    /// The task of the session is the command unless `--task` names one.
+    Wrap {
+        #[command(flatten)]
+        session: SessionOptions,
+        
+        /// Longest the block lasts if the command keeps running
+        #[arg(long = "max", default_value = "8h")]
+        max: String,
+        
+        /// The command to run, after --
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+    
+    /// Show the remaining time of the session (run by locked shortcuts)
+    #[command(hide = true)]
+    Remind,
+}
+
+#[derive(Subcommand)]
+enum IpcCommand {
+    /// Print the status of the running session as JSON
+    Status,
+    
+    /// Stop the running session
+    Stop,
+    
+    /// Print the status as a JSON line every second
+    Subscribe,
+    
+    /// Start a session, in the idle TUI or as a background process
+    Start {
+        /// Blocking duration with units (e.g., 25m, 30s, 1h)
+        #[arg(long = "duration", short = 'd')]
+        duration: String,
+        
+        /// Task name or reason for the focus session
+        #[arg(long = "task", short = 't')]
+        task: Option<String>,
+    },
+}
+
+/// Options of a focus session, shared by the session flags, `start`, `plan`
+/// and `wrap`
+#[derive(Args, Clone, Default)]
+struct SessionOptions {
+    /// Task name or reason for the focus session
+    #[arg(long = "task", short = 't')]
+    task: Option<String>,
+    
+    /// Block only this website list (repeatable); overrides the preset and profile
+    #[arg(long = "list", short = 'l')]
+    lists: Vec<String>,
+    
+    /// Priority of the session; higher priorities keep their domains blocked
+    /// even when a lower-priority session ends (default: normal)
+    #[arg(long = "priority", short = 'p', value_enum)]
+    priority: Option<Priority>,
+    
+    /// Only run the timer and record the session; block nothing (no root needed)
+    #[arg(long = "timer-only", conflicts_with = "lists")]
+    timer_only: bool,
+}
+
+impl SessionOptions {
+    /// Priority of the session, normal unless given
+    fn priority(&self) -> Priority {
+        self.priority.unwrap_or_default()
+    }
+}
+
+/// The running session `stop` and `attach-cli` address when several run
+#[derive(Args)]
+struct SessionTarget {
+    /// The session with this ID, or the start of it (see 'timeguardian status')
+    #[arg(long = "session")]
+    session: Option<String>,
+    
+    /// The session blocking this website list
+    #[arg(long = "list", short = 'l')]
+    list: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// List the automatic backups of the configuration
+    Backups,
+    
+    /// Restore the configuration from an automatic backup
+    Restore {
+        /// Restore the most recent backup
+        #[arg(long, conflicts_with = "backup")]
+        latest: bool,
+        
+        /// File name of the backup to restore (see `config backups`)
+        backup: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ListAction {
+    /// Show what changed between two versions of a list
+    /// (e.g. `list diff Work@yesterday Work`)
+    Diff {
+        /// Older version: NAME@yesterday, NAME@2026-10-01, NAME@3h or NAME@N (N-th newest backup)
+        from: String,
+        
+        /// Newer version; the current list if omitted
+        to: Option<String>,
+    },
+    
+    /// Show the versions of a list kept in the config backups
+    History {
+        /// Name of the list
+        name: String,
+    },
+    
+    /// Replace a list with an earlier version (e.g. `list restore Work@2`)
+    Restore {
+        /// Version to restore, NAME@...
+        version: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleAction {
+    /// Show the upcoming scheduled blocks
+    Preview {
+        /// How far to look ahead (e.g., 12h, 7d)
+        #[arg(long = "next", default_value = "7d")]
+        next: String,
+    },
+    
+    /// Block the schedules that are active right now (run from cron or a timer)
+    Apply,
+    
+    /// Postpone the next scheduled block by 10 minutes
+    Snooze {
+        /// Name of the schedule; the next block of any schedule if omitted
+        name: Option<String>,
+    },
+    
+    /// Skip the next scheduled block, logging why
+    Skip {
+        /// Name of the schedule; the next block of any schedule if omitted
+        name: Option<String>,
+        
+        /// Why the block is skipped
+        #[arg(long)]
+        reason: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TelemetryAction {
+    /// Show whether usage is counted and why
+    Status,
+    
+    /// Print the exact payload an upload would send
+    Show,
+    
+    /// Start counting usage
+    Enable,
+    
+    /// Stop counting usage and delete the counters
+    Disable,
+    
+    /// Send the payload to the configured endpoint (needs the `telemetry-upload` feature)
+    Upload,
+}
+
+#[derive(Subcommand)]
+enum StatsAction {
+    /// Print a badge with this month's focus time and the current streak,
+    /// without task names or domains
+    Badge {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = stats::BadgeFormat::Markdown)]
+        format: stats::BadgeFormat,
+        
+        /// Write the badge to this file instead of printing it
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
+    
+    /// Show which lists and domains were blocked the longest
+    TopDomains {
+        /// How far to look back (e.g., 7d, 30d)
+        #[arg(long, default_value = "30d")]
+        period: String,
+        
+        /// Number of domains to show
+        #[arg(long, short = 'n', default_value_t = 10)]
+        limit: usize,
+    },
+    
+    /// Check that no session was edited or removed from the history by hand
+    Verify,
+    
+    /// Show one session with everything recorded about it
+    Show {
+        /// History id (e.g. 54) or session ID, or the start of one
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum StateAction {
+    /// Bundle config, history, metrics and backups into one archive
+    Export {
+        /// Archive to write (e.g. state.tar.zst)
+        path: PathBuf,
+    },
+    
+    /// Restore config and state from an exported archive
+    Import {
+        /// Archive to read
+        path: PathBuf,
+        
+        /// Replace existing files
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Encrypt the configuration and history and upload them to the backup target
+    Push,
+    
+    /// Download the backup and restore the configuration and history from it
+    Pull,
 }
 
 /// Application configuration structure
@@ -80,22 +618,49 @@ enum Commands {
 struct Config {
     website_list_path: String,
     website_lists: Option<Vec<tui::WebsiteList>>,
-    use_sudo: Option<bool>,
-}
-
-/// Get the path to the hosts file based on the operating system
-fn get_hosts_path() -> PathBuf {
-    if cfg!(target_os = "linux") || cfg!(target_os = "macos") {
-        PathBuf::from("/etc/hosts")
-    } else if cfg!(target_os = "windows") {
-        PathBuf::from(r"C:\Windows\System32\drivers\etc\hosts")
-    } else {
-        panic!("Unsupported operating system")
-    }
+    list_files: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "sudo::deserialize_policy")]
+    use_sudo: Option<sudo::SudoPolicy>,
+    sudo_helper: Option<String>,
+    backup: Option<backup::BackupTarget>,
+    end_behavior: Option<EndBehavior>,
+    presets: Option<Vec<Preset>>,
+    profiles: Option<Vec<profile::Profile>>,
+    accessible: Option<bool>,
+    schedules: Option<Vec<schedule::Schedule>>,
+    tabs: Option<Vec<tui::CustomTab>>,
+    suspend_behavior: Option<suspend::SuspendBehavior>,
+    progress_bar: Option<bool>,
+    mirror: Option<mirror::MirrorConfig>,
+    firewall: Option<firewall::FirewallConfig>,
+    intent_prompt: Option<bool>,
+    battery: Option<battery::BatteryConfig>,
+    theme: Option<tui::theme::ThemeName>,
+    safe_domains: Option<Vec<String>>,
+    in_use_warning: Option<bool>,
+    soft_launch: Option<bool>,
+    schedule_snooze_limit: Option<u32>,
+    sinkhole: Option<std::net::IpAddr>,
+    macros: Option<Vec<tui::Macro>>,
+    default_duration: Option<String>,
+    daily_note: Option<notes::DailyNoteConfig>,
+    services: Option<Vec<services::Service>>,
+    clock: Option<format::ClockStyle>,
+    telemetry: Option<telemetry::TelemetryConfig>,
+    lockout: Option<lockout::LockoutConfig>,
+    new_tab: Option<newtab::NewTabConfig>,
+    block_page: Option<blockpage::BlockPageConfig>,
 }
 
 /// Find or create the application's configuration directory
 fn get_config_dir() -> Result<PathBuf> {
+    if let Some(dir) = demo::dir() {
+        return Ok(dir.to_path_buf());
+    }
+    if let Some(dir) = CONFIG_DIR_OVERRIDE.get() {
+        fs::create_dir_all(dir).wrap_err_with(|| format!("Could not create configuration directory: {:?}", dir))?;
+        return Ok(dir.clone());
+    }
     if let Some(base_dirs) = BaseDirs::new() {
         let config_dir = base_dirs.config_dir().join(APP_NAME);
         if !config_dir.exists() {
@@ -110,747 +675,628 @@ fn get_config_dir() -> Result<PathBuf> {
     }
 }
 
+/// Find or create the directory for state TimeGuardian writes on its own:
+/// history, hosts backup, claims, journal, metrics and config backups.
+///
+/// This is the XDG state directory (`~/.local/state/timeguardian`) or the
+/// local data directory where there is none; an overridden config directory
+/// holds the state as well.
+fn get_state_dir() -> Result<PathBuf> {
+    if demo::dir().is_some() || CONFIG_DIR_OVERRIDE.get().is_some() {
+        return get_config_dir();
+    }
+    let state_dir = match BaseDirs::new() {
+        Some(base_dirs) => base_dirs.state_dir().unwrap_or(base_dirs.data_local_dir()).join(APP_NAME),
+        None => env::current_dir()?.join(".local").join("state").join(APP_NAME),
+    };
+    fs::create_dir_all(&state_dir)
+        .wrap_err_with(|| format!("Could not create state directory: {:?}", state_dir))?;
+    Ok(state_dir)
+}
+
+/// Move state files left in the config directory by older versions
+fn migrate_state() -> Result<()> {
+    let (config_dir, state_dir) = (get_config_dir()?, get_state_dir()?);
+    if config_dir == state_dir {
+        return Ok(());
+    }
+    for name in STATE_FILES {
+        let (old, new) = (config_dir.join(name), state_dir.join(name));
+        if old.exists() && !new.exists() {
+            fs::rename(&old, &new).wrap_err_with(|| format!("Could not move {:?} to {:?}", old, new))?;
+        }
+    }
+    Ok(())
+}
+
+/// A time of day on the clock of the config (`clock = "12h"` or `"24h"`),
+/// else the clock of the locale
+pub fn time_of_day(time: &impl chrono::Timelike) -> String {
+    let style = CLOCK_STYLE.get_or_init(|| {
+        load_config()
+            .ok()
+            .and_then(|config| config.clock)
+            .unwrap_or_else(format::ClockStyle::from_locale)
+    });
+    format::time_of_day(time, *style)
+}
+
+/// Path of the configuration file
+fn config_path() -> Result<PathBuf> {
+    match DECLARATIVE_CONFIG.get() {
+        Some(path) => Ok(path.clone()),
+        None => Ok(get_config_dir()?.join("config.toml")),
+    }
+}
+
+/// Whether the configuration is managed declaratively and must not be written
+fn config_is_read_only() -> bool {
+    DECLARATIVE_CONFIG.get().is_some()
+}
+
 /// Load configuration or return default configuration
 fn load_config() -> Result<Config> {
-    let config_path = get_config_dir()?.join("config.toml");
+    let config_path = config_path()?;
     
-    if config_path.exists() {
-        let config_content = fs::read_to_string(&config_path)
-            .wrap_err_with(|| format!("Could not read configuration file: {:?}", config_path))?;
-        
-        let config: Config = toml::from_str(&config_content)
-            .wrap_err("Could not parse configuration")?;
-        
-        Ok(config)
+    let mut config = if config_path.exists() {
+        read_config(&config_path)?
     } else {
         // Return default configuration
-        Ok(Config {
+        Config {
             website_list_path: "websites.txt".to_string(),
             website_lists: None,
-            use_sudo: Some(false),
-        })
+            list_files: None,
+            use_sudo: Some(sudo::SudoPolicy::default()),
+            sudo_helper: None,
+            backup: None,
+            end_behavior: None,
+            presets: None,
+            profiles: None,
+            accessible: None,
+            schedules: None,
+            tabs: None,
+            suspend_behavior: None,
+            progress_bar: None,
+            mirror: None,
+            firewall: None,
+            intent_prompt: None,
+            battery: None,
+            theme: None,
+            safe_domains: None,
+            in_use_warning: None,
+            soft_launch: None,
+            schedule_snooze_limit: Some(snooze::DEFAULT_SNOOZE_LIMIT),
+            sinkhole: Some(hosts::DEFAULT_SINKHOLE),
+            macros: None,
+            default_duration: None,
+            daily_note: None,
+            services: None,
+            clock: None,
+            telemetry: None,
+            lockout: None,
+            new_tab: None,
+            block_page: None,
+        }
+    };
+    
+    // The lists of the administrator come last; they are never saved with the user's
+    if demo::dir().is_none() {
+        let system = ListStore::system_lists()?;
+        if !system.is_empty() {
+            config.website_lists.get_or_insert_with(Vec::new).extend(system);
+        }
     }
+    Ok(config)
 }
 
-/// Save configuration to file
+/// Read a config file together with the list files it references
+fn read_config(path: &std::path::Path) -> Result<Config> {
+    let content = fs::read_to_string(path)
+        .wrap_err_with(|| format!("Could not read configuration file: {:?}", path))?;
+    let mut config: Config = toml::from_str(&content)
+        .wrap_err("Could not parse configuration")?;
+    
+    if let Some(files) = &config.list_files {
+        let base = path.parent().unwrap_or(std::path::Path::new("."));
+        let lists = config.website_lists.get_or_insert_with(Vec::new);
+        for list in ListStore::new(base).load(files)? {
+            if lists.iter().any(|other| other.name.eq_ignore_ascii_case(&list.name)) {
+                return Err(color_eyre::eyre::eyre!("The list '{}' is defined more than once in the configuration", list.name));
+            }
+            lists.push(list);
+        }
+    }
+    
+    Ok(config)
+}
+
+/// A config file as one TOML document with its lists inline, for backups and exports
+fn inlined_config(path: &std::path::Path) -> Result<String> {
+    let mut config = read_config(path)?;
+    config.list_files = None;
+    toml::to_string(&config).wrap_err("Could not serialize configuration")
+}
+
+/// Take the exclusive config lock; it is released when the file is dropped.
+///
+/// Writers hold it for their whole read-modify-write cycle. Readers don't
+/// need it because the config is replaced atomically.
+fn lock_config() -> Result<fs::File> {
+    let lock_path = get_config_dir()?.join("config.lock");
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .wrap_err_with(|| format!("Could not open config lock: {:?}", lock_path))?;
+    lock.lock().wrap_err("Could not lock the configuration")?;
+    Ok(lock)
+}
+
+/// Load the config, apply `change` and save it while holding the config lock
+fn update_config(change: impl FnOnce(&mut Config) -> Result<()>) -> Result<()> {
+    let _lock = lock_config()?;
+    let mut config = load_config()?;
+    change(&mut config)?;
+    save_config(&config)
+}
+
+/// Save configuration to file (callers hold the config lock)
 fn save_config(config: &Config) -> Result<()> {
+    if let Some(path) = DECLARATIVE_CONFIG.get() {
+        return Err(color_eyre::eyre::eyre!(
+            "The configuration is read-only: {:?} is managed declaratively (--config or {}). Change it there.",
+            path,
+            CONFIG_ENV
+        ));
+    }
     let config_dir = get_config_dir()?;
     let config_path = config_dir.join("config.toml");
     
-    let toml_string = toml::to_string(config)
+    // Keep the previous version before overwriting it
+    let mut previous_files = Vec::new();
+    if config_path.exists() {
+        backup_config(&config_dir)?;
+        previous_files = fs::read_to_string(&config_path)
+            .ok()
+            .and_then(|content| toml::from_str::<Config>(&content).ok())
+            .and_then(|previous| previous.list_files)
+            .unwrap_or_default();
+    }
+    
+    // Every list goes to its own file, config.toml only references them
+    let lists: Vec<tui::WebsiteList> =
+        config.website_lists.iter().flatten().filter(|list| !list.system).cloned().collect();
+    let mut config = config.clone();
+    config.list_files = Some(ListStore::new(&config_dir).save(&lists, &previous_files)?);
+    config.website_lists = None;
+    
+    let toml_string = toml::to_string(&config)
         .wrap_err("Could not serialize configuration")?;
     
-    fs::write(&config_path, toml_string)
+    // Write to a temporary file first so a crash never leaves a truncated config
+    let temp_path = config_dir.join("config.toml.tmp");
+    fs::write(&temp_path, toml_string)
+        .wrap_err_with(|| format!("Could not save configuration: {:?}", temp_path))?;
+    fs::rename(&temp_path, &config_path)
         .wrap_err_with(|| format!("Could not save configuration: {:?}", config_path))?;
     
     Ok(())
 }
 
-/// Initialize the website blocker application
-fn initialize_app() -> Result<()> {
-    let config_dir = get_config_dir()?;
+/// Copy the current config to a timestamped backup, keeping the newest ones
+fn backup_config(config_dir: &std::path::Path) -> Result<()> {
+    let backup_dir = get_state_dir()?.join(CONFIG_BACKUP_DIR);
+    fs::create_dir_all(&backup_dir)
+        .wrap_err_with(|| format!("Could not create backup directory: {:?}", backup_dir))?;
     
-    // Create backup file if it doesn't exist
-    let backup_path = config_dir.join(HOSTS_BACKUP);
-    if !backup_path.exists() {
-        let hosts_content = fs::read_to_string(get_hosts_path())
-            .wrap_err("Could not read hosts file")?;
-        
-        fs::write(&backup_path, hosts_content)
-            .wrap_err_with(|| format!("Could not create hosts file backup: {:?}", backup_path))?;
+    let backup_path = backup_dir.join(format!("config-{}.toml", Local::now().format("%Y%m%d-%H%M%S-%3f")));
+    let config_path = config_dir.join("config.toml");
+    // Backups hold the lists inline so every version is complete on its own;
+    // a config that can't be read is kept as it is
+    match inlined_config(&config_path) {
+        Ok(content) => fs::write(&backup_path, content).map(|_| 0),
+        Err(_) => fs::copy(&config_path, &backup_path),
+    }
+    .wrap_err_with(|| format!("Could not back up configuration: {:?}", backup_path))?;
+    
+    let backups = config_backups()?;
+    for old in backups.iter().take(backups.len().saturating_sub(CONFIG_BACKUPS_KEPT)) {
+        let _ = fs::remove_file(old);
     }
     
     Ok(())
 }
 
-/// Check if root permissions are required and request them if needed
-fn check_and_get_permissions() -> Result<bool> {
-    if cfg!(unix) {
-        // Test if we can write to the hosts file
-        match OpenOptions::new()
-            .write(true)
-            .open(get_hosts_path())
-        {
-            Ok(_) => Ok(true),
-            Err(_) => {
-                println!("This application needs write permissions for the hosts file.");
-                println!("Do you want to run the application with sudo permissions? (y/n)");
-                
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
-                
-                if input.trim().to_lowercase() == "y" || input.trim().to_lowercase() == "yes" {
-                    // Find the current executable
-                    let current_exe = env::current_exe()?;
-                    
-                    // Restart with sudo
-                    let status = Command::new("sudo")
-                        .arg(current_exe)
-                        .args(env::args().skip(1))
-                        .status()?;
-                    
-                    if status.success() {
-                        std::process::exit(0);
-                    } else {
-                        println!("Running with sudo failed.");
-                        Ok(false)
-                    }
-                } else {
-                    println!("Without sufficient permissions, website blocking will not work.");
-                    Ok(false)
-                }
-            }
-        }
-    } else {
-        // On Windows and other systems, perform other permission checks
-        Ok(true)
+/// Automatic config backups, oldest first
+fn config_backups() -> Result<Vec<PathBuf>> {
+    let backup_dir = get_state_dir()?.join(CONFIG_BACKUP_DIR);
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
     }
+    
+    let mut backups: Vec<PathBuf> = fs::read_dir(&backup_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    backups.sort();
+    Ok(backups)
 }
 
-/// Run blocker with timer
-fn block_websites_with_timer(
-    websites: &[String], 
-    duration: Duration, 
-    task_name: &str,
-    duration_text: &str,
-) -> Result<()> {
-    // Check and get permissions if needed
-    if !check_and_get_permissions()? {
-        return Ok(());
-    }
-
-    let hosts_path = get_hosts_path();
-    let config_dir = get_config_dir()?;
-    let backup_path = config_dir.join(HOSTS_BACKUP);
-
-    // Read current content of hosts file
-    let hosts_content = fs::read_to_string(&hosts_path)
-        .wrap_err_with(|| format!("Could not read hosts file: {:?}", hosts_path))?;
-
-    // Create backup if it doesn't exist
-    let mut backup_file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&backup_path)
-        .wrap_err_with(|| format!("Could not create backup file: {:?}", backup_path))?;
-
-    backup_file.write_all(hosts_content.as_bytes())
-        .wrap_err("Could not write to backup file")?;
-
-    // Create the new hosts content with blocked websites
-    let mut new_hosts_content = hosts_content;
-
-    // Remove any existing temporary entries
-    if let Some(start) = new_hosts_content.find(TEMP_HOSTS_MARKER) {
-        if let Some(end) = new_hosts_content[start..].find("\n# ===== End") {
-            let end_idx = start + end + "\n# ===== End Temporary Hosts =====".len();
-            new_hosts_content = new_hosts_content[..start].to_string() + &new_hosts_content[end_idx..];
+/// Handle `timeguardian config` commands
+fn run_config_command(action: &ConfigAction) -> Result<()> {
+    let backups = config_backups()?;
+    
+    match action {
+        ConfigAction::Backups => {
+            if backups.is_empty() {
+                println!("No configuration backups yet.");
+            }
+            for backup in &backups {
+                if let Some(name) = backup.file_name() {
+                    println!("{}", name.to_string_lossy());
+                }
+            }
         }
-    }
-
-    // Add new temporary entries
-    new_hosts_content.push_str(&format!("\n{}\n", TEMP_HOSTS_MARKER));
-    for website in websites {
-        let website = website.trim();
-        if !website.is_empty() {
-            println!("Blocking website: {}", website);
-            new_hosts_content.push_str(&format!("127.0.0.1\t{}\n", website));
+        ConfigAction::Restore { latest, backup } => {
+            let source = match (latest, backup) {
+                (_, Some(name)) => backups
+                    .iter()
+                    .find(|path| path.file_name().is_some_and(|file| file.to_string_lossy() == *name))
+                    .ok_or_else(|| color_eyre::eyre::eyre!("No configuration backup named {}", name))?,
+                (true, None) => backups
+                    .last()
+                    .ok_or_else(|| color_eyre::eyre::eyre!("No configuration backups yet"))?,
+                (false, None) => {
+                    println!("Use --latest or name a backup from 'timeguardian config backups'.");
+                    return Ok(());
+                }
+            };
             
-            // Add www. version if it doesn't have it
-            if !website.starts_with("www.") {
-                new_hosts_content.push_str(&format!("127.0.0.1\twww.{}\n", website));
-            }
+            // Only restore something we can actually load
+            let content = fs::read_to_string(source)
+                .wrap_err_with(|| format!("Could not read backup: {:?}", source))?;
+            let restored: Config = toml::from_str(&content)
+                .wrap_err_with(|| format!("Backup is not a valid configuration: {:?}", source))?;
+            
+            let _lock = lock_config()?;
+            save_config(&restored)?;
+            println!("Configuration restored from {}", source.file_name().unwrap_or_default().to_string_lossy());
         }
     }
-    new_hosts_content.push_str("# ===== End Temporary Hosts =====\n");
-
-    // Write the updated hosts file
-    let mut hosts_file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(&hosts_path)
-        .wrap_err_with(|| format!("Could not open hosts file: {:?}", hosts_path))?;
-
-    hosts_file.write_all(new_hosts_content.as_bytes())
-        .wrap_err("Could not update hosts file")?;
-
-    // Flush DNS cache
-    flush_dns_cache();
-
-    // Terminal output
-    let message = format!(
-        "Blocking websites for {} for task: {}",
-        duration_text, task_name
-    );
-    
-    let mut spinner = Spinner::new(Spinners::Dots12, message);
-    
-    // Start timer
-    enable_raw_mode()?;
-    let start_time = Instant::now();
-    
-    while start_time.elapsed() < duration {
-        // Check for user input to end early
-        if crossterm::event::poll(Duration::from_millis(100))? {
-            let event = crossterm::event::read()?;
-            if matches!(event, Event::Key(key) if key.code == KeyCode::Esc || key.code == KeyCode::Char('q')) {
-                break;
-            }
-        }
-        
-        // Display remaining time (overwritten by spinner)
-        let remaining = duration.checked_sub(start_time.elapsed()).unwrap_or_default();
-        // The Spinner library doesn't support direct message changes
-        // Create a new spinner with the updated message instead
-        spinner.stop();
-        spinner = Spinner::new(
-            Spinners::Dots12,
-            format!(
-                "Remaining time: {:02}:{:02}:{:02}",
-                remaining.as_secs() / 3600,
-                (remaining.as_secs() % 3600) / 60,
-                remaining.as_secs() % 60
-            ),
-        );
-    }
-    
-    disable_raw_mode()?;
-    spinner.stop();
-
-    // Remove blocking after timer expires
-    stop_blocking()?;
-    
-    println!("\nBlocking removed! ✅");
     
     Ok(())
 }
 
-/// Run the TUI application
-fn run_tui() -> Result<()> {
-    // Setup permissions first
-    if !check_and_get_permissions()? {
-        println!("The TUI cannot be started without the necessary permissions.");
-        return Ok(());
+/// Initialize the website blocker application
+fn initialize_app() -> Result<()> {
+    // Create backup file if it doesn't exist
+    let backup_path = get_state_dir()?.join(HOSTS_BACKUP);
+    if !backup_path.exists() {
+        let hosts_content = fs::read_to_string(get_hosts_path())
+            .wrap_err("Could not read hosts file")?;
+        
+        fs::write(&backup_path, hosts_content)
+            .wrap_err_with(|| format!("Could not create hosts file backup: {:?}", backup_path))?;
     }
     
-    // Initialize app data
-    initialize_app()?;
-    
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = stdout();
-    stdout.execute(EnterAlternateScreen)?;
-    
-    // Create a terminal instance
-    let backend = ratatui::backend::CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    Ok(())
+}
 
-    // Create app state
-    let mut app = App::new();
-    
-    // Initialize app
-    app.init()?;
+/// Check if root permissions are required and obtain them as the `use_sudo`
+/// policy allows
+fn check_and_get_permissions() -> Result<bool> {
+    // On Windows and other systems, perform other permission checks
+    if !cfg!(unix) {
+        return Ok(true);
+    }
+    // Test if we can write to the hosts file
+    if OpenOptions::new().write(true).open(get_hosts_path()).is_ok() {
+        return Ok(true);
+    }
     
-    // Load existing website lists from config if available
+    println!("This application needs write permissions for the hosts file.");
     let config = load_config()?;
-    if let Some(website_lists) = config.website_lists {
-        app.website_lists = website_lists;
-        if (!app.website_lists.is_empty()) {
-            app.website_list_state.select(Some(0));
-            app.selected_list_index = Some(0);
+    let policy = config.use_sudo.unwrap_or_default();
+    let escalate = match policy {
+        sudo::SudoPolicy::Never => {
+            println!("Restarting as root is disabled (use_sudo = \"never\"); run it as root instead.");
+            false
+        }
+        // Only a person at a terminal can answer; background sessions, cron
+        // jobs and pipes don't escalate and leave the policy as it is
+        sudo::SudoPolicy::AskOnce if !io::stdin().is_terminal() => false,
+        sudo::SudoPolicy::AskOnce => {
+            println!("Do you want to run the application with sudo permissions? (y/n)");
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            let answer = match input.trim().to_lowercase().as_str() {
+                "y" | "yes" => Some(true),
+                "n" | "no" => Some(false),
+                _ => None,
+            };
             
-            // Ensure the first list is properly selected
-            if !app.website_lists[0].websites.is_empty() {
-                app.website_state.select(Some(0));
-                app.selected_website_index = Some(0);
+            // Remember an explicit answer instead of asking on every launch
+            if let Some(yes) = answer
+                && !config_is_read_only()
+            {
+                let remembered = if yes { sudo::SudoPolicy::Always } else { sudo::SudoPolicy::Never };
+                update_config(|config| {
+                    config.use_sudo = Some(remembered);
+                    Ok(())
+                })?;
+                println!("Remembered as use_sudo = \"{}\" in {:?}.", remembered.name(), config_path()?);
             }
+            answer == Some(true)
         }
+        sudo::SudoPolicy::Always | sudo::SudoPolicy::UseHelper | sudo::SudoPolicy::UsePolkit => true,
+    };
+    if !escalate {
+        println!("Without sufficient permissions, website blocking will not work.");
+        return Ok(false);
     }
     
-    // Create event handler
-    let tick_rate = Duration::from_millis(250);
-    let event_handler = tui::event::EventHandler::new(tick_rate);
-    
-    // Main loop
-    while app.running {
-        // Draw UI
-        terminal.draw(|frame| tui::ui::render(&mut app, frame))?;
-        
-        // Handle events
-        match event_handler.receiver.recv() {
-            Ok(tui::event::Event::Key(key_event)) => {
-                match app.mode {
-                    TuiMode::Normal => match key_event.code {
-                        KeyCode::Char('q') => {
-                            app.running = false;
-                        }
-                        KeyCode::Char('?') => {
-                            app.mode = TuiMode::Help;
-                        }
-                        // Vim-style tab navigation
-                        KeyCode::Char('l') | KeyCode::Tab => {
-                            app.tabs.next();
-                        }
-                        KeyCode::Char('h') | KeyCode::BackTab => {
-                            app.tabs.previous();
-                        }
-                        _ => {
-                            // Handle different tabs
-                            match app.tabs.index {
-                                0 => {
-                                    handle_website_list_tab_events(&mut app, key_event.code)?;
-                                }
-                                1 => {
-                                    handle_timer_tab_events(&mut app, key_event.code)?;
-                                }
-                                _ => {}
-                            }
-                        }
-                    },
-                    TuiMode::Editing => match key_event.code {
-                        KeyCode::Esc => app.mode = TuiMode::Normal,
-                        KeyCode::Enter => {
-                            let input_value = app.input.value().to_string();
-                            if (!input_value.is_empty()) {
-                                match app.tabs.index {
-                                    0 => {
-                                        if app.selected_list_index.is_some() {
-                                            app.add_website(input_value);
-                                            app.status_message = "Website added successfully".to_string();
-                                        } else {
-                                            app.add_list(input_value);
-                                            app.status_message = "List added successfully".to_string();
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                                app.input = Input::default();
-                                app.mode = TuiMode::Normal;
-                            }
-                        }
-                        // Handle other key events for input editing
-                        _ => {
-                            app.input.handle_event(&crossterm::event::Event::Key(key_event));
-                        }
-                    },
-                    TuiMode::Help => match key_event.code {
-                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => {
-                            app.mode = TuiMode::Normal;
-                        }
-                        _ => {}
-                    },
-                }
-            }
-            Ok(tui::event::Event::Tick) => {
-                app.tick();
-                
-                // Check if blocking session has ended
-                if app.is_blocking {
-                    if let Some(end_time) = app.blocking_end_time {
-                        if Instant::now() >= end_time {
-                            stop_blocking_websites()?;
-                            app.stop_blocking()?;
-                        }
-                    }
-                }
-            }
-            Ok(tui::event::Event::Resize(_, _)) => {}
-            Ok(tui::event::Event::Mouse(_)) => {}, // Add handling for mouse events
-            Err(_) => {
-                app.running = false;
-            }
-        }
+    let mut command = policy.relaunch_command(config.sudo_helper.as_deref())?;
+    let status = command
+        .status()
+        .wrap_err_with(|| format!("Could not run {:?}", command.get_program()))?;
+    if status.success() {
+        std::process::exit(0);
     }
+    println!("Running with {} failed.", command.get_program().to_string_lossy());
+    Ok(false)
+}
 
-    // When the app exits, save the website lists to config
-    let mut config = load_config()?;
-    config.website_lists = Some(app.website_lists.clone());
-    save_config(&config)?;
-    
-    // Restore terminal
-    disable_raw_mode()?;
-    terminal.backend_mut().execute(LeaveAlternateScreen)?;
-    
-    Ok(())
+/// Append a finished session to the daily note, if `daily_note` is configured
+fn append_daily_note(history_id: u64) -> Result<Option<PathBuf>> {
+    let Some(daily_note) = load_config()?.daily_note else {
+        return Ok(None);
+    };
+    let history = history::History::load()?;
+    notes::append(&daily_note, history.find(history_id)?).map(Some)
 }
 
-/// Handle key events for the website list tab
-fn handle_website_list_tab_events(app: &mut App, key: KeyCode) -> Result<()> {
-    match key {
-        // Navigate lists with vim-style keys
-        KeyCode::Char('h') | KeyCode::Left => {
-            app.website_state.select(None);
-            app.selected_website_index = None;
-        }
-        KeyCode::Char('l') | KeyCode::Right => {
-            if app.selected_list_index.is_some() {
-                if let Some(list) = app.current_website_list() {
-                    if (!list.websites.is_empty()) {
-                        app.website_state.select(Some(0));
-                        app.selected_website_index = Some(0);
-                    }
-                }
-            }
-        }
-        KeyCode::Char('k') | KeyCode::Up => {
-            if app.selected_website_index.is_some() {
-                // Navigate websites
-                let websites_len = app.current_website_list().map_or(0, |list| list.websites.len());
-                if websites_len > 0 {
-                    let i = app.selected_website_index.map_or(0, |i| {
-                        if i > 0 { i - 1 } else { websites_len - 1 }
-                    });
-                    app.website_state.select(Some(i));
-                    app.selected_website_index = Some(i);
-                }
-            } else {
-                // Navigate lists
-                let lists_len = app.website_lists.len();
-                if lists_len > 0 {
-                    let i = app.selected_list_index.map_or(0, |i| {
-                        if i > 0 { i - 1 } else { lists_len - 1 }
-                    });
-                    app.website_list_state.select(Some(i));
-                    app.selected_list_index = Some(i);
-                }
-            }
-        }
-        KeyCode::Char('j') | KeyCode::Down => {
-            if app.selected_website_index.is_some() {
-                // Navigate websites
-                let websites_len = app.current_website_list().map_or(0, |list| list.websites.len());
-                if websites_len > 0 {
-                    let i = app.selected_website_index.map_or(0, |i| {
-                        if i < websites_len - 1 { i + 1 } else { 0 }
-                    });
-                    app.website_state.select(Some(i));
-                    app.selected_website_index = Some(i);
-                }
-            } else {
-                // Navigate lists
-                let lists_len = app.website_lists.len();
-                if lists_len > 0 {
-                    let i = app.selected_list_index.map_or(0, |i| {
-                        if i < lists_len - 1 { i + 1 } else { 0 }
-                    });
-                    app.website_list_state.select(Some(i));
-                    app.selected_list_index = Some(i);
-                }
-            }
-        }
-        
-        // Vim-style quick movement
-        KeyCode::Char('g') => {
-            // Go to the top
-            if app.selected_website_index.is_some() {
-                app.website_state.select(Some(0));
-                app.selected_website_index = Some(0);
-            } else if !app.website_lists.is_empty() {
-                app.website_list_state.select(Some(0));
-                app.selected_list_index = Some(0);
-            }
-        }
-        KeyCode::Char('G') => {
-            // Go to the bottom
-            if app.selected_website_index.is_some() {
-                let websites_len = app.current_website_list().map_or(0, |list| list.websites.len());
-                if websites_len > 0 {
-                    app.website_state.select(Some(websites_len - 1));
-                    app.selected_website_index = Some(websites_len - 1);
-                }
-            } else if !app.website_lists.is_empty() {
-                let lists_len = app.website_lists.len();
-                app.website_list_state.select(Some(lists_len - 1));
-                app.selected_list_index = Some(lists_len - 1);
-            }
-        }
-        
-        // Add new list or website (vim-style)
-        KeyCode::Char('o') | KeyCode::Char('n') => {
-            app.input = Input::default();
-            app.mode = TuiMode::Editing;
-        }
-        KeyCode::Char('a') => {
-            if app.selected_list_index.is_some() {
-                app.input = Input::default();
-                app.mode = TuiMode::Editing;
-            } else {
-                app.status_message = "Please select a list first".to_string();
-            }
-        }
-        
-        // Delete website or list (vim-style)
-        KeyCode::Char('d') | KeyCode::Char('x') => {
-            if app.selected_website_index.is_some() {
-                app.delete_website();
-                app.status_message = "Website removed".to_string();
-            }
-        }
-        KeyCode::Char('D') => {
-            if app.selected_list_index.is_some() {
-                app.delete_list();
-                app.status_message = "List removed".to_string();
-            }
-        }
-        
-        _ => {}
+/// Domains of `websites` with open connections, unless `in_use_warning = false`
+fn domains_in_use(websites: &[String], config: &Config) -> Vec<inuse::InUse> {
+    if !config.in_use_warning.unwrap_or(true) {
+        return Vec::new();
     }
-    
-    Ok(())
+    inuse::domains_in_use(websites, config.firewall.as_ref().and_then(|firewall| firewall.resolver))
 }
 
-/// Handle key events for the timer tab
-fn handle_timer_tab_events(app: &mut App, key: KeyCode) -> Result<()> {
-    match key {
-        // Adjust time with vim-style navigation
-        KeyCode::Char('k') | KeyCode::Up => {
-            app.increase_time();
-        }
-        KeyCode::Char('j') | KeyCode::Down => {
-            app.decrease_time();
-        }
-        
-        // Change time unit (vim-style using t or u)
-        KeyCode::Char('t') | KeyCode::Char('u') => {
-            app.cycle_time_unit();
-        }
-        
-        // Start blocking (vim-style using space or enter)
-        KeyCode::Char(' ') | KeyCode::Enter => {
-            if !app.is_blocking && app.selected_list_index.is_some() {
-                let websites = app.current_websites();
-                
-                if !websites.is_empty() {
-                    let duration_ms = app.get_blocking_milliseconds();
-                    let duration = Duration::from_millis(duration_ms);
-                    
-                    match start_blocking_websites(&websites, duration_ms) {
-                        Ok(_) => {
-                            app.start_blocking(duration)?;
-                        }
-                        Err(e) => {
-                            app.status_message = format!("Error blocking websites: {}", e);
-                        }
-                    }
-                } else {
-                    app.status_message = "Selected list has no websites to block".to_string();
-                }
+/// What the first block of `lists` would break
+fn list_impact(lists: &[&tui::WebsiteList], config: &Config, safety: &safety::SafetyList) -> approval::Impact {
+    let websites: Vec<String> = lists.iter().flat_map(|list| list.websites.iter().cloned()).collect();
+    let websites = services::expand(&websites, config.services.as_deref().unwrap_or_default());
+    approval::Impact {
+        lists: lists.iter().map(|list| list.name.clone()).collect(),
+        entries: websites.len(),
+        in_use: inuse::domains_in_use(&websites, config.firewall.as_ref().and_then(|firewall| firewall.resolver)),
+        protected: safety.protected(&websites).into_iter().map(str::to_string).collect(),
+    }
+}
+
+/// Collect the websites to block from the configuration.
+///
+/// If `list_names` is empty, all enabled lists are used. Lists named
+/// explicitly are used even when disabled. Enabled system lists are always
+/// added. Service names are replaced with the domains of the service.
+fn collect_websites(config: &Config, list_names: &[String]) -> Result<Vec<String>> {
+    let mut websites = Vec::new();
+    
+    if let Some(website_lists) = &config.website_lists {
+        for name in list_names {
+            if !website_lists.iter().any(|list| list.name.eq_ignore_ascii_case(name)) {
+                return Err(color_eyre::eyre::eyre!("Unknown website list: {}", name));
             }
         }
         
-        // Stop blocking (vim-style using Esc)
-        KeyCode::Esc => {
-            if app.is_blocking {
-                match stop_blocking_websites() {
-                    Ok(_) => {
-                        app.stop_blocking()?;
-                    }
-                    Err(e) => {
-                        app.status_message = format!("Error stopping website blocking: {}", e);
-                    }
-                }
+        for list in website_lists {
+            let named = list_names.iter().any(|name| list.name.eq_ignore_ascii_case(name));
+            let selected = named || (list.enabled && (list_names.is_empty() || list.system));
+            if selected {
+                websites.extend(list.websites.clone());
             }
         }
+    } else {
+        // Try to read from website list path
+        let website_list = fs::read_to_string(&config.website_list_path)
+            .wrap_err_with(|| format!("Could not read website list: {}", &config.website_list_path))?;
         
-        // Quick time adjustments (vim-style)
-        KeyCode::Char('+') => {
-            // Increase time by larger step
-            for _ in 0..5 { app.increase_time(); }
-        }
-        KeyCode::Char('-') => {
-            // Decrease time by larger step
-            for _ in 0..5 { app.decrease_time(); }
-        }
-        
-        _ => {}
+        websites = website_list
+            .lines()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty() && !s.starts_with('#'))
+            .collect();
     }
     
-    Ok(())
+    Ok(services::expand(&websites, config.services.as_deref().unwrap_or_default()))
 }
 
-/// Block websites using the TUI interface
-fn start_blocking_websites(websites: &Vec<String>, _duration_ms: u64) -> std::io::Result<()> {
-    // Check if we're running as root/admin
-    #[cfg(target_family = "unix")]
-    {
-        if !is_root() {
-            eprintln!("Warning: TimeGuardian needs root privileges to modify the hosts file.");
-            eprintln!("Please run the application with sudo or as administrator.");
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::PermissionDenied, 
-                "Insufficient permissions to modify hosts file"
-            ));
-        }
-    }
-
-    let hosts_path = get_hosts_path();
-    let config_dir = get_config_dir().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    let backup_path = config_dir.join(HOSTS_BACKUP);
-
-    // Read current content of hosts file
-    let hosts_content = fs::read_to_string(&hosts_path)?;
-
-    // Create backup if it doesn't exist or update the existing backup
-    let mut backup_file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&backup_path)?;
+/// The names in `list_names` that belong to enabled lists
+fn enabled_lists(config: &Config, list_names: &[String]) -> Vec<String> {
+    let lists = config.website_lists.as_deref().unwrap_or_default();
+    list_names
+        .iter()
+        .filter(|name| {
+            lists
+                .iter()
+                .find(|list| list.name.eq_ignore_ascii_case(name))
+                .is_none_or(|list| list.enabled)
+        })
+        .cloned()
+        .collect()
+}
 
-    backup_file.write_all(hosts_content.as_bytes())?;
+/// Domains that must not be blocked with the active profile, honoring
+/// `--i-know-what-im-doing`
+fn safety_list(cli: &Cli, config: &Config) -> safety::SafetyList {
+    let profiles = config.profiles.clone().unwrap_or_default();
+    let active = profile::resolve(&profiles, cli.profile.as_deref(), &profile::Environment::detect());
+    safety::SafetyList::new(config.safe_domains.as_deref().unwrap_or_default(), active, cli.i_know_what_im_doing)
+}
 
-    // Create the new hosts content with blocked websites
-    let mut new_hosts_content = hosts_content.clone();
-    
-    // Remove any existing TimeGuardian entries
-    if let Some(start_idx) = new_hosts_content.find(TEMP_HOSTS_MARKER) {
-        if let Some(end_idx) = new_hosts_content[start_idx..].find("\n# ===== End") {
-            let end_idx = start_idx + end_idx + "\n# ===== End Temporary Hosts =====".len();
-            new_hosts_content = new_hosts_content[..start_idx].to_string() + &new_hosts_content[end_idx..];
-        }
+/// Move the options of `start` to the session flags of `cli`
+fn apply_start_command(cli: &mut Cli) {
+    let Some(Commands::Start { duration, preset, session, strict, detach }) =
+        cli.command.take_if(|command| matches!(command, Commands::Start { .. }))
+    else {
+        return;
+    };
+    cli.duration = duration.or(cli.duration.take());
+    cli.session.task = session.task.or(cli.session.task.take());
+    if !session.lists.is_empty() {
+        cli.session.lists = session.lists;
     }
+    cli.preset = preset.or(cli.preset.take());
+    cli.session.priority = session.priority.or(cli.session.priority);
+    cli.strict = strict;
+    cli.session.timer_only |= session.timer_only;
+    cli.detach |= detach;
+    cli.start = true;
+}
 
-    // Add new website blocks with multiple domain variants
-    new_hosts_content.push_str(&format!("\n{}\n", TEMP_HOSTS_MARKER));
-    for website in websites {
-        let website = website.trim().to_lowercase();
-        if !website.is_empty() {
-            println!("Blocking website: {}", website);
-            
-            // Remove any protocol prefixes if present
-            let clean_website = if website.starts_with("http://") {
-                &website[7..]
-            } else if website.starts_with("https://") {
-                &website[8..]
-            } else {
-                &website
-            };
-            
-            // Remove any trailing path components
-            let domain = clean_website.split('/').next().unwrap_or(clean_website);
-            
-            // Block the base domain
-            new_hosts_content.push_str(&format!("127.0.0.1\t{}\n", domain));
-            
-            // Block common subdomains
-            if !domain.starts_with("www.") {
-                new_hosts_content.push_str(&format!("127.0.0.1\twww.{}\n", domain));
-            }
-            
-            // Block mobile version
-            new_hosts_content.push_str(&format!("127.0.0.1\tm.{}\n", domain));
-            
-            // Block app subdomain
-            new_hosts_content.push_str(&format!("127.0.0.1\tapp.{}\n", domain));
+/// The lists given with `-l`, else those of the active profile
+fn session_lists(cli: &Cli, lists: &[String], config: &Config) -> Vec<String> {
+    if !lists.is_empty() {
+        return lists.to_vec();
+    }
+    let profiles = config.profiles.clone().unwrap_or_default();
+    let environment = profile::Environment::detect();
+    match profile::resolve(&profiles, cli.profile.as_deref(), &environment) {
+        Some(active) => {
+            println!("Using profile '{}' ({})", active.name, environment.describe());
+            active.lists.clone()
         }
+        None => Vec::new(),
     }
-    new_hosts_content.push_str("# ===== End Temporary Hosts =====\n");
+}
 
-    // Write the modified hosts file
-    let mut hosts_file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(&hosts_path)?;
+/// Say why a session found nothing to block
+fn explain_no_websites(config: &Config) {
+    if config.website_lists.as_deref().unwrap_or_default().iter().any(|list| !list.enabled) {
+        println!("No websites to block: all website lists are disabled. Enable one in the TUI or pass -l <list>.");
+    } else {
+        println!("No websites to block. Please set up the application first.");
+    }
+}
 
-    hosts_file.write_all(new_hosts_content.as_bytes())?;
+/// Whether stdout is a terminal capable of raw mode, the live countdown and the TUI
+fn is_interactive_terminal() -> bool {
+    let dumb_terminal = env::var("TERM").is_ok_and(|term| term == "dumb");
+    io::stdout().is_terminal() && !dumb_terminal
+}
 
-    // Perform a more thorough DNS cache flush
-    flush_dns_cache();
+/// Whether screen-reader-friendly output was requested (flag or config)
+fn accessible_mode(cli: &Cli, config: &Config) -> bool {
+    cli.accessible || config.accessible.unwrap_or(false)
+}
 
+/// Re-evaluate allowed windows of list entries about once a minute
+fn refresh_hosts_periodically(last_refresh: &mut Instant) -> Result<()> {
+    if last_refresh.elapsed() >= HOSTS_REFRESH_INTERVAL {
+        *last_refresh = Instant::now();
+        hosts::refresh()?;
+    }
     Ok(())
 }
 
-/// Stop blocking websites
-fn stop_blocking_websites() -> std::io::Result<()> {
-    // Same code as in the stop_blocking function
-    let hosts_path = get_hosts_path();
-    let config_dir = get_config_dir().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    let backup_path = config_dir.join(HOSTS_BACKUP);
-
-    if backup_path.exists() {
-        let backup_content = fs::read_to_string(&backup_path)?;
-        fs::write(&hosts_path, backup_content)?;
-    }
+/// Identifier used for the hosts claim of this process
+fn session_source(kind: &str) -> String {
+    format!("{}-{}", kind, std::process::id())
+}
 
-    Ok(())
+/// Identifier used for the hosts claim of a schedule
+fn schedule_source(schedule: &schedule::Schedule) -> String {
+    let name: String = schedule
+        .name
+        .chars()
+        .map(|c| if c.is_whitespace() { '-' } else { c.to_ascii_lowercase() })
+        .collect();
+    format!("schedule-{}", name)
 }
 
-/// Stop website blocking and restore hosts file
-fn stop_blocking() -> Result<()> {
+/// Restore the hosts file from the backup and forget all claims.
+///
+/// With `up_to`, claims above that priority keep blocking: only the claims
+/// up to it end, unless no higher one exists. Returns the number of claims
+/// kept.
+fn stop_blocking(up_to: Option<Priority>) -> Result<usize> {
+    let _lock = hosts::lock_claims()?;
+    if let Some(priority) = up_to {
+        let (released, kept) = hosts::release_up_to(priority)?;
+        if kept > 0 {
+            let session_ids: Vec<String> = released.into_iter().filter_map(|claim| claim.session_id).collect();
+            history::end_sessions(&session_ids, EndReason::Reset)?;
+            return Ok(kept);
+        }
+    }
+    
     let hosts_path = get_hosts_path();
-    let config_dir = get_config_dir()?;
-    let backup_path = config_dir.join(HOSTS_BACKUP);
+    let backup_path = get_state_dir()?.join(HOSTS_BACKUP);
     
     if backup_path.exists() {
         let backup_content = fs::read_to_string(&backup_path)?;
-        fs::write(&hosts_path, backup_content)?;
+        let hosts_content = fs::read_to_string(&hosts_path).unwrap_or_default();
+        let restored = merge_stale_backup(&backup_content, &hosts::strip_marker_block(&hosts_content))?;
+        journal::write("reset", &[], &hosts_path, &hosts_content, &restored)?;
     }
     
-    Ok(())
+    // The sessions whose block goes away end here, whatever their process does later
+    let mut claims = hosts::BlockState::load()?.claims;
+    claims.extend(hosts::sessions_in_hosts_file()?);
+    let session_ids: Vec<String> = claims.into_iter().filter_map(|claim| claim.session_id).collect();
+    history::end_sessions(&session_ids, EndReason::Reset)?;
+    
+    hosts::clear_claims()?;
+    hosts::flush_dns_cache();
+    mirror::apply("");
+    firewall::apply(&[], &[])?;
+    timer::unlock_shortcuts();
+    
+    Ok(0)
 }
 
-/// Flush DNS cache based on the operating system
-fn flush_dns_cache() {
-    #[cfg(target_os = "windows")]
-    {
-        // For Windows
-        let _ = Command::new("ipconfig")
-            .args(["/flushdns"])
-            .output();
+/// The hosts file to restore: the backup, merged hunk by hunk with changes
+/// other tools made after it was taken.
+///
+/// Without a terminal to ask, their changes are kept.
+fn merge_stale_backup(backup: &str, current: &str) -> Result<String> {
+    let hunks = merge::diff(backup, current);
+    let conflicts = merge::conflicts(&hunks);
+    if conflicts == 0 {
+        return Ok(backup.to_string());
     }
     
-    #[cfg(target_os = "macos")]
-    {
-        // For macOS
-        let _ = Command::new("dscacheutil")
-            .args(["-flushcache"])
-            .output();
-        let _ = Command::new("killall")
-            .args(["-HUP", "mDNSResponder"])
-            .output();
+    if !io::stdin().is_terminal() {
+        println!("The hosts file changed since the backup was taken; keeping those changes.");
+        return merge::merge(&hunks, |_, _| Ok(merge::Choice::Theirs));
     }
     
-    #[cfg(target_os = "linux")]
-    {
-        // Try multiple Linux DNS cache flush methods for better compatibility
-        
-        // For systemd-resolved
-        let _ = Command::new("systemd-resolve")
-            .args(["--flush-caches"])
-            .output();
-            
-        // For nscd
-        let _ = Command::new("service")
-            .args(["nscd", "restart"])
-            .output();
-            
-        // For dnsmasq
-        let _ = Command::new("systemctl")
-            .args(["restart", "dnsmasq"])
-            .output();
-            
-        // For NetworkManager
-        let _ = Command::new("systemctl")
-            .args(["restart", "NetworkManager"])
-            .output();
-            
-        // For browsers - kill DNS cache
-        let _ = Command::new("pkill")
-            .args(["-HUP", "chrome"])
-            .output();
-        let _ = Command::new("pkill")
-            .args(["-HUP", "firefox"])
-            .output();
-            
-        // Extra check - restart local resolver service if present
-        let _ = Command::new("resolvectl")
-            .args(["flush-caches"])
-            .output();
-    }
-    
-    // Print confirmation message
-    println!("DNS cache flush attempted");
+    println!("The hosts file was changed by other tools since TimeGuardian's backup was taken.");
+    merge::merge(&hunks, |hunk, number| {
+        let merge::Hunk::Changed { backup, current } = hunk else {
+            return Ok(merge::Choice::Theirs);
+        };
+        println!("\nChange {} of {}:", number, conflicts);
+        for line in backup {
+            println!("  - {}", line);
+        }
+        for line in current {
+            println!("  + {}", line);
+        }
+        loop {
+            print!("Keep [t]heirs (+), [b]ackup (-) or b[o]th? [t] ");
+            io::Write::flush(&mut stdout())?;
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer)? == 0 {
+                return Ok(merge::Choice::Theirs);
+            }
+            if let Some(choice) = merge::Choice::from_answer(&answer) {
+                return Ok(choice);
+            }
+        }
+    })
 }
 
 /// Check if the application is running with root/admin privileges
@@ -881,7 +1327,8 @@ fn parse_duration(duration_str: &str) -> Result<u64> {
         "s" => Ok(number * 1000),          // seconds to ms
         "m" => Ok(number * 60 * 1000),     // minutes to ms
         "h" => Ok(number * 60 * 60 * 1000),// hours to ms
-        _ => Err(color_eyre::eyre::eyre!("Invalid time unit. Use s, m, or h")),
+        "d" => Ok(number * 24 * 60 * 60 * 1000), // days to ms
+        _ => Err(color_eyre::eyre::eyre!("Invalid time unit. Use s, m, h or d")),
     }
 }
 
@@ -890,63 +1337,84 @@ fn main() -> Result<()> {
     // Setup error handling
     color_eyre::install()?;
     
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    // The way out must not depend on a config or state that may be broken
+    if matches!(cli.command, Some(Commands::Rescue)) {
+        return rescue::run();
+    }
+    apply_start_command(&mut cli);
+    if let Some(path) = cli.config.clone().or_else(|| env::var_os(CONFIG_ENV).map(PathBuf::from)) {
+        if !path.exists() {
+            return Err(color_eyre::eyre::eyre!("Configuration file not found: {:?}", path));
+        }
+        let _ = DECLARATIVE_CONFIG.set(path);
+    }
+    if let Some(dir) = cli.config_dir.clone().or_else(|| env::var_os(CONFIG_DIR_ENV).map(PathBuf::from)) {
+        let _ = CONFIG_DIR_OVERRIDE.set(dir);
+    }
+    if let Err(e) = migrate_state() {
+        eprintln!("Could not move state files to the state directory: {}", e);
+    }
+    
+    // Remove blocks that outlived their session, even without a daemon.
+    // IPC output is parsed by scripts, so it stays quiet there.
+    let quiet = matches!(cli.command, Some(Commands::Ipc { .. }));
+    
+    // Finish or undo a hosts file write or firewall change interrupted by a crash first
+    match journal::recover() {
+        Ok(Some(message)) if !quiet => eprintln!("{}", message),
+        Err(e) if !quiet => eprintln!("Could not recover the interrupted blocking operation: {}", e),
+        _ => {}
+    }
+    match hosts::heal_expired() {
+        Ok((0, _)) => {}
+        Ok((removed, session_ids)) => {
+            // Their process died before ending the session
+            if let Err(e) = history::end_sessions(&session_ids, EndReason::Crashed)
+                && !quiet
+            {
+                eprintln!("Could not record the crashed sessions: {}", e);
+            }
+            if !quiet {
+                eprintln!("Removed {} expired block(s) from the hosts file.", removed);
+            }
+        }
+        Err(e) if !quiet => eprintln!("Could not remove expired blocks: {}", e),
+        _ => {}
+    }
+    
+    if !matches!(cli.command, Some(Commands::Telemetry { .. }))
+        && let Ok(config) = load_config()
+        && let Some(telemetry) = &config.telemetry
+    {
+        telemetry::count(telemetry, &telemetry::used_features(&cli));
+    }
     
     match &cli.command {
+        Some(Commands::Init { from, force, pull }) => {
+            setup::run_init(from, *force, *pull)?;
+        }
         Some(Commands::Setup { list_path }) => {
-            // Set up the application with a website list
-            let _config_dir = get_config_dir()?;
-            
-            let websites = fs::read_to_string(list_path)
-                .wrap_err_with(|| format!("Could not read website list file: {}", list_path))?;
-            
-            let mut config = load_config()?;
-            config.website_list_path = list_path.clone();
-            
-            // Parse websites and create default lists
-            let social_media = tui::WebsiteList {
-                name: "Social Media".to_string(),
-                websites: vec![
-                    "www.facebook.com".to_string(),
-                    "facebook.com".to_string(),
-                    "www.twitter.com".to_string(),
-                    "twitter.com".to_string(),
-                    "www.instagram.com".to_string(),
-                    "instagram.com".to_string(),
-                ],
-            };
-            
-            let entertainment = tui::WebsiteList {
-                name: "Entertainment".to_string(),
-                websites: vec![
-                    "www.youtube.com".to_string(),
-                    "youtube.com".to_string(),
-                    "www.netflix.com".to_string(),
-                    "netflix.com".to_string(),
-                    "www.reddit.com".to_string(),
-                    "reddit.com".to_string(),
-                ],
-            };
-            
-            let user_list = tui::WebsiteList {
-                name: "Custom Sites".to_string(),
-                websites: websites
-                    .lines()
-                    .map(|line| line.trim().to_string())
-                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
-                    .collect(),
-            };
-            
-            config.website_lists = Some(vec![social_media, entertainment, user_list]);
-            save_config(&config)?;
-            
-            println!("Setup completed successfully!");
+            setup::run_setup(list_path)?;
+        }
+        Some(Commands::Stop { target }) => {
+            attach::stop_session(target)?;
         }
-        Some(Commands::Reset) => {
-            // Reset hosts file to original state
-            stop_blocking()?;
-            println!("Website blocking has been reset.");
+        Some(Commands::Reset { priority, force }) => {
+            // Lower-priority sources must not end a higher-priority session
+            let kept = stop_blocking((!force).then_some(*priority))?;
+            if kept > 0 {
+                println!(
+                    "Ended the sessions up to priority '{}'; {} session(s) above it keep blocking. Use --force to reset them as well.",
+                    priority.label(),
+                    kept
+                );
+            } else {
+                println!("Website blocking has been reset.");
+            }
         }
+        // Handled before the config and state are touched
+        Some(Commands::Rescue) => {}
         Some(Commands::Permissions) => {
             // Request permissions
             if check_and_get_permissions()? {
@@ -955,49 +1423,139 @@ fn main() -> Result<()> {
                 println!("Could not obtain required permissions.");
             }
         }
+        Some(Commands::Backup { action }) => {
+            backup::run_command(action)?;
+        }
+        Some(Commands::List { action }) => {
+            snapshot::run_command(action)?;
+        }
+        Some(Commands::State { action: StateAction::Export { path } }) => {
+            let count = state::export(path)?;
+            println!("Exported {} file(s) to {:?}", count, path);
+        }
+        Some(Commands::State { action: StateAction::Import { path, force } }) => {
+            for name in state::import(path, *force)? {
+                println!("Restored {}", name);
+            }
+        }
+        Some(Commands::Ipc { request }) => {
+            ipc::run_command(request)?;
+        }
+        Some(Commands::Plan { preset, queue, duration, session, at }) => {
+            plan::run_command(&cli, preset.as_deref(), queue.as_deref(), duration.as_deref(), session, at.as_deref())?;
+        }
+        Some(Commands::Profile) => {
+            profile::run_command(cli.profile.as_deref())?;
+        }
+        Some(Commands::Again { from }) => {
+            timer::run_again(&cli, *from)?;
+        }
+        Some(Commands::History { limit }) => {
+            stats::print_history(*limit)?;
+        }
+        Some(Commands::Stats { action }) => {
+            stats::run_command(action)?;
+        }
+        Some(Commands::Telemetry { action }) => {
+            telemetry::run_command(action)?;
+        }
+        Some(Commands::Metrics { format }) => {
+            let metrics = metrics::Metrics::load()?;
+            match format {
+                metrics::MetricsFormat::Text => print!("{}", metrics.text()),
+                metrics::MetricsFormat::Prometheus => print!("{}", metrics.prometheus()),
+            }
+        }
+        Some(Commands::Status { watch: true, interval, json, .. }) => {
+            attach::watch_status(Duration::from_millis(parse_duration(interval)?), *json)?;
+        }
+        Some(Commands::Status { qr, verbose, .. }) => {
+            diagnose::status(*qr, *verbose)?;
+        }
+        Some(Commands::Why { domain }) => {
+            let config = load_config()?;
+            let lists = config.website_lists.unwrap_or_default();
+            diagnose::why(domain, &lists, &config.services.unwrap_or_default())?;
+        }
+        Some(Commands::Services) => {
+            let config = load_config()?;
+            for service in services::all(&config.services.unwrap_or_default()) {
+                println!("{}: {}", service.name, service.domains.join(", "));
+            }
+            println!("\nAdd a name to a list to block all domains of the service.");
+        }
+        Some(Commands::Config { action }) => {
+            run_config_command(action)?;
+        }
+        Some(Commands::Schedule { action }) => {
+            snooze::run_command(&cli, action)?;
+        }
+        Some(Commands::AttachCli { target }) => {
+            attach::attach_session(target, accessible_mode(&cli, &load_config()?))?;
+        }
+        Some(Commands::NewTab) => {
+            newtab::run_command()?;
+        }
+        Some(Commands::Wrap { session, max, command }) => {
+            wrap::run_command(&cli, session, max, command)?;
+        }
+        Some(Commands::Remind) => {
+            lockout::run_command()?;
+        }
+        Some(Commands::Demo) => {
+            let dir = demo::prepare()?;
+            let result = tui::run_tui(&cli);
+            demo::cleanup(&dir);
+            result?;
+        }
         Some(Commands::Tui) => {
             // TUI application
-            run_tui()?;
+            tui::run_tui(&cli)?;
         }
-        None => {
-            // CLI mode with direct command
-            if let (Some(duration_str), Some(task)) = (&cli.duration, &cli.task) {
-                let duration_ms = parse_duration(duration_str)?;
-                let duration = Duration::from_millis(duration_ms);
-                
-                let config = load_config()?;
-                let mut websites = Vec::new();
-                
-                if let Some(website_lists) = &config.website_lists {
-                    for list in website_lists {
-                        websites.extend(list.websites.clone());
-                    }
-                } else {
-                    // Try to read from website list path
-                    let website_list = fs::read_to_string(&config.website_list_path)
-                        .wrap_err_with(|| format!("Could not read website list: {}", &config.website_list_path))?;
-                    
-                    websites = website_list
-                        .lines()
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty() && !s.starts_with('#'))
-                        .collect();
-                }
-                
-                if websites.is_empty() {
-                    println!("No websites to block. Please set up the application first.");
-                    return Ok(());
-                }
-                
-                block_websites_with_timer(&websites, duration, task, duration_str)?;
-            } else {
+        // `start` was turned into the session flags by `apply_start_command`
+        Some(Commands::Start { .. }) | None => {
+            if !timer::run_command(&cli)? {
                 // Show usage info
                 let supported_commands = [
                     "tui                - Start the TUI interface",
+                    "init --from <url|file> - Set up a new machine from a shared config",
                     "setup --list <path>- Set up website lists from file",
-                    "reset              - Reset all website blocking",
+                    "stop               - End the running session early (only its blocks)",
+                    "reset [--force]    - Restore the hosts file backup and end all sessions (recovery)",
                     "permissions        - Check/request required permissions",
-                    "-d <time> -t <task>- Block websites for duration (e.g., -d 30m -t work)",
+                    "backup push|pull   - Encrypted backup of the config and history",
+                    "state export|import <file> - Move config and state in one archive (.tar.zst)",
+                    "ipc <request>      - JSON status/control of the running session",
+                    "profile            - Show the detected network and active profile",
+                    "status [--qr] [-v] - Show the sessions blocking websites (a QR code for phone timers, firewall lookups)",
+                    "why <domain>       - Explain why a website still loads",
+                    "services           - Services lists can name instead of domains (e.g. YouTube)",
+                    "schedule preview|apply - Upcoming scheduled blocks / enforce them",
+                    "config restore --latest - Restore the configuration from a backup",
+                    "list diff|history|restore <list@version> - Earlier versions of a list (e.g. Work@yesterday)",
+                    "again [--from <id>]- Repeat the last (or a past) session",
+                    "wrap -- <command>  - Block while a command runs (e.g. wrap -- nvim notes.md)",
+                    "history            - List recent sessions",
+                    "metrics [--format prometheus] - Reconcile and IPC counters",
+                    "stats badge        - Shareable focus summary (markdown or --format svg)",
+                    "stats top-domains  - Lists and domains blocked the longest (--period 30d)",
+                    "stats verify       - Check that no session was edited or removed from the history",
+                    "telemetry status|show|enable|disable|upload - Opt-in anonymous usage counters",
+                    "start [-d <time>] [-t <task>] - Start a focus session (e.g., start -d 30m -t work)",
+                    "  -p <priority>    - Session priority: low, normal, high, critical",
+                    "  -l <list>        - Block only this list; sessions can run side by side",
+                    "  --preset <name>  - Start the session from a configured preset",
+                    "  --strict         - No stopping or pausing before the end",
+                    "  --timer-only     - Only run the timer and record the session; block nothing, no root",
+                    "  --detach         - Run the session in the background",
+                    "-d <time> -t <task>- Deprecated form of 'start -d <time> -t <task>'",
+                    "--accessible       - Screen-reader-friendly output",
+                    "--i-know-what-im-doing - Allow blocking localhost, package mirrors and safe domains",
+                    "--theme <name>     - TUI colors: default, deuteranopia, protanopia, tritanopia, monochrome",
+                    "--config <path>    - Read-only config, e.g. from Nix (also TIMEGUARDIAN_CONFIG)",
+                    "attach-cli         - Follow the background session",
+                    "demo               - Try the TUI with fake data (no root, no real blocking)",
+                    "                     (inside a project bound to a preset, just run 'timeguardian')",
                 ];
                 
                 println!("TimeGuardian - Focus by blocking distracting websites");
@@ -1007,7 +1565,7 @@ fn main() -> Result<()> {
                 for cmd in supported_commands {
                     println!("  {}", cmd);
                 }
-                println!("\nTime units: s (seconds), m (minutes), h (hours)");
+                println!("\nTime units: s (seconds), m (minutes), h (hours), d (days)");
             }
         }
     }
@@ -0,0 +1,216 @@
+/*
+* TimeGuardian Merge Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module merges the hosts file backup with the current hosts file when
+* `reset` finds that other tools changed the file after the backup was
+* taken. Instead of overwriting their changes, every differing hunk is
+* resolved separately: keep theirs, keep the backup or keep both.
+*/
+
+use color_eyre::Result;
+
+/// A run of lines that is equal in both files or differs between them
+#[derive(Debug, PartialEq, Eq)]
+pub enum Hunk<'a> {
+    Same(Vec<&'a str>),
+    Changed {
+        /// Lines of the backup
+        backup: Vec<&'a str>,
+        /// Lines of the current file (theirs)
+        current: Vec<&'a str>,
+    },
+}
+
+/// How a changed hunk is resolved
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Choice {
+    /// Keep the lines of the current file
+    Theirs,
+    /// Keep the lines of the backup
+    Backup,
+    /// Keep the backup lines followed by the current lines
+    Both,
+}
+
+impl Choice {
+    /// Parse an answer like "t", "b" or "o"; empty keeps theirs
+    pub fn from_answer(answer: &str) -> Option<Self> {
+        match answer.trim().to_lowercase().as_str() {
+            "" | "t" | "theirs" => Some(Self::Theirs),
+            "b" | "backup" => Some(Self::Backup),
+            "o" | "both" => Some(Self::Both),
+            _ => None,
+        }
+    }
+}
+
+/// Line diff of the backup and the current file (longest common subsequence)
+pub fn diff<'a>(backup: &'a str, current: &'a str) -> Vec<Hunk<'a>> {
+    let old: Vec<&str> = backup.lines().collect();
+    let new: Vec<&str> = current.lines().collect();
+
+    // common[i][j]: length of the LCS of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            if let Some(Hunk::Same(lines)) = hunks.last_mut() {
+                lines.push(old[i]);
+            } else {
+                hunks.push(Hunk::Same(vec![old[i]]));
+            }
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        if !matches!(hunks.last(), Some(Hunk::Changed { .. })) {
+            hunks.push(Hunk::Changed { backup: Vec::new(), current: Vec::new() });
+        }
+        let Some(Hunk::Changed { backup, current }) = hunks.last_mut() else {
+            unreachable!("a changed hunk was just pushed");
+        };
+        if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            backup.push(old[i]);
+            i += 1;
+        } else {
+            current.push(new[j]);
+            j += 1;
+        }
+    }
+    hunks
+}
+
+/// Build the merged file, asking `choose` how to resolve each changed hunk
+/// (called with the hunk and its 1-based number)
+pub fn merge(hunks: &[Hunk], mut choose: impl FnMut(&Hunk, usize) -> Result<Choice>) -> Result<String> {
+    let mut merged: Vec<&str> = Vec::new();
+    let mut number = 0;
+    for hunk in hunks {
+        match hunk {
+            Hunk::Same(lines) => merged.extend(lines),
+            Hunk::Changed { backup, current } => {
+                number += 1;
+                match choose(hunk, number)? {
+                    Choice::Theirs => merged.extend(current),
+                    Choice::Backup => merged.extend(backup),
+                    Choice::Both => {
+                        merged.extend(backup);
+                        merged.extend(current);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut content = merged.join("\n");
+    content.push('\n');
+    Ok(content)
+}
+
+/// Number of changed hunks
+pub fn conflicts(hunks: &[Hunk]) -> usize {
+    hunks.iter().filter(|hunk| matches!(hunk, Hunk::Changed { .. })).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A changed hunk from its backup and current lines
+    fn changed<'a>(backup: &[&'a str], current: &[&'a str]) -> Hunk<'a> {
+        Hunk::Changed { backup: backup.to_vec(), current: current.to_vec() }
+    }
+
+    #[test]
+    fn diff_of_equal_files_has_no_conflicts() {
+        let hunks = diff("a\nb\n", "a\nb\n");
+        assert_eq!(hunks, vec![Hunk::Same(vec!["a", "b"])]);
+        assert_eq!(conflicts(&hunks), 0);
+    }
+
+    #[test]
+    fn diff_finds_insertions_and_deletions() {
+        let inserted = diff("a\nb\n", "a\nx\nb\n");
+        assert_eq!(inserted, vec![Hunk::Same(vec!["a"]), changed(&[], &["x"]), Hunk::Same(vec!["b"])]);
+
+        let deleted = diff("a\nb\nc\n", "a\nc\n");
+        assert_eq!(deleted, vec![Hunk::Same(vec!["a"]), changed(&["b"], &[]), Hunk::Same(vec!["c"])]);
+
+        let appended = diff("a\n", "a\nx\ny\n");
+        assert_eq!(appended, vec![Hunk::Same(vec!["a"]), changed(&[], &["x", "y"])]);
+    }
+
+    #[test]
+    fn adjacent_changes_form_one_hunk() {
+        // A deleted and an inserted line next to each other are one replacement
+        let hunks = diff("a\nb\nc\nd\n", "a\nB\nC\nd\n");
+        assert_eq!(
+            hunks,
+            vec![Hunk::Same(vec!["a"]), changed(&["b", "c"], &["B", "C"]), Hunk::Same(vec!["d"])]
+        );
+        assert_eq!(conflicts(&hunks), 1);
+    }
+
+    #[test]
+    fn separated_changes_are_numbered_in_order() {
+        let hunks = diff("a\nb\nc\nd\ne\n", "a\nB\nc\nd\nE\n");
+        assert_eq!(conflicts(&hunks), 2);
+
+        let mut seen = Vec::new();
+        let merged = merge(&hunks, |hunk, number| {
+            if let Hunk::Changed { current, .. } = hunk {
+                seen.push((number, current.join("\n")));
+            }
+            Ok(if number == 1 { Choice::Theirs } else { Choice::Backup })
+        })
+        .unwrap();
+        assert_eq!(seen, vec![(1, "B".to_string()), (2, "E".to_string())]);
+        assert_eq!(merged, "a\nB\nc\nd\ne\n");
+    }
+
+    #[test]
+    fn merge_applies_each_choice() {
+        let hunks = diff("a\nb\nc\n", "a\nB\nc\n");
+        let cases = [
+            (Choice::Theirs, "a\nB\nc\n"),
+            (Choice::Backup, "a\nb\nc\n"),
+            (Choice::Both, "a\nb\nB\nc\n"),
+        ];
+        for (choice, expected) in cases {
+            assert_eq!(merge(&hunks, |_, _| Ok(choice)).unwrap(), expected, "{:?}", choice);
+        }
+    }
+
+    #[test]
+    fn merge_stops_at_the_first_error() {
+        let hunks = diff("a\n", "b\n");
+        assert!(merge(&hunks, |_, _| Err(color_eyre::eyre::eyre!("aborted"))).is_err());
+    }
+
+    #[test]
+    fn answers_pick_a_choice() {
+        let cases = [
+            ("", Some(Choice::Theirs)),
+            ("t", Some(Choice::Theirs)),
+            (" Backup ", Some(Choice::Backup)),
+            ("o", Some(Choice::Both)),
+            ("x", None),
+        ];
+        for (answer, expected) in cases {
+            assert_eq!(Choice::from_answer(answer), expected, "{:?}", answer);
+        }
+    }
+}
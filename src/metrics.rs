@@ -0,0 +1,175 @@
+/*
+* TimeGuardian Metrics Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module keeps counters about the work TimeGuardian does: how often the
+* hosts file is reconciled and rewritten, how long that takes, and how many
+* IPC requests were answered. Sessions run in short-lived processes, so the
+* counters are persisted in the state directory and add up across runs.
+*
+* `timeguardian metrics` prints them; `--format prometheus` prints the
+* Prometheus text format, e.g. for the node_exporter textfile collector.
+*/
+
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf, time::Duration};
+
+use crate::get_state_dir;
+
+/// File in the state directory holding the counters
+pub const METRICS_FILE: &str = "metrics.toml";
+
+/// Output of `timeguardian metrics`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MetricsFormat {
+    /// Human-readable summary (default)
+    #[default]
+    Text,
+    /// Prometheus text exposition format
+    Prometheus,
+}
+
+/// Counters since `since`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Metrics {
+    /// When counting started
+    pub since: Option<DateTime<Utc>>,
+    /// Reconciliations of the hosts file
+    #[serde(default)]
+    pub reconciles: u64,
+    /// Reconciliations that changed the hosts file
+    #[serde(default)]
+    pub hosts_rewrites: u64,
+    /// Total time spent reconciling, in microseconds
+    #[serde(default)]
+    pub reconcile_micros_total: u64,
+    /// Slowest reconciliation, in microseconds
+    #[serde(default)]
+    pub reconcile_micros_max: u64,
+    /// Answered IPC requests per command
+    #[serde(default)]
+    pub ipc_requests: BTreeMap<String, u64>,
+}
+
+impl Metrics {
+    /// Load the counters from the state directory
+    pub fn load() -> Result<Self> {
+        let path = metrics_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Could not read metrics: {:?}", path))?;
+        toml::from_str(&content).wrap_err("Could not parse metrics")
+    }
+
+    /// Save the counters to the state directory
+    pub fn save(&self) -> Result<()> {
+        let path = metrics_path()?;
+        let content = toml::to_string(self).wrap_err("Could not serialize metrics")?;
+        fs::write(&path, content).wrap_err_with(|| format!("Could not save metrics: {:?}", path))
+    }
+
+    /// Human-readable summary
+    pub fn text(&self) -> String {
+        let since = self
+            .since
+            .map(|since| since.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "never".to_string());
+        let average = self.reconcile_micros_total.checked_div(self.reconciles).unwrap_or(0);
+
+        let mut text = format!(
+            "Counting since: {}\nReconciles: {} ({} hosts file rewrites)\nReconcile latency: {:.1} ms average, {:.1} ms max\n",
+            since,
+            self.reconciles,
+            self.hosts_rewrites,
+            average as f64 / 1000.0,
+            self.reconcile_micros_max as f64 / 1000.0
+        );
+        let requests: u64 = self.ipc_requests.values().sum();
+        text.push_str(&format!("IPC requests: {}\n", requests));
+        for (command, count) in &self.ipc_requests {
+            text.push_str(&format!("  {}: {}\n", command, count));
+        }
+        text
+    }
+
+    /// Prometheus text exposition format
+    pub fn prometheus(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            text.push_str(&format!("# HELP timeguardian_{} {}\n# TYPE timeguardian_{} {}\n", name, help, name, kind));
+            for (labels, value) in samples {
+                text.push_str(&format!("timeguardian_{}{} {}\n", name, labels, value));
+            }
+        };
+
+        metric(
+            "reconciles_total",
+            "counter",
+            "Reconciliations of the hosts file.",
+            vec![(String::new(), self.reconciles.to_string())],
+        );
+        metric(
+            "hosts_rewrites_total",
+            "counter",
+            "Reconciliations that changed the hosts file.",
+            vec![(String::new(), self.hosts_rewrites.to_string())],
+        );
+        metric(
+            "reconcile_seconds_total",
+            "counter",
+            "Time spent reconciling the hosts file.",
+            vec![(String::new(), (self.reconcile_micros_total as f64 / 1e6).to_string())],
+        );
+        metric(
+            "reconcile_seconds_max",
+            "gauge",
+            "Slowest reconciliation of the hosts file.",
+            vec![(String::new(), (self.reconcile_micros_max as f64 / 1e6).to_string())],
+        );
+        metric(
+            "ipc_requests_total",
+            "counter",
+            "Answered IPC requests.",
+            self.ipc_requests
+                .iter()
+                .map(|(command, count)| (format!("{{cmd=\"{}\"}}", command), count.to_string()))
+                .collect(),
+        );
+        text
+    }
+}
+
+/// Count a reconciliation of the hosts file
+pub fn record_reconcile(elapsed: Duration, rewrote: bool) {
+    update(|metrics| {
+        let micros = elapsed.as_micros() as u64;
+        metrics.reconciles += 1;
+        metrics.hosts_rewrites += u64::from(rewrote);
+        metrics.reconcile_micros_total += micros;
+        metrics.reconcile_micros_max = metrics.reconcile_micros_max.max(micros);
+    });
+}
+
+/// Count an answered IPC request
+pub fn record_ipc_request(command: &str) {
+    update(|metrics| *metrics.ipc_requests.entry(command.to_string()).or_default() += 1);
+}
+
+/// Apply `change` to the stored counters; metrics never fail the operation
+/// they describe
+fn update(change: impl FnOnce(&mut Metrics)) {
+    let mut metrics = Metrics::load().unwrap_or_default();
+    metrics.since.get_or_insert_with(Utc::now);
+    change(&mut metrics);
+    let _ = metrics.save();
+}
+
+/// Path of the metrics file
+fn metrics_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(METRICS_FILE))
+}
@@ -0,0 +1,109 @@
+/*
+* TimeGuardian Mirror Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module copies the TimeGuardian section of the hosts file into
+* environments with their own hosts file: the running WSL distributions
+* (on Windows) and named Docker containers. Work inside them would otherwise
+* bypass the block entirely.
+*
+*   [mirror]
+*   wsl = true
+*   containers = ["devbox"]
+*
+* Mirroring is best effort: a stopped distribution or container is skipped
+* and picks the block up with the next change.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use crate::hosts::{TEMP_HOSTS_END_MARKER, TEMP_HOSTS_MARKER};
+use crate::load_config;
+
+/// Where the block is mirrored to
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MirrorConfig {
+    /// Mirror into every running WSL distribution (Windows only)
+    #[serde(default)]
+    pub wsl: bool,
+    /// Names of Docker containers to mirror into
+    #[serde(default)]
+    pub containers: Vec<String>,
+}
+
+/// Replace the TimeGuardian section of the mirrored hosts files with `block`
+/// (the marker block including its markers, or empty to unblock)
+pub fn apply(block: &str) {
+    let Some(config) = load_config().ok().and_then(|config| config.mirror) else {
+        return;
+    };
+
+    let script = replace_block_script();
+
+    #[cfg(target_os = "windows")]
+    if config.wsl {
+        for distro in running_wsl_distributions() {
+            let mut command = Command::new("wsl.exe");
+            command.args(["-d", &distro, "-u", "root", "--", "sh", "-c", &script]);
+            let _ = run_with_input(command, block);
+        }
+    }
+
+    for container in &config.containers {
+        let mut command = Command::new("docker");
+        command.args(["exec", "-i", "-u", "root", container, "sh", "-c", &script]);
+        let _ = run_with_input(command, block);
+    }
+}
+
+/// Shell script that drops the old section of /etc/hosts and appends stdin.
+///
+/// The file is rewritten in place because Docker bind-mounts /etc/hosts,
+/// which rules out replacing it by a rename (`sed -i`).
+fn replace_block_script() -> String {
+    format!(
+        "kept=$(sed '/^{}$/,/^{}$/d' /etc/hosts) && {{ printf '%s\\n' \"$kept\"; cat; }} > /etc/hosts",
+        TEMP_HOSTS_MARKER, TEMP_HOSTS_END_MARKER
+    )
+}
+
+/// Run a command with `input` on stdin; whether it succeeded
+fn run_with_input(mut command: Command, input: &str) -> bool {
+    let Ok(mut child) = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    child.wait().is_ok_and(|status| status.success())
+}
+
+/// Names of the running WSL distributions
+#[cfg(target_os = "windows")]
+fn running_wsl_distributions() -> Vec<String> {
+    let Ok(output) = Command::new("wsl.exe").args(["--list", "--running", "--quiet"]).output() else {
+        return Vec::new();
+    };
+
+    // wsl.exe prints UTF-16LE
+    let utf16: Vec<u16> = output
+        .stdout
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&utf16)
+        .lines()
+        .map(|line| line.trim_matches(|c: char| c.is_whitespace() || c == '\0').to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
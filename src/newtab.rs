@@ -0,0 +1,217 @@
+/*
+* TimeGuardian New Tab Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module writes a static HTML page for the new tab of the browser, so
+* every new tab shows the task and the remaining time instead of a search
+* box and shortcuts to distracting sites:
+*
+*   [new_tab]
+*   path = "~/.local/share/timeguardian/newtab.html"
+*
+* Point the new tab page (or the home page) of the browser at the file. The
+* process running a session rewrites it when the session starts, changes or
+* ends, and once a minute for today's stats. The page counts down by itself
+* and reloads every minute to pick up the changes. `timeguardian new-tab`
+* writes it once, e.g. to set up the browser.
+*/
+
+use chrono::{Local, NaiveDate};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use timeguardian::format;
+
+use crate::history::History;
+use crate::ipc::{SessionHandle, SessionStatus};
+use crate::{ipc, load_config, preset, stats};
+
+/// How often the session is checked for changes
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the page is written without a change, for today's stats
+const REWRITE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where the new tab page goes
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NewTabConfig {
+    /// Path of the HTML file; a leading "~" is the home directory
+    pub path: String,
+}
+
+impl NewTabConfig {
+    pub fn path(&self) -> PathBuf {
+        preset::expand_home(&self.path)
+    }
+}
+
+/// Keeps the page up to date in a background thread; the page shows no
+/// session any more once this is dropped
+pub struct NewTabPage {
+    stop: Arc<AtomicBool>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for NewTabPage {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Keep the page of `[new_tab]` in line with the session of `handle`;
+/// `None` without `[new_tab]`
+pub fn keep_updated(handle: SessionHandle) -> Option<NewTabPage> {
+    let path = load_config().ok()?.new_tab?.path();
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let stopped = stop.clone();
+    let worker = thread::spawn(move || {
+        let mut written: Option<(SessionStatus, Instant)> = None;
+        while !stopped.load(Ordering::Relaxed) {
+            let status = handle.status();
+            let changed = written.as_ref().is_none_or(|(last, at)| {
+                last.active != status.active
+                    || last.task != status.task
+                    || last.ends_at != status.ends_at
+                    || at.elapsed() >= REWRITE_INTERVAL
+            });
+            if changed {
+                // The page is a convenience; a failed write is tried again next time
+                let _ = write(&path, &status);
+                written = Some((status, Instant::now()));
+            }
+            thread::sleep(CHECK_INTERVAL);
+        }
+        let _ = write(&path, &SessionStatus::default());
+    });
+
+    Some(NewTabPage { stop, worker: Some(worker) })
+}
+
+/// Write the page for `status` to `path`
+pub fn write(path: &PathBuf, status: &SessionStatus) -> Result<()> {
+    let history = History::load().unwrap_or_default();
+    let html = render(status, &history);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).wrap_err_with(|| format!("Could not create {:?}", parent))?;
+    }
+    fs::write(path, html).wrap_err_with(|| format!("Could not write the new tab page {:?}", path))
+}
+
+/// The HTML of the page
+fn render(status: &SessionStatus, history: &History) -> String {
+    let session = match (status.active, status.ends_at) {
+        (true, Some(ends_at)) => format!(
+            r#"<p class="label">Focus on</p>
+    <h1>{task}</h1>
+    <p id="remaining" class="remaining" data-ends="{ends}">{remaining}</p>
+    <p class="label">until {until}</p>"#,
+            task = escape(status.task.as_deref().unwrap_or("Focus session")),
+            ends = ends_at.timestamp_millis(),
+            remaining = format::clock(Duration::from_secs(status.remaining_secs.unwrap_or(0))),
+            until = ends_at.format("%H:%M"),
+        ),
+        _ => r#"<h1>No focus session</h1>
+    <p class="label">Start one with <code>timeguardian start</code></p>"#
+            .to_string(),
+    };
+
+    format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta http-equiv="refresh" content="60">
+  <title>TimeGuardian</title>
+  <style>
+    body {{ margin: 0; min-height: 100vh; display: flex; flex-direction: column; align-items: center;
+           justify-content: center; background: #1e1e2e; color: #cdd6f4; font-family: system-ui, sans-serif; }}
+    h1 {{ font-size: 2.5rem; margin: 0.2em 0; text-align: center; }}
+    .remaining {{ font-size: 5rem; font-variant-numeric: tabular-nums; margin: 0.1em 0; }}
+    .label {{ color: #a6adc8; margin: 0.2em 0; }}
+    .stats {{ margin-top: 3em; color: #a6adc8; }}
+  </style>
+</head>
+<body>
+  <main>
+    {session}
+  </main>
+  <p class="stats">Today: {today}</p>
+  <script>
+    const remaining = document.getElementById("remaining");
+    if (remaining) {{
+      const ends = Number(remaining.dataset.ends);
+      const tick = () => {{
+        const left = Math.max(0, Math.round((ends - Date.now()) / 1000));
+        const clock = [Math.floor(left / 3600), Math.floor(left / 60) % 60, left % 60]
+          .map((part) => String(part).padStart(2, "0")).join(":");
+        remaining.textContent = left > 0 ? clock : "Session over";
+      }};
+      tick();
+      setInterval(tick, 1000);
+    }}
+  </script>
+</body>
+</html>
+"##,
+        session = session,
+        today = escape(&today(history, Local::now().date_naive())),
+    )
+}
+
+/// Today's stats like "3 sessions, 1h 15m focused, 4-day streak"
+fn today(history: &History, date: NaiveDate) -> String {
+    let completed: Vec<_> = history
+        .sessions
+        .iter()
+        .filter(|record| record.completed == Some(true) && record.started_at.date_naive() == date)
+        .collect();
+    let focused: Duration = completed.iter().map(|record| record.duration()).sum();
+
+    let mut text = format!(
+        "{} session{}, {} focused",
+        completed.len(),
+        if completed.len() == 1 { "" } else { "s" },
+        format::hours_minutes(focused)
+    );
+    let streak = stats::Summary::from_history(history).streak_days;
+    if streak > 0 {
+        text.push_str(&format!(", {}-day streak", streak));
+    }
+    text
+}
+
+/// Text safe to put into HTML
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Write the new tab page once for the current session
+pub fn run_command() -> Result<()> {
+    let Some(new_tab) = load_config()?.new_tab else {
+        return Err(eyre!("Set path in [new_tab] of the config first"));
+    };
+    let status = ipc::query(&ipc::Request::Status)?.and_then(|response| response.status).unwrap_or_default();
+    let path = new_tab.path();
+    write(&path, &status)?;
+    println!("Wrote {}; set it as the new tab page of your browser.", path.display());
+    Ok(())
+}
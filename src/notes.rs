@@ -0,0 +1,136 @@
+/*
+* TimeGuardian Daily Note Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module appends a summary of every finished session to a Markdown
+* file, so the focus history lands in the daily notes of an Obsidian vault or
+* any other folder of notes. The path is a pattern filled in from the start of
+* the session:
+*
+*   [daily_note]
+*   path = "~/notes/daily/{date}.md"
+*   heading = "## Focus"
+*
+* Sessions go below the heading, which is added to the end of the note the
+* first time. Each session is one list item with its start, task, length and
+* outcome, followed by its intent.
+*/
+
+use color_eyre::{eyre::Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+use crate::history::{Enforcement, SessionRecord};
+use crate::preset;
+
+/// Where the summaries go
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DailyNoteConfig {
+    /// Path of the note: `{date}` (2024-05-17), `{year}`, `{month}` and `{day}`
+    /// are replaced, a leading "~" is the home directory
+    pub path: String,
+    /// Heading the sessions are listed under
+    #[serde(default = "default_heading")]
+    pub heading: String,
+}
+
+fn default_heading() -> String {
+    "## Focus sessions".to_string()
+}
+
+/// Append a finished session to its daily note and return the path of the note
+pub fn append(config: &DailyNoteConfig, record: &SessionRecord) -> Result<PathBuf> {
+    let path = note_path(config, record);
+    let mut content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error).wrap_err_with(|| format!("Could not read daily note {:?}", path)),
+    };
+
+    let mut entry = entry(record);
+    match section_end(&content, &config.heading) {
+        Some(end) => {
+            // The entry starts on a line of its own
+            if !content[..end].ends_with('\n') {
+                entry.insert(0, '\n');
+            }
+            content.insert_str(end, &entry);
+        }
+        None => {
+            if !content.is_empty() {
+                content.push_str(if content.ends_with('\n') { "\n" } else { "\n\n" });
+            }
+            content.push_str(&config.heading);
+            content.push_str("\n\n");
+            content.push_str(&entry);
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).wrap_err_with(|| format!("Could not create {:?}", parent))?;
+    }
+    fs::write(&path, content).wrap_err_with(|| format!("Could not write daily note {:?}", path))?;
+    Ok(path)
+}
+
+/// The note of the day the session started
+fn note_path(config: &DailyNoteConfig, record: &SessionRecord) -> PathBuf {
+    let start = record.started_at;
+    let path = config
+        .path
+        .replace("{date}", &start.format("%Y-%m-%d").to_string())
+        .replace("{year}", &start.format("%Y").to_string())
+        .replace("{month}", &start.format("%m").to_string())
+        .replace("{day}", &start.format("%d").to_string());
+    preset::expand_home(&path)
+}
+
+/// Offset after the last line of the section below `heading`, if the note has one
+fn section_end(content: &str, heading: &str) -> Option<usize> {
+    let mut offset = 0;
+    let mut in_section = false;
+    let mut end = None;
+    let level = heading.chars().take_while(|c| *c == '#').count();
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == heading.trim_end() {
+            in_section = true;
+        } else if in_section && level > 0 && is_heading_up_to(trimmed, level) {
+            break;
+        }
+        offset += line.len();
+        if in_section && !trimmed.is_empty() {
+            end = Some(offset);
+        }
+    }
+    end
+}
+
+/// Whether `line` is a heading of `level` or higher
+fn is_heading_up_to(line: &str, level: usize) -> bool {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    hashes > 0 && hashes <= level && line[hashes..].starts_with(' ')
+}
+
+/// The Markdown lines of one session
+fn entry(record: &SessionRecord) -> String {
+    let mut details = vec![record.duration_text(), record.outcome().to_string()];
+    if record.enforcement != Enforcement::Blocked {
+        details.push(record.enforcement.note().to_string());
+    }
+    if !record.lists.is_empty() {
+        details.push(format!("lists: {}", record.lists.join(", ")));
+    }
+
+    let mut entry = format!(
+        "- {} **{}** ({})\n",
+        crate::time_of_day(&record.started_at),
+        record.task,
+        details.join(", ")
+    );
+    if let Some(intent) = &record.intent {
+        let achieved = record.achieved.map(|achieved| format!(" ({})", achieved.label())).unwrap_or_default();
+        entry.push_str(&format!("  - Intent: {}{}\n", intent, achieved));
+    }
+    entry
+}
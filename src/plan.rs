@@ -0,0 +1,269 @@
+/*
+* TimeGuardian Plan Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module lays out the timeline of sessions before they are started
+* (`timeguardian plan`): a preset with its chained presets, or a queue of
+* lengths like "50m,10m,50m" that alternates focus sessions and breaks.
+* Every segment gets its wall-clock start and end and the lists it blocks,
+* and scheduled blocks that fall into the plan are shown next to it, so a
+* complicated queue can be checked without committing to it.
+*/
+
+use chrono::{DateTime, Local, Utc};
+use color_eyre::{eyre::eyre, Result};
+use std::time::Duration;
+use timeguardian::schedule::{self, Occurrence};
+
+use crate::hosts::Priority;
+use crate::preset::{find_preset, EndBehavior, Preset};
+use crate::{collect_websites, load_config, parse_duration, profile, time_of_day, Cli, SessionOptions, DEFAULT_DURATION};
+
+/// What a segment of the plan does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentKind {
+    /// A session blocking its lists
+    Focus,
+    /// A break between sessions; nothing is blocked
+    Break,
+}
+
+/// One planned step, before it is placed on the clock
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub kind: SegmentKind,
+    /// Preset or task of the session
+    pub label: String,
+    pub duration: Duration,
+    /// Names of the blocked lists, resolved from "all enabled lists"
+    pub lists: Vec<String>,
+    /// Number of domains the lists block
+    pub domains: usize,
+    /// The session only runs the timer
+    pub timer_only: bool,
+    pub priority: Priority,
+}
+
+/// A step placed on the clock
+#[derive(Clone, Debug)]
+pub struct Segment {
+    pub step: Step,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+}
+
+/// Place `steps` one after another from `start`
+pub fn timeline(start: DateTime<Local>, steps: Vec<Step>) -> Vec<Segment> {
+    let mut at = start;
+    steps
+        .into_iter()
+        .map(|step| {
+            let end = at + chrono::Duration::from_std(step.duration).unwrap_or_default();
+            let segment = Segment { step, start: at, end };
+            at = end;
+            segment
+        })
+        .collect()
+}
+
+/// The lines of the plan, followed by the scheduled blocks overlapping it
+pub fn describe(segments: &[Segment], occurrences: &[Occurrence]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for segment in segments {
+        let step = &segment.step;
+        let what = match step.kind {
+            SegmentKind::Break => "break  nothing blocked".to_string(),
+            SegmentKind::Focus if step.timer_only => format!("focus  {} (timer only, nothing blocked)", step.label),
+            SegmentKind::Focus => format!(
+                "focus  {} (lists: {}; {} domain{}{})",
+                step.label,
+                if step.lists.is_empty() { "none".to_string() } else { step.lists.join(", ") },
+                step.domains,
+                if step.domains == 1 { "" } else { "s" },
+                match step.priority {
+                    Priority::Normal => String::new(),
+                    priority => format!("; {} priority", priority.name()),
+                }
+            ),
+        };
+        lines.push(format!("  {}  {}", window(segment.start, segment.end), what));
+    }
+    if let Some(last) = segments.last() {
+        lines.push(format!("Done at {}.", time_of_day(&last.end)));
+    }
+
+    if occurrences.is_empty() {
+        lines.push("No scheduled blocks during the plan.".to_string());
+        return lines;
+    }
+    lines.push(String::new());
+    lines.push("Scheduled blocks during the plan:".to_string());
+    for occurrence in occurrences {
+        let (start, end) = (occurrence.start.with_timezone(&Local), occurrence.end.with_timezone(&Local));
+        let lists = if occurrence.schedule.lists.is_empty() {
+            "all enabled lists".to_string()
+        } else {
+            occurrence.schedule.lists.join(", ")
+        };
+        lines.push(format!("  {}  schedule '{}' (lists: {})", window(start, end), occurrence.schedule.name, lists));
+
+        let overlapped: Vec<&Segment> = segments
+            .iter()
+            .filter(|segment| segment.start < end && start < segment.end)
+            .collect();
+        for segment in overlapped {
+            let note = match segment.step.kind {
+                SegmentKind::Break => "its lists stay blocked during this break",
+                SegmentKind::Focus => "blocks along with this session",
+            };
+            lines.push(format!("    overlaps {}: {}", window(segment.start, segment.end), note));
+        }
+    }
+    lines
+}
+
+/// Start and end of the plan in UTC, for looking up schedules
+pub fn span(segments: &[Segment]) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    Some((segments.first()?.start.with_timezone(&Utc), segments.last()?.end.with_timezone(&Utc)))
+}
+
+/// "10:00–10:50"
+fn window(start: DateTime<Local>, end: DateTime<Local>) -> String {
+    format!("{}–{}", time_of_day(&start), time_of_day(&end))
+}
+
+/// Print the timeline of `timeguardian plan` without starting anything
+pub fn run_command(
+    cli: &Cli,
+    preset: Option<&str>,
+    queue: Option<&str>,
+    duration: Option<&str>,
+    session: &SessionOptions,
+    at: Option<&str>,
+) -> Result<()> {
+    let config = load_config()?;
+    let presets = config.presets.clone().unwrap_or_default();
+    let preset = preset.map(|name| find_preset(&presets, name)).transpose()?;
+
+    // Same lists as a session started now: the flags, the preset, else the profile
+    let profile_lists = || {
+        let profiles = config.profiles.clone().unwrap_or_default();
+        profile::resolve(&profiles, cli.profile.as_deref(), &profile::Environment::detect())
+            .map(|active| active.lists.clone())
+            .unwrap_or_default()
+    };
+    let focus = |preset: Option<&Preset>, duration: Duration| -> Result<Step> {
+        let lists = match preset {
+            _ if !session.lists.is_empty() => session.lists.clone(),
+            Some(preset) => preset.lists.clone(),
+            None => profile_lists(),
+        };
+        let timer_only = session.timer_only || preset.is_some_and(|preset| preset.timer_only);
+        let label = session
+            .task
+            .clone()
+            .or_else(|| preset.and_then(|preset| preset.task.clone()))
+            .or_else(|| preset.map(|preset| preset.name.clone()))
+            .unwrap_or_else(|| "Focus session".to_string());
+        let domains = if timer_only { 0 } else { collect_websites(&config, &lists)?.len() };
+        let all = config.website_lists.as_deref().unwrap_or_default();
+        let lists = match (&config.website_lists, lists.is_empty()) {
+            (Some(_), true) => all.iter().filter(|list| list.enabled).map(|list| list.name.clone()).collect(),
+            (None, true) => vec![config.website_list_path.clone()],
+            // System lists block along with the named ones
+            (_, false) => {
+                let mut lists = lists;
+                for list in all.iter().filter(|list| list.system && list.enabled) {
+                    if !lists.iter().any(|name| name.eq_ignore_ascii_case(&list.name)) {
+                        lists.push(list.name.clone());
+                    }
+                }
+                lists
+            }
+        };
+        let priority = session.priority();
+        Ok(Step { kind: SegmentKind::Focus, label, duration, lists, domains, timer_only, priority })
+    };
+
+    let mut steps = Vec::new();
+    let mut notes = Vec::new();
+    if let Some(queue) = queue {
+        // Focus sessions and breaks alternate, starting with a session
+        for (index, item) in queue.split(',').map(str::trim).filter(|item| !item.is_empty()).enumerate() {
+            let length = Duration::from_millis(parse_duration(item)?);
+            if index % 2 == 0 {
+                steps.push(focus(preset, length)?);
+            } else {
+                steps.push(Step {
+                    kind: SegmentKind::Break,
+                    label: "Break".to_string(),
+                    duration: length,
+                    lists: Vec::new(),
+                    domains: 0,
+                    timer_only: false,
+                    priority: Priority::default(),
+                });
+            }
+        }
+    } else if let Some(mut preset) = preset {
+        let mut planned = vec![preset.name.clone()];
+        loop {
+            let length = duration.map(str::to_string).filter(|_| planned.len() == 1).unwrap_or(preset.duration.clone());
+            steps.push(focus(Some(preset), Duration::from_millis(parse_duration(&length)?))?);
+            match (preset.end_behavior, &preset.next) {
+                (EndBehavior::Chain, Some(next)) => {
+                    preset = find_preset(&presets, next)?;
+                    if planned.iter().any(|name| name.eq_ignore_ascii_case(&preset.name)) {
+                        notes.push(format!("The chain goes on with '{}' and repeats from there.", preset.name));
+                        break;
+                    }
+                    planned.push(preset.name.clone());
+                }
+                _ => break,
+            }
+        }
+    } else {
+        let length = match duration {
+            Some(duration) => duration.to_string(),
+            None => config.default_duration.clone().unwrap_or_else(|| DEFAULT_DURATION.to_string()),
+        };
+        steps.push(focus(None, Duration::from_millis(parse_duration(&length)?))?);
+    }
+    if steps.is_empty() {
+        return Err(eyre!("The queue has no sessions"));
+    }
+
+    let now = Local::now();
+    let start = match at {
+        Some(at) => {
+            let time = chrono::NaiveTime::parse_from_str(at, "%H:%M")
+                .map_err(|_| eyre!("Invalid start time '{}'; use HH:MM", at))?;
+            // The next time the clock shows `at`
+            let mut day = now.date_naive();
+            if time < now.time() {
+                day = day.succ_opt().unwrap_or(day);
+            }
+            day.and_time(time)
+                .and_local_timezone(Local)
+                .earliest()
+                .ok_or_else(|| eyre!("{} doesn't exist on {} in the local timezone", at, day))?
+        }
+        None => now,
+    };
+
+    let segments = timeline(start, steps);
+    let schedules = config.schedules.clone().unwrap_or_default();
+    let occurrences = match span(&segments) {
+        Some((from, until)) => schedule::upcoming(&schedules, from, until)?,
+        None => Vec::new(),
+    };
+
+    println!("Plan from {}:", start.format("%a %Y-%m-%d"));
+    for line in describe(&segments, &occurrences) {
+        println!("{}", line);
+    }
+    for note in notes {
+        println!("{}", note);
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+/*
+* TimeGuardian Preset Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module defines session presets and what happens when a session ends.
+* Presets bundle a duration, task and website lists under a name, and each
+* preset can choose its own end-of-session behavior.
+*
+* A preset can be bound to project directories, so a session started inside
+* the project picks it without `--preset`: either the preset lists the
+* directories, or a `.timeguardian.toml` in the project names the preset:
+*
+*   [[presets]]
+*   name = "Thesis"
+*   duration = "50m"
+*   lists = ["Social"]
+*   directories = ["~/code/thesis"]
+*
+*   # ~/code/thesis/.timeguardian.toml
+*   preset = "Thesis"
+*
+* A preset can also have a checklist ("Phone in the drawer?") that has to be
+* ticked off item by item before its block starts.
+*/
+
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use crossterm::{
+    event::{self, Event},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use directories::BaseDirs;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, Instant},
+};
+
+/// Interval between repeated end-of-session notifications
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// File in a project directory naming the preset of the project
+pub const PROJECT_FILE: &str = ".timeguardian.toml";
+
+/// What happens when a session ends
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EndBehavior {
+    /// Just remove the block
+    #[default]
+    Unblock,
+    /// Remove the block and repeat a notification until acknowledged
+    Notify,
+    /// Remove the block and lock the screen to force a real break
+    LockScreen,
+    /// Remove the block and start the preset named in `next`
+    Chain,
+}
+
+impl EndBehavior {
+    /// Human readable name of the behavior
+    pub fn label(&self) -> &'static str {
+        match self {
+            EndBehavior::Unblock => "Unblock",
+            EndBehavior::Notify => "Notify until acknowledged",
+            EndBehavior::LockScreen => "Lock screen",
+            EndBehavior::Chain => "Chain next session",
+        }
+    }
+
+    /// The next behavior available in the TUI (chaining needs a preset)
+    pub fn next_for_tui(&self) -> Self {
+        match self {
+            EndBehavior::Unblock => EndBehavior::Notify,
+            EndBehavior::Notify => EndBehavior::LockScreen,
+            EndBehavior::LockScreen | EndBehavior::Chain => EndBehavior::Unblock,
+        }
+    }
+}
+
+/// A named session configuration
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Preset {
+    /// Name used to select the preset (`--preset <name>`)
+    pub name: String,
+    /// Blocking duration with units (e.g. "25m")
+    pub duration: String,
+    /// Task name for the session
+    pub task: Option<String>,
+    /// Names of the website lists to block; all lists if empty
+    #[serde(default)]
+    pub lists: Vec<String>,
+    /// Only run the timer and record the session, without blocking anything
+    #[serde(default)]
+    pub timer_only: bool,
+    /// What happens when the session ends
+    #[serde(default)]
+    pub end_behavior: EndBehavior,
+    /// Preset started afterwards when `end_behavior` is `chain`
+    pub next: Option<String>,
+    /// Project directories selecting this preset ("~" is the home directory)
+    #[serde(default)]
+    pub directories: Vec<String>,
+    /// Items to tick off before the block starts
+    #[serde(default)]
+    pub checklist: Vec<String>,
+}
+
+/// Content of a project's `.timeguardian.toml`
+#[derive(Deserialize)]
+struct ProjectFile {
+    /// Name of the preset
+    preset: String,
+}
+
+/// Find a preset by name (case-insensitive)
+pub fn find_preset<'a>(presets: &'a [Preset], name: &str) -> Result<&'a Preset> {
+    presets
+        .iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| eyre!("Unknown preset: {}", name))
+}
+
+/// The preset of the project containing `dir`, with the file or directory
+/// that selected it.
+///
+/// The nearest `.timeguardian.toml` wins; otherwise the preset with the
+/// deepest directory containing `dir`.
+pub fn for_directory<'a>(presets: &'a [Preset], dir: &Path) -> Result<Option<(&'a Preset, PathBuf)>> {
+    for ancestor in dir.ancestors() {
+        let path = ancestor.join(PROJECT_FILE);
+        if path.is_file() {
+            let content = fs::read_to_string(&path).wrap_err_with(|| format!("Could not read {:?}", path))?;
+            let project: ProjectFile = toml::from_str(&content).wrap_err_with(|| format!("Could not parse {:?}", path))?;
+            let preset = find_preset(presets, &project.preset).wrap_err_with(|| format!("Preset of {:?}", path))?;
+            return Ok(Some((preset, path)));
+        }
+    }
+
+    Ok(presets
+        .iter()
+        .flat_map(|preset| preset.directories.iter().map(move |directory| (preset, expand_home(directory))))
+        .filter(|(_, directory)| dir.starts_with(directory))
+        .max_by_key(|(_, directory)| directory.components().count()))
+}
+
+/// Replace a leading "~" with the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), BaseDirs::new()) {
+        (Some(rest), Some(dirs)) => dirs.home_dir().join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Ring the terminal bell and print a message until a key is pressed.
+///
+/// Without an interactive terminal the notification is printed once.
+pub fn notify_until_acknowledged(message: &str, interactive: bool) -> Result<()> {
+    if !interactive {
+        println!("{}", message);
+        return Ok(());
+    }
+
+    enable_raw_mode()?;
+    let mut last_notification: Option<Instant> = None;
+    loop {
+        if last_notification.is_none_or(|at| at.elapsed() >= NOTIFY_INTERVAL) {
+            print!("\x07\r{} Press any key to acknowledge.", message);
+            io::stdout().flush()?;
+            last_notification = Some(Instant::now());
+        }
+
+        if event::poll(Duration::from_millis(200))? && matches!(event::read()?, Event::Key(_)) {
+            break;
+        }
+    }
+    disable_raw_mode()?;
+    println!();
+
+    Ok(())
+}
+
+/// Lock the screen using the platform's session locker
+pub fn lock_screen() -> Result<()> {
+    #[cfg(target_os = "linux")]
+    let status = Command::new("loginctl").arg("lock-session").status();
+
+    #[cfg(target_os = "macos")]
+    let status = Command::new("pmset").arg("displaysleepnow").status();
+
+    #[cfg(target_os = "windows")]
+    let status = Command::new("rundll32.exe")
+        .args(["user32.dll,LockWorkStation"])
+        .status();
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    let status: io::Result<std::process::ExitStatus> =
+        Err(io::Error::other("screen locking is not supported on this platform"));
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(eyre!("Could not lock the screen ({})", status)),
+        Err(e) => Err(eyre!("Could not lock the screen: {}", e)),
+    }
+}
@@ -0,0 +1,148 @@
+/*
+* TimeGuardian Profile Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module handles profiles: named selections of website lists that are
+* activated automatically depending on the current Wi-Fi network or hostname
+* ("Office Wi-Fi -> Work", "home -> Personal").
+*/
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+use crate::load_config;
+
+/// A named selection of website lists with automatic activation rules
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Profile {
+    /// Name of the profile
+    pub name: String,
+    /// Website lists blocked by CLI sessions while the profile is active
+    #[serde(default)]
+    pub lists: Vec<String>,
+    /// Wi-Fi networks (SSIDs) that activate the profile
+    #[serde(default)]
+    pub ssids: Vec<String>,
+    /// Hostnames that activate the profile
+    #[serde(default)]
+    pub hostnames: Vec<String>,
+    /// Domains that must never be blocked while the profile is active (e.g. the SSO)
+    #[serde(default)]
+    pub safe_domains: Vec<String>,
+}
+
+/// The network environment profiles are matched against
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Environment {
+    pub ssid: Option<String>,
+    pub hostname: Option<String>,
+}
+
+impl Environment {
+    /// Detect the current Wi-Fi network and hostname
+    pub fn detect() -> Self {
+        Self {
+            ssid: current_ssid(),
+            hostname: current_hostname(),
+        }
+    }
+
+    /// Short description for status output
+    pub fn describe(&self) -> String {
+        format!(
+            "Wi-Fi: {}, host: {}",
+            self.ssid.as_deref().unwrap_or("none"),
+            self.hostname.as_deref().unwrap_or("unknown")
+        )
+    }
+}
+
+/// Pick the active profile.
+///
+/// An explicitly requested profile wins; otherwise the first profile whose
+/// SSID or hostname rules match the environment is used.
+pub fn resolve<'a>(
+    profiles: &'a [Profile],
+    requested: Option<&str>,
+    environment: &Environment,
+) -> Option<&'a Profile> {
+    if let Some(name) = requested {
+        return profiles.iter().find(|p| p.name.eq_ignore_ascii_case(name));
+    }
+
+    profiles.iter().find(|profile| {
+        let ssid_match = environment
+            .ssid
+            .as_ref()
+            .is_some_and(|ssid| profile.ssids.iter().any(|s| s == ssid));
+        let host_match = environment
+            .hostname
+            .as_ref()
+            .is_some_and(|host| profile.hostnames.iter().any(|h| h.eq_ignore_ascii_case(host)));
+        ssid_match || host_match
+    })
+}
+
+/// Run a command and return its trimmed stdout if it succeeded
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// SSID of the connected Wi-Fi network, if any
+fn current_ssid() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    let ssid = command_output("iwgetid", &["-r"]).or_else(|| {
+        // NetworkManager: lines look like "yes:MyNetwork"
+        command_output("nmcli", &["-t", "-f", "active,ssid", "dev", "wifi"])?
+            .lines()
+            .find_map(|line| line.strip_prefix("yes:").map(str::to_string))
+    });
+
+    #[cfg(target_os = "macos")]
+    let ssid = command_output("networksetup", &["-getairportnetwork", "en0"])
+        .and_then(|out| out.split_once(": ").map(|(_, ssid)| ssid.to_string()));
+
+    #[cfg(target_os = "windows")]
+    let ssid = command_output("netsh", &["wlan", "show", "interfaces"]).and_then(|out| {
+        out.lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("SSID") && !line.starts_with("SSID name"))
+            .and_then(|line| line.split_once(':'))
+            .map(|(_, ssid)| ssid.trim().to_string())
+    });
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    let ssid: Option<String> = None;
+
+    ssid.filter(|ssid| !ssid.is_empty())
+}
+
+/// Hostname of this machine
+pub fn current_hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| command_output("hostname", &[]))
+        .filter(|host| !host.is_empty())
+}
+
+/// Print the detected environment and the profile it activates
+pub fn run_command(name: Option<&str>) -> Result<()> {
+    let config = load_config()?;
+    let profiles = config.profiles.unwrap_or_default();
+    let environment = Environment::detect();
+
+    println!("Detected {}", environment.describe());
+    match resolve(&profiles, name, &environment) {
+        Some(active) => println!("Active profile: {} (lists: {})", active.name, active.lists.join(", ")),
+        None => println!("No active profile; all lists are used."),
+    }
+    if profiles.is_empty() {
+        println!("No profiles configured. Add [[profiles]] entries with name, lists, ssids and hostnames.");
+    }
+    Ok(())
+}
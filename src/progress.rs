@@ -0,0 +1,68 @@
+/*
+* TimeGuardian Progress Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module shows progress for long CLI operations such as downloads and
+* backup synchronization, so they do not look like hangs. Progress is drawn
+* on stderr and hidden automatically when it is not a terminal.
+*/
+
+use color_eyre::{eyre::Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{io::Read, time::Duration};
+
+/// How often spinners redraw while waiting
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Spinner for a step of unknown length
+pub fn spinner(message: &str) -> ProgressBar {
+    let bar = ProgressBar::new_spinner().with_message(message.to_string());
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} {msg} ({elapsed})")
+            .expect("valid progress template"),
+    );
+    bar.enable_steady_tick(TICK_INTERVAL);
+    bar
+}
+
+/// Bar for a transfer of `len` bytes; a byte counting spinner if the length is unknown
+pub fn bytes(message: &str, len: Option<u64>) -> ProgressBar {
+    let bar = match len {
+        Some(len) => {
+            let bar = ProgressBar::new(len);
+            bar.set_style(
+                ProgressStyle::with_template("{msg} [{bar:30}] {bytes}/{total_bytes} ({eta})")
+                    .expect("valid progress template")
+                    .progress_chars("=> "),
+            );
+            bar
+        }
+        None => {
+            let bar = ProgressBar::new_spinner();
+            bar.set_style(
+                ProgressStyle::with_template("{spinner} {msg} {bytes}")
+                    .expect("valid progress template"),
+            );
+            bar.enable_steady_tick(TICK_INTERVAL);
+            bar
+        }
+    };
+    bar.set_message(message.to_string());
+    bar
+}
+
+/// Read an HTTP response body while showing download progress
+pub fn read_response(response: ureq::Response, message: &str) -> Result<Vec<u8>> {
+    let len = response
+        .header("Content-Length")
+        .and_then(|value| value.parse().ok());
+    let bar = bytes(message, len);
+
+    let mut data = Vec::new();
+    bar.wrap_read(response.into_reader())
+        .read_to_end(&mut data)
+        .wrap_err("Could not read response")?;
+    bar.finish_and_clear();
+
+    Ok(data)
+}
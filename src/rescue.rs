@@ -0,0 +1,173 @@
+/*
+* TimeGuardian Rescue Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module is the way out when everything else is broken
+* (`timeguardian rescue`). It never reads the config and doesn't need the
+* state to be readable: it removes every TimeGuardian section from the hosts
+* file, flushes the DNS cache, takes down the firewall rules, stops session
+* processes that still claim blocks and clears the block state, so nothing
+* puts the block back. Every step runs even if an earlier one failed, and
+* each one reports what it did.
+*/
+
+use color_eyre::{eyre::eyre, Result};
+use std::{fs, path::PathBuf};
+
+use crate::{firewall, get_state_dir, hosts, ipc, journal, lockout};
+
+/// Suffix of the copy of the hosts file taken before it is changed
+const HOSTS_COPY_SUFFIX: &str = "timeguardian-rescue";
+
+/// Run every step and print what was done; fails if the hosts file could
+/// not be repaired
+pub fn run() -> Result<()> {
+    // Read the sessions first; the hosts file loses them in the next step
+    let sessions = hosts::sessions_in_hosts_file().unwrap_or_default();
+
+    let repaired = clean_hosts_file();
+    match &repaired {
+        Ok(message) => println!("{}", message),
+        Err(error) => println!("Could not repair the hosts file: {}", error),
+    }
+
+    hosts::flush_dns_cache();
+    println!("Flushed the DNS cache.");
+
+    for line in firewall::rescue() {
+        println!("{}", line);
+    }
+
+    for line in stop_session_processes(sessions.iter().map(|claim| claim.source.as_str())) {
+        println!("{}", line);
+    }
+
+    for line in clear_state() {
+        println!("{}", line);
+    }
+
+    if get_state_dir().is_ok_and(|dir| dir.join(lockout::LOCKOUT_FILE).exists()) {
+        match lockout::release() {
+            Ok(()) => println!("Gave back the locked shortcuts."),
+            Err(error) => println!("Could not give back the locked shortcuts: {}", error),
+        }
+    }
+
+    repaired.map(|_| ())
+}
+
+/// Remove every TimeGuardian section from the hosts file, keeping a copy of
+/// the old file next to it
+fn clean_hosts_file() -> Result<String> {
+    let path = hosts::get_hosts_path();
+    let content = fs::read_to_string(&path).map_err(|error| eyre!("Could not read {:?}: {}", path, error))?;
+
+    let mut cleaned = content.clone();
+    let mut sections = 0;
+    while cleaned.contains(hosts::TEMP_HOSTS_MARKER) {
+        cleaned = hosts::strip_marker_block(&cleaned);
+        sections += 1;
+    }
+    if sections == 0 {
+        return Ok(format!("No TimeGuardian markers in {}.", path.display()));
+    }
+
+    let copy = copy_path(&path);
+    let written = fs::write(&copy, &content).and_then(|_| fs::write(&path, &cleaned));
+    hosts::invalidate_cache();
+    written.map_err(|error| {
+        eyre!("Could not write {:?}: {} (run `sudo timeguardian rescue`)", path, error)
+    })?;
+    Ok(format!(
+        "Removed {} TimeGuardian section(s) from {} (the old file is at {}).",
+        sections,
+        path.display(),
+        copy.display()
+    ))
+}
+
+/// Where the hosts file is copied before the rescue changes it
+fn copy_path(hosts_path: &std::path::Path) -> PathBuf {
+    let mut name = hosts_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", HOSTS_COPY_SUFFIX));
+    hosts_path.with_file_name(name)
+}
+
+/// Stop the processes behind `sources` ("cli-1234") that are still running
+#[cfg(unix)]
+fn stop_session_processes<'a>(sources: impl Iterator<Item = &'a str>) -> Vec<String> {
+    use std::time::{Duration, Instant};
+
+    let mut done = Vec::new();
+    for source in sources {
+        let Some(pid) = source.rsplit_once('-').and_then(|(_, pid)| pid.parse::<i32>().ok()) else {
+            continue;
+        };
+        // The pid may belong to another program by now
+        if pid == std::process::id() as i32 || !is_timeguardian(pid) {
+            continue;
+        }
+
+        unsafe { libc::kill(pid, libc::SIGTERM) };
+        let started = Instant::now();
+        while alive(pid) && started.elapsed() < Duration::from_secs(2) {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        if alive(pid) {
+            unsafe { libc::kill(pid, libc::SIGKILL) };
+        }
+        done.push(format!("Stopped the session process {} ({}).", pid, source));
+    }
+
+    // A socket without a host left behind by a killed process
+    let socket = ipc::socket_path();
+    if socket.exists() && !ipc::host_running() && fs::remove_file(&socket).is_ok() {
+        done.push(format!("Removed the stale IPC socket {}.", socket.display()));
+    }
+    done
+}
+
+/// Without signals, session processes have to be ended by hand
+#[cfg(not(unix))]
+fn stop_session_processes<'a>(sources: impl Iterator<Item = &'a str>) -> Vec<String> {
+    sources
+        .map(|source| format!("Session {} may still be running; end it in the task manager.", source))
+        .collect()
+}
+
+#[cfg(unix)]
+fn alive(pid: i32) -> bool {
+    unsafe { libc::kill(pid, 0) == 0 }
+}
+
+/// Whether the process `pid` is TimeGuardian
+#[cfg(unix)]
+fn is_timeguardian(pid: i32) -> bool {
+    std::process::Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "comm="])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).to_lowercase().contains("timeguardian"))
+}
+
+/// Remove the block state and pending journal entries, which would put the
+/// block back on the next run
+fn clear_state() -> Vec<String> {
+    let Ok(dir) = get_state_dir() else {
+        return vec!["The state directory is not accessible; no state was cleared.".to_string()];
+    };
+    [
+        (hosts::BLOCK_STATE_FILE, "block state"),
+        (journal::JOURNAL_FILE, "pending hosts file write"),
+        (journal::FIREWALL_JOURNAL_FILE, "pending firewall change"),
+    ]
+    .into_iter()
+    .filter_map(|(file, what)| {
+        let path = dir.join(file);
+        match fs::remove_file(&path) {
+            Ok(()) => Some(format!("Cleared the {}.", what)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+            Err(error) => Some(format!("Could not clear the {}: {}", what, error)),
+        }
+    })
+    .collect()
+}
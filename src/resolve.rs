@@ -0,0 +1,179 @@
+/*
+* TimeGuardian Resolve Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module looks up the addresses of blocked domains for the firewall
+* backends. The system resolver can't be used for that: it answers from the
+* hosts file, where the domains already point to the sinkhole. Queries go
+* straight to the upstream DNS servers from resolv.conf (the ones behind
+* systemd-resolved if it is used), or to `resolver` in the [firewall] config.
+*/
+
+use color_eyre::{eyre::eyre, Result};
+use std::{
+    fs,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket},
+    process,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Resolver configuration of the system
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+/// Upstream servers of systemd-resolved; its stub in resolv.conf answers from the hosts file
+const RESOLVED_CONF: &str = "/run/systemd/resolve/resolv.conf";
+/// How long to wait for an answer from one server
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long an empty answer is trusted
+const NEGATIVE_TTL: Duration = Duration::from_secs(300);
+/// Record type of IPv4 addresses
+const TYPE_A: u16 = 1;
+/// Record type of IPv6 addresses
+const TYPE_AAAA: u16 = 28;
+
+/// Addresses of a domain
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Resolved {
+    /// IPv4 and IPv6 addresses
+    pub addresses: Vec<IpAddr>,
+    /// How long the answer may be cached (the smallest TTL of the records)
+    pub ttl: Duration,
+}
+
+/// DNS servers to ask: `configured`, or the non-local servers of the system
+pub fn upstream_servers(configured: Option<IpAddr>) -> Vec<IpAddr> {
+    if let Some(server) = configured {
+        return vec![server];
+    }
+    let mut servers: Vec<IpAddr> = Vec::new();
+    for content in [RESOLVED_CONF, RESOLV_CONF].iter().filter_map(|path| fs::read_to_string(path).ok()) {
+        let found = content.lines().filter_map(|line| {
+            let address = line.trim().strip_prefix("nameserver")?.trim();
+            // Drop the zone of link-local IPv6 servers ("fe80::1%eth0")
+            address.split('%').next()?.parse::<IpAddr>().ok()
+        });
+        for server in found {
+            if !server.is_loopback() && !servers.contains(&server) {
+                servers.push(server);
+            }
+        }
+    }
+    servers
+}
+
+/// IPv4 and IPv6 addresses of `domain`, from the first server that answers
+pub fn resolve(domain: &str, servers: &[IpAddr]) -> Result<Resolved> {
+    if servers.is_empty() {
+        return Err(eyre!("No DNS server to ask; set resolver in the [firewall] config"));
+    }
+    let mut last_error = None;
+    for server in servers {
+        let answers = query(*server, domain, TYPE_A).and_then(|v4| Ok((v4, query(*server, domain, TYPE_AAAA)?)));
+        match answers {
+            Ok(((mut addresses, ttl_v4), (v6, ttl_v6))) => {
+                addresses.extend(v6);
+                let ttl = [ttl_v4, ttl_v6].into_iter().flatten().min().unwrap_or(NEGATIVE_TTL);
+                return Ok(Resolved { addresses, ttl });
+            }
+            Err(error) => last_error = Some(error),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| eyre!("No answer for {}", domain)))
+}
+
+/// Ask `server` for the records of `record_type`; the addresses and their smallest TTL
+fn query(server: IpAddr, domain: &str, record_type: u16) -> Result<(Vec<IpAddr>, Option<Duration>)> {
+    let id = query_id();
+    let mut packet = Vec::with_capacity(512);
+    // Header: id, recursion desired, one question
+    for field in [id, 0x0100, 1, 0, 0, 0] {
+        packet.extend(field.to_be_bytes());
+    }
+    for label in domain.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(eyre!("{} is not a valid domain", domain));
+        }
+        packet.push(label.len() as u8);
+        packet.extend(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend(record_type.to_be_bytes());
+    packet.extend(1u16.to_be_bytes()); // class IN
+
+    let local: IpAddr = if server.is_ipv4() { Ipv4Addr::UNSPECIFIED.into() } else { Ipv6Addr::UNSPECIFIED.into() };
+    let socket = UdpSocket::bind((local, 0))?;
+    socket.set_read_timeout(Some(QUERY_TIMEOUT))?;
+    socket.send_to(&packet, (server, 53))?;
+
+    let mut buffer = [0u8; 1500];
+    loop {
+        let (len, from) = socket
+            .recv_from(&mut buffer)
+            .map_err(|error| eyre!("{} did not answer for {}: {}", server, domain, error))?;
+        let answer = &buffer[..len];
+        // Ignore stray datagrams
+        if from.ip() != server || answer.len() < 12 || u16::from_be_bytes([answer[0], answer[1]]) != id {
+            continue;
+        }
+        return parse_answer(answer, record_type).ok_or_else(|| eyre!("{} sent an invalid answer for {}", server, domain));
+    }
+}
+
+/// Addresses and smallest TTL in a response; `None` if it is malformed or an error
+fn parse_answer(answer: &[u8], record_type: u16) -> Option<(Vec<IpAddr>, Option<Duration>)> {
+    let u16_at = |pos: usize| Some(u16::from_be_bytes([*answer.get(pos)?, *answer.get(pos + 1)?]));
+    let flags = u16_at(2)?;
+    match flags & 0x000f {
+        0 => {}
+        // The domain does not exist
+        3 => return Some((Vec::new(), None)),
+        _ => return None,
+    }
+    let (questions, answers) = (u16_at(4)?, u16_at(6)?);
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(answer, pos)? + 4;
+    }
+    let mut addresses = Vec::new();
+    let mut ttl: Option<Duration> = None;
+    for _ in 0..answers {
+        pos = skip_name(answer, pos)?;
+        let kind = u16_at(pos)?;
+        let record_ttl = u32::from_be_bytes(answer.get(pos + 4..pos + 8)?.try_into().ok()?);
+        let len = usize::from(u16_at(pos + 8)?);
+        let data = answer.get(pos + 10..pos + 10 + len)?;
+        pos += 10 + len;
+
+        let address = match (kind, data.len()) {
+            (TYPE_A, 4) if kind == record_type => IpAddr::from(<[u8; 4]>::try_from(data).ok()?),
+            (TYPE_AAAA, 16) if kind == record_type => IpAddr::from(<[u8; 16]>::try_from(data).ok()?),
+            // CNAMEs are followed by the server; their targets come as further records
+            _ => continue,
+        };
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+        let record_ttl = Duration::from_secs(u64::from(record_ttl));
+        ttl = Some(ttl.map_or(record_ttl, |ttl| ttl.min(record_ttl)));
+    }
+    Some((addresses, ttl))
+}
+
+/// Position after the (possibly compressed) name at `pos`
+fn skip_name(packet: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *packet.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            // A pointer ends the name
+            len if len & 0xc0 == 0xc0 => return Some(pos + 2),
+            len => pos += 1 + usize::from(len),
+        }
+    }
+}
+
+/// Id of a query, different between processes and calls
+fn query_id() -> u16 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+    (nanos ^ process::id().rotate_left(16)) as u16
+}
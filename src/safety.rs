@@ -0,0 +1,93 @@
+/*
+* TimeGuardian Safety Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module guards against blocking domains the machine depends on:
+* localhost, its own hostname, package mirrors and domains configured as
+* safe (e.g. the corporate SSO of a work profile). Blocking them could lock
+* the user out of their work tools, so TimeGuardian refuses to add or block
+* them unless `--i-know-what-im-doing` is passed.
+*
+*   safe_domains = ["intranet.example.com"]
+*
+*   [[profiles]]
+*   name = "Work"
+*   safe_domains = ["login.example-sso.com"]
+*/
+
+use color_eyre::{eyre::eyre, Result};
+
+use crate::{hosts, idn, profile::{self, Profile}};
+
+/// Domains that are always protected (including their subdomains)
+const BUILTIN_SAFE_DOMAINS: &[&str] = &[
+    "localhost",
+    "localhost.localdomain",
+    // Package mirrors
+    "deb.debian.org",
+    "security.debian.org",
+    "archive.ubuntu.com",
+    "security.ubuntu.com",
+    "mirrors.fedoraproject.org",
+    "dl.fedoraproject.org",
+    "archlinux.org",
+    "formulae.brew.sh",
+    "crates.io",
+    "static.crates.io",
+    "index.crates.io",
+    "pypi.org",
+    "files.pythonhosted.org",
+    "registry.npmjs.org",
+];
+
+/// The protected domains and whether the user overrode the protection
+#[derive(Clone, Debug, Default)]
+pub struct SafetyList {
+    domains: Vec<String>,
+    /// `--i-know-what-im-doing` was passed
+    pub overridden: bool,
+}
+
+impl SafetyList {
+    /// Built-in domains, this machine's hostname and the configured safe
+    /// domains of the config and the active profile
+    pub fn new(safe_domains: &[String], profile: Option<&Profile>, overridden: bool) -> Self {
+        let mut domains: Vec<String> = BUILTIN_SAFE_DOMAINS.iter().map(|domain| domain.to_string()).collect();
+        domains.extend(profile::current_hostname());
+        domains.extend(safe_domains.iter().cloned());
+        domains.extend(profile.into_iter().flat_map(|profile| profile.safe_domains.iter().cloned()));
+        for domain in &mut domains {
+            *domain = idn::to_ascii(domain.trim().trim_end_matches('.'));
+        }
+        domains.retain(|domain| !domain.is_empty());
+
+        Self { domains, overridden }
+    }
+
+    /// The entries of `websites` that would block a protected domain
+    pub fn protected<'a>(&self, websites: &'a [String]) -> Vec<&'a str> {
+        websites
+            .iter()
+            .filter(|website| {
+                hosts::expand_website(website).iter().any(|domain| {
+                    self.domains
+                        .iter()
+                        .any(|safe| domain == safe || domain.ends_with(&format!(".{}", safe)))
+                })
+            })
+            .map(|website| website.as_str())
+            .collect()
+    }
+
+    /// Fail if `websites` contains protected domains, unless overridden
+    pub fn check(&self, websites: &[String]) -> Result<()> {
+        let protected = self.protected(websites);
+        if protected.is_empty() || self.overridden {
+            return Ok(());
+        }
+        Err(eyre!(
+            "Refusing to block critical domains: {}. Remove them from the list or pass --i-know-what-im-doing.",
+            protected.join(", ")
+        ))
+    }
+}
@@ -0,0 +1,159 @@
+/*
+* TimeGuardian Schedule Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module handles recurring blocking schedules. A schedule is defined either
+* in the system's local time or in a fixed timezone ("09:00-12:00 Europe/Berlin"),
+* and is resolved into concrete UTC time windows on every run, so it stays
+* correct when the system timezone changes while traveling.
+*/
+
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::session::Priority;
+
+/// A recurring time window during which website lists are blocked
+///
+/// ```
+/// use chrono::{Duration, TimeZone, Utc};
+/// use timeguardian::{Priority, Schedule};
+///
+/// let mornings = Schedule {
+///     name: "Mornings".to_string(),
+///     days: Vec::new(),
+///     start: "09:00".to_string(),
+///     end: "12:00".to_string(),
+///     timezone: Some("Europe/Berlin".to_string()),
+///     lists: vec!["Social".to_string()],
+///     priority: Priority::Normal,
+/// };
+///
+/// let from = Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap();
+/// let occurrences = mornings.occurrences(from, from + Duration::days(1))?;
+/// assert_eq!(occurrences.len(), 1);
+/// // 09:00 in Berlin is 08:00 UTC in winter
+/// assert_eq!(occurrences[0].start, Utc.with_ymd_and_hms(2026, 1, 5, 8, 0, 0).unwrap());
+/// # Ok::<(), color_eyre::Report>(())
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Schedule {
+    /// Name of the schedule
+    pub name: String,
+    /// Days on which the window starts; every day if empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    /// Start of the window ("HH:MM")
+    pub start: String,
+    /// End of the window ("HH:MM"); a time before `start` ends the next day
+    pub end: String,
+    /// IANA timezone of `start` and `end` (e.g. "Europe/Berlin"); local time if unset
+    pub timezone: Option<String>,
+    /// Names of the website lists to block; all lists if empty
+    #[serde(default)]
+    pub lists: Vec<String>,
+    /// Priority of the scheduled block
+    #[serde(default)]
+    pub priority: Priority,
+}
+
+/// A concrete occurrence of a schedule
+#[derive(Clone, Debug)]
+pub struct Occurrence<'a> {
+    /// The schedule occurring
+    pub schedule: &'a Schedule,
+    /// Start of the window
+    pub start: DateTime<Utc>,
+    /// End of the window
+    pub end: DateTime<Utc>,
+}
+
+impl Schedule {
+    /// The fixed timezone of the schedule, if any
+    pub fn zone(&self) -> Result<Option<Tz>> {
+        self.timezone
+            .as_deref()
+            .map(|name| {
+                name.parse::<Tz>()
+                    .map_err(|_| eyre!("Unknown timezone '{}' in schedule '{}'", name, self.name))
+            })
+            .transpose()
+    }
+
+    /// Start and end time of the window
+    fn times(&self) -> Result<(NaiveTime, NaiveTime)> {
+        let parse = |value: &str| {
+            NaiveTime::parse_from_str(value, "%H:%M")
+                .map_err(|_| eyre!("Invalid time '{}' in schedule '{}' (use HH:MM)", value, self.name))
+        };
+        Ok((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    /// Occurrences overlapping the range `from..until`
+    pub fn occurrences(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<Occurrence<'_>>> {
+        match self.zone()? {
+            Some(zone) => self.occurrences_in(&zone, from, until),
+            None => self.occurrences_in(&chrono::Local, from, until),
+        }
+    }
+
+    fn occurrences_in<Z: TimeZone>(
+        &self,
+        zone: &Z,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Occurrence<'_>>> {
+        let (start_time, end_time) = self.times()?;
+
+        // Start a day early to catch windows running over midnight
+        let mut date = from.with_timezone(zone).date_naive() - Duration::days(1);
+        let last = until.with_timezone(zone).date_naive();
+
+        let mut occurrences = Vec::new();
+        while date <= last {
+            if self.days.is_empty() || self.days.contains(&date.weekday()) {
+                let end_date = if end_time <= start_time { date + Duration::days(1) } else { date };
+                if let (Some(start), Some(end)) = (
+                    resolve_local(zone, date, start_time),
+                    resolve_local(zone, end_date, end_time),
+                ) && end > from
+                    && start < until
+                {
+                    occurrences.push(Occurrence { schedule: self, start, end });
+                }
+            }
+            date += Duration::days(1);
+        }
+
+        Ok(occurrences)
+    }
+}
+
+/// Convert a wall-clock time in `zone` to UTC.
+///
+/// Times skipped by a DST change move to the end of the gap; repeated
+/// times use their first occurrence.
+fn resolve_local<Z: TimeZone>(zone: &Z, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    let naive = date.and_time(time);
+    match zone.from_local_datetime(&naive) {
+        LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => Some(at.with_timezone(&Utc)),
+        LocalResult::None => (1..=4)
+            .map(|hours| naive + Duration::hours(hours))
+            .find_map(|later| zone.from_local_datetime(&later).earliest())
+            .map(|at| at.with_timezone(&Utc)),
+    }
+}
+
+/// All occurrences of the given schedules in `from..until`, ordered by start
+pub fn upcoming(schedules: &[Schedule], from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<Occurrence<'_>>> {
+    let mut occurrences = Vec::new();
+    for schedule in schedules {
+        occurrences.extend(schedule.occurrences(from, until)?);
+    }
+    occurrences.sort_by_key(|occurrence| occurrence.start);
+    Ok(occurrences)
+}
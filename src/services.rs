@@ -0,0 +1,100 @@
+/*
+* TimeGuardian Services Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module lets lists name services instead of domains. "YouTube" in a
+* list blocks youtube.com along with youtu.be, the video CDN and the image
+* hosts, which are easy to forget and keep the site half working. Common
+* services are bundled; more can be defined in the config, and a definition
+* with the name of a bundled service adds to its domains:
+*
+*   [[services]]
+*   name = "YouTube"
+*   domains = ["youtube-nocookie.com"]
+*
+* Entries without a dot are looked up by name, ignoring case. Allowed
+* windows of the entry ("YouTube @12:00-13:00") apply to every domain.
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::hosts;
+
+/// A service blocked by name
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Service {
+    /// Name used in lists (e.g. "YouTube")
+    pub name: String,
+    /// Domains of the service
+    pub domains: Vec<String>,
+}
+
+/// Services known without any configuration
+const BUNDLED: &[(&str, &[&str])] = &[
+    ("YouTube", &["youtube.com", "youtu.be", "googlevideo.com", "ytimg.com", "youtubei.googleapis.com"]),
+    ("Reddit", &["reddit.com", "redd.it", "redditmedia.com", "redditstatic.com"]),
+    ("Twitter", &["twitter.com", "x.com", "t.co", "twimg.com"]),
+    ("Facebook", &["facebook.com", "fb.com", "fbcdn.net", "messenger.com"]),
+    ("Instagram", &["instagram.com", "cdninstagram.com"]),
+    ("TikTok", &["tiktok.com", "tiktokcdn.com", "tiktokv.com", "byteoversea.com"]),
+    ("Netflix", &["netflix.com", "nflxvideo.net", "nflximg.net", "nflxext.com"]),
+    ("Twitch", &["twitch.tv", "ttvnw.net", "jtvnw.net"]),
+    ("Discord", &["discord.com", "discord.gg", "discordapp.com", "discordapp.net"]),
+    ("LinkedIn", &["linkedin.com", "licdn.com"]),
+    ("HackerNews", &["news.ycombinator.com"]),
+    ("Pinterest", &["pinterest.com", "pinimg.com"]),
+];
+
+/// Bundled services merged with the ones from the config
+pub fn all(custom: &[Service]) -> Vec<Service> {
+    let mut services: Vec<Service> = BUNDLED
+        .iter()
+        .map(|(name, domains)| Service {
+            name: name.to_string(),
+            domains: domains.iter().map(|domain| domain.to_string()).collect(),
+        })
+        .collect();
+
+    for service in custom {
+        match services.iter_mut().find(|known| known.name.eq_ignore_ascii_case(&service.name)) {
+            Some(known) => {
+                for domain in &service.domains {
+                    if !known.domains.contains(domain) {
+                        known.domains.push(domain.clone());
+                    }
+                }
+            }
+            None => services.push(service.clone()),
+        }
+    }
+    services
+}
+
+/// Replace the service names among `websites` with their domains
+pub fn expand(websites: &[String], custom: &[Service]) -> Vec<String> {
+    if !websites.iter().any(|website| is_name(hosts::parse_entry(website).0)) {
+        return websites.to_vec();
+    }
+
+    let services = all(custom);
+    let mut expanded = Vec::new();
+    for website in websites {
+        let (name, windows) = hosts::parse_entry(website);
+        let service = is_name(name)
+            .then(|| services.iter().find(|service| service.name.eq_ignore_ascii_case(name)))
+            .flatten();
+        match service {
+            Some(service) => {
+                let windows: String = windows.iter().map(|window| format!(" @{}", window)).collect();
+                expanded.extend(service.domains.iter().map(|domain| format!("{}{}", domain, windows)));
+            }
+            None => expanded.push(website.clone()),
+        }
+    }
+    expanded
+}
+
+/// Whether a list entry names a service rather than a domain
+fn is_name(entry: &str) -> bool {
+    !entry.is_empty() && !entry.contains('.')
+}
@@ -0,0 +1,125 @@
+/*
+* TimeGuardian Session Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module describes a focus session as the backend reports it: its task,
+* priority and the wall-clock times it runs between. The binary keeps the
+* running session itself (claims, history, IPC); frontends get a `Session`
+* from the status of the IPC client and show it, e.g. with the session
+* widget.
+*/
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::widget::SessionState;
+
+/// Priority of a blocking source
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// Background blocks that anything may override
+    Low,
+    /// Ordinary focus sessions (default)
+    #[default]
+    Normal,
+    /// Important sessions that ordinary sources cannot end
+    High,
+    /// Critical deadlines that override every other source
+    Critical,
+}
+
+impl Priority {
+    /// Human readable name of the priority
+    pub fn label(&self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Normal => "Normal",
+            Priority::High => "High",
+            Priority::Critical => "Critical deadline",
+        }
+    }
+
+    /// Name of the priority as used on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Critical => "critical",
+        }
+    }
+
+    /// The next priority level, wrapping around after Critical
+    pub fn next(&self) -> Self {
+        match self {
+            Priority::Low => Priority::Normal,
+            Priority::Normal => Priority::High,
+            Priority::High => Priority::Critical,
+            Priority::Critical => Priority::Low,
+        }
+    }
+}
+
+/// A running focus session
+///
+/// ```
+/// use chrono::{Duration, Local};
+/// use timeguardian::{Priority, Session};
+///
+/// let started_at = Local::now();
+/// let session = Session {
+///     id: None,
+///     task: Some("Write report".to_string()),
+///     priority: Priority::High,
+///     started_at,
+///     ends_at: started_at + Duration::minutes(25),
+/// };
+/// let halfway = started_at + Duration::seconds(750);
+/// assert_eq!(session.remaining(halfway), std::time::Duration::from_secs(750));
+/// assert_eq!(session.progress(halfway), 0.5);
+/// assert!(!session.is_over(halfway));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session {
+    /// ULID of the session in the history
+    pub id: Option<String>,
+    /// Task of the session, if any
+    pub task: Option<String>,
+    /// Priority of the session
+    pub priority: Priority,
+    /// When the session started
+    pub started_at: DateTime<Local>,
+    /// When the session ends
+    pub ends_at: DateTime<Local>,
+}
+
+impl Session {
+    /// Planned length of the session
+    pub fn duration(&self) -> Duration {
+        (self.ends_at - self.started_at).to_std().unwrap_or_default()
+    }
+
+    /// Time left at `now` (zero once the session is over)
+    pub fn remaining(&self, now: DateTime<Local>) -> Duration {
+        (self.ends_at - now).to_std().unwrap_or_default().min(self.duration())
+    }
+
+    /// Share of the session that has passed at `now`, from 0.0 to 1.0
+    pub fn progress(&self, now: DateTime<Local>) -> f64 {
+        self.state(now).progress()
+    }
+
+    /// Whether the session has ended at `now`
+    pub fn is_over(&self, now: DateTime<Local>) -> bool {
+        now >= self.ends_at
+    }
+
+    /// State for the session widget at `now`
+    pub fn state(&self, now: DateTime<Local>) -> SessionState {
+        SessionState::new(self.task.clone(), self.duration(), self.remaining(now))
+    }
+}
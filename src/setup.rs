@@ -0,0 +1,160 @@
+/*
+* TimeGuardian Setup Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module sets up a new machine. `timeguardian init --from` imports a
+* shared config or a plain website list from a file or URL, backs up the
+* hosts file, checks the permissions and can pull the synced backup right
+* away. `timeguardian setup --list` creates the default website lists next
+* to a list of the user's own.
+*/
+
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use std::fs;
+
+use crate::tui::WebsiteList;
+use crate::{
+    backup, check_and_get_permissions, config_path, get_config_dir, initialize_app, load_config, lock_config, progress,
+    save_config, update_config, Config,
+};
+
+/// Bootstrap a new machine: import the config, back up the hosts file,
+/// check permissions and optionally pull the synced backup
+pub fn run_init(from: &str, force: bool, pull: bool) -> Result<()> {
+    let config_path = config_path()?;
+    if config_path.exists() && !force {
+        println!("A configuration already exists at {:?}.", config_path);
+        println!("Use --force to replace it (the current one is kept in 'timeguardian config backups').");
+        return Ok(());
+    }
+
+    // A full config, or else a plain list of websites
+    let content = fetch_text(from)?;
+    let config = match toml::from_str::<Config>(&content) {
+        Ok(config) => config,
+        Err(_) => {
+            let websites: Vec<String> = content
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect();
+            if websites.is_empty() || websites.iter().any(|website| website.contains(char::is_whitespace)) {
+                return Err(eyre!("{} is neither a TimeGuardian config nor a website list", from));
+            }
+
+            let mut config = load_config()?;
+            config.website_lists = Some(vec![WebsiteList {
+                name: "Imported".to_string(),
+                websites,
+                enabled: true,
+                system: false,
+            }]);
+            config
+        }
+    };
+
+    {
+        let _lock = lock_config()?;
+        save_config(&config)?;
+    }
+    let lists = config.website_lists.as_deref().unwrap_or_default();
+    println!(
+        "Imported {} website lists ({} websites) from {}",
+        lists.len(),
+        lists.iter().map(|list| list.websites.len()).sum::<usize>(),
+        from
+    );
+
+    initialize_app()?;
+    if check_and_get_permissions()? {
+        println!("Required permissions are available.");
+    }
+
+    if pull {
+        match &config.backup {
+            Some(target) => backup::pull(target)?,
+            None => println!("The imported config has no [backup] section to pull from."),
+        }
+    }
+
+    if config.schedules.as_ref().is_some_and(|schedules| !schedules.is_empty()) {
+        println!("Schedules are enforced by 'timeguardian schedule apply'; run it from cron or a systemd timer.");
+    }
+    println!("Initialization completed successfully!");
+
+    Ok(())
+}
+
+/// Create the default website lists and a list of the websites in `list_path`
+pub fn run_setup(list_path: &str) -> Result<()> {
+    let _config_dir = get_config_dir()?;
+
+    let websites = fs::read_to_string(list_path)
+        .wrap_err_with(|| format!("Could not read website list file: {}", list_path))?;
+
+    // Parse websites and create default lists
+    let social_media = WebsiteList {
+        name: "Social Media".to_string(),
+        websites: vec![
+            "www.facebook.com".to_string(),
+            "facebook.com".to_string(),
+            "www.twitter.com".to_string(),
+            "twitter.com".to_string(),
+            "www.instagram.com".to_string(),
+            "instagram.com".to_string(),
+        ],
+        enabled: true,
+        system: false,
+    };
+
+    let entertainment = WebsiteList {
+        name: "Entertainment".to_string(),
+        websites: vec![
+            "www.youtube.com".to_string(),
+            "youtube.com".to_string(),
+            "www.netflix.com".to_string(),
+            "netflix.com".to_string(),
+            "www.reddit.com".to_string(),
+            "reddit.com".to_string(),
+        ],
+        enabled: true,
+        system: false,
+    };
+
+    let user_list = WebsiteList {
+        name: "Custom Sites".to_string(),
+        websites: websites
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect(),
+        enabled: true,
+        system: false,
+    };
+
+    update_config(|config| {
+        config.website_list_path = list_path.to_string();
+        config.website_lists = Some(vec![social_media, entertainment, user_list]);
+        Ok(())
+    })?;
+
+    println!("Setup completed successfully!");
+
+    Ok(())
+}
+
+/// Read a local file or download an http(s) URL
+fn fetch_text(source: &str) -> Result<String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let response = ureq::get(source)
+            .call()
+            .wrap_err_with(|| format!("Could not download {}", source))?;
+        let data = progress::read_response(response, &format!("Downloading {}", source))?;
+        String::from_utf8(data).wrap_err_with(|| format!("Could not read {}", source))
+    } else {
+        fs::read_to_string(source).wrap_err_with(|| format!("Could not read {}", source))
+    }
+}
@@ -0,0 +1,205 @@
+/*
+* TimeGuardian Snapshot Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module reads past versions of website lists from the automatic config
+* backups, which are taken before every write of the config (imports and
+* merges included). A version is named like "Work@yesterday":
+*
+*   Work             the current list
+*   Work@yesterday   the list at midnight
+*   Work@2026-10-01  the list at the end of that day
+*   Work@3h          the list three hours ago
+*   Work@2           the list in the second newest backup
+*
+* A backup holds the config as it was right before its timestamp, so the
+* list at a point in time is the one in the first backup taken after it.
+*/
+
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveDateTime, TimeZone};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use std::{fs, path::Path, time::Duration};
+
+use crate::tui::WebsiteList;
+use crate::{config_backups, load_config, parse_duration, update_config, Config, ListAction};
+
+/// A version of the config
+pub struct Snapshot {
+    /// When the backup was taken; `None` for the current config
+    pub taken_at: Option<DateTime<Local>>,
+    /// Lists of that version
+    pub lists: Vec<WebsiteList>,
+}
+
+impl Snapshot {
+    /// The list named `name`, if it existed in this version
+    pub fn list(&self, name: &str) -> Option<&WebsiteList> {
+        self.lists.iter().find(|list| list.name.eq_ignore_ascii_case(name))
+    }
+
+    /// When the version was current, for output
+    pub fn label(&self) -> String {
+        match self.taken_at {
+            Some(at) => format!("before {}", at.format("%Y-%m-%d %H:%M:%S")),
+            None => "current".to_string(),
+        }
+    }
+}
+
+/// All versions, oldest first, ending with the current config
+pub fn all() -> Result<Vec<Snapshot>> {
+    let mut snapshots = Vec::new();
+    for path in config_backups()? {
+        let Some(taken_at) = backup_time(&path) else {
+            continue;
+        };
+        let content = fs::read_to_string(&path).wrap_err_with(|| format!("Could not read backup: {:?}", path))?;
+        // Backups of a broken config are skipped
+        if let Ok(config) = toml::from_str::<Config>(&content) {
+            snapshots.push(Snapshot { taken_at: Some(taken_at), lists: config.website_lists.unwrap_or_default() });
+        }
+    }
+    snapshots.push(Snapshot { taken_at: None, lists: load_config()?.website_lists.unwrap_or_default() });
+    Ok(snapshots)
+}
+
+/// Resolve a reference like "Work@yesterday" to the list name and its version
+pub fn resolve(reference: &str) -> Result<(String, Snapshot)> {
+    let (name, at) = reference.split_once('@').unwrap_or((reference, ""));
+    let mut snapshots = all()?;
+
+    let index = if at.is_empty() {
+        snapshots.len() - 1
+    } else if let Ok(back) = at.parse::<usize>() {
+        // The current config is not a backup
+        let backups = snapshots.len() - 1;
+        if back == 0 || back > backups {
+            return Err(eyre!("There are {} config backups; '@{}' is out of range", backups, back));
+        }
+        backups - back
+    } else {
+        let time = point_in_time(at)?;
+        snapshots
+            .iter()
+            .position(|snapshot| snapshot.taken_at.is_none_or(|taken_at| taken_at > time))
+            .unwrap_or(snapshots.len() - 1)
+    };
+    Ok((name.to_string(), snapshots.swap_remove(index)))
+}
+
+/// Entries only in `old` and only in `new`
+pub fn diff<'a>(old: &'a [String], new: &'a [String]) -> (Vec<&'a str>, Vec<&'a str>) {
+    let removed = old.iter().filter(|entry| !new.contains(entry)).map(String::as_str).collect();
+    let added = new.iter().filter(|entry| !old.contains(entry)).map(String::as_str).collect();
+    (removed, added)
+}
+
+/// Time named by the part after '@'
+fn point_in_time(at: &str) -> Result<DateTime<Local>> {
+    let midnight = |date: NaiveDate| {
+        Local
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+            .earliest()
+            .ok_or_else(|| eyre!("Midnight of {} does not exist in the local time zone", date))
+    };
+    let today = Local::now().date_naive();
+    match at {
+        "yesterday" => midnight(today),
+        _ => match NaiveDate::parse_from_str(at, "%Y-%m-%d") {
+            Ok(date) => midnight(date.checked_add_days(Days::new(1)).unwrap_or(date)),
+            Err(_) => {
+                let ago = Duration::from_millis(
+                    parse_duration(at).map_err(|_| eyre!("Unknown version '@{}' (try @yesterday, @2026-10-01, @3h or @2)", at))?,
+                );
+                Ok(Local::now() - chrono::Duration::from_std(ago)?)
+            }
+        },
+    }
+}
+
+/// Timestamp in a backup name like "config-20261016-090105-425.toml"
+fn backup_time(path: &Path) -> Option<DateTime<Local>> {
+    let stem = path.file_stem()?.to_str()?.strip_prefix("config-")?;
+    let time = NaiveDateTime::parse_from_str(stem, "%Y%m%d-%H%M%S-%3f").ok()?;
+    Local.from_local_datetime(&time).earliest()
+}
+
+pub fn run_command(action: &ListAction) -> Result<()> {
+    match action {
+        ListAction::Diff { from, to } => {
+            let (name, old) = resolve(from)?;
+            let (to_name, new) = resolve(to.as_deref().unwrap_or(&name))?;
+            let empty = Vec::new();
+            let old_websites = old.list(&name).map_or(&empty, |list| &list.websites);
+            let new_websites = new.list(&to_name).map_or(&empty, |list| &list.websites);
+            if old.list(&name).is_none() && new.list(&to_name).is_none() {
+                return Err(eyre!("No list named {} in either version", name));
+            }
+
+            println!("--- {} ({})", name, old.label());
+            println!("+++ {} ({})", to_name, new.label());
+            let (removed, added) = diff(old_websites, new_websites);
+            if removed.is_empty() && added.is_empty() {
+                println!("No changes.");
+            }
+            for website in removed {
+                println!("- {}", website);
+            }
+            for website in added {
+                println!("+ {}", website);
+            }
+        }
+        ListAction::History { name } => {
+            let snapshots = all()?;
+            let backups = snapshots.len() - 1;
+            let mut previous: Option<&Vec<String>> = None;
+            let mut found = false;
+            for (index, version) in snapshots.iter().enumerate() {
+                let websites = version.list(name).map(|list| &list.websites);
+                // Only versions where the list changed; before it existed it didn't change
+                let unchanged = websites == previous;
+                let before_list = websites.is_none() && !found;
+                previous = websites;
+                if (index > 0 && unchanged) || before_list {
+                    continue;
+                }
+                let reference = match backups - index {
+                    0 => name.clone(),
+                    back => format!("{}@{}", name, back),
+                };
+                match websites {
+                    Some(websites) => {
+                        found = true;
+                        println!("{:<16} {:<26} {} website(s)", reference, version.label(), websites.len());
+                    }
+                    None => println!("{:<16} {:<26} (no such list)", reference, version.label()),
+                }
+            }
+            if !found {
+                return Err(eyre!("No list named {} in the config or its backups", name));
+            }
+        }
+        ListAction::Restore { version } => {
+            let (name, snapshot) = resolve(version)?;
+            let restored = snapshot
+                .list(&name)
+                .cloned()
+                .ok_or_else(|| eyre!("No list named {} in {}", name, snapshot.label()))?;
+
+            update_config(|config| {
+                let lists = config.website_lists.get_or_insert_with(Vec::new);
+                match lists.iter_mut().find(|list| list.name.eq_ignore_ascii_case(&name)) {
+                    Some(list) => list.websites = restored.websites.clone(),
+                    None => lists.push(restored.clone()),
+                }
+                Ok(())
+            })?;
+            println!("Restored {} ({} website(s), {})", restored.name, restored.websites.len(), snapshot.label());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,483 @@
+/*
+* TimeGuardian Snooze Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module records what the user decided about upcoming scheduled blocks.
+* A minute before a block starts the TUI shows a warning, and the block can be
+* started right away, snoozed for 10 minutes (a limited number of times per
+* day) or skipped with a reason. `schedule apply` respects these decisions,
+* and skip reasons stay in the log for later review. The `timeguardian
+* schedule` commands live here as well.
+*
+*   schedule_snooze_limit = 2
+*/
+
+use chrono::{DateTime, Duration, Local, Utc};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+use timeguardian::schedule::{self, Occurrence, Schedule};
+
+use crate::hosts;
+use crate::safety::SafetyList;
+use crate::{
+    check_and_get_permissions, collect_websites, enabled_lists, get_state_dir, is_root, load_config, parse_duration,
+    safety_list, schedule_source, time_of_day, Cli, Config, ScheduleAction,
+};
+
+/// File in the state directory holding the decisions
+pub const SNOOZE_FILE: &str = "snoozes.toml";
+
+/// How long a snooze postpones a scheduled block
+pub const SNOOZE_MINUTES: i64 = 10;
+
+/// How long before a scheduled block the warning is shown
+pub const WARNING_SECONDS: i64 = 60;
+
+/// Snoozes allowed per day unless configured otherwise
+pub const DEFAULT_SNOOZE_LIMIT: u32 = 2;
+
+/// Decisions are kept this long
+const KEEP_DAYS: i64 = 30;
+
+/// What the user decided about one occurrence of a schedule
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Decision {
+    /// Name of the schedule
+    pub schedule: String,
+    /// Planned start of the occurrence
+    pub occurrence: DateTime<Utc>,
+    /// When the decision was made
+    pub decided_at: DateTime<Utc>,
+    /// The block starts at this time instead
+    pub snoozed_until: Option<DateTime<Utc>>,
+    /// The block was started ahead of time
+    pub started_early: Option<DateTime<Utc>>,
+    /// The occurrence is skipped for this reason
+    pub skip_reason: Option<String>,
+}
+
+/// The log of decisions, oldest first
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Decisions {
+    #[serde(default)]
+    pub decisions: Vec<Decision>,
+}
+
+impl Decisions {
+    /// Load the decisions from the state directory
+    pub fn load() -> Result<Self> {
+        let path = snooze_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Could not read snoozes: {:?}", path))?;
+        toml::from_str(&content).wrap_err("Could not parse snoozes")
+    }
+
+    /// Save the decisions, dropping old ones
+    pub fn save(&mut self) -> Result<()> {
+        let cutoff = Utc::now() - Duration::days(KEEP_DAYS);
+        self.decisions.retain(|decision| decision.decided_at > cutoff);
+
+        let path = snooze_path()?;
+        let content = toml::to_string(self).wrap_err("Could not serialize snoozes")?;
+        fs::write(&path, content).wrap_err_with(|| format!("Could not save snoozes: {:?}", path))
+    }
+
+    /// Decisions about `occurrence`, oldest first
+    fn about<'a>(&'a self, occurrence: &Occurrence) -> impl Iterator<Item = &'a Decision> + use<'a> {
+        let (name, start) = (occurrence.schedule.name.clone(), occurrence.start);
+        self.decisions
+            .iter()
+            .filter(move |decision| decision.schedule == name && decision.occurrence == start)
+    }
+
+    /// Why `occurrence` is skipped, if it is
+    pub fn skip_reason(&self, occurrence: &Occurrence) -> Option<&str> {
+        self.about(occurrence).find_map(|decision| decision.skip_reason.as_deref())
+    }
+
+    /// When blocking starts for `occurrence`; `None` if it is skipped
+    pub fn effective_start(&self, occurrence: &Occurrence) -> Option<DateTime<Utc>> {
+        if self.skip_reason(occurrence).is_some() {
+            return None;
+        }
+        let mut start = occurrence.start;
+        for decision in self.about(occurrence) {
+            if let Some(until) = decision.snoozed_until {
+                start = until;
+            }
+            if let Some(at) = decision.started_early {
+                start = at;
+            }
+        }
+        Some(start)
+    }
+
+    /// Snoozes made today
+    pub fn snoozes_today(&self) -> u32 {
+        let today = Local::now().date_naive();
+        self.decisions
+            .iter()
+            .filter(|decision| {
+                decision.snoozed_until.is_some() && decision.decided_at.with_timezone(&Local).date_naive() == today
+            })
+            .count() as u32
+    }
+
+    /// Postpone `occurrence` by the snooze length; fails once `limit`
+    /// snoozes were used today
+    pub fn snooze(&mut self, occurrence: &Occurrence, limit: u32) -> Result<DateTime<Utc>> {
+        if self.snoozes_today() >= limit {
+            return Err(eyre!(
+                "No snoozes left today (limit: {}). Skip the block with a reason instead.",
+                limit
+            ));
+        }
+
+        let now = Utc::now();
+        let start = self.effective_start(occurrence).unwrap_or(occurrence.start).max(now);
+        let until = (start + Duration::minutes(SNOOZE_MINUTES)).min(occurrence.end);
+        self.record(occurrence, |decision| decision.snoozed_until = Some(until));
+        Ok(until)
+    }
+
+    /// Start blocking `occurrence` now instead of at its planned start
+    pub fn start_now(&mut self, occurrence: &Occurrence) {
+        let now = Utc::now();
+        self.record(occurrence, |decision| decision.started_early = Some(now));
+    }
+
+    /// Skip `occurrence`, logging why
+    pub fn skip(&mut self, occurrence: &Occurrence, reason: &str) {
+        self.record(occurrence, |decision| decision.skip_reason = Some(reason.to_string()));
+    }
+
+    fn record(&mut self, occurrence: &Occurrence, change: impl FnOnce(&mut Decision)) {
+        let mut decision = Decision {
+            schedule: occurrence.schedule.name.clone(),
+            occurrence: occurrence.start,
+            decided_at: Utc::now(),
+            snoozed_until: None,
+            started_early: None,
+            skip_reason: None,
+        };
+        change(&mut decision);
+        self.decisions.push(decision);
+    }
+}
+
+/// The scheduled block starting within the warning lead time, with the time
+/// blocking starts
+pub fn pending_warning<'a>(
+    schedules: &'a [Schedule],
+    decisions: &Decisions,
+    now: DateTime<Utc>,
+) -> Result<Option<(Occurrence<'a>, DateTime<Utc>)>> {
+    let until = now + Duration::seconds(WARNING_SECONDS);
+    Ok(schedule::upcoming(schedules, now, until)?
+        .into_iter()
+        .filter_map(|occurrence| {
+            let start = decisions.effective_start(&occurrence)?;
+            (start > now && start <= until).then_some((occurrence, start))
+        })
+        .min_by_key(|(_, start)| *start))
+}
+
+/// Path of the snooze file
+fn snooze_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(SNOOZE_FILE))
+}
+
+/// Handle `timeguardian schedule` commands
+pub fn run_command(cli: &Cli, action: &ScheduleAction) -> Result<()> {
+    let config = load_config()?;
+    let schedules = config.schedules.clone().unwrap_or_default();
+    if schedules.is_empty() {
+        println!("No schedules configured. Add [[schedules]] entries with name, days, start, end and timezone.");
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let mut decisions = Decisions::load()?;
+    match action {
+        ScheduleAction::Preview { next } => {
+            let range = Duration::milliseconds(parse_duration(next)? as i64);
+            let occurrences = schedule::upcoming(&schedules, now, now + range)?;
+            if occurrences.is_empty() {
+                println!("No scheduled blocks in the next {}.", next);
+            }
+
+            for occurrence in occurrences {
+                let start = occurrence.start.with_timezone(&Local);
+                let end = occurrence.end.with_timezone(&Local);
+                let mut line = format!(
+                    "{} - {}  {}",
+                    start.format("%a %Y-%m-%d %H:%M"),
+                    time_of_day(&end),
+                    occurrence.schedule.name
+                );
+                if let Some(zone) = occurrence.schedule.zone()? {
+                    line.push_str(&format!(
+                        " ({} - {} {})",
+                        time_of_day(&occurrence.start.with_timezone(&zone)),
+                        time_of_day(&occurrence.end.with_timezone(&zone)),
+                        zone.name()
+                    ));
+                }
+                match decisions.effective_start(&occurrence) {
+                    None => line.push_str(&format!(
+                        " [skipped: {}]",
+                        decisions.skip_reason(&occurrence).unwrap_or_default()
+                    )),
+                    Some(start) if start <= now => line.push_str(" [active]"),
+                    Some(start) if start != occurrence.start => line.push_str(&format!(
+                        " [starts {}]",
+                        time_of_day(&start.with_timezone(&Local))
+                    )),
+                    Some(_) => {}
+                }
+                println!("{}", line);
+            }
+            println!("\nTimes are shown in local time ({}).", Local::now().format("%Z"));
+        }
+        ScheduleAction::Apply => {
+            if let Some((occurrence, start)) = pending_warning(&schedules, &decisions, now)? {
+                println!(
+                    "Schedule '{}' starts at {}; postpone it with 'timeguardian schedule snooze'.",
+                    occurrence.schedule.name,
+                    time_of_day(&start.with_timezone(&Local))
+                );
+            }
+
+            // Blocks started early (from the warning) are active before their planned start
+            let warning_lead = Duration::seconds(WARNING_SECONDS);
+            let active: Vec<_> = schedule::upcoming(&schedules, now, now + warning_lead)?
+                .into_iter()
+                .filter(|occurrence| {
+                    occurrence.start <= now || decisions.effective_start(occurrence).is_some_and(|start| start <= now)
+                })
+                .collect();
+            if active.is_empty() {
+                println!("No schedule is active right now.");
+                return Ok(());
+            }
+            if !check_and_get_permissions()? {
+                return Ok(());
+            }
+
+            let safety = safety_list(cli, &config);
+            for occurrence in active {
+                let schedule = occurrence.schedule;
+                match decisions.effective_start(&occurrence) {
+                    None => {
+                        hosts::release(&schedule_source(schedule))?;
+                        println!(
+                            "Schedule '{}' skipped: {}",
+                            schedule.name,
+                            decisions.skip_reason(&occurrence).unwrap_or_default()
+                        );
+                    }
+                    Some(start) if start > now => {
+                        hosts::release(&schedule_source(schedule))?;
+                        println!(
+                            "Schedule '{}' snoozed until {}",
+                            schedule.name,
+                            time_of_day(&start.with_timezone(&Local))
+                        );
+                    }
+                    Some(_) => println!("{}", apply_occurrence(&config, &safety, &occurrence, now)?),
+                }
+            }
+        }
+        ScheduleAction::Snooze { name } => {
+            let occurrence = next_occurrence(&schedules, name.as_deref(), now)?;
+            let limit = config.schedule_snooze_limit.unwrap_or(DEFAULT_SNOOZE_LIMIT);
+            let until = decisions.snooze(&occurrence, limit)?;
+            decisions.save()?;
+            println!(
+                "Schedule '{}' snoozed until {} ({} of {} snoozes used today)",
+                occurrence.schedule.name,
+                time_of_day(&until.with_timezone(&Local)),
+                decisions.snoozes_today(),
+                limit
+            );
+            if occurrence.start <= now && is_root() {
+                hosts::release(&schedule_source(occurrence.schedule))?;
+            }
+        }
+        ScheduleAction::Skip { name, reason } => {
+            let occurrence = next_occurrence(&schedules, name.as_deref(), now)?;
+            decisions.skip(&occurrence, reason);
+            decisions.save()?;
+            let start = occurrence.start.with_timezone(&Local);
+            println!(
+                "Skipped schedule '{}' of {} {}",
+                occurrence.schedule.name,
+                start.format("%a"),
+                time_of_day(&start)
+            );
+            if occurrence.start <= now && is_root() {
+                hosts::release(&schedule_source(occurrence.schedule))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The running or next block of the schedule `name` (any schedule if
+/// `None`) within the next day
+fn next_occurrence<'a>(
+    schedules: &'a [Schedule],
+    name: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<Occurrence<'a>> {
+    schedule::upcoming(schedules, now, now + Duration::days(1))?
+        .into_iter()
+        .find(|occurrence| name.is_none_or(|name| occurrence.schedule.name.eq_ignore_ascii_case(name)))
+        .ok_or_else(|| match name {
+            Some(name) => eyre!("Schedule '{}' has no block in the next 24 hours", name),
+            None => eyre!("No scheduled block in the next 24 hours"),
+        })
+}
+
+/// Block the websites of a schedule occurrence until its end
+pub fn apply_occurrence(
+    config: &Config,
+    safety: &SafetyList,
+    occurrence: &Occurrence,
+    now: DateTime<Utc>,
+) -> Result<String> {
+    let schedule = occurrence.schedule;
+
+    // Schedules leave out disabled lists even when they name them
+    let lists = enabled_lists(config, &schedule.lists);
+    if !schedule.lists.is_empty() && lists.is_empty() {
+        hosts::release(&schedule_source(schedule))?;
+        return Ok(format!("Schedule '{}' skipped: all of its lists are disabled", schedule.name));
+    }
+    let websites = collect_websites(config, &lists)?;
+    if let Err(error) = safety.check(&websites) {
+        return Ok(format!("Schedule '{}' skipped: {}", schedule.name, error));
+    }
+    let remaining = (occurrence.end - now).to_std()?;
+    hosts::claim(
+        &schedule_source(schedule),
+        schedule.priority,
+        Some(&schedule.name),
+        None,
+        &lists,
+        &websites,
+        remaining,
+    )?;
+    Ok(format!(
+        "Schedule '{}' blocks {} websites until {}",
+        schedule.name,
+        websites.len(),
+        time_of_day(&occurrence.end.with_timezone(&Local))
+    ))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use timeguardian::Priority;
+
+    fn schedule() -> Schedule {
+        Schedule {
+            name: "Work".to_string(),
+            days: Vec::new(),
+            start: "09:00".to_string(),
+            end: "12:00".to_string(),
+            timezone: Some("UTC".to_string()),
+            lists: Vec::new(),
+            priority: Priority::Normal,
+        }
+    }
+
+    /// An occurrence of `schedule` starting `start` minutes from now and
+    /// lasting `length` minutes
+    fn occurrence(schedule: &Schedule, start: i64, length: i64) -> Occurrence<'_> {
+        let start = Utc::now() + Duration::minutes(start);
+        Occurrence { schedule, start, end: start + Duration::minutes(length) }
+    }
+
+    #[test]
+    fn snoozes_postpone_the_block_until_the_daily_limit() {
+        let schedule = schedule();
+        let occurrence = occurrence(&schedule, 1, 60);
+        let mut decisions = Decisions::default();
+
+        let first = decisions.snooze(&occurrence, 2).unwrap();
+        assert_eq!(first, occurrence.start + Duration::minutes(SNOOZE_MINUTES));
+        // A second snooze postpones the snoozed start
+        let second = decisions.snooze(&occurrence, 2).unwrap();
+        assert_eq!(second, first + Duration::minutes(SNOOZE_MINUTES));
+        assert_eq!(decisions.effective_start(&occurrence), Some(second));
+        assert_eq!(decisions.snoozes_today(), 2);
+
+        assert!(decisions.snooze(&occurrence, 2).is_err());
+        assert_eq!(decisions.effective_start(&occurrence), Some(second));
+    }
+
+    #[test]
+    fn snoozes_never_postpone_past_the_end() {
+        let schedule = schedule();
+        let occurrence = occurrence(&schedule, 1, 5);
+        let mut decisions = Decisions::default();
+        assert_eq!(decisions.snooze(&occurrence, 1).unwrap(), occurrence.end);
+    }
+
+    #[test]
+    fn skipping_and_starting_early_only_affect_their_occurrence() {
+        let schedule = schedule();
+        let skipped = occurrence(&schedule, 1, 60);
+        let early = occurrence(&schedule, 30, 60);
+        let other = occurrence(&schedule, 60, 60);
+        let mut decisions = Decisions::default();
+        decisions.skip(&skipped, "Doctor's appointment");
+        decisions.start_now(&early);
+
+        assert_eq!(decisions.skip_reason(&skipped), Some("Doctor's appointment"));
+        assert_eq!(decisions.effective_start(&skipped), None);
+        assert!(decisions.effective_start(&early).unwrap() < early.start);
+        assert_eq!(decisions.skip_reason(&other), None);
+        assert_eq!(decisions.effective_start(&other), Some(other.start));
+        // Skipping doesn't use up snoozes
+        assert_eq!(decisions.snoozes_today(), 0);
+    }
+
+    #[test]
+    fn warning_comes_shortly_before_the_effective_start() {
+        let schedules = [schedule()];
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 9, 0, 0).unwrap();
+        let mut decisions = Decisions::default();
+
+        let (occurrence, at) = pending_warning(&schedules, &decisions, start - Duration::seconds(30))
+            .unwrap()
+            .unwrap();
+        assert_eq!((occurrence.start, at), (start, start));
+        assert!(pending_warning(&schedules, &decisions, start - Duration::minutes(5)).unwrap().is_none());
+
+        // A snoozed block warns again before its new start, a skipped one never
+        let snoozed_until = start + Duration::minutes(SNOOZE_MINUTES);
+        decisions.record(&occurrence, |decision| decision.snoozed_until = Some(snoozed_until));
+        assert!(pending_warning(&schedules, &decisions, start - Duration::seconds(30)).unwrap().is_none());
+        let (_, at) = pending_warning(&schedules, &decisions, snoozed_until - Duration::seconds(30))
+            .unwrap()
+            .unwrap();
+        assert_eq!(at, snoozed_until);
+
+        decisions.skip(&occurrence, "Holiday");
+        assert!(pending_warning(&schedules, &decisions, snoozed_until - Duration::seconds(30)).unwrap().is_none());
+    }
+}
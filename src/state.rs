@@ -0,0 +1,227 @@
+/*
+* TimeGuardian State Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module implements `timeguardian state export/import`. The config
+* (lists, presets, schedules) and everything in the state directory (history,
+* metrics, config backups, ...) are bundled into one zstd-compressed tar
+* archive, for moving to a new machine or attaching to a bug report.
+*
+* The archive starts with a manifest holding the format version; archives of
+* a newer format are refused. The hosts file backup, the active claims, the
+* firewall state and the write journal describe the exporting machine, so
+* they are exported for debugging but never imported.
+*/
+
+use chrono::{DateTime, Utc};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    config_is_read_only, config_path, firewall, get_config_dir, get_state_dir, hosts, inlined_config, journal, lock_config,
+    save_config, Config, CONFIG_BACKUP_DIR, HOSTS_BACKUP, STATE_FILES,
+};
+
+/// Name of the manifest, the first entry of the archive
+const MANIFEST: &str = "manifest.toml";
+
+/// Version of the archive layout
+const FORMAT_VERSION: u32 = 1;
+
+/// Path of the config file in the archive
+const CONFIG_ENTRY: &str = "config/config.toml";
+
+/// Directory of the state files in the archive
+const STATE_PREFIX: &str = "state";
+
+/// zstd compression level of exports
+const COMPRESSION_LEVEL: i32 = 9;
+
+/// Description of an archive
+#[derive(Serialize, Deserialize, Debug)]
+struct Manifest {
+    /// Version of the archive layout
+    format: u32,
+    /// TimeGuardian version that wrote the archive
+    app_version: String,
+    /// When the archive was written
+    created_at: DateTime<Utc>,
+}
+
+/// Write the config and state to `path`; returns the number of files
+pub fn export(path: &Path) -> Result<usize> {
+    let file = File::create(path).wrap_err_with(|| format!("Could not create {:?}", path))?;
+    let encoder = zstd::Encoder::new(file, COMPRESSION_LEVEL)?.auto_finish();
+    let mut archive = tar::Builder::new(encoder);
+
+    let manifest = Manifest {
+        format: FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+    };
+    let manifest = toml::to_string(&manifest).wrap_err("Could not serialize manifest")?;
+    append_bytes(&mut archive, MANIFEST, manifest.as_bytes())?;
+
+    let mut count = 0;
+    // With the lists inline, so the archive doesn't depend on the list files
+    let config = config_path()?;
+    if config.exists() {
+        append_bytes(&mut archive, CONFIG_ENTRY, inlined_config(&config)?.as_bytes())?;
+        count += 1;
+    }
+
+    let state_dir = get_state_dir()?;
+    for name in STATE_FILES {
+        let source = state_dir.join(name);
+        let entry = Path::new(STATE_PREFIX).join(name);
+        if source.is_dir() {
+            archive.append_dir_all(&entry, &source)?;
+            count += fs::read_dir(&source)?.count();
+        } else if source.is_file() {
+            archive.append_path_with_name(&source, &entry)?;
+            count += 1;
+        }
+    }
+
+    archive.into_inner()?;
+    Ok(count)
+}
+
+/// Restore the config and state from the archive at `path`.
+///
+/// Existing files are only replaced with `force`; the replaced config is
+/// kept as a config backup. Returns the names of the restored files.
+pub fn import(path: &Path, force: bool) -> Result<Vec<String>> {
+    if config_is_read_only() {
+        return Err(eyre!("The configuration is managed declaratively (--config); it can't be imported"));
+    }
+    if !hosts::sessions_in_hosts_file()?.is_empty() {
+        return Err(eyre!("Websites are blocked right now. Import the state once all sessions ended."));
+    }
+
+    let files = read_archive(path)?;
+    let (config_dir, state_dir) = (get_config_dir()?, get_state_dir()?);
+    let targets: Vec<(PathBuf, &[u8], String)> = files
+        .iter()
+        .filter_map(|(entry, content)| {
+            let target = if entry == Path::new(CONFIG_ENTRY) {
+                config_dir.join("config.toml")
+            } else {
+                state_dir.join(entry.strip_prefix(STATE_PREFIX).ok()?)
+            };
+            Some((target, content.as_slice(), entry.display().to_string()))
+        })
+        .collect();
+
+    // Config backups have unique names, replacing one changes nothing
+    let existing: Vec<&str> = targets
+        .iter()
+        .filter(|(target, _, _)| target.exists() && !target.starts_with(state_dir.join(CONFIG_BACKUP_DIR)))
+        .map(|(_, _, name)| name.as_str())
+        .collect();
+    if !existing.is_empty() && !force {
+        return Err(eyre!(
+            "The import would replace existing files: {}. Pass --force to replace them.",
+            existing.join(", ")
+        ));
+    }
+
+    let _lock = lock_config()?;
+    let mut restored = Vec::new();
+    for (target, content, name) in targets {
+        // Saved like any config change: backed up first, lists split into files
+        if name == CONFIG_ENTRY {
+            let text = std::str::from_utf8(content).wrap_err("The config in the archive is not valid UTF-8")?;
+            let config: Config = toml::from_str(text).wrap_err("Could not parse the config in the archive")?;
+            save_config(&config)?;
+            restored.push(name);
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, content).wrap_err_with(|| format!("Could not restore {:?}", target))?;
+        restored.push(name);
+    }
+    Ok(restored)
+}
+
+/// Entries of the archive that may be imported, after checking the manifest
+fn read_archive(path: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let file = File::open(path).wrap_err_with(|| format!("Could not open {:?}", path))?;
+    let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+    let mut manifest: Option<Manifest> = None;
+    let mut files = Vec::new();
+
+    for entry in archive.entries().wrap_err("Could not read the archive")? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.into_owned();
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+
+        if name == Path::new(MANIFEST) {
+            let text = String::from_utf8(content).wrap_err("The manifest is not valid UTF-8")?;
+            let parsed: Manifest = toml::from_str(&text).wrap_err("Could not parse the manifest")?;
+            if parsed.format > FORMAT_VERSION {
+                return Err(eyre!(
+                    "The archive was written by TimeGuardian {} in a newer format ({}); update TimeGuardian to import it",
+                    parsed.app_version,
+                    parsed.format
+                ));
+            }
+            manifest = Some(parsed);
+            continue;
+        }
+        if manifest.is_none() {
+            return Err(eyre!("{:?} is not a TimeGuardian state archive", path));
+        }
+        if importable(&name) {
+            files.push((name, content));
+        }
+    }
+
+    if manifest.is_none() {
+        return Err(eyre!("{:?} is not a TimeGuardian state archive", path));
+    }
+    Ok(files)
+}
+
+/// Whether an archive entry may be written: the config or a known state file,
+/// without machine-specific files and never outside the target directories
+fn importable(entry: &Path) -> bool {
+    if entry == Path::new(CONFIG_ENTRY) {
+        return true;
+    }
+    let Ok(relative) = entry.strip_prefix(STATE_PREFIX) else {
+        return false;
+    };
+    let machine_specific = [HOSTS_BACKUP, hosts::BLOCK_STATE_FILE, journal::JOURNAL_FILE, firewall::FIREWALL_STATE_FILE];
+    relative.components().all(|component| matches!(component, Component::Normal(_)))
+        && relative
+            .components()
+            .next()
+            .and_then(|first| first.as_os_str().to_str())
+            .is_some_and(|first| STATE_FILES.contains(&first) && !machine_specific.contains(&first))
+}
+
+/// Add a file with the given content to the archive
+fn append_bytes<W: std::io::Write>(archive: &mut tar::Builder<W>, name: &str, content: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    archive.append_data(&mut header, name, content)?;
+    Ok(())
+}
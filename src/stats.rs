@@ -0,0 +1,382 @@
+/*
+* TimeGuardian Stats Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module summarizes the session history for sharing, e.g. in
+* accountability posts (`timeguardian stats badge`). The badge only contains
+* totals: task names, intents and blocked domains never leave the machine.
+*
+* `timeguardian stats top-domains` attributes the blocked time to lists and
+* domains, to find lists worth pruning. Blocked attempts are not counted: the
+* hosts file backend cannot observe them.
+*/
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    time::Duration,
+};
+use timeguardian::format;
+
+use crate::history::{Enforcement, History, SessionRecord};
+use crate::{hosts, idn, load_config, parse_duration, time_of_day, StatsAction};
+use crate::tui::WebsiteList;
+
+/// Output of `stats badge`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BadgeFormat {
+    /// One line of markdown (default)
+    #[default]
+    Markdown,
+    /// A self-contained SVG image
+    Svg,
+}
+
+/// Anonymous totals of the history
+pub struct Summary {
+    /// Focus time of the completed sessions this month
+    pub month_focus: Duration,
+    /// Completed sessions this month
+    pub month_sessions: usize,
+    /// Consecutive days with a completed session, up to today or yesterday
+    pub streak_days: usize,
+}
+
+impl Summary {
+    /// Summarize the completed sessions of `history`
+    pub fn from_history(history: &History) -> Self {
+        let today = Local::now().date_naive();
+        let completed = history.sessions.iter().filter(|record| record.completed == Some(true));
+
+        let this_month: Vec<_> = completed
+            .clone()
+            .filter(|record| {
+                let date = record.started_at.date_naive();
+                date.year() == today.year() && date.month() == today.month()
+            })
+            .collect();
+        let days: BTreeSet<NaiveDate> = completed.map(|record| record.started_at.date_naive()).collect();
+
+        Self {
+            month_focus: this_month.iter().map(|record| record.duration()).sum(),
+            month_sessions: this_month.len(),
+            streak_days: streak(&days, today),
+        }
+    }
+
+    /// Short text like "42h focused this month, 12-day streak"
+    pub fn text(&self) -> String {
+        let focus = self.month_focus.as_secs();
+        let focus = if focus >= 3600 {
+            format!("{}h", focus / 3600)
+        } else {
+            format!("{}m", focus / 60)
+        };
+        let mut text = format!("{} focused this month", focus);
+        if self.streak_days > 0 {
+            text.push_str(&format!(", {}-day streak", self.streak_days));
+        }
+        text
+    }
+
+    /// The badge in the requested format
+    pub fn badge(&self, format: BadgeFormat) -> String {
+        match format {
+            BadgeFormat::Markdown => format!(
+                "**TimeGuardian**: {} ({} sessions)\n",
+                self.text(),
+                self.month_sessions
+            ),
+            BadgeFormat::Svg => svg_badge("TimeGuardian", &self.text()),
+        }
+    }
+}
+
+/// Blocked time attributed to one list or domain
+pub struct Attribution {
+    pub name: String,
+    /// Completed sessions that blocked it
+    pub sessions: usize,
+    /// Total length of those sessions
+    pub blocked: Duration,
+}
+
+/// Blocked time per list and per domain of the sessions completed since
+/// `since`, largest first. Sessions without lists count for every enabled list;
+/// timer-only and unenforced sessions blocked nothing and don't count.
+pub fn attribute(
+    history: &History,
+    lists: &[WebsiteList],
+    since: DateTime<Local>,
+) -> (Vec<Attribution>, Vec<Attribution>) {
+    let mut by_list: BTreeMap<&str, (usize, Duration)> = BTreeMap::new();
+    let mut by_domain: BTreeMap<String, (usize, Duration)> = BTreeMap::new();
+
+    let completed = history
+        .sessions
+        .iter()
+        .filter(|record| record.completed == Some(true) && record.started_at >= since && record.enforcement == Enforcement::Blocked);
+    for record in completed {
+        let blocked: Vec<&WebsiteList> = if record.lists.is_empty() {
+            lists.iter().filter(|list| list.enabled).collect()
+        } else {
+            lists.iter().filter(|list| record.lists.contains(&list.name)).collect()
+        };
+
+        // A domain in several lists, in either form of an IDN, is blocked once per session
+        let domains: BTreeSet<String> = blocked
+            .iter()
+            .flat_map(|list| list.websites.iter())
+            .filter(|entry| !entry.starts_with(hosts::ALLOW_PREFIX))
+            .map(|entry| idn::to_unicode(&idn::key(entry)))
+            .collect();
+        for name in blocked.iter().map(|list| list.name.as_str()) {
+            let entry = by_list.entry(name).or_default();
+            entry.0 += 1;
+            entry.1 += record.duration();
+        }
+        for domain in domains {
+            let entry = by_domain.entry(domain).or_default();
+            entry.0 += 1;
+            entry.1 += record.duration();
+        }
+    }
+
+    let ranked = |totals: BTreeMap<&str, (usize, Duration)>| {
+        let mut ranked: Vec<Attribution> = totals
+            .into_iter()
+            .map(|(name, (sessions, blocked))| Attribution { name: name.to_string(), sessions, blocked })
+            .collect();
+        ranked.sort_by_key(|attribution| std::cmp::Reverse(attribution.blocked));
+        ranked
+    };
+    let by_domain = by_domain.iter().map(|(domain, totals)| (domain.as_str(), *totals)).collect();
+    (ranked(by_list), ranked(by_domain))
+}
+
+/// Length of the run of consecutive days ending today (or yesterday, so the
+/// streak doesn't break before today's session)
+fn streak(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> usize {
+    let mut day = if days.contains(&today) {
+        today
+    } else {
+        today - ChronoDuration::days(1)
+    };
+    let mut count = 0;
+    while days.contains(&day) {
+        count += 1;
+        day -= ChronoDuration::days(1);
+    }
+    count
+}
+
+/// Flat two-part badge in the style of shields.io
+fn svg_badge(label: &str, message: &str) -> String {
+    // Verdana 11px averages about 7px per character
+    let width = |text: &str| text.chars().count() as u32 * 7 + 10;
+    let (label_width, message_width) = (width(label), width(message));
+    let total = label_width + message_width;
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{total}" height="20" role="img" aria-label="{label}: {message}">
+  <title>{label}: {message}</title>
+  <rect width="{label_width}" height="20" fill="#555"/>
+  <rect x="{label_width}" width="{message_width}" height="20" fill="#0072b2"/>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{message_x}" y="14">{message}</text>
+  </g>
+</svg>
+"##,
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
+
+/// Run a `timeguardian stats` subcommand
+pub fn run_command(action: &StatsAction) -> Result<()> {
+    match action {
+        StatsAction::Badge { format, output } => {
+            let badge = Summary::from_history(&History::load()?).badge(*format);
+            match output {
+                Some(path) => {
+                    fs::write(path, badge).wrap_err_with(|| format!("Could not write badge: {:?}", path))?;
+                    println!("Badge written to {}", path.display());
+                }
+                None => print!("{}", badge),
+            }
+        }
+        StatsAction::Verify => {
+            let history = History::load()?;
+            let verification = history.verify();
+            if verification.unsealed > 0 {
+                println!(
+                    "{} older session(s) were recorded before the history was sealed and are not covered.",
+                    verification.unsealed
+                );
+            }
+            if verification.truncated {
+                println!("The newest session(s) were removed: the last session is not the one sealed last.");
+            }
+            if verification.broken.is_empty() && !verification.truncated {
+                println!("The history is intact: {} sealed session(s).", verification.sealed);
+                return Ok(());
+            }
+            for id in &verification.broken {
+                match history.find(*id) {
+                    Ok(record) => println!(
+                        "Session #{} ({}, {}) was edited, or a session before it was removed.",
+                        id,
+                        record.started_at.format("%Y-%m-%d %H:%M"),
+                        record.task
+                    ),
+                    Err(_) => println!("Session #{} was edited, or a session before it was removed.", id),
+                }
+            }
+            return Err(eyre!("The history was changed outside TimeGuardian"));
+        }
+        StatsAction::Show { id } => {
+            let history = History::load()?;
+            let record = history.find_by_reference(id)?;
+            for line in session_details(record)? {
+                println!("{}", line);
+            }
+        }
+        StatsAction::TopDomains { period, limit } => {
+            let since = Local::now() - chrono::Duration::milliseconds(parse_duration(period)? as i64);
+            let lists = load_config()?.website_lists.unwrap_or_default();
+            let history = History::load()?;
+            let (by_list, by_domain) = attribute(&history, &lists, since);
+            let unenforced = history
+                .sessions
+                .iter()
+                .filter(|record| record.started_at >= since && record.enforcement == Enforcement::Unenforced)
+                .count();
+            if unenforced > 0 {
+                println!("{} unenforced session(s) without permissions blocked nothing and are not counted.\n", unenforced);
+            }
+            if by_list.is_empty() {
+                println!("No completed sessions in the last {}.", period);
+                return Ok(());
+            }
+
+            println!("Blocked time by list (completed sessions, last {}):", period);
+            for list in &by_list {
+                println!("  {:<24} {:>9}  ({} sessions)", list.name, format::hours_minutes(list.blocked), list.sessions);
+            }
+            let never: Vec<&str> = lists
+                .iter()
+                .filter(|list| !by_list.iter().any(|blocked| blocked.name == list.name))
+                .map(|list| list.name.as_str())
+                .collect();
+            if !never.is_empty() {
+                println!("  Never blocked: {}", never.join(", "));
+            }
+
+            println!("\nBlocked time by domain:");
+            for domain in by_domain.iter().take(*limit) {
+                println!("  {:<24} {:>9}  ({} sessions)", domain.name, format::hours_minutes(domain.blocked), domain.sessions);
+            }
+            println!("\nBlocked attempts are not counted: the hosts file backend cannot see them.");
+        }
+    }
+    Ok(())
+}
+
+/// Print the most recent sessions with their history ids and outcomes
+pub fn print_history(limit: usize) -> Result<()> {
+    let history = History::load()?;
+    if history.sessions.is_empty() {
+        println!("No sessions recorded yet.");
+    }
+    let skip = history.sessions.len().saturating_sub(limit);
+    for record in history.sessions.iter().skip(skip) {
+        println!(
+            "#{:<4} {}  {:>5}  {:<16}  {}",
+            record.id,
+            record.started_at.format("%Y-%m-%d %H:%M"),
+            record.duration_text(),
+            record.outcome(),
+            match record.enforcement.note() {
+                "" => record.task.clone(),
+                note => format!("{} ({})", record.task, note),
+            }
+        );
+        if let Some(intent) = &record.intent {
+            let achieved = record.achieved.map(|achieved| achieved.label()).unwrap_or("no answer");
+            println!("      intent: {} ({})", intent, achieved);
+        }
+    }
+
+    // Planned ends against unplanned ones: stops, resets and crashes
+    let ends = history.end_counts();
+    if !ends.is_empty() {
+        let endings: Vec<String> =
+            ends.iter().map(|(reason, count)| format!("{} {}", count, reason.label())).collect();
+        println!("\nEndings: {}", endings.join(", "));
+    }
+
+    let (yes, partial, no) = history.achievement_counts();
+    if let Some(rate) = (yes * 100).checked_div(yes + partial + no) {
+        println!(
+            "\nIntents: {} achieved, {} partly, {} not achieved ({}% fully achieved)",
+            yes, partial, no, rate
+        );
+    }
+
+    Ok(())
+}
+
+/// Everything recorded about a session: its history record and, while it
+/// blocks, its claim and the hosts file section written for it
+fn session_details(record: &SessionRecord) -> Result<Vec<String>> {
+    let mut lines = vec![
+        format!("Session #{} {}", record.id, record.session_id.as_deref().unwrap_or("(no session ID, older version)")),
+        format!("  task:        {}", record.task),
+        format!("  started:     {}", record.started_at.format("%Y-%m-%d %H:%M")),
+        format!("  length:      {}", record.duration_text()),
+        format!(
+            "  lists:       {}",
+            if record.lists.is_empty() { "all enabled lists".to_string() } else { record.lists.join(", ") }
+        ),
+        format!("  priority:    {}", record.priority.name()),
+        format!("  outcome:     {}", record.outcome()),
+    ];
+    if !record.enforcement.note().is_empty() {
+        lines.push(format!("  enforcement: {}", record.enforcement.note()));
+    }
+    if let Some(intent) = &record.intent {
+        let achieved = record.achieved.map(|achieved| achieved.label()).unwrap_or("no answer");
+        lines.push(format!("  intent:      {} ({})", intent, achieved));
+    }
+    if !record.checklist.is_empty() {
+        lines.push(format!("  checklist:   {} (ticked off)", record.checklist.join(", ")));
+    }
+
+    let Some(session_id) = &record.session_id else {
+        return Ok(lines);
+    };
+    let state = hosts::BlockState::load()?;
+    match state.claims.iter().find(|claim| claim.session_id.as_ref() == Some(session_id)) {
+        Some(claim) => lines.push(format!(
+            "  blocking:    {} domains as {} until {}",
+            claim.domains.len(),
+            claim.source,
+            time_of_day(&claim.expires_at.with_timezone(&Local))
+        )),
+        None => lines.push("  blocking:    nothing (no active claim)".to_string()),
+    }
+    let marked = hosts::sessions_in_hosts_file()?
+        .iter()
+        .any(|claim| claim.session_id.as_ref() == Some(session_id));
+    if marked {
+        lines.push("  hosts file:  has a section for this session".to_string());
+    }
+    Ok(lines)
+}
+
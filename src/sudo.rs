@@ -0,0 +1,97 @@
+/*
+* TimeGuardian Sudo Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module decides how TimeGuardian gets the rights to write the hosts
+* file when it was started without them. `use_sudo` in the config picks the
+* policy; `true`/`false` from older versions still work:
+*
+*   use_sudo = "ask-once"      # ask, then remember the answer (default)
+*   use_sudo = "always"        # restart with sudo without asking
+*   use_sudo = "never"         # fail instead of escalating
+*   use_sudo = "use-polkit"    # restart with pkexec
+*   use_sudo = "use-helper"    # restart with the command in sudo_helper
+*   sudo_helper = "doas"
+*/
+
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{env, ffi::OsString, process::Command};
+
+/// Variables locating the configuration, kept when the environment is reset
+const PRESERVED_ENV: &[&str] = &["TIMEGUARDIAN_CONFIG", "TIMEGUARDIAN_CONFIG_DIR"];
+
+/// How missing rights are obtained
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SudoPolicy {
+    /// Never escalate; TimeGuardian has to be started as root
+    Never,
+    /// Ask on the first launch and remember the answer in the config
+    #[default]
+    AskOnce,
+    /// Restart with sudo without asking
+    Always,
+    /// Restart with the command configured in `sudo_helper` (e.g. doas, run0)
+    UseHelper,
+    /// Restart with pkexec, asking through polkit
+    UsePolkit,
+}
+
+/// `use_sudo` as a policy or as the bool of older versions
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PolicyOrBool {
+    Policy(SudoPolicy),
+    Bool(bool),
+}
+
+/// Read `use_sudo`; `true` means always and `false` asking, as before
+pub fn deserialize_policy<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<SudoPolicy>, D::Error> {
+    Ok(Option::<PolicyOrBool>::deserialize(deserializer)?.map(|value| match value {
+        PolicyOrBool::Policy(policy) => policy,
+        PolicyOrBool::Bool(true) => SudoPolicy::Always,
+        PolicyOrBool::Bool(false) => SudoPolicy::AskOnce,
+    }))
+}
+
+impl SudoPolicy {
+    /// Value of the policy in the config
+    pub fn name(&self) -> &'static str {
+        match self {
+            SudoPolicy::Never => "never",
+            SudoPolicy::AskOnce => "ask-once",
+            SudoPolicy::Always => "always",
+            SudoPolicy::UseHelper => "use-helper",
+            SudoPolicy::UsePolkit => "use-polkit",
+        }
+    }
+
+    /// Command restarting TimeGuardian with the same arguments and elevated rights
+    pub fn relaunch_command(&self, helper: Option<&str>) -> Result<Command> {
+        let program = match self {
+            SudoPolicy::Never => return Err(eyre!("use_sudo = \"never\" does not allow restarting as root")),
+            SudoPolicy::AskOnce | SudoPolicy::Always => "sudo",
+            SudoPolicy::UsePolkit => "pkexec",
+            SudoPolicy::UseHelper => {
+                helper.ok_or_else(|| eyre!("use_sudo = \"use-helper\" needs the command in sudo_helper, e.g. \"doas\""))?
+            }
+        };
+
+        let mut command = Command::new(program);
+        // The tools reset the environment; keep the config location
+        let preserved: Vec<OsString> = PRESERVED_ENV
+            .iter()
+            .filter_map(|name| {
+                let mut assignment = OsString::from(format!("{}=", name));
+                assignment.push(env::var_os(name)?);
+                Some(assignment)
+            })
+            .collect();
+        if !preserved.is_empty() {
+            command.arg("env").args(preserved);
+        }
+        command.arg(env::current_exe()?).args(env::args_os().skip(1));
+        Ok(command)
+    }
+}
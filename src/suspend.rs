@@ -0,0 +1,89 @@
+/*
+* TimeGuardian Suspend Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module detects when the machine was suspended during a session.
+* The monotonic clock stops while the system sleeps but the wall clock keeps
+* going, so a jump between the two reveals a suspend without platform power
+* APIs. Depending on the configuration the suspended time either counts
+* towards the session or pauses it.
+*/
+
+use chrono::{DateTime, Local, Utc};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::{hosts, ipc::SessionHandle};
+
+/// Smallest clock difference treated as a suspend
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(5);
+
+/// How time spent suspended affects a running session
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SuspendBehavior {
+    /// Suspended time counts towards the session; it ends at the planned time (default)
+    #[default]
+    Count,
+    /// The session pauses while suspended and ends correspondingly later
+    Pause,
+}
+
+/// Watches a running session for suspends
+pub struct SuspendWatch {
+    behavior: SuspendBehavior,
+    source: Option<String>,
+    last_wall: DateTime<Utc>,
+    last_monotonic: Instant,
+}
+
+impl SuspendWatch {
+    /// Watch the session with the given hosts claim source; `None` for
+    /// sessions that don't block
+    pub fn new(behavior: SuspendBehavior, source: Option<&str>) -> Self {
+        Self {
+            behavior,
+            source: source.map(str::to_string),
+            last_wall: Utc::now(),
+            last_monotonic: Instant::now(),
+        }
+    }
+
+    /// Check for a suspend since the last call.
+    ///
+    /// When pausing, the session end, its hosts claim and the IPC status
+    /// are moved back by the suspended time. Returns the suspended time.
+    pub fn poll(&mut self, end_time: &mut DateTime<Local>, session: &SessionHandle) -> Result<Option<Duration>> {
+        let wall = (Utc::now() - self.last_wall).to_std().unwrap_or_default();
+        let monotonic = self.last_monotonic.elapsed();
+        self.last_wall = Utc::now();
+        self.last_monotonic = Instant::now();
+
+        let suspended = wall.saturating_sub(monotonic);
+        if suspended < SUSPEND_THRESHOLD {
+            return Ok(None);
+        }
+
+        if self.behavior == SuspendBehavior::Pause {
+            *end_time += chrono::Duration::from_std(suspended)?;
+            if let Some(source) = &self.source {
+                hosts::extend(source, suspended)?;
+            }
+            session.extend(suspended);
+        }
+
+        Ok(Some(suspended))
+    }
+
+    /// Start watching afresh (while no session is running)
+    pub fn reset(&mut self) {
+        self.last_wall = Utc::now();
+        self.last_monotonic = Instant::now();
+    }
+
+    /// Whether suspended time pauses the session
+    pub fn pauses(&self) -> bool {
+        self.behavior == SuspendBehavior::Pause
+    }
+}
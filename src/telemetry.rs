@@ -0,0 +1,239 @@
+/*
+* TimeGuardian Telemetry Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module keeps anonymous usage counters, strictly opt-in:
+*
+*   [telemetry]
+*   enabled = true
+*   endpoint = "https://example.org/timeguardian"
+*
+* Only which commands and session options are used gets counted, locally in
+* the state directory; no task names, lists, domains, paths or identifiers.
+* `timeguardian telemetry show` prints the exact payload an upload would
+* send. Uploading is only compiled in with the `telemetry-upload` cargo
+* feature and only happens on `timeguardian telemetry upload`. Setting
+* `TIMEGUARDIAN_NO_TELEMETRY` or `DO_NOT_TRACK` turns everything off,
+* whatever the config says.
+*/
+
+use chrono::{DateTime, Utc};
+use clap::CommandFactory;
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+use crate::hosts::Priority;
+use crate::{get_state_dir, load_config, update_config, Cli, TelemetryAction};
+
+/// File in the state directory holding the counters
+pub const TELEMETRY_FILE: &str = "telemetry.toml";
+
+/// Environment variables that turn telemetry off
+const OFF_SWITCHES: &[&str] = &["TIMEGUARDIAN_NO_TELEMETRY", "DO_NOT_TRACK"];
+
+/// Telemetry settings; off unless `enabled = true`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TelemetryConfig {
+    /// Whether usage is counted
+    #[serde(default)]
+    pub enabled: bool,
+    /// Where `telemetry upload` sends the payload
+    pub endpoint: Option<String>,
+}
+
+impl TelemetryConfig {
+    /// Whether usage is counted: enabled and not switched off by the environment
+    pub fn active(&self) -> bool {
+        self.enabled && off_switch().is_none()
+    }
+}
+
+/// The environment variable switching telemetry off, if one is set
+pub fn off_switch() -> Option<&'static str> {
+    OFF_SWITCHES
+        .iter()
+        .copied()
+        .find(|name| env::var(name).is_ok_and(|value| !value.is_empty() && value != "0"))
+}
+
+/// Counters kept since `since`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Counters {
+    /// When counting started
+    pub since: Option<DateTime<Utc>>,
+    /// Uses per feature, e.g. "command:start" or "start:strict"
+    #[serde(default)]
+    pub features: BTreeMap<String, u64>,
+}
+
+impl Counters {
+    /// Load the counters from the state directory
+    pub fn load() -> Result<Self> {
+        let path = telemetry_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Could not read telemetry counters: {:?}", path))?;
+        toml::from_str(&content).wrap_err("Could not parse telemetry counters")
+    }
+
+    /// Save the counters to the state directory
+    pub fn save(&self) -> Result<()> {
+        let path = telemetry_path()?;
+        let content = toml::to_string(self).wrap_err("Could not serialize telemetry counters")?;
+        fs::write(&path, content).wrap_err_with(|| format!("Could not save telemetry counters: {:?}", path))
+    }
+
+    /// The exact JSON an upload sends
+    pub fn payload(&self) -> Result<String> {
+        let payload = Payload {
+            version: env!("CARGO_PKG_VERSION"),
+            os: env::consts::OS,
+            // Only the day, so the payload can't be matched to a first run
+            since: self.since.map(|since| since.format("%Y-%m-%d").to_string()),
+            features: &self.features,
+        };
+        serde_json::to_string_pretty(&payload).wrap_err("Could not serialize telemetry payload")
+    }
+}
+
+/// What an upload sends
+#[derive(Serialize)]
+struct Payload<'a> {
+    version: &'static str,
+    os: &'static str,
+    since: Option<String>,
+    features: &'a BTreeMap<String, u64>,
+}
+
+/// Count uses of `features` if telemetry is active; never fails the command
+pub fn count(config: &TelemetryConfig, features: &[String]) {
+    if !config.active() || features.is_empty() {
+        return;
+    }
+    let mut counters = Counters::load().unwrap_or_default();
+    counters.since.get_or_insert_with(Utc::now);
+    for feature in features {
+        *counters.features.entry(feature.clone()).or_default() += 1;
+    }
+    let _ = counters.save();
+}
+
+/// Delete the counters
+pub fn reset() -> Result<()> {
+    let path = telemetry_path()?;
+    if path.exists() {
+        fs::remove_file(&path).wrap_err_with(|| format!("Could not remove {:?}", path))?;
+    }
+    Ok(())
+}
+
+/// Send the payload to the configured endpoint and start counting anew
+#[cfg(feature = "telemetry-upload")]
+pub fn upload(config: &TelemetryConfig) -> Result<()> {
+    if let Some(name) = off_switch() {
+        return Err(eyre!("Telemetry is switched off by {}", name));
+    }
+    if !config.enabled {
+        return Err(eyre!("Telemetry is off; set enabled = true in [telemetry] first"));
+    }
+    let Some(endpoint) = &config.endpoint else {
+        return Err(eyre!("No telemetry endpoint configured ([telemetry] endpoint)"));
+    };
+
+    let payload = Counters::load()?.payload()?;
+    ureq::post(endpoint)
+        .set("Content-Type", "application/json")
+        .send_string(&payload)
+        .wrap_err_with(|| format!("Could not upload to {}", endpoint))?;
+    reset()
+}
+
+/// Path of the counters file
+fn telemetry_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(TELEMETRY_FILE))
+}
+
+/// Run a `timeguardian telemetry` subcommand
+pub fn run_command(action: &TelemetryAction) -> Result<()> {
+    let config = load_config()?.telemetry.unwrap_or_default();
+    match action {
+        TelemetryAction::Status => {
+            match (config.enabled, off_switch()) {
+                (_, Some(name)) => println!("Telemetry is off: {} is set.", name),
+                (true, None) => println!("Telemetry is on: usage is counted locally."),
+                (false, None) => println!("Telemetry is off (the default)."),
+            }
+            match &config.endpoint {
+                Some(endpoint) => println!("Uploads go to {} (only on 'timeguardian telemetry upload').", endpoint),
+                None => println!("No upload endpoint configured."),
+            }
+            if !cfg!(feature = "telemetry-upload") {
+                println!("This build can't upload; it was built without the telemetry-upload feature.");
+            }
+        }
+        TelemetryAction::Show => {
+            println!("{}", Counters::load()?.payload()?);
+        }
+        TelemetryAction::Enable => {
+            update_config(|config| {
+                config.telemetry.get_or_insert_with(Default::default).enabled = true;
+                Ok(())
+            })?;
+            println!("Telemetry enabled. Review the payload any time with 'timeguardian telemetry show'.");
+            if let Some(name) = off_switch() {
+                println!("Nothing is counted while {} is set.", name);
+            }
+        }
+        TelemetryAction::Disable => {
+            update_config(|config| {
+                config.telemetry.get_or_insert_with(Default::default).enabled = false;
+                Ok(())
+            })?;
+            reset()?;
+            println!("Telemetry disabled and the counters deleted.");
+        }
+        TelemetryAction::Upload => {
+            #[cfg(feature = "telemetry-upload")]
+            {
+                upload(&config)?;
+                println!("Uploaded the usage counters; counting starts anew.");
+            }
+            #[cfg(not(feature = "telemetry-upload"))]
+            {
+                return Err(eyre!(
+                    "This build has no upload support; rebuild with --features telemetry-upload"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Names of the command and session options in use, for the telemetry counters.
+/// Only names are counted, never values such as tasks or lists.
+pub fn used_features(cli: &Cli) -> Vec<String> {
+    let command = Cli::command()
+        .try_get_matches_from(env::args_os())
+        .ok()
+        .and_then(|matches| matches.subcommand_name().map(str::to_string));
+    let mut features = vec![format!("command:{}", command.as_deref().unwrap_or("none"))];
+
+    let options = [
+        ("preset", cli.preset.is_some()),
+        ("lists", !cli.session.lists.is_empty()),
+        ("priority", cli.session.priority() != Priority::Normal),
+        ("strict", cli.strict),
+        ("timer-only", cli.session.timer_only),
+        ("detach", cli.detach),
+        ("accessible", cli.accessible),
+    ];
+    features.extend(options.iter().filter(|(_, used)| *used).map(|(option, _)| format!("option:{}", option)));
+    features
+}
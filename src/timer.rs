@@ -0,0 +1,742 @@
+/*
+* TimeGuardian Timer Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module runs focus sessions in the terminal: `timeguardian start`,
+* the bare session flags, presets with their chains and `timeguardian
+* again`. An interactive terminal gets a live countdown that can be paused,
+* extended and stopped; redirected output, dumb terminals and screen
+* readers get a plain line-based countdown instead.
+*/
+
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use crossterm::{
+    event::{Event, KeyCode},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use std::{
+    env,
+    io::{self, stdout, IsTerminal},
+    time::{Duration, Instant},
+};
+use timeguardian::format;
+
+use crate::history::{self, EndReason, Enforcement};
+use crate::hosts::{self, Priority};
+use crate::preset::{self, find_preset, EndBehavior};
+use crate::{
+    accessible_mode, append_daily_note, approval, attach, battery, blockpage, check_and_get_permissions,
+    collect_websites, domains_in_use, explain_no_websites, ipc, is_interactive_terminal, list_impact, live,
+    load_config, lockout, newtab, parse_duration, refresh_hosts_periodically, safety_list, session_lists,
+    session_source, suspend, time_of_day, ulid, Cli, Config, DEFAULT_DURATION,
+};
+
+/// How much `+` extends a session
+const CLI_EXTEND_STEP: Duration = Duration::from_secs(5 * 60);
+/// How often a paused session moves its end and its claim along
+const CLI_PAUSE_STEP: Duration = Duration::from_secs(10);
+
+/// A focus session run in the terminal
+pub struct Timer<'a> {
+    /// Websites to block; none for a timer-only session
+    pub websites: &'a [String],
+    pub duration: Duration,
+    pub task: &'a str,
+    /// The duration as given, for the messages
+    pub duration_text: &'a str,
+    pub priority: Priority,
+    /// Website lists the websites come from
+    pub lists: &'a [String],
+    /// Items to tick off before the block starts
+    pub checklist: &'a [String],
+}
+
+/// Run the session with its countdown.
+///
+/// Returns `true` if the session ran until the end and `false` if it was
+/// aborted or could not be started.
+pub fn run(timer: &Timer, cli: &Cli) -> Result<bool> {
+    let Timer { websites, duration, task: task_name, duration_text, priority, lists, checklist } = *timer;
+
+    // Without websites only the timer runs, and the hosts file is never touched
+    let mut enforcement = if websites.is_empty() { Enforcement::TimerOnly } else { Enforcement::Blocked };
+
+    // Without permissions the session still runs, it just doesn't block
+    if enforcement == Enforcement::Blocked && !check_and_get_permissions()? {
+        println!("Blocking disabled (no permissions): only the timer runs.");
+        enforcement = Enforcement::Unenforced;
+    }
+    let timer_only = enforcement != Enforcement::Blocked;
+
+    let config = load_config()?;
+    if !timer_only && !approve_new_lists(lists, &config, cli)? {
+        return Ok(false);
+    }
+    // Without a terminal (e.g. `--detach`) nobody can tick the checklist off
+    let ticked = !checklist.is_empty() && io::stdin().is_terminal();
+    if ticked && !tick_off_checklist(checklist)? {
+        return Ok(false);
+    }
+    let accessible = accessible_mode(cli, &config);
+    let websites = if timer_only { websites.to_vec() } else { exclude_domains_in_use(websites, &config)? };
+    let intent = if config.intent_prompt.unwrap_or(false) && io::stdin().is_terminal() {
+        ask_intent()?
+    } else {
+        None
+    };
+
+    for website in websites.iter().filter(|_| !timer_only) {
+        let website = website.trim();
+        if !website.is_empty() {
+            println!("Blocking website: {}", website);
+        }
+    }
+
+    // Register our claim and let the reconciler update the hosts file
+    let source = session_source("cli");
+    let session_id = ulid::new();
+    if !timer_only {
+        hosts::claim(&source, priority, Some(task_name), Some(&session_id), lists, &websites, duration)?;
+    }
+    let recorded = history::record(&session_id, task_name, lists, priority, duration, intent.as_deref(), enforcement);
+    let history_id = match recorded {
+        Ok(history_id) => history_id,
+        Err(error) => {
+            // Without a session nothing would ever release the claim
+            if !timer_only {
+                let _ = hosts::release(&source);
+            }
+            return Err(error);
+        }
+    };
+    if ticked && let Err(error) = history::tick_off(history_id, checklist) {
+        eprintln!("Could not record the checklist: {}", error);
+    }
+
+    // The session ends at a fixed wall-clock time
+    let start_time = Local::now();
+    let mut end_time = start_time + chrono::Duration::from_std(duration)?;
+    let claimed = (!timer_only).then_some(source.as_str());
+    let mut suspend_watch = suspend::SuspendWatch::new(config.suspend_behavior.unwrap_or_default(), claimed);
+    let mut battery_watch = battery::BatteryWatch::new(config.battery.clone(), claimed);
+
+    // Publish the session on the IPC socket
+    let session = ipc::SessionHandle::new(false);
+    session.set_session(Some(task_name.to_string()), priority, start_time, end_time);
+    session.set_session_id(&session_id);
+    session.set_lists(lists);
+    if cli.strict {
+        session.set_strict();
+    }
+    let _ipc_server = ipc::serve(session.clone());
+    let _host_socket = ipc::register(session.clone());
+    let _new_tab = _ipc_server.as_ref().and_then(|_| newtab::keep_updated(session.clone()));
+    let _block_page = if timer_only {
+        None
+    } else {
+        blockpage::serve(session.clone()).unwrap_or_else(|e| {
+            println!("{}", e);
+            None
+        })
+    };
+
+    println!(
+        "{} for {} for task: {} (until {})",
+        match enforcement {
+            Enforcement::Blocked => "Blocking websites",
+            Enforcement::TimerOnly => "Timer running",
+            Enforcement::Unenforced => "Timer running (blocking disabled)",
+        },
+        duration_text,
+        task_name,
+        time_of_day(&end_time)
+    );
+    let locked = cli.strict && lock_shortcuts(&config);
+
+    // Plain line-based output when we can't drive the terminal
+    if accessible || !is_interactive_terminal() {
+        let result = run_line_mode_timer(
+            duration,
+            end_time,
+            &session,
+            &mut suspend_watch,
+            &mut battery_watch,
+            accessible,
+            timer_only,
+        );
+
+        let ended_by = *result.as_ref().unwrap_or(&EndReason::Stopped);
+        let retained = if timer_only { Vec::new() } else { hosts::release(&source)? };
+        history::finish(history_id, ended_by)?;
+        if locked {
+            unlock_shortcuts();
+        }
+        let ended_by = result?;
+        if !timer_only {
+            println!("Blocking removed!");
+        }
+        if !retained.is_empty() {
+            println!(
+                "{} domains stay blocked by a higher-priority session.",
+                retained.len()
+            );
+        }
+        reflect_on_intent(history_id, intent.as_deref())?;
+        note_session(history_id);
+        return Ok(ended_by == EndReason::Expired);
+    }
+
+    // Terminal output
+    if cli.strict {
+        println!("[+] extend by {} min  (strict: no stopping or pausing)", CLI_EXTEND_STEP.as_secs() / 60);
+    } else {
+        println!("[p] pause/resume  [+] extend by {} min  [q] stop", CLI_EXTEND_STEP.as_secs() / 60);
+    }
+
+    // Start timer
+    enable_raw_mode()?;
+    let mut line = live::LiveLine::default();
+    let show_bar = config.progress_bar.unwrap_or(true);
+
+    // Errors end the loop but not before the terminal and the block are cleaned up
+    let result = (|| -> Result<EndReason> {
+        let mut total = duration;
+        let mut paused_at: Option<Instant> = None;
+        let mut battery_note: Option<String> = None;
+        let mut last_hosts_refresh = Instant::now();
+
+        while paused_at.is_some() || Local::now() < end_time {
+            // Keys: stop early, pause and extend
+            let mut resumed = false;
+            if crossterm::event::poll(Duration::from_millis(100))?
+                && let Event::Key(key) = crossterm::event::read()?
+            {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('p') | KeyCode::Char(' ') if cli.strict => {}
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        return Ok(EndReason::Stopped);
+                    }
+                    KeyCode::Char('p') | KeyCode::Char(' ') => match paused_at {
+                        Some(_) => resumed = true,
+                        None => paused_at = Some(Instant::now()),
+                    },
+                    KeyCode::Char('+') | KeyCode::Char('e') => {
+                        end_time += chrono::Duration::from_std(CLI_EXTEND_STEP)?;
+                        total += CLI_EXTEND_STEP;
+                        if !timer_only {
+                            hosts::extend(&source, CLI_EXTEND_STEP)?;
+                        }
+                        session.extend(CLI_EXTEND_STEP);
+                    }
+                    _ => {}
+                }
+            }
+
+            // The block stays while paused; the session end and the claim move
+            // along in steps, so the claim doesn't expire during a long pause
+            if let Some(at) = paused_at
+                && (resumed || at.elapsed() >= CLI_PAUSE_STEP)
+            {
+                let paused = at.elapsed();
+                end_time += chrono::Duration::from_std(paused)?;
+                total += paused;
+                if !timer_only {
+                    hosts::extend(&source, paused)?;
+                }
+                session.extend(paused);
+                paused_at = (!resumed).then(Instant::now);
+            }
+
+            // Stop requested over IPC
+            if session.take_stop_request() {
+                return Ok(EndReason::StoppedRemotely);
+            }
+
+            // Pause the session if the machine was suspended
+            suspend_watch.poll(&mut end_time, &session)?;
+            if !timer_only {
+                refresh_hosts_periodically(&mut last_hosts_refresh)?;
+            }
+
+            match battery_watch.poll(&mut end_time, &session)? {
+                Some(battery::BatteryEvent::Low(percent)) => battery_note = Some(format!("battery at {}%", percent)),
+                Some(battery::BatteryEvent::Paused(percent)) => {
+                    battery_note = Some(format!("paused, battery at {}%", percent));
+                }
+                Some(battery::BatteryEvent::Resumed(paused)) => {
+                    total += paused;
+                    battery_note = None;
+                }
+                None => {}
+            }
+
+            // While paused, the remaining time stands still
+            let remaining = match paused_at {
+                Some(at) => time_until(end_time) + at.elapsed(),
+                None => time_until(end_time),
+            };
+            let mut text = format!("{} remaining", format::clock(remaining));
+            if show_bar {
+                text = format!("{} {}", live::progress_bar(total.saturating_sub(remaining), total), text);
+            }
+            text = format!("{} | {}", text, task_name);
+            if paused_at.is_some() {
+                text.push_str(" | paused");
+            }
+            if enforcement == Enforcement::Unenforced {
+                text.push_str(" | blocking disabled (no permissions)");
+            }
+            if let Some(note) = &battery_note {
+                text = format!("{} | {}", text, note);
+            }
+            line.draw(&text)?;
+        }
+        Ok(EndReason::Expired)
+    })();
+
+    let finished = line.finish();
+    disable_raw_mode()?;
+
+    // Remove blocking after timer expires, or after the error
+    let ended_by = *result.as_ref().unwrap_or(&EndReason::Stopped);
+    let retained = if timer_only { Vec::new() } else { hosts::release(&source)? };
+    history::finish(history_id, ended_by)?;
+    if locked {
+        unlock_shortcuts();
+    }
+    let ended_by = result?;
+    finished?;
+
+    if timer_only {
+        println!("\nSession finished! ✅");
+    } else {
+        println!("\nBlocking removed! ✅");
+    }
+    if !retained.is_empty() {
+        println!(
+            "{} domains stay blocked by a higher-priority session.",
+            retained.len()
+        );
+    }
+    reflect_on_intent(history_id, intent.as_deref())?;
+    note_session(history_id);
+
+    Ok(ended_by == EndReason::Expired)
+}
+
+/// Start the session given by the flags of `cli`; `false` if they don't
+/// describe one
+pub fn run_command(cli: &Cli) -> Result<bool> {
+    if cli.detach && (cli.start || cli.preset.is_some() || (cli.duration.is_some() && cli.session.task.is_some())) {
+        warn_bare_flags(cli);
+        attach::detach_session(cli.session.timer_only)?;
+    } else if let Some(preset_name) = &cli.preset {
+        warn_bare_flags(cli);
+        run_preset_session(cli, preset_name)?;
+    } else if let Some(preset_name) = project_preset(cli)? {
+        run_preset_session(cli, &preset_name)?;
+    } else if let Some((duration_text, task)) = session_flags(cli)? {
+        warn_bare_flags(cli);
+        let duration = Duration::from_millis(parse_duration(&duration_text)?);
+
+        let config = load_config()?;
+        let timer_only = cli.session.timer_only;
+        let lists = if timer_only { Vec::new() } else { session_lists(cli, &cli.session.lists, &config) };
+        let websites = if timer_only { Vec::new() } else { collect_websites(&config, &lists)? };
+
+        if websites.is_empty() && !timer_only {
+            explain_no_websites(&config);
+            return Ok(true);
+        }
+        safety_list(cli, &config).check(&websites)?;
+
+        let timer = Timer {
+            websites: &websites,
+            duration,
+            task: &task,
+            duration_text: &duration_text,
+            priority: cli.session.priority(),
+            lists: &lists,
+            checklist: &[],
+        };
+        if run(&timer, cli)? {
+            finish_session(config.end_behavior.unwrap_or_default())?;
+        }
+    } else {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Take over the shortcuts of `[lockout]` for a strict session; `true` if
+/// they have to be given back at the end
+fn lock_shortcuts(config: &Config) -> bool {
+    let Some(lockout) = config.lockout.as_ref().filter(|lockout| !lockout.shortcuts.is_empty()) else {
+        return false;
+    };
+    match lockout::engage(lockout) {
+        Ok(note) => {
+            println!("{}", note);
+            true
+        }
+        Err(error) => {
+            eprintln!("Could not lock shortcuts: {}", error);
+            // Whatever was changed before the error goes back at the end
+            true
+        }
+    }
+}
+
+/// Give back the shortcuts taken by `lock_shortcuts`; failures only print a warning
+pub fn unlock_shortcuts() {
+    if let Err(error) = lockout::release() {
+        eprintln!("Could not restore shortcuts: {}", error);
+    }
+}
+
+/// Append a session of the CLI to the daily note; failures only print a warning
+fn note_session(history_id: u64) {
+    if let Err(error) = append_daily_note(history_id) {
+        eprintln!("Could not update the daily note: {}", error);
+    }
+}
+
+/// Before the first block of new lists, show what it breaks and ask to go
+/// ahead; `false` if declined
+fn approve_new_lists(lists: &[String], config: &Config, cli: &Cli) -> Result<bool> {
+    if !config.soft_launch.unwrap_or(true) || !io::stdin().is_terminal() {
+        return Ok(true);
+    }
+    let pending = approval::pending(config.website_lists.as_deref().unwrap_or_default(), lists)?;
+    if pending.is_empty() {
+        return Ok(true);
+    }
+
+    let impact = list_impact(&pending, config, &safety_list(cli, config));
+    for line in impact.lines() {
+        println!("{}", line);
+    }
+    print!("Block {} from now on? (y/n): ", if pending.len() == 1 { "this list" } else { "these lists" });
+    io::Write::flush(&mut stdout())?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        println!("Session not started.");
+        return Ok(false);
+    }
+    approval::approve(&impact.lists)?;
+    Ok(true)
+}
+
+/// Warn about domains in use and offer to leave them out of the session
+fn exclude_domains_in_use(websites: &[String], config: &Config) -> Result<Vec<String>> {
+    let mut websites = websites.to_vec();
+    for in_use in domains_in_use(&websites, config) {
+        println!("{} has {} open connection(s) right now.", in_use.domain, in_use.connections);
+        if !io::stdin().is_terminal() {
+            println!("It is blocked anyway; open connections may break.");
+            continue;
+        }
+        print!("Leave it out of this session? (y/n): ");
+        io::Write::flush(&mut stdout())?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            websites.retain(|website| hosts::expand_website(website).first() != Some(&in_use.domain));
+        }
+    }
+    Ok(websites)
+}
+
+/// Tick off the checklist of a preset item by item; `false` if the session
+/// should not start
+fn tick_off_checklist(checklist: &[String]) -> Result<bool> {
+    println!("Before the block starts:");
+    for item in checklist {
+        loop {
+            print!("  [ ] {} (y when done, q to cancel): ", item);
+            io::Write::flush(&mut stdout())?;
+
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer)? == 0 {
+                return Ok(false);
+            }
+            match answer.trim().to_lowercase().as_str() {
+                "y" | "yes" => break,
+                "q" | "n" | "no" => {
+                    println!("Session not started.");
+                    return Ok(false);
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Ask what success looks like for the session; `None` if skipped
+fn ask_intent() -> Result<Option<String>> {
+    print!("What will success look like? (Enter to skip): ");
+    io::Write::flush(&mut stdout())?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok((!answer.is_empty()).then(|| answer.to_string()))
+}
+
+/// Show the intent of a finished session and record whether it was achieved
+fn reflect_on_intent(history_id: u64, intent: Option<&str>) -> Result<()> {
+    let Some(intent) = intent else {
+        return Ok(());
+    };
+
+    println!("Your intent: {}", intent);
+    loop {
+        print!("Achieved? [y]es / [p]artial / [n]o (Enter to skip): ");
+        io::Write::flush(&mut stdout())?;
+
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 || answer.trim().is_empty() {
+            return Ok(());
+        }
+        if let Some(achieved) = history::Achievement::from_answer(&answer) {
+            return history::reflect(history_id, achieved);
+        }
+    }
+}
+
+/// Run a CLI session from a preset, following chained presets
+fn run_preset_session(cli: &Cli, name: &str) -> Result<()> {
+    let config = load_config()?;
+    let safety = safety_list(cli, &config);
+    let presets = config.presets.clone().unwrap_or_default();
+    let mut preset = find_preset(&presets, name)?;
+    let mut first_session = true;
+
+    loop {
+        // Command line flags override the values of the selected preset
+        let overrides = |value: &Option<String>| value.clone().filter(|_| first_session);
+        let duration_str = overrides(&cli.duration).unwrap_or_else(|| preset.duration.clone());
+        let task = overrides(&cli.session.task)
+            .or_else(|| preset.task.clone())
+            .unwrap_or_else(|| preset.name.clone());
+        let duration = Duration::from_millis(parse_duration(&duration_str)?);
+
+        let timer_only = cli.session.timer_only || preset.timer_only;
+        let websites = if timer_only {
+            Vec::new()
+        } else {
+            collect_websites(&config, &preset.lists)?
+        };
+        if websites.is_empty() && !timer_only {
+            println!("Preset '{}' has no websites to block.", preset.name);
+            return Ok(());
+        }
+        safety.check(&websites)?;
+
+        let timer = Timer {
+            websites: &websites,
+            duration,
+            task: &task,
+            duration_text: &duration_str,
+            priority: cli.session.priority(),
+            lists: &preset.lists,
+            checklist: &preset.checklist,
+        };
+        if !run(&timer, cli)? {
+            return Ok(());
+        }
+
+        match (preset.end_behavior, &preset.next) {
+            (EndBehavior::Chain, Some(next)) => {
+                preset = find_preset(&presets, next)?;
+                first_session = false;
+                println!("Starting next session: {}", preset.name);
+            }
+            (behavior, _) => return finish_session(behavior),
+        }
+    }
+}
+
+/// Length and task of a session started with flags; `start` fills in the defaults
+fn session_flags(cli: &Cli) -> Result<Option<(String, String)>> {
+    if !cli.start {
+        return Ok(cli.duration.clone().zip(cli.session.task.clone()));
+    }
+    let duration = match &cli.duration {
+        Some(duration) => duration.clone(),
+        None => load_config()?.default_duration.unwrap_or_else(|| DEFAULT_DURATION.to_string()),
+    };
+    let task = cli.session.task.clone().unwrap_or_else(|| "Focus session".to_string());
+    Ok(Some((duration, task)))
+}
+
+/// Point users of the bare session flags to `start`
+fn warn_bare_flags(cli: &Cli) {
+    if !cli.start {
+        eprintln!("Note: starting sessions with the bare flags is deprecated; use 'timeguardian start' with the same options.");
+    }
+}
+
+/// Name of the preset bound to the current directory, unless lists were
+/// chosen with `-l`
+fn project_preset(cli: &Cli) -> Result<Option<String>> {
+    if !cli.session.lists.is_empty() {
+        return Ok(None);
+    }
+    let presets = load_config()?.presets.unwrap_or_default();
+    let Some((preset, source)) = preset::for_directory(&presets, &env::current_dir()?)? else {
+        return Ok(None);
+    };
+    println!("Using preset '{}' for this project ({})", preset.name, source.display());
+    Ok(Some(preset.name.clone()))
+}
+
+/// Repeat a session from the history (the most recent one by default)
+pub fn run_again(cli: &Cli, from: Option<u64>) -> Result<()> {
+    let history = history::History::load()?;
+    let record = match from {
+        Some(id) => history.find(id)?,
+        None => match history.last() {
+            Some(record) => record,
+            None => {
+                println!("No previous session to repeat.");
+                return Ok(());
+            }
+        },
+    };
+
+    let config = load_config()?;
+    let timer_only = record.enforcement == Enforcement::TimerOnly;
+    let websites = if timer_only { Vec::new() } else { collect_websites(&config, &record.lists)? };
+    if websites.is_empty() && !timer_only {
+        println!("The session has no websites to block.");
+        return Ok(());
+    }
+    safety_list(cli, &config).check(&websites)?;
+
+    println!("Repeating session #{}: {}", record.id, record.task);
+    let duration_text = record.duration_text();
+    let timer = Timer {
+        websites: &websites,
+        duration: record.duration(),
+        task: &record.task,
+        duration_text: &duration_text,
+        priority: cli.session.priority().max(record.priority),
+        lists: &record.lists,
+        checklist: &[],
+    };
+    if run(&timer, cli)? {
+        finish_session(config.end_behavior.unwrap_or_default())?;
+    }
+
+    Ok(())
+}
+
+/// Apply the end-of-session behavior after the block was removed
+fn finish_session(behavior: EndBehavior) -> Result<()> {
+    match behavior {
+        EndBehavior::Unblock => {}
+        EndBehavior::Notify => {
+            preset::notify_until_acknowledged("Focus session finished!", is_interactive_terminal())?;
+        }
+        EndBehavior::LockScreen => {
+            if let Err(e) = preset::lock_screen() {
+                println!("{}", e);
+            }
+        }
+        EndBehavior::Chain => {
+            println!("No next session configured to chain into.");
+        }
+    }
+
+    Ok(())
+}
+
+/// Simple countdown for redirected output, dumb terminals and screen readers.
+///
+/// Prints the progress and remaining time once a minute; in accessible mode
+/// the remaining time is spoken in words every five minutes and once more
+/// shortly before the end. Returns how the session ended: `Expired` when it ran
+/// its course, `StoppedRemotely` when it was stopped over IPC.
+fn run_line_mode_timer(
+    duration: Duration,
+    mut end_time: DateTime<Local>,
+    session: &ipc::SessionHandle,
+    suspend_watch: &mut suspend::SuspendWatch,
+    battery_watch: &mut battery::BatteryWatch,
+    accessible: bool,
+    timer_only: bool,
+) -> Result<EndReason> {
+    let report_interval = Duration::from_secs(if accessible { 300 } else { 60 });
+    let mut next_report = Duration::ZERO;
+    let mut final_minute_reported = false;
+    let mut last_hosts_refresh = Instant::now();
+
+    while Local::now() < end_time {
+        if session.take_stop_request() {
+            println!("Session stopped");
+            return Ok(EndReason::StoppedRemotely);
+        }
+
+        if !timer_only {
+            refresh_hosts_periodically(&mut last_hosts_refresh)?;
+        }
+
+        if let Some(suspended) = suspend_watch.poll(&mut end_time, session)? {
+            let effect = if suspend_watch.pauses() { "paused" } else { "counted" };
+            println!(
+                "System was suspended for {}; the time was {}, the session ends at {}",
+                format::spoken(suspended),
+                effect,
+                time_of_day(&end_time)
+            );
+        }
+
+        match battery_watch.poll(&mut end_time, session)? {
+            Some(battery::BatteryEvent::Low(percent)) => {
+                println!("Battery at {}%; plug in to keep the session going", percent);
+            }
+            Some(battery::BatteryEvent::Paused(percent)) => {
+                println!("Battery at {}%; the session is paused until the machine is charging", percent);
+            }
+            Some(battery::BatteryEvent::Resumed(paused)) => {
+                println!(
+                    "Power is back after {}; the session ends at {}",
+                    format::spoken(paused),
+                    time_of_day(&end_time)
+                );
+            }
+            None => {}
+        }
+
+        let remaining = time_until(end_time);
+        let elapsed = duration.saturating_sub(remaining);
+        if accessible {
+            let final_minute = remaining <= Duration::from_secs(60) && duration > Duration::from_secs(120);
+            if elapsed >= next_report || (final_minute && !final_minute_reported) {
+                // Round up so a fresh session doesn't announce a minute less
+                let remaining = Duration::from_secs(remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0));
+                println!("{} remaining", format::spoken(remaining));
+                next_report += report_interval;
+                final_minute_reported |= final_minute;
+            }
+        } else if elapsed >= next_report {
+            let percent = (elapsed.as_secs_f64() / duration.as_secs_f64() * 100.0) as u64;
+            println!("[{:>3}%] Remaining time: {}", percent, format::clock(remaining));
+            next_report += report_interval;
+        }
+
+        std::thread::sleep(Duration::from_millis(250).min(time_until(end_time)));
+    }
+
+    println!("{}Session finished", if accessible { "" } else { "[100%] " });
+    Ok(EndReason::Expired)
+}
+
+/// Time left until the given wall-clock time (zero if it has passed)
+fn time_until(end_time: DateTime<Local>) -> Duration {
+    (end_time - Local::now()).to_std().unwrap_or_default()
+}
@@ -0,0 +1,89 @@
+/*
+* TimeGuardian TUI Action Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module separates what the TUI does from the keys that trigger it. An
+* action is a single step such as "select the Work list" or "start the
+* session"; macros bind a sequence of steps to one key in the configuration:
+*
+*   [[macros]]
+*   name = "usual day"
+*   key = "F5"
+*   steps = ["profile Work", "list Work", "duration 50m", "task Deep work", "start"]
+*/
+
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::parse_duration;
+
+/// A single step the TUI can perform
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Activate a profile by name
+    Profile(String),
+    /// Select a website list by name
+    SelectList(String),
+    /// Set the length of the next session
+    Duration(Duration),
+    /// Set the task of the next session
+    Task(String),
+    /// Switch to the tab with this title
+    Tab(String),
+    /// Start a session with the selected list
+    Start,
+}
+
+impl Action {
+    /// Parse a step like "list Work" or "duration 50m"
+    pub fn parse(step: &str) -> Result<Self> {
+        let (verb, argument) = step.trim().split_once(' ').unwrap_or((step.trim(), ""));
+        let argument = argument.trim();
+        let action = match verb {
+            "profile" => Self::Profile(argument.to_string()),
+            "list" => Self::SelectList(argument.to_string()),
+            "duration" => Self::Duration(Duration::from_millis(parse_duration(argument)?)),
+            "task" => Self::Task(argument.to_string()),
+            "tab" => Self::Tab(argument.to_string()),
+            "start" => return Ok(Self::Start),
+            other => return Err(eyre!("Unknown step '{}'", other)),
+        };
+        if argument.is_empty() {
+            return Err(eyre!("Step '{}' needs an argument", verb));
+        }
+        Ok(action)
+    }
+}
+
+/// A sequence of actions bound to a key
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Macro {
+    /// Name shown when the macro runs
+    pub name: String,
+    /// Key triggering the macro: a single character ("M") or a function key ("F5")
+    pub key: String,
+    /// Steps of the macro, in order
+    pub steps: Vec<String>,
+}
+
+impl Macro {
+    /// Whether `key` triggers the macro
+    pub fn matches(&self, key: KeyCode) -> bool {
+        let mut chars = self.key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => key == KeyCode::Char(c),
+            _ => self
+                .key
+                .strip_prefix('F')
+                .and_then(|number| number.parse().ok())
+                .is_some_and(|number| key == KeyCode::F(number)),
+        }
+    }
+
+    /// The actions of the macro; fails on the first invalid step
+    pub fn actions(&self) -> Result<Vec<Action>> {
+        self.steps.iter().map(|step| Action::parse(step)).collect()
+    }
+}
@@ -6,34 +6,111 @@
 * It manages website lists, blocking sessions, and user interactions.
 */
 
+use chrono::{DateTime, Local};
 use color_eyre::Result;
-use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 use tui_input::Input;
+use timeguardian::{format, Countdown};
 
+pub use timeguardian::WebsiteList;
+
+use crate::blocker::{self, Blocker};
+use crate::blockpage::BlockPage;
+use crate::history::Achievement;
+use crate::hosts::{BlockClaim, Priority};
+use crate::idn;
+use crate::ipc::{SessionHandle, SessionStatus};
+use crate::preset::EndBehavior;
+use crate::profile::Profile;
+use crate::safety::SafetyList;
 use crate::tui::{
-    ui::{TabsState, TimeUnit},
+    action::Macro,
+    custom::CustomTab,
+    dialog::Dialog,
+    docs::DocsState,
+    paste::{self, PastePreview},
+    theme::Theme,
+    ui::TabsState,
 };
 
+/// Number of built-in tabs preceding the custom tabs
+pub const BUILTIN_TABS: usize = 2;
+
 /// Result type for app operations
+#[allow(dead_code)]
 pub type AppResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
-/// Website list structure 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct WebsiteList {
-    pub name: String,
-    pub websites: Vec<String>,
-}
-
 /// Application mode enum for the UI state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TuiMode {
     /// Normal mode for navigation
     Normal,
-    /// Editing mode for text input
-    Editing,
+    /// A modal dialog is open
+    Dialog,
     /// Help screen mode
     Help,
+    /// Command line opened with ':'
+    Command,
+    /// User guide viewer
+    Docs,
+    /// Prompt for the intent of a new session
+    Intent,
+    /// Summary of the session that just ended
+    Summary,
+    /// Prompt for the reason to skip a scheduled block
+    SkipReason,
+    /// Capabilities of the blocking backend
+    Backend,
+    /// Sessions and lists blocking each domain
+    Blocked,
+    /// Text area for pasting many domains into the selected list
+    Paste,
+}
+
+/// What a dialog of the TUI is for, acted on when it is submitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogPurpose {
+    /// Name of a new website list
+    AddList,
+    /// Domain to add to the selected list
+    AddWebsite,
+    /// Confirmation before deleting the selected list
+    DeleteList,
+    /// Priority, end behavior and enabled lists of the next session
+    SessionSettings,
+    /// Approval of the first block of the selected list, then a session of this length
+    ApproveList(Duration),
+}
+
+/// A scheduled block that is about to start
+#[derive(Debug, Clone)]
+pub struct ScheduleWarning {
+    /// Name of the schedule
+    pub schedule: String,
+    /// When blocking starts
+    pub starts_at: DateTime<Local>,
+}
+
+/// What happened in a session, shown when it ends
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    /// History id of the session
+    pub history_id: Option<u64>,
+    pub task: String,
+    /// Name of the blocked list
+    pub list: Option<String>,
+    /// Planned length of the session
+    pub planned: Duration,
+    /// Time actually spent in the session
+    pub focused: Duration,
+    /// Whether the session ran until the end
+    pub completed: bool,
+    /// What success should look like, if an intent was entered
+    pub intent: Option<String>,
+    /// Answer to the rating prompt
+    pub achieved: Option<Achievement>,
+    /// Name and start of the next scheduled block
+    pub next_block: Option<(String, DateTime<Local>)>,
 }
 
 /// Main application state structure
@@ -72,16 +149,106 @@ pub struct App {
     pub is_blocking: bool,
     
     /// Time when the current blocking session ends
-    pub blocking_end_time: Option<Instant>,
+    pub blocking_end_time: Option<DateTime<Local>>,
+    
+    /// Length of the running blocking session (used for progress display)
+    pub session_duration: Duration,
+    
+    /// Length of the next session, picked in the timer tab
+    pub countdown: Countdown,
+    
+    /// Priority of the next blocking session
+    pub priority: Priority,
+    
+    /// What happens when a blocking session ends
+    pub end_behavior: EndBehavior,
+    
+    /// Time of the last end-of-session alarm while it is unacknowledged
+    pub alarm_since: Option<Instant>,
+    
+    /// Task of the running session (set when started over IPC)
+    pub session_task: Option<String>,
+    
+    /// Session state published on the IPC socket
+    pub session_handle: SessionHandle,
+    
+    /// Name of the active profile
+    pub active_profile: Option<String>,
+    
+    /// The profile was chosen by hand and does not follow the network
+    pub profile_pinned: bool,
+    
+    /// Action sequences bound to keys
+    pub macros: Vec<Macro>,
+    
+    /// History id of the running session
+    pub history_id: Option<u64>,
+    
+    /// Server of the block page while the session blocks
+    pub block_page: Option<BlockPage>,
+    
+    /// Screen-reader-friendly status bar
+    pub accessible: bool,
+    
+    /// User-defined tabs following the built-in ones
+    pub custom_tabs: Vec<CustomTab>,
+    
+    /// All active sessions in the hosts file, including other processes
+    pub sessions: Vec<BlockClaim>,
+    
+    /// Hosts claim source of this TUI's own session
+    pub claim_source: String,
     
-    /// Duration of the current blocking session
-    pub block_duration_ms: u64,
+    /// Position in the user guide
+    pub docs: DocsState,
     
-    /// Time unit for the timer tab
-    pub time_unit: TimeUnit,
+    /// Ask for an intent when a session starts
+    pub intent_prompt: bool,
     
-    /// Time value for the timer tab
-    pub time_value: u64,
+    /// Intent of the running session
+    pub intent: Option<String>,
+    
+    /// Summary of the session that just ended
+    pub summary: Option<SessionSummary>,
+    
+    /// End of the running break
+    pub break_end: Option<DateTime<Local>>,
+    
+    /// Scheduled block starting within the next minute
+    pub schedule_warning: Option<ScheduleWarning>,
+    
+    /// Colors and symbols of the interface
+    pub theme: Theme,
+    
+    /// Domains that must not be added to lists or blocked
+    pub safety: SafetyList,
+    
+    /// Backend blocking the websites
+    pub blocker: Box<dyn Blocker>,
+    
+    /// The hosts file is not writable; sessions only run the timer
+    pub blocking_disabled: bool,
+    
+    /// Domains in use the user was warned about; starting again blocks them anyway
+    pub in_use_warned: Vec<String>,
+    
+    /// Session of the CLI process serving the IPC socket, if one is running
+    pub cli_session: Option<SessionStatus>,
+    
+    /// The configuration is declarative (`--config`) and lists can't be edited
+    pub read_only: bool,
+    
+    /// Text pasted for the bulk import
+    pub paste: String,
+    
+    /// The open dialog and what it is for
+    pub dialog: Option<(DialogPurpose, Dialog)>,
+    
+    /// Why each domain is blocked, shown with `:blocked`
+    pub provenance: Vec<String>,
+    
+    /// First line of `provenance` in view
+    pub provenance_scroll: usize,
 }
 
 impl App {
@@ -100,12 +267,48 @@ impl App {
             website_state: ratatui::widgets::ListState::default(),
             is_blocking: false,
             blocking_end_time: None,
-            block_duration_ms: 25 * 60 * 1000, // Default: 25 minutes
-            time_unit: TimeUnit::Minutes,
-            time_value: 25,
+            session_duration: Duration::ZERO,
+            countdown: Countdown::default(),
+            priority: Priority::Normal,
+            end_behavior: EndBehavior::Unblock,
+            alarm_since: None,
+            session_task: None,
+            session_handle: SessionHandle::new(true),
+            active_profile: None,
+            profile_pinned: false,
+            macros: Vec::new(),
+            history_id: None,
+            block_page: None,
+            accessible: false,
+            custom_tabs: Vec::new(),
+            sessions: Vec::new(),
+            claim_source: String::new(),
+            docs: DocsState::default(),
+            intent_prompt: false,
+            intent: None,
+            summary: None,
+            break_end: None,
+            schedule_warning: None,
+            theme: Theme::default(),
+            safety: SafetyList::default(),
+            blocker: blocker::active(),
+            blocking_disabled: false,
+            in_use_warned: Vec::new(),
+            cli_session: None,
+            read_only: false,
+            paste: String::new(),
+            dialog: None,
+            provenance: Vec::new(),
+            provenance_scroll: 0,
         }
     }
     
+    /// Open a modal dialog
+    pub fn open_dialog(&mut self, purpose: DialogPurpose, dialog: Dialog) {
+        self.dialog = Some((purpose, dialog));
+        self.mode = TuiMode::Dialog;
+    }
+    
     /// Initialize the application
     pub fn init(&mut self) -> Result<()> {
         self.status_message = "Welcome to TimeGuardian! Press '?' for help.".to_string();
@@ -114,67 +317,74 @@ impl App {
     
     /// Get the websites from the currently selected list
     pub fn current_websites(&self) -> Vec<String> {
-        if let Some(index) = self.selected_list_index {
-            if index < self.website_lists.len() {
-                return self.website_lists[index].websites.clone();
-            }
+        if let Some(index) = self.selected_list_index
+            && index < self.website_lists.len()
+        {
+            return self.website_lists[index].websites.clone();
         }
         Vec::new()
     }
     
     /// Get the currently selected website list 
     pub fn current_website_list(&self) -> Option<&WebsiteList> {
-        if let Some(index) = self.selected_list_index {
-            if index < self.website_lists.len() {
-                return Some(&self.website_lists[index]);
-            }
+        if let Some(index) = self.selected_list_index
+            && index < self.website_lists.len()
+        {
+            return Some(&self.website_lists[index]);
         }
         None
     }
     
     /// Add a new website to the selected list
     pub fn add_website(&mut self, website: String) {
-        if let Some(index) = self.selected_list_index {
-            if index < self.website_lists.len() {
-                let cleaned_website = website.trim().to_string();
-                if !cleaned_website.is_empty() {
-                    let list = &mut self.website_lists[index];
+        if let Some(index) = self.selected_list_index
+            && index < self.website_lists.len()
+        {
+            let cleaned_website = website.trim().to_string();
+            if !cleaned_website.is_empty() {
+                let list = &mut self.website_lists[index];
+                
+                // Skip if already exists, also in the other form of an IDN
+                let key = idn::key(&cleaned_website);
+                if !list.websites.iter().any(|website| idn::key(website) == key) {
+                    list.websites.push(cleaned_website);
                     
-                    // Skip if already exists
-                    if !list.websites.contains(&cleaned_website) {
-                        list.websites.push(cleaned_website);
-                        
-                        // Auto select the new website
-                        let new_index = list.websites.len() - 1;
-                        self.website_state.select(Some(new_index));
-                        self.selected_website_index = Some(new_index);
-                    }
+                    // Auto select the new website
+                    let new_index = list.websites.len() - 1;
+                    self.website_state.select(Some(new_index));
+                    self.selected_website_index = Some(new_index);
                 }
             }
         }
     }
     
+    /// What importing the pasted text into the selected list would add
+    pub fn paste_preview(&self) -> PastePreview {
+        let existing = self.current_website_list().map(|list| list.websites.as_slice()).unwrap_or_default();
+        paste::preview(&self.paste, existing, &self.safety)
+    }
+    
     /// Delete the selected website
     pub fn delete_website(&mut self) {
-        if let (Some(list_index), Some(website_index)) = (self.selected_list_index, self.selected_website_index) {
-            if list_index < self.website_lists.len() {
-                let list = &mut self.website_lists[list_index];
-                if website_index < list.websites.len() {
-                    list.websites.remove(website_index);
-                    
-                    // Update selection
-                    if list.websites.is_empty() {
-                        self.website_state.select(None);
-                        self.selected_website_index = None;
+        if let (Some(list_index), Some(website_index)) = (self.selected_list_index, self.selected_website_index)
+            && list_index < self.website_lists.len()
+        {
+            let list = &mut self.website_lists[list_index];
+            if website_index < list.websites.len() {
+                list.websites.remove(website_index);
+                
+                // Update selection
+                if list.websites.is_empty() {
+                    self.website_state.select(None);
+                    self.selected_website_index = None;
+                } else {
+                    let new_index = if website_index >= list.websites.len() {
+                        list.websites.len() - 1
                     } else {
-                        let new_index = if website_index >= list.websites.len() {
-                            list.websites.len() - 1
-                        } else {
-                            website_index
-                        };
-                        self.website_state.select(Some(new_index));
-                        self.selected_website_index = Some(new_index);
-                    }
+                        website_index
+                    };
+                    self.website_state.select(Some(new_index));
+                    self.selected_website_index = Some(new_index);
                 }
             }
         }
@@ -189,6 +399,8 @@ impl App {
                 self.website_lists.push(WebsiteList {
                     name: cleaned_name,
                     websites: Vec::new(),
+                    enabled: true,
+                    system: false,
                 });
                 
                 // Auto select the new list
@@ -203,134 +415,98 @@ impl App {
         }
     }
     
+    /// Enable or disable the selected website list; returns the new state
+    pub fn toggle_list(&mut self) -> Option<bool> {
+        let index = self.selected_list_index?;
+        let list = self.website_lists.get_mut(index)?;
+        list.enabled = !list.enabled;
+        Some(list.enabled)
+    }
+    
     /// Delete the selected website list
     pub fn delete_list(&mut self) {
-        if let Some(index) = self.selected_list_index {
-            if index < self.website_lists.len() {
-                self.website_lists.remove(index);
-                
-                // Update selection
-                if self.website_lists.is_empty() {
-                    self.website_list_state.select(None);
-                    self.selected_list_index = None;
+        if let Some(index) = self.selected_list_index
+            && index < self.website_lists.len()
+        {
+            self.website_lists.remove(index);
+            
+            // Update selection
+            if self.website_lists.is_empty() {
+                self.website_list_state.select(None);
+                self.selected_list_index = None;
+            } else {
+                let new_index = if index >= self.website_lists.len() {
+                    self.website_lists.len() - 1
                 } else {
-                    let new_index = if index >= self.website_lists.len() {
-                        self.website_lists.len() - 1
-                    } else {
-                        index
-                    };
-                    self.website_list_state.select(Some(new_index));
-                    self.selected_list_index = Some(new_index);
-                }
-                
-                // Clear website selection
-                self.website_state.select(None);
-                self.selected_website_index = None;
+                    index
+                };
+                self.website_list_state.select(Some(new_index));
+                self.selected_list_index = Some(new_index);
             }
+            
+            // Clear website selection
+            self.website_state.select(None);
+            self.selected_website_index = None;
         }
     }
     
-    /// Process a tick event
-    pub fn tick(&mut self) {
-        // Update any time-based state here
-    }
-    
-    /// Increase the blocking time value
-    pub fn increase_time(&mut self) {
-        match self.time_unit {
-            TimeUnit::Minutes => {
-                if self.time_value < 120 {
-                    self.time_value += 5;
-                }
-            }
-            TimeUnit::Hours => {
-                if self.time_value < 8 {
-                    self.time_value += 1;
-                }
-            }
-            TimeUnit::Seconds => {
-                if self.time_value < 55 {
-                    self.time_value += 5;
-                } else {
-                    self.time_value = 60;
-                }
-            }
+    /// Add user-defined tabs after the built-in ones
+    pub fn add_custom_tabs(&mut self, tabs: Vec<CustomTab>) {
+        for tab in tabs {
+            self.tabs.push(tab.title.clone());
+            self.custom_tabs.push(tab);
         }
-        self.update_blocking_duration();
     }
     
-    /// Decrease the blocking time value
-    pub fn decrease_time(&mut self) {
-        match self.time_unit {
-            TimeUnit::Minutes => {
-                if self.time_value > 5 {
-                    self.time_value -= 5;
-                } else {
-                    self.time_value = 1;
-                }
-            }
-            TimeUnit::Hours => {
-                if self.time_value > 1 {
-                    self.time_value -= 1;
-                }
-            }
-            TimeUnit::Seconds => {
-                if self.time_value > 10 {
-                    self.time_value -= 5;
-                } else {
-                    self.time_value = 5;
-                }
-            }
-        }
-        self.update_blocking_duration();
+    /// The user-defined tab currently shown, if any
+    pub fn current_custom_tab(&self) -> Option<&CustomTab> {
+        self.tabs
+            .index
+            .checked_sub(BUILTIN_TABS)
+            .and_then(|index| self.custom_tabs.get(index))
     }
     
-    /// Cycle through available time units
-    pub fn cycle_time_unit(&mut self) {
-        match self.time_unit {
-            TimeUnit::Minutes => {
-                self.time_unit = TimeUnit::Hours;
-                self.time_value = 1; // Default hour value
-            }
-            TimeUnit::Hours => {
-                self.time_unit = TimeUnit::Seconds;
-                self.time_value = 30; // Default seconds value
-            }
-            TimeUnit::Seconds => {
-                self.time_unit = TimeUnit::Minutes;
-                self.time_value = 25; // Default minute value
-            }
+    /// Activate a profile, selecting its first list when idle.
+    ///
+    /// Returns `true` if the active profile changed.
+    pub fn apply_profile(&mut self, profile: Option<&Profile>) -> bool {
+        let name = profile.map(|p| p.name.clone());
+        if name == self.active_profile {
+            return false;
         }
-        self.update_blocking_duration();
-    }
-    
-    /// Update the blocking duration based on the current time unit and value
-    fn update_blocking_duration(&mut self) {
-        match self.time_unit {
-            TimeUnit::Minutes => {
-                self.block_duration_ms = self.time_value * 60 * 1000;
-            }
-            TimeUnit::Hours => {
-                self.block_duration_ms = self.time_value * 60 * 60 * 1000;
-            }
-            TimeUnit::Seconds => {
-                self.block_duration_ms = self.time_value * 1000;
-            }
+        self.active_profile = name;
+        
+        if let Some(profile) = profile
+            && !self.is_blocking
+            && let Some(index) = self.website_lists.iter().position(|list| {
+                profile.lists.iter().any(|name| list.name.eq_ignore_ascii_case(name))
+            })
+        {
+            self.website_list_state.select(Some(index));
+            self.selected_list_index = Some(index);
+            self.website_state.select(None);
+            self.selected_website_index = None;
         }
+        true
     }
     
-    /// Get the blocking duration in milliseconds
-    pub fn get_blocking_milliseconds(&self) -> u64 {
-        self.block_duration_ms
+    /// Process a tick event
+    pub fn tick(&mut self) {
+        // Update any time-based state here
     }
     
     /// Start a blocking session
     pub fn start_blocking(&mut self, duration: Duration) -> Result<()> {
+        let start_time = Local::now();
+        let end_time = start_time + chrono::Duration::from_std(duration)?;
+        
         self.is_blocking = true;
-        self.blocking_end_time = Some(Instant::now() + duration);
+        self.blocking_end_time = Some(end_time);
+        self.session_duration = duration;
+        self.session_handle.set_session(self.session_task(), self.priority, start_time, end_time);
         self.status_message = format!(
             "Blocking websites for {:?}",
-            self.format_duration(duration)
+            format::human(duration)
         );
         Ok(())
     }
@@ -338,41 +514,40 @@ impl App {
     /// Stop the current blocking session
     pub fn stop_blocking(&mut self) -> Result<()> {
         self.is_blocking = false;
+        self.block_page = None;
         self.blocking_end_time = None;
+        self.session_task = None;
+        self.intent = None;
+        self.session_handle.clear_session();
         self.status_message = "Website blocking stopped".to_string();
         Ok(())
     }
     
-    /// Format a duration for display
-    pub fn format_duration(&self, duration: Duration) -> String {
-        let total_secs = duration.as_secs();
-        let hours = total_secs / 3600;
-        let minutes = (total_secs % 3600) / 60;
-        let seconds = total_secs % 60;
-        
-        if hours > 0 {
-            format!("{}h {:02}m {:02}s", hours, minutes, seconds)
-        } else if minutes > 0 {
-            format!("{}m {:02}s", minutes, seconds)
-        } else {
-            format!("{}s", seconds)
-        }
+    /// Show the summary of the session that just ended
+    pub fn show_summary(&mut self, summary: SessionSummary) {
+        self.summary = Some(summary);
+        self.mode = TuiMode::Summary;
+    }
+    
+    /// Task shown for the running session: the IPC task or the blocked list
+    pub fn session_task(&self) -> Option<String> {
+        self.session_task
+            .clone()
+            .or_else(|| self.current_website_list().map(|list| format!("Blocking {}", list.name)))
     }
     
     /// Get the remaining time in the current blocking session
     pub fn get_remaining_time(&self) -> Option<Duration> {
-        if self.is_blocking {
-            if let Some(end_time) = self.blocking_end_time {
-                let now = Instant::now();
-                if now < end_time {
-                    return Some(end_time - now);
-                }
-            }
+        if self.is_blocking
+            && let Some(end_time) = self.blocking_end_time
+        {
+            return (end_time - Local::now()).to_std().ok();
         }
         None
     }
     
     /// Save configuration to file (unused but kept for future functionality)
+    #[allow(dead_code)]
     pub fn save_configuration(&mut self) -> AppResult<()> {
         // Save configuration logic would go here
         Ok(())
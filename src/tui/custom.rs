@@ -0,0 +1,93 @@
+/*
+* TimeGuardian TUI Custom Tabs Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module defines user-defined tabs. A custom tab is declared in the
+* configuration as a list of panes built from the existing widgets, e.g.:
+*
+*   [[tabs]]
+*   title = "Work"
+*   direction = "horizontal"
+*   panes = [{ widget = "list", list = "Work", size = 40 }, { widget = "session" }]
+*/
+
+use serde::{Deserialize, Serialize};
+
+/// A user-defined tab
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CustomTab {
+    /// Title shown in the tab bar
+    pub title: String,
+    /// How the panes are arranged
+    #[serde(default)]
+    pub direction: PaneDirection,
+    /// Panes of the tab, in order
+    pub panes: Vec<Pane>,
+}
+
+/// Arrangement of the panes of a custom tab
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PaneDirection {
+    /// Panes stacked from top to bottom (default)
+    #[default]
+    Vertical,
+    /// Panes side by side
+    Horizontal,
+}
+
+/// A single pane of a custom tab
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Pane {
+    /// Widget shown in the pane
+    pub widget: PaneWidget,
+    /// Website list shown by a `list` pane
+    pub list: Option<String>,
+    /// Share of the tab in percent; panes without a size split the rest
+    pub size: Option<u16>,
+}
+
+/// Widgets available in custom tabs
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PaneWidget {
+    /// Overview of all website lists
+    Lists,
+    /// The websites of the list named in `list`
+    List,
+    /// Timer settings and controls, as on the Timer tab
+    Timer,
+    /// Large countdown of the running session
+    Session,
+    /// All active sessions, including those of other processes
+    Sessions,
+}
+
+impl CustomTab {
+    /// Name of the first list shown by the tab, if any
+    pub fn list_name(&self) -> Option<&str> {
+        self.panes
+            .iter()
+            .filter(|pane| pane.widget == PaneWidget::List)
+            .find_map(|pane| pane.list.as_deref())
+    }
+
+    /// Whether the tab controls the timer (accepts timer key bindings)
+    pub fn has_timer(&self) -> bool {
+        self.panes
+            .iter()
+            .any(|pane| matches!(pane.widget, PaneWidget::Timer | PaneWidget::Session))
+    }
+
+    /// Size of every pane in percent
+    pub fn pane_sizes(&self) -> Vec<u16> {
+        let fixed: u16 = self.panes.iter().filter_map(|pane| pane.size).sum();
+        let flexible = self.panes.iter().filter(|pane| pane.size.is_none()).count() as u16;
+        let share = 100u16.saturating_sub(fixed) / flexible.max(1);
+
+        self.panes
+            .iter()
+            .map(|pane| pane.size.unwrap_or(share))
+            .collect()
+    }
+}
@@ -0,0 +1,262 @@
+/*
+* TimeGuardian TUI Dialog Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module provides the modal dialogs of the TUI. A dialog is a title and
+* a form of fields: yes/no confirmations, text inputs, single and multiple
+* choice lists. Tab and Shift-Tab move between the fields, Enter submits and
+* Esc cancels. The dialog only collects the answers; what a submitted dialog
+* does is up to whoever opened it.
+*/
+
+use crossterm::event::{KeyCode, KeyEvent};
+use tui_input::{backend::crossterm::EventHandler, Input};
+
+/// One field of a dialog
+#[derive(Debug, Clone)]
+pub enum Field {
+    /// A yes/no question
+    Confirm { label: String, value: bool },
+    /// A line of text
+    Input { label: String, input: Input, required: bool },
+    /// One of several options
+    Select { label: String, options: Vec<String>, selected: usize },
+    /// Any number of several options
+    MultiSelect { label: String, options: Vec<String>, checked: Vec<bool>, cursor: usize },
+}
+
+/// The answer of one field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    Text(String),
+    Choice(usize),
+    Choices(Vec<usize>),
+}
+
+impl Value {
+    /// The answer of a confirmation
+    pub fn as_bool(&self) -> bool {
+        matches!(self, Value::Bool(true))
+    }
+
+    /// The text of an input, trimmed
+    pub fn as_text(&self) -> &str {
+        match self {
+            Value::Text(text) => text.trim(),
+            _ => "",
+        }
+    }
+}
+
+/// What a key did to the dialog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The dialog stays open
+    Pending,
+    /// Esc, or "no" to a plain confirmation
+    Cancelled,
+    /// The answers of the fields, in order
+    Submitted(Vec<Value>),
+}
+
+/// A modal dialog
+#[derive(Debug, Clone)]
+pub struct Dialog {
+    /// Shown in the border
+    pub title: String,
+    /// Lines of text above the fields
+    pub message: Vec<String>,
+    /// The form
+    pub fields: Vec<Field>,
+    /// Index of the focused field
+    pub focus: usize,
+    /// Why the last submit was refused
+    pub error: Option<String>,
+}
+
+impl Dialog {
+    /// An empty dialog; add fields with the builder methods
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            message: Vec::new(),
+            fields: Vec::new(),
+            focus: 0,
+            error: None,
+        }
+    }
+
+    /// A dialog that asks a single yes/no question
+    pub fn confirm(title: impl Into<String>, question: impl Into<String>) -> Self {
+        Self::new(title).with_confirm(question, true)
+    }
+
+    /// A dialog that asks for a single line of text
+    pub fn input(title: impl Into<String>, label: impl Into<String>) -> Self {
+        Self::new(title).with_input(label, "", true)
+    }
+
+    /// Show `lines` above the fields
+    pub fn with_message(mut self, lines: Vec<String>) -> Self {
+        self.message = lines;
+        self
+    }
+
+    /// Add a yes/no field
+    pub fn with_confirm(mut self, label: impl Into<String>, value: bool) -> Self {
+        self.fields.push(Field::Confirm { label: label.into(), value });
+        self
+    }
+
+    /// Add a text field; required fields must not be left empty
+    pub fn with_input(mut self, label: impl Into<String>, value: &str, required: bool) -> Self {
+        self.fields.push(Field::Input {
+            label: label.into(),
+            input: Input::new(value.to_string()),
+            required,
+        });
+        self
+    }
+
+    /// Add a single choice field
+    pub fn with_select(mut self, label: impl Into<String>, options: Vec<String>, selected: usize) -> Self {
+        let selected = selected.min(options.len().saturating_sub(1));
+        self.fields.push(Field::Select { label: label.into(), options, selected });
+        self
+    }
+
+    /// Add a multiple choice field with the `checked` options ticked
+    pub fn with_multi_select(mut self, label: impl Into<String>, options: Vec<String>, checked: &[usize]) -> Self {
+        let checked = (0..options.len()).map(|index| checked.contains(&index)).collect();
+        self.fields.push(Field::MultiSelect {
+            label: label.into(),
+            options,
+            checked,
+            cursor: 0,
+        });
+        self
+    }
+
+    /// The focused field
+    pub fn focused(&self) -> Option<&Field> {
+        self.fields.get(self.focus)
+    }
+
+    /// Handle a key
+    pub fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        // A lone question is answered with y or n
+        if let [Field::Confirm { .. }] = self.fields.as_slice() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => return Outcome::Submitted(vec![Value::Bool(true)]),
+                KeyCode::Char('n') | KeyCode::Char('N') => return Outcome::Cancelled,
+                _ => {}
+            }
+        }
+
+        match key.code {
+            KeyCode::Esc => return Outcome::Cancelled,
+            KeyCode::Enter => return self.submit(),
+            KeyCode::Tab => self.move_focus(1),
+            KeyCode::BackTab => self.move_focus(self.fields.len().saturating_sub(1)),
+            _ => self.edit_focused(key),
+        }
+        Outcome::Pending
+    }
+
+    /// Insert pasted text into the focused text field
+    pub fn paste(&mut self, text: &str) {
+        if let Some(Field::Input { input, .. }) = self.fields.get_mut(self.focus) {
+            // Text fields hold a single line
+            let line = text.lines().next().unwrap_or_default();
+            *input = Input::new(format!("{}{}", input.value(), line));
+        }
+    }
+
+    /// The answers, unless a required field is empty
+    fn submit(&mut self) -> Outcome {
+        let empty = self.fields.iter().position(|field| {
+            matches!(field, Field::Input { input, required: true, .. } if input.value().trim().is_empty())
+        });
+        if let Some(index) = empty {
+            self.focus = index;
+            self.error = Some("This field can't be empty".to_string());
+            return Outcome::Pending;
+        }
+
+        let values = self
+            .fields
+            .iter()
+            .map(|field| match field {
+                Field::Confirm { value, .. } => Value::Bool(*value),
+                Field::Input { input, .. } => Value::Text(input.value().to_string()),
+                Field::Select { selected, .. } => Value::Choice(*selected),
+                Field::MultiSelect { checked, .. } => Value::Choices(
+                    checked.iter().enumerate().filter(|(_, checked)| **checked).map(|(index, _)| index).collect(),
+                ),
+            })
+            .collect();
+        Outcome::Submitted(values)
+    }
+
+    /// Move the focus `step` fields forward, wrapping around
+    fn move_focus(&mut self, step: usize) {
+        if !self.fields.is_empty() {
+            self.focus = (self.focus + step) % self.fields.len();
+        }
+    }
+
+    /// Pass a key to the focused field
+    fn edit_focused(&mut self, key: KeyEvent) {
+        let Some(field) = self.fields.get_mut(self.focus) else {
+            return;
+        };
+        self.error = None;
+        match field {
+            Field::Confirm { value, .. } => {
+                if matches!(key.code, KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')) {
+                    *value = !*value;
+                }
+            }
+            Field::Input { input, .. } => {
+                input.handle_event(&crossterm::event::Event::Key(key));
+            }
+            Field::Select { options, selected, .. } => match key.code {
+                KeyCode::Up | KeyCode::Char('k') => *selected = selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') if *selected + 1 < options.len() => *selected += 1,
+                _ => {}
+            },
+            Field::MultiSelect { options, checked, cursor, .. } => match key.code {
+                KeyCode::Up | KeyCode::Char('k') => *cursor = cursor.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') if *cursor + 1 < options.len() => *cursor += 1,
+                KeyCode::Char(' ') => {
+                    if let Some(checked) = checked.get_mut(*cursor) {
+                        *checked = !*checked;
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    /// Linear description for screen readers
+    pub fn describe(&self) -> String {
+        let field = match self.focused() {
+            Some(Field::Confirm { label, value }) => format!("{} {}", label, if *value { "yes" } else { "no" }),
+            Some(Field::Input { label, input, .. }) => format!("{} {}", label, input.value()),
+            Some(Field::Select { label, options, selected }) => {
+                format!("{} {}", label, options.get(*selected).map(String::as_str).unwrap_or_default())
+            }
+            Some(Field::MultiSelect { label, options, checked, cursor }) => format!(
+                "{} {} {}",
+                label,
+                options.get(*cursor).map(String::as_str).unwrap_or_default(),
+                if checked.get(*cursor) == Some(&true) { "checked" } else { "not checked" }
+            ),
+            None => String::new(),
+        };
+        let message: String = self.message.iter().map(|line| format!(" {}", line)).collect();
+        let error = self.error.as_deref().map(|error| format!(". {}", error)).unwrap_or_default();
+        format!("{}:{} {}{}. Enter to confirm, Escape to cancel", self.title, message, field, error)
+    }
+}
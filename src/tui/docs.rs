@@ -0,0 +1,70 @@
+/*
+* TimeGuardian TUI Docs Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module contains the user guide shown by the TUI (F1 or :docs).
+* The pages are generated at build time from the markdown files in
+* docs/guide, so the guide is available offline.
+*/
+
+/// A page of the user guide
+pub struct DocPage {
+    pub title: &'static str,
+    pub lines: &'static [DocLine],
+}
+
+/// A line of a guide page
+pub enum DocLine {
+    Heading(&'static str),
+    Bullet(&'static str),
+    Code(&'static str),
+    Text(&'static str),
+    Blank,
+}
+
+include!(concat!(env!("OUT_DIR"), "/guide.rs"));
+
+/// Position of the guide viewer
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DocsState {
+    /// Index of the shown page
+    pub page: usize,
+    /// First visible line of the page
+    pub scroll: u16,
+}
+
+impl DocsState {
+    /// The shown page
+    pub fn current(&self) -> &'static DocPage {
+        &GUIDE[self.page.min(GUIDE.len() - 1)]
+    }
+
+    /// Open the page whose title starts with `name` (case-insensitive)
+    pub fn open(&mut self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        match GUIDE.iter().position(|page| page.title.to_lowercase().starts_with(&name)) {
+            Some(index) => {
+                self.page = index;
+                self.scroll = 0;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn next_page(&mut self) {
+        self.page = (self.page + 1) % GUIDE.len();
+        self.scroll = 0;
+    }
+
+    pub fn previous_page(&mut self) {
+        self.page = (self.page + GUIDE.len() - 1) % GUIDE.len();
+        self.scroll = 0;
+    }
+
+    /// Scroll by `lines` (negative scrolls up), staying within the page
+    pub fn scroll_by(&mut self, lines: i32) {
+        let max = self.current().lines.len().saturating_sub(1) as i32;
+        self.scroll = (self.scroll as i32 + lines).clamp(0, max) as u16;
+    }
+}
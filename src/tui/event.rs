@@ -18,8 +18,12 @@ pub enum Event {
     /// Key event from keyboard
     Key(KeyEvent),
     /// Mouse event (reserved for future use)
+    #[allow(dead_code)]
     Mouse(MouseEvent),
+    /// Text pasted into the terminal (bracketed paste)
+    Paste(String),
     /// Terminal resize event (reserved for future use)
+    #[allow(dead_code)]
     Resize(u16, u16),
     /// Tick event for UI refresh
     Tick,
@@ -71,6 +75,12 @@ impl EventHandler {
                                 return;
                             }
                         }
+                        CrosstermEvent::Paste(text) => {
+                            if let Err(err) = event_sender.send(Event::Paste(text)) {
+                                eprintln!("Error sending paste event: {:?}", err);
+                                return;
+                            }
+                        }
                         // Ignoring FocusGained and FocusLost events
                         _ => {}
                     }
@@ -6,9 +6,19 @@
 * It re-exports all submodules and their public items for easier access.
 */
 
+pub mod action;
 pub mod app;
+pub mod custom;
+pub mod dialog;
+pub mod docs;
 pub mod event;
+pub mod paste;
+pub mod run;
+pub mod theme;
 pub mod ui;
 
 // Re-export the main App struct and TuiMode for convenience
-pub use app::{App, TuiMode, WebsiteList};
\ No newline at end of file
+pub use action::{Action, Macro};
+pub use app::{App, DialogPurpose, ScheduleWarning, TuiMode, WebsiteList};
+pub use custom::CustomTab;
+pub use run::run_tui;
\ No newline at end of file
@@ -0,0 +1,80 @@
+/*
+* TimeGuardian TUI Paste Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module parses a pasted blob of domains for the bulk import into a
+* list. One domain per line, comma separated lists, URLs and hosts file lines
+* ("0.0.0.0 example.com") all work; text after '#' is ignored. Domains are
+* normalized (lowercase, without scheme, www, path, port or trailing dot) and
+* deduplicated against each other and the list. Internationalized domains are
+* added in their unicode form; "xn--" and unicode forms count as duplicates.
+*/
+
+use std::{collections::BTreeSet, net::IpAddr};
+
+use crate::idn;
+use crate::safety::SafetyList;
+
+/// What importing a pasted text would do
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PastePreview {
+    /// New domains, in the order they were pasted
+    pub added: Vec<String>,
+    /// Domains already in the list or pasted twice
+    pub duplicates: usize,
+    /// Words that are not domains
+    pub invalid: Vec<String>,
+    /// Critical domains that must not be blocked
+    pub protected: Vec<String>,
+}
+
+/// Parse `text` against the `existing` entries of the target list
+pub fn preview(text: &str, existing: &[String], safety: &SafetyList) -> PastePreview {
+    let mut seen: BTreeSet<String> = existing.iter().map(|entry| idn::key(entry)).collect();
+    let mut preview = PastePreview::default();
+
+    let words = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ',' || c == ';'))
+        .filter(|word| !word.is_empty());
+    for word in words {
+        // Addresses of hosts file lines
+        if word.parse::<IpAddr>().is_ok() {
+            continue;
+        }
+        let Some(domain) = normalize(word) else {
+            preview.invalid.push(word.to_string());
+            continue;
+        };
+        if !seen.insert(idn::to_ascii(&domain)) {
+            preview.duplicates += 1;
+        } else if safety.check(std::slice::from_ref(&domain)).is_err() {
+            preview.protected.push(domain);
+        } else {
+            preview.added.push(domain);
+        }
+    }
+    preview
+}
+
+/// The bare domain of a pasted word in the unicode form, if it is one
+pub fn normalize(word: &str) -> Option<String> {
+    let word = word.trim().trim_matches(|c| c == '"' || c == '\'').to_lowercase();
+    let host = word.split_once("://").map_or(word.as_str(), |(_, rest)| rest);
+    let host = host.split(['/', '?']).next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default().trim_end_matches('.');
+    // The www variant is blocked along with the domain anyway
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let host = idn::to_ascii(host);
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let valid = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    valid.then(|| idn::to_unicode(&host))
+}
@@ -0,0 +1,1369 @@
+/*
+* TimeGuardian TUI Run Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module runs the TUI: it sets up the terminal, handles the keys of every
+* tab, dialog and prompt, and starts and ends the sessions of the TUI. The
+* main loop also follows what happens around it: scheduled blocks about to
+* start, sessions of other processes, network changes and IPC requests.
+*/
+
+use chrono::Local;
+use color_eyre::Result;
+use crossterm::{
+    event::{DisableBracketedPaste, EnableBracketedPaste, KeyCode, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    ExecutableCommand,
+};
+use ratatui::Terminal;
+use std::{
+    io::{self, stdout},
+    time::{Duration, Instant},
+};
+use timeguardian::{format, schedule, ListStore};
+use tui_input::{backend::crossterm::EventHandler, Input};
+
+use crate::history::{self, EndReason, Enforcement};
+use crate::hosts::{self, Priority};
+use crate::preset::{self, EndBehavior};
+use crate::tui::{self, dialog::{Dialog, Value}, Action, App, DialogPurpose, TuiMode};
+use crate::{
+    append_daily_note, approval, battery, blocker, blockpage, check_and_get_permissions, config_is_read_only, demo,
+    diagnose, domains_in_use, initialize_app, ipc, is_interactive_terminal, is_root, list_impact, load_config,
+    newtab, parse_duration, profile, refresh_hosts_periodically, safety, safety_list, services, session_source,
+    snooze, suspend, time_of_day, ulid, update_config, Cli,
+};
+
+/// How often the TUI checks which profile matches the network
+const PROFILE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// How often the sessions of other processes are looked up
+const SESSIONS_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+/// How often the TUI looks for a scheduled block about to start
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Length of the break offered in the session summary
+const TUI_BREAK: Duration = Duration::from_secs(5 * 60);
+
+/// Restores the terminal when the TUI exits, also through an error
+struct TuiGuard {
+    /// Claim of the TUI's session, released if the TUI exits through an
+    /// error: nothing but its expiry would end it otherwise
+    claim: Option<String>,
+}
+
+impl Drop for TuiGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = stdout().execute(DisableBracketedPaste);
+        let _ = stdout().execute(LeaveAlternateScreen);
+        if let Some(source) = &self.claim {
+            let _ = hosts::release(source);
+        }
+    }
+}
+
+/// Run the TUI application
+pub fn run_tui(cli: &Cli) -> Result<()> {
+    let requested_profile = cli.profile.as_deref();
+    // The TUI needs a real terminal
+    if !is_interactive_terminal() {
+        println!("The TUI needs an interactive terminal.");
+        println!("Use 'timeguardian start -d <time> -t <task>' for line-based output instead.");
+        return Ok(());
+    }
+
+    // Without permissions the TUI still runs, with sessions that only time
+    let blocking_disabled = !check_and_get_permissions()?;
+
+    // Initialize app data
+    initialize_app()?;
+
+    // Setup terminal
+    enable_raw_mode()?;
+    let mut guard = TuiGuard {
+        claim: (!blocking_disabled).then(|| session_source("tui")),
+    };
+    let mut stdout = stdout();
+    stdout.execute(EnterAlternateScreen)?;
+    stdout.execute(EnableBracketedPaste)?;
+
+    // Create a terminal instance
+    let backend = ratatui::backend::CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    // Create app state
+    let mut app = App::new();
+    app.claim_source = session_source("tui");
+    if blocking_disabled {
+        app.blocking_disabled = true;
+        app.blocker = Box::new(blocker::DisabledBlocker);
+    }
+    // A CLI session may already serve the socket; the TUI takes over once it ends
+    let mut ipc_server = ipc::serve(app.session_handle.clone());
+    let _host_socket = ipc::register(app.session_handle.clone());
+    let mut _new_tab = ipc_server.as_ref().and_then(|_| newtab::keep_updated(app.session_handle.clone()));
+
+    // Initialize app
+    app.init()?;
+
+    // Load existing website lists from config if available
+    let config = load_config()?;
+    app.end_behavior = config.end_behavior.unwrap_or_default();
+    app.intent_prompt = config.intent_prompt.unwrap_or(false);
+    app.accessible = cli.accessible || config.accessible.unwrap_or(false);
+    app.read_only = config_is_read_only();
+    app.theme = cli.theme.or(config.theme).unwrap_or_default().theme();
+    app.add_custom_tabs(config.tabs.clone().unwrap_or_default());
+    app.macros = config.macros.clone().unwrap_or_default();
+    app.profile_pinned = requested_profile.is_some();
+    let loaded_lists = config.website_lists.clone().unwrap_or_default();
+    let suspend_behavior = config.suspend_behavior.unwrap_or_default();
+    let source = session_source("tui");
+    let claimed = (!blocking_disabled).then_some(source.as_str());
+    let mut suspend_watch = suspend::SuspendWatch::new(suspend_behavior, claimed);
+    let mut battery_watch = battery::BatteryWatch::new(config.battery.clone(), claimed);
+    if let Some(website_lists) = config.website_lists {
+        app.website_lists = website_lists;
+        if !app.website_lists.is_empty() {
+            app.website_list_state.select(Some(0));
+            app.selected_list_index = Some(0);
+
+            // Ensure the first list is properly selected
+            if !app.website_lists[0].websites.is_empty() {
+                app.website_state.select(Some(0));
+                app.selected_website_index = Some(0);
+            }
+        }
+    }
+
+    // Activate the profile matching the current network
+    let profiles = config.profiles.clone().unwrap_or_default();
+    let environment = profile::Environment::detect();
+    let safe_domains = config.safe_domains.clone().unwrap_or_default();
+    let active_profile = profile::resolve(&profiles, requested_profile, &environment);
+    app.safety = safety::SafetyList::new(&safe_domains, active_profile, cli.i_know_what_im_doing);
+    app.apply_profile(active_profile);
+    let mut last_profile_check = Instant::now();
+    let mut last_hosts_refresh = Instant::now();
+    let mut last_sessions_refresh: Option<Instant> = None;
+    let schedules = config.schedules.clone().unwrap_or_default();
+    let mut last_schedule_check: Option<Instant> = None;
+
+    // Create event handler
+    let tick_rate = Duration::from_millis(250);
+    let event_handler = tui::event::EventHandler::new(tick_rate);
+
+    // Main loop
+    while app.running {
+        // Draw UI
+        terminal.draw(|frame| tui::ui::render(&mut app, frame))?;
+
+        // Handle events
+        match event_handler.receiver.recv() {
+            Ok(tui::event::Event::Key(_)) if app.alarm_since.is_some() => {
+                // Any key acknowledges the end-of-session alarm
+                app.alarm_since = None;
+                app.status_message = "Session finished".to_string();
+            }
+            Ok(tui::event::Event::Key(key_event)) => {
+                match app.mode {
+                    TuiMode::Normal => match key_event.code {
+                        KeyCode::Char('q') => {
+                            app.running = false;
+                        }
+                        KeyCode::Char('?') => {
+                            app.mode = TuiMode::Help;
+                        }
+                        KeyCode::F(1) => {
+                            app.mode = TuiMode::Docs;
+                        }
+                        KeyCode::Char(':') => {
+                            app.input = Input::default();
+                            app.mode = TuiMode::Command;
+                        }
+                        // Vim-style tab navigation
+                        KeyCode::Char('l') | KeyCode::Tab => {
+                            app.tabs.next();
+                        }
+                        KeyCode::Char('h') | KeyCode::BackTab => {
+                            app.tabs.previous();
+                        }
+                        // Answers to the warning before a scheduled block
+                        KeyCode::Char('N') if app.schedule_warning.is_some() => {
+                            start_scheduled_block_now(&mut app, &schedules, cli)?;
+                        }
+                        KeyCode::Char('Z') if app.schedule_warning.is_some() => {
+                            snooze_scheduled_block(&mut app, &schedules)?;
+                        }
+                        KeyCode::Char('X') if app.schedule_warning.is_some() => {
+                            app.input = Input::default();
+                            app.mode = TuiMode::SkipReason;
+                        }
+                        code if app.macros.iter().any(|m| m.matches(code)) => {
+                            run_tui_macro(&mut app, code, &profiles, &safe_domains, cli)?;
+                        }
+                        _ => {
+                            // Handle different tabs
+                            match app.tabs.index {
+                                0 => {
+                                    handle_website_list_tab_events(&mut app, key_event.code)?;
+                                }
+                                1 => {
+                                    handle_timer_tab_events(&mut app, key_event.code)?;
+                                }
+                                _ => {
+                                    handle_custom_tab_events(&mut app, key_event.code)?;
+                                }
+                            }
+                        }
+                    },
+                    TuiMode::Dialog => {
+                        handle_dialog_key(&mut app, key_event)?;
+                    }
+                    TuiMode::Help => match key_event.code {
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => {
+                            app.mode = TuiMode::Normal;
+                        }
+                        KeyCode::F(1) => {
+                            app.mode = TuiMode::Docs;
+                        }
+                        _ => {}
+                    },
+                    TuiMode::Command => match key_event.code {
+                        KeyCode::Esc => app.mode = TuiMode::Normal,
+                        KeyCode::Enter => {
+                            let command = app.input.value().trim().to_string();
+                            app.input = Input::default();
+                            run_tui_command(&mut app, &command);
+                        }
+                        _ => {
+                            app.input.handle_event(&crossterm::event::Event::Key(key_event));
+                        }
+                    },
+                    TuiMode::Intent => match key_event.code {
+                        KeyCode::Esc => app.mode = TuiMode::Normal,
+                        KeyCode::Enter => {
+                            let intent = app.input.value().trim().to_string();
+                            app.intent = (!intent.is_empty()).then_some(intent);
+                            app.input = Input::default();
+                            app.mode = TuiMode::Normal;
+                            let duration = app.countdown.duration();
+                            start_tui_session(&mut app, duration)?;
+                        }
+                        _ => {
+                            app.input.handle_event(&crossterm::event::Event::Key(key_event));
+                        }
+                    },
+                    TuiMode::SkipReason => match key_event.code {
+                        KeyCode::Esc => app.mode = TuiMode::Normal,
+                        KeyCode::Enter => {
+                            let reason = app.input.value().trim().to_string();
+                            if !reason.is_empty() {
+                                app.input = Input::default();
+                                app.mode = TuiMode::Normal;
+                                skip_scheduled_block(&mut app, &schedules, &reason)?;
+                            }
+                        }
+                        _ => {
+                            app.input.handle_event(&crossterm::event::Event::Key(key_event));
+                        }
+                    },
+                    TuiMode::Summary => match key_event.code {
+                        KeyCode::Char('r') => {
+                            let from = close_summary(&mut app);
+                            repeat_tui_session(&mut app, from)?;
+                        }
+                        KeyCode::Char('b') => {
+                            close_summary(&mut app);
+                            app.break_end = Some(Local::now() + chrono::Duration::from_std(TUI_BREAK)?);
+                        }
+                        KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                            close_summary(&mut app);
+                        }
+                        KeyCode::Char(c) => {
+                            // Rating prompt for the intent
+                            if let Some(achieved) = history::Achievement::from_answer(&c.to_string())
+                                && let Some(summary) = app.summary.as_mut()
+                                && summary.intent.is_some()
+                                && summary.achieved.is_none()
+                                && let Some(id) = summary.history_id
+                            {
+                                history::reflect(id, achieved)?;
+                                summary.achieved = Some(achieved);
+                            }
+                        }
+                        _ => {}
+                    },
+                    TuiMode::Backend => match key_event.code {
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => app.mode = TuiMode::Normal,
+                        _ => {}
+                    },
+                    TuiMode::Blocked => match key_event.code {
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => app.mode = TuiMode::Normal,
+                        KeyCode::Up | KeyCode::Char('k') => app.provenance_scroll = app.provenance_scroll.saturating_sub(1),
+                        KeyCode::Down | KeyCode::Char('j') if app.provenance_scroll + 1 < app.provenance.len() => {
+                            app.provenance_scroll += 1;
+                        }
+                        _ => {}
+                    },
+                    TuiMode::Paste => match key_event.code {
+                        KeyCode::Esc => {
+                            app.paste.clear();
+                            app.mode = TuiMode::Normal;
+                        }
+                        KeyCode::Char('s') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            import_paste(&mut app);
+                        }
+                        KeyCode::Enter => app.paste.push('\n'),
+                        KeyCode::Backspace => {
+                            app.paste.pop();
+                        }
+                        KeyCode::Char(c) => app.paste.push(c),
+                        _ => {}
+                    },
+                    TuiMode::Docs => match key_event.code {
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::F(1) => app.mode = TuiMode::Normal,
+                        KeyCode::Char('j') | KeyCode::Down => app.docs.scroll_by(1),
+                        KeyCode::Char('k') | KeyCode::Up => app.docs.scroll_by(-1),
+                        KeyCode::PageDown | KeyCode::Char(' ') => app.docs.scroll_by(10),
+                        KeyCode::PageUp => app.docs.scroll_by(-10),
+                        KeyCode::Char('l') | KeyCode::Right | KeyCode::Tab => app.docs.next_page(),
+                        KeyCode::Char('h') | KeyCode::Left | KeyCode::BackTab => app.docs.previous_page(),
+                        _ => {}
+                    },
+                }
+            }
+            Ok(tui::event::Event::Tick) => {
+                app.tick();
+
+                if let Some(break_end) = app.break_end {
+                    match (break_end - Local::now()).to_std() {
+                        Ok(left) if !app.is_blocking => app.status_message = format!("Break: {} left", format::clock(left)),
+                        Ok(_) => app.break_end = None,
+                        Err(_) => {
+                            app.break_end = None;
+                            app.status_message = "Break over. Start the next session on the Timer tab".to_string();
+                        }
+                    }
+                }
+
+                // Pause the session if the machine was suspended
+                match app.blocking_end_time.as_mut() {
+                    Some(end_time) if app.is_blocking => {
+                        if let Some(suspended) = suspend_watch.poll(end_time, &app.session_handle)? {
+                            app.status_message = format!(
+                                "System was suspended for {}; the session {}",
+                                format::spoken(suspended),
+                                if suspend_watch.pauses() { "was paused" } else { "kept running" }
+                            );
+                        }
+                    }
+                    _ => suspend_watch.reset(),
+                }
+                if app.is_blocking
+                    && let Some(end_time) = app.blocking_end_time.as_mut()
+                {
+                    match battery_watch.poll(end_time, &app.session_handle)? {
+                        Some(battery::BatteryEvent::Low(percent)) => {
+                            app.status_message = format!("Battery at {}%; plug in to keep the session going", percent);
+                        }
+                        Some(battery::BatteryEvent::Paused(percent)) => {
+                            app.status_message = format!("Battery at {}%; session paused until charging", percent);
+                        }
+                        Some(battery::BatteryEvent::Resumed(paused)) => {
+                            app.session_duration += paused;
+                            app.status_message = format!("Power is back; the session was paused for {}", format::spoken(paused));
+                        }
+                        None => {}
+                    }
+                } else {
+                    battery_watch.reset();
+                }
+                if app.is_blocking && !app.blocking_disabled {
+                    refresh_hosts_periodically(&mut last_hosts_refresh)?;
+                }
+
+                // Warn a minute before a scheduled block starts
+                if last_schedule_check.is_none_or(|at| at.elapsed() >= SCHEDULE_CHECK_INTERVAL) {
+                    last_schedule_check = Some(Instant::now());
+                    let decisions = snooze::Decisions::load().unwrap_or_default();
+                    app.schedule_warning = snooze::pending_warning(&schedules, &decisions, chrono::Utc::now())
+                        .unwrap_or_default()
+                        .map(|(occurrence, start)| tui::ScheduleWarning {
+                            schedule: occurrence.schedule.name.clone(),
+                            starts_at: start.with_timezone(&Local),
+                        });
+                }
+
+                // Sessions of other processes come and go independently
+                if last_sessions_refresh.is_none_or(|at| at.elapsed() >= SESSIONS_REFRESH_INTERVAL) {
+                    last_sessions_refresh = Some(Instant::now());
+                    let now = chrono::Utc::now();
+                    app.sessions = hosts::sessions_in_hosts_file()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|session| session.expires_at > now)
+                        .collect();
+
+                    if ipc_server.is_none() {
+                        let cli_session = ipc::query(&ipc::Request::Status)
+                            .unwrap_or_default()
+                            .and_then(|response| response.status)
+                            .filter(|status| status.active);
+                        if app.cli_session.is_none()
+                            && let Some(status) = &cli_session
+                        {
+                            app.status_message = format!(
+                                "A CLI session is running: {} ({} left)",
+                                status.task.as_deref().unwrap_or("Focus session"),
+                                format::spoken(Duration::from_secs(status.remaining_secs.unwrap_or(0)))
+                            );
+                        }
+                        app.cli_session = cli_session;
+                        if !ipc::host_running() {
+                            ipc_server = ipc::serve(app.session_handle.clone());
+                            _new_tab = ipc_server.as_ref().and_then(|_| newtab::keep_updated(app.session_handle.clone()));
+                        }
+                    }
+                }
+
+                // Check if blocking session has ended
+                if app.is_blocking
+                    && let Some(end_time) = app.blocking_end_time
+                    && Local::now() >= end_time
+                {
+                    hosts::release(&session_source("tui"))?;
+                    end_tui_session(&mut app, EndReason::Expired)?;
+                    app.status_message = "Session finished".to_string();
+
+                    match app.end_behavior {
+                        EndBehavior::Notify => {
+                            app.alarm_since = Some(Instant::now() - Duration::from_secs(60));
+                            app.status_message = "Session finished! Press any key to acknowledge.".to_string();
+                        }
+                        EndBehavior::LockScreen => {
+                            if let Err(e) = preset::lock_screen() {
+                                app.status_message = e.to_string();
+                            }
+                        }
+                        EndBehavior::Unblock | EndBehavior::Chain => {}
+                    }
+                }
+
+                // Follow network changes
+                if !app.profile_pinned && last_profile_check.elapsed() >= PROFILE_CHECK_INTERVAL {
+                    last_profile_check = Instant::now();
+                    let environment = profile::Environment::detect();
+                    let active_profile = profile::resolve(&profiles, None, &environment);
+                    if app.apply_profile(active_profile) {
+                        app.safety = safety::SafetyList::new(&safe_domains, active_profile, cli.i_know_what_im_doing);
+                        app.status_message = match &app.active_profile {
+                            Some(name) => format!("Switched to profile '{}' ({})", name, environment.describe()),
+                            None => format!("No profile matches the current network ({})", environment.describe()),
+                        };
+                    }
+                }
+
+                // Requests from IPC clients
+                if app.session_handle.take_stop_request() && app.is_blocking {
+                    stop_tui_session(&mut app, EndReason::StoppedRemotely)?;
+                }
+                if let Some(request) = app.session_handle.take_start_request() {
+                    match parse_duration(&request.duration) {
+                        Ok(duration_ms) if app.selected_list_index.is_some() => {
+                            app.session_task = request.task;
+                            start_tui_session(&mut app, Duration::from_millis(duration_ms))?;
+                        }
+                        Ok(_) => app.status_message = "Please select a list first".to_string(),
+                        Err(e) => app.status_message = e.to_string(),
+                    }
+                }
+
+                // Repeat the alarm until it is acknowledged
+                if let Some(since) = app.alarm_since
+                    && since.elapsed() >= Duration::from_secs(10)
+                {
+                    print!("\x07");
+                    io::Write::flush(&mut io::stdout())?;
+                    app.alarm_since = Some(Instant::now());
+                }
+            }
+            Ok(tui::event::Event::Paste(text)) => {
+                // Pasting on the lists tab opens the bulk import
+                if app.mode == TuiMode::Normal
+                    && app.tabs.index == 0
+                    && app.selected_list_index.is_some()
+                    && !app.read_only
+                    && !app.current_website_list().is_some_and(|list| list.system)
+                {
+                    app.paste.clear();
+                    app.mode = TuiMode::Paste;
+                }
+                match app.mode {
+                    TuiMode::Paste => app.paste.push_str(&text.replace("\r\n", "\n").replace('\r', "\n")),
+                    TuiMode::Dialog => {
+                        if let Some((_, dialog)) = &mut app.dialog {
+                            dialog.paste(&text);
+                        }
+                    }
+                    TuiMode::Command | TuiMode::Intent | TuiMode::SkipReason => {
+                        // Single-line inputs take the first line
+                        let line = text.lines().next().unwrap_or_default();
+                        app.input = Input::new(format!("{}{}", app.input.value(), line));
+                    }
+                    _ => {}
+                }
+            }
+            Ok(tui::event::Event::Resize(_, _)) => {}
+            Ok(tui::event::Event::Mouse(_)) => {}, // Add handling for mouse events
+            Err(_) => {
+                app.running = false;
+            }
+        }
+    }
+
+    // The session of a summary left open still goes to the daily note
+    if app.summary.is_some() {
+        close_summary(&mut app);
+    }
+
+    // When the app exits, save the website lists to config. Other processes
+    // may have changed the config meanwhile, so only our own changes are written.
+    let mut conflicts = Vec::new();
+    if !app.read_only {
+        update_config(|config| {
+            if app.website_lists != loaded_lists {
+                let theirs = config.website_lists.clone().unwrap_or_default();
+                let (merged, changed_on_disk) = merge_website_lists(&loaded_lists, &app.website_lists, &theirs);
+                config.website_lists = Some(merged);
+                conflicts = changed_on_disk;
+            }
+            config.end_behavior = Some(app.end_behavior);
+            Ok(())
+        })?;
+    }
+
+    // Restore terminal; a session still running keeps its block until it expires
+    guard.claim = None;
+    drop(guard);
+
+    if !conflicts.is_empty() {
+        println!(
+            "Your changes to {} were not saved: another process changed them while the TUI was open.",
+            conflicts.iter().map(|name| format!("'{}'", name)).collect::<Vec<_>>().join(", ")
+        );
+        println!("Your other changes were saved; make these again in the TUI.");
+    }
+
+    Ok(())
+}
+
+/// Merge the website lists edited in the TUI (`ours`) with those on disk
+/// (`theirs`), both starting from `base`, list by list.
+///
+/// A list changed on one side only takes that change. Lists changed on both
+/// sides keep the version on disk; their names are returned with the merge.
+fn merge_website_lists(
+    base: &[tui::WebsiteList],
+    ours: &[tui::WebsiteList],
+    theirs: &[tui::WebsiteList],
+) -> (Vec<tui::WebsiteList>, Vec<String>) {
+    let find = |lists: &[tui::WebsiteList], name: &str| lists.iter().find(|list| list.name == name).cloned();
+    let mut names: Vec<&str> = Vec::new();
+    for list in ours.iter().chain(theirs) {
+        if !names.contains(&list.name.as_str()) {
+            names.push(&list.name);
+        }
+    }
+
+    let mut merged = Vec::new();
+    let mut conflicts = Vec::new();
+    for name in names {
+        let (base, ours, theirs) = (find(base, name), find(ours, name), find(theirs, name));
+        let list = if ours == base || ours == theirs {
+            theirs
+        } else if theirs == base {
+            ours
+        } else {
+            conflicts.push(name.to_string());
+            theirs
+        };
+        merged.extend(list);
+    }
+    (merged, conflicts)
+}
+
+/// Handle key events for the website list tab
+fn handle_website_list_tab_events(app: &mut App, key: KeyCode) -> Result<()> {
+    match key {
+        // Navigate lists with vim-style keys
+        KeyCode::Char('h') | KeyCode::Left => {
+            app.website_state.select(None);
+            app.selected_website_index = None;
+        }
+        KeyCode::Char('l') | KeyCode::Right => {
+            if app.selected_list_index.is_some()
+                && let Some(list) = app.current_website_list()
+                && !list.websites.is_empty()
+            {
+                app.website_state.select(Some(0));
+                app.selected_website_index = Some(0);
+            }
+        }
+        KeyCode::Char('k') | KeyCode::Up => {
+            if app.selected_website_index.is_some() {
+                // Navigate websites
+                let websites_len = app.current_website_list().map_or(0, |list| list.websites.len());
+                if websites_len > 0 {
+                    let i = app.selected_website_index.map_or(0, |i| {
+                        if i > 0 { i - 1 } else { websites_len - 1 }
+                    });
+                    app.website_state.select(Some(i));
+                    app.selected_website_index = Some(i);
+                }
+            } else {
+                // Navigate lists
+                let lists_len = app.website_lists.len();
+                if lists_len > 0 {
+                    let i = app.selected_list_index.map_or(0, |i| {
+                        if i > 0 { i - 1 } else { lists_len - 1 }
+                    });
+                    app.website_list_state.select(Some(i));
+                    app.selected_list_index = Some(i);
+                }
+            }
+        }
+        KeyCode::Char('j') | KeyCode::Down => {
+            if app.selected_website_index.is_some() {
+                // Navigate websites
+                let websites_len = app.current_website_list().map_or(0, |list| list.websites.len());
+                if websites_len > 0 {
+                    let i = app.selected_website_index.map_or(0, |i| {
+                        if i < websites_len - 1 { i + 1 } else { 0 }
+                    });
+                    app.website_state.select(Some(i));
+                    app.selected_website_index = Some(i);
+                }
+            } else {
+                // Navigate lists
+                let lists_len = app.website_lists.len();
+                if lists_len > 0 {
+                    let i = app.selected_list_index.map_or(0, |i| {
+                        if i < lists_len - 1 { i + 1 } else { 0 }
+                    });
+                    app.website_list_state.select(Some(i));
+                    app.selected_list_index = Some(i);
+                }
+            }
+        }
+
+        // Vim-style quick movement
+        KeyCode::Char('g') => {
+            // Go to the top
+            if app.selected_website_index.is_some() {
+                app.website_state.select(Some(0));
+                app.selected_website_index = Some(0);
+            } else if !app.website_lists.is_empty() {
+                app.website_list_state.select(Some(0));
+                app.selected_list_index = Some(0);
+            }
+        }
+        KeyCode::Char('G') => {
+            // Go to the bottom
+            if app.selected_website_index.is_some() {
+                let websites_len = app.current_website_list().map_or(0, |list| list.websites.len());
+                if websites_len > 0 {
+                    app.website_state.select(Some(websites_len - 1));
+                    app.selected_website_index = Some(websites_len - 1);
+                }
+            } else if !app.website_lists.is_empty() {
+                let lists_len = app.website_lists.len();
+                app.website_list_state.select(Some(lists_len - 1));
+                app.selected_list_index = Some(lists_len - 1);
+            }
+        }
+
+        // A declarative config is edited in the file, not here
+        KeyCode::Char('o' | 'n' | 'a' | 'P' | ' ' | 'd' | 'x' | 'D') if app.read_only => {
+            app.status_message = "The configuration is read-only (--config); edit the lists in the file".to_string();
+        }
+
+        // System lists belong to the administrator
+        KeyCode::Char('a' | 'P' | ' ' | 'd' | 'x' | 'D') if app.current_website_list().is_some_and(|list| list.system) => {
+            app.status_message = format!(
+                "System lists are read-only and block in every session; they are managed in {}",
+                ListStore::system_dir().display()
+            );
+        }
+
+        // Add new list or website (vim-style)
+        KeyCode::Char('o') | KeyCode::Char('n') => {
+            if app.selected_list_index.is_some() {
+                app.open_dialog(DialogPurpose::AddWebsite, Dialog::input("Add website", "Domain"));
+            } else {
+                app.open_dialog(DialogPurpose::AddList, Dialog::input("Add list", "Name"));
+            }
+        }
+        KeyCode::Char('a') => {
+            if app.selected_list_index.is_some() {
+                app.open_dialog(DialogPurpose::AddWebsite, Dialog::input("Add website", "Domain"));
+            } else {
+                app.status_message = "Please select a list first".to_string();
+            }
+        }
+        KeyCode::Char('P') => {
+            if app.selected_list_index.is_some() {
+                app.paste.clear();
+                app.mode = TuiMode::Paste;
+            } else {
+                app.status_message = "Please select a list first".to_string();
+            }
+        }
+
+        // Enable or disable the selected list
+        KeyCode::Char(' ') if app.selected_list_index.is_some() => {
+            if let Some(enabled) = app.toggle_list() {
+                app.status_message = if enabled {
+                    "List enabled".to_string()
+                } else {
+                    "List disabled: left out of sessions and schedules that block all lists".to_string()
+                };
+            }
+        }
+
+        // Delete website or list (vim-style)
+        KeyCode::Char('d') | KeyCode::Char('x') if app.selected_website_index.is_some() => {
+            app.delete_website();
+            app.status_message = "Website removed".to_string();
+        }
+        KeyCode::Char('D') if app.selected_list_index.is_some() => {
+            let list = app.current_website_list().map(|list| list.name.clone()).unwrap_or_default();
+            let question = format!("Delete the list '{}' and its websites?", list);
+            app.open_dialog(DialogPurpose::DeleteList, Dialog::confirm("Delete list", question));
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Pass a key to the open dialog and act on its answers once submitted
+fn handle_dialog_key(app: &mut App, key_event: crossterm::event::KeyEvent) -> Result<()> {
+    let Some((purpose, dialog)) = &mut app.dialog else {
+        app.mode = TuiMode::Normal;
+        return Ok(());
+    };
+    let purpose = *purpose;
+    let values = match dialog.handle_key(key_event) {
+        tui::dialog::Outcome::Pending => return Ok(()),
+        tui::dialog::Outcome::Cancelled => Vec::new(),
+        tui::dialog::Outcome::Submitted(values) => values,
+    };
+    app.dialog = None;
+    app.mode = TuiMode::Normal;
+    let Some(value) = values.first() else {
+        if let DialogPurpose::ApproveList(_) = purpose {
+            app.status_message = "Session not started".to_string();
+        }
+        return Ok(());
+    };
+
+    match purpose {
+        DialogPurpose::AddList => {
+            app.add_list(value.as_text().to_string());
+            app.status_message = "List added successfully".to_string();
+        }
+        DialogPurpose::AddWebsite => {
+            let website = value.as_text().to_string();
+            if let Err(error) = app.safety.check(std::slice::from_ref(&website)) {
+                app.status_message = error.to_string();
+            } else {
+                app.add_website(website);
+                app.status_message = "Website added successfully".to_string();
+            }
+        }
+        DialogPurpose::DeleteList => {
+            if value.as_bool() {
+                app.delete_list();
+                app.status_message = "List removed".to_string();
+            }
+        }
+        DialogPurpose::SessionSettings => {
+            if let [Value::Choice(priority), Value::Choice(end), Value::Choices(enabled)] = values.as_slice() {
+                app.priority = TUI_PRIORITIES[*priority];
+                app.end_behavior = TUI_END_BEHAVIORS[*end];
+                for (index, list) in app.website_lists.iter_mut().enumerate().filter(|(_, list)| !list.system) {
+                    list.enabled = enabled.contains(&index);
+                }
+                app.status_message = format!(
+                    "Session priority: {}, when the session ends: {}",
+                    app.priority.label(),
+                    app.end_behavior.label()
+                );
+            }
+        }
+        DialogPurpose::ApproveList(duration) => {
+            if value.as_bool() {
+                let lists: Vec<String> = app.current_website_list().map(|list| list.name.clone()).into_iter().collect();
+                approval::approve(&lists)?;
+                start_tui_session(app, duration)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Priorities offered by the session settings, in order
+const TUI_PRIORITIES: [Priority; 4] = [Priority::Low, Priority::Normal, Priority::High, Priority::Critical];
+/// End behaviors offered by the session settings; chains need a preset
+const TUI_END_BEHAVIORS: [EndBehavior; 3] = [EndBehavior::Unblock, EndBehavior::Notify, EndBehavior::LockScreen];
+
+/// Open the form for the priority, end behavior and lists of the next session
+fn open_session_settings(app: &mut App) {
+    let priority = TUI_PRIORITIES.iter().position(|priority| *priority == app.priority).unwrap_or(1);
+    let end = TUI_END_BEHAVIORS.iter().position(|end| *end == app.end_behavior).unwrap_or(0);
+    let lists: Vec<String> = app.website_lists.iter().map(|list| list.name.clone()).collect();
+    let enabled: Vec<usize> = (0..lists.len()).filter(|index| app.website_lists[*index].enabled).collect();
+    let dialog = Dialog::new("Session settings")
+        .with_select("Priority", TUI_PRIORITIES.iter().map(|priority| priority.label().to_string()).collect(), priority)
+        .with_select("When the session ends", TUI_END_BEHAVIORS.iter().map(|end| end.label().to_string()).collect(), end)
+        .with_multi_select("Enabled lists", lists, &enabled);
+    app.open_dialog(DialogPurpose::SessionSettings, dialog);
+}
+
+/// Add the new domains of the paste area to the selected list
+fn import_paste(app: &mut App) {
+    let preview = app.paste_preview();
+    for domain in &preview.added {
+        app.add_website(domain.clone());
+    }
+    let list = app.current_website_list().map(|list| list.name.clone()).unwrap_or_default();
+    app.status_message = format!("Added {} website(s) to '{}'", preview.added.len(), list);
+    if preview.duplicates + preview.invalid.len() + preview.protected.len() > 0 {
+        app.status_message.push_str(&format!(
+            "; skipped {} duplicate(s), {} invalid, {} protected",
+            preview.duplicates,
+            preview.invalid.len(),
+            preview.protected.len()
+        ));
+    }
+    app.paste.clear();
+    app.mode = TuiMode::Normal;
+}
+
+/// Handle key events for the timer tab
+fn handle_timer_tab_events(app: &mut App, key: KeyCode) -> Result<()> {
+    match key {
+        // Adjust time with vim-style navigation
+        KeyCode::Char('k') | KeyCode::Up => {
+            app.countdown.increase();
+        }
+        KeyCode::Char('j') | KeyCode::Down => {
+            app.countdown.decrease();
+        }
+
+        // Change time unit (vim-style using t or u)
+        KeyCode::Char('t') | KeyCode::Char('u') => {
+            app.countdown.cycle_unit();
+        }
+
+        // Start blocking (vim-style using space or enter)
+        KeyCode::Char(' ') | KeyCode::Enter if !app.is_blocking && app.selected_list_index.is_some() => {
+            begin_tui_session(app)?;
+        }
+
+        // Stop blocking (vim-style using Esc)
+        KeyCode::Esc if app.is_blocking => {
+            stop_tui_session(app, EndReason::Stopped)?;
+        }
+        KeyCode::Esc if app.cli_session.is_some() => {
+            app.status_message = match ipc::query(&ipc::Request::Stop) {
+                Ok(Some(response)) if response.ok => "Stopped the CLI session".to_string(),
+                Ok(Some(response)) => format!("The CLI session could not be stopped: {}", response.error.unwrap_or_default()),
+                Ok(None) => "The CLI session already ended".to_string(),
+                Err(error) => format!("The CLI session could not be stopped: {}", error),
+            };
+            app.cli_session = None;
+        }
+
+        // Repeat the most recent session
+        KeyCode::Char('r') if !app.is_blocking => {
+            repeat_tui_session(app, None)?;
+        }
+
+        // Cycle the end-of-session behavior
+        KeyCode::Char('e') => {
+            app.end_behavior = app.end_behavior.next_for_tui();
+            app.status_message = format!("When the session ends: {}", app.end_behavior.label());
+        }
+
+        // Cycle the session priority
+        KeyCode::Char('p') if !app.is_blocking => {
+            app.priority = app.priority.next();
+            app.status_message = format!("Session priority: {}", app.priority.label());
+        }
+
+        // Set up the next session in one form
+        KeyCode::Char('S') if !app.is_blocking => {
+            open_session_settings(app);
+        }
+
+        // Quick time adjustments (vim-style)
+        KeyCode::Char('+') => {
+            // Increase time by larger step
+            for _ in 0..5 { app.countdown.increase(); }
+        }
+        KeyCode::Char('-') => {
+            // Decrease time by larger step
+            for _ in 0..5 { app.countdown.decrease(); }
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Handle key events for a user-defined tab
+fn handle_custom_tab_events(app: &mut App, key: KeyCode) -> Result<()> {
+    let Some(tab) = app.current_custom_tab().cloned() else {
+        return Ok(());
+    };
+
+    // A tab showing a list works on that list
+    if !app.is_blocking
+        && let Some(name) = tab.list_name()
+        && let Some(index) = app.website_lists.iter().position(|list| list.name.eq_ignore_ascii_case(name))
+    {
+        app.website_list_state.select(Some(index));
+        app.selected_list_index = Some(index);
+    }
+
+    if tab.has_timer() {
+        handle_timer_tab_events(app, key)?;
+    }
+
+    Ok(())
+}
+
+/// Start a session with the selected list, asking for the intent first if enabled
+fn begin_tui_session(app: &mut App) -> Result<()> {
+    if app.intent_prompt && app.cli_session.is_none() {
+        app.input = Input::default();
+        app.mode = TuiMode::Intent;
+        Ok(())
+    } else {
+        let duration = app.countdown.duration();
+        start_tui_session(app, duration)
+    }
+}
+
+/// Perform a single TUI action, independent of the key that triggered it
+fn run_tui_action(
+    app: &mut App,
+    action: &Action,
+    profiles: &[profile::Profile],
+    safe_domains: &[String],
+    cli: &Cli,
+) -> Result<()> {
+    match action {
+        Action::Profile(name) => {
+            let profile = profile::resolve(profiles, Some(name), &profile::Environment::default())
+                .ok_or_else(|| color_eyre::eyre::eyre!("No profile named '{}'", name))?;
+            app.apply_profile(Some(profile));
+            app.profile_pinned = true;
+            app.safety = safety::SafetyList::new(safe_domains, Some(profile), cli.i_know_what_im_doing);
+        }
+        Action::SelectList(name) => {
+            if app.is_blocking {
+                return Err(color_eyre::eyre::eyre!("A session is already running"));
+            }
+            let index = app
+                .website_lists
+                .iter()
+                .position(|list| list.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| color_eyre::eyre::eyre!("No list named '{}'", name))?;
+            app.website_list_state.select(Some(index));
+            app.selected_list_index = Some(index);
+            app.website_state.select(None);
+            app.selected_website_index = None;
+        }
+        Action::Duration(duration) => app.countdown.set(*duration),
+        Action::Task(task) => app.session_task = Some(task.clone()),
+        Action::Tab(title) => {
+            if !app.tabs.select(title) {
+                return Err(color_eyre::eyre::eyre!("No tab named '{}'", title));
+            }
+        }
+        Action::Start => {
+            if app.is_blocking {
+                return Err(color_eyre::eyre::eyre!("A session is already running"));
+            }
+            if app.selected_list_index.is_none() {
+                return Err(color_eyre::eyre::eyre!("No list selected"));
+            }
+            begin_tui_session(app)?;
+        }
+    }
+    Ok(())
+}
+
+/// Run the macro bound to `key`, stopping at the first step that fails
+fn run_tui_macro(
+    app: &mut App,
+    key: KeyCode,
+    profiles: &[profile::Profile],
+    safe_domains: &[String],
+    cli: &Cli,
+) -> Result<()> {
+    let Some(macro_) = app.macros.iter().find(|m| m.matches(key)).cloned() else {
+        return Ok(());
+    };
+    let actions = match macro_.actions() {
+        Ok(actions) => actions,
+        Err(error) => {
+            app.status_message = format!("Macro '{}' is invalid: {}", macro_.name, error);
+            return Ok(());
+        }
+    };
+
+    app.status_message = format!("Ran macro '{}'", macro_.name);
+    for action in &actions {
+        if let Err(error) = run_tui_action(app, action, profiles, safe_domains, cli) {
+            app.status_message = format!("Macro '{}' stopped: {}", macro_.name, error);
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Start blocking the selected list from the TUI
+fn start_tui_session(app: &mut App, duration: Duration) -> Result<()> {
+    if app.cli_session.is_some() {
+        app.status_message = "A CLI session is already running; press Esc to stop it first".to_string();
+        return Ok(());
+    }
+    let config = load_config()?;
+    let mut websites = app.current_websites();
+    if websites.is_empty() {
+        app.status_message = "Selected list has no websites to block".to_string();
+        return Ok(());
+    }
+    // System lists block in every session
+    let selected = app.selected_list_index;
+    for (index, list) in app.website_lists.iter().enumerate() {
+        if list.system && list.enabled && Some(index) != selected {
+            websites.extend(list.websites.iter().cloned());
+        }
+    }
+    let websites = services::expand(&websites, config.services.as_deref().unwrap_or_default());
+    if let Err(error) = app.safety.check(&websites) {
+        app.status_message = error.to_string();
+        return Ok(());
+    }
+
+    // The first block of a list shows what it breaks and waits for approval
+    let lists: Vec<String> = app.current_website_list().map(|list| list.name.clone()).into_iter().collect();
+    if !app.blocking_disabled && config.soft_launch.unwrap_or(true) {
+        let pending = approval::pending(&app.website_lists, &lists)?;
+        if !pending.is_empty() {
+            let impact = list_impact(&pending, &config, &app.safety);
+            // The dialog already warns about the domains in use
+            app.in_use_warned = impact.in_use.iter().map(|in_use| in_use.domain.clone()).collect();
+            let dialog = Dialog::confirm(format!("First block of '{}'", lists.join(", ")), "Block this list from now on?")
+                .with_message(impact.lines());
+            app.open_dialog(DialogPurpose::ApproveList(duration), dialog);
+            return Ok(());
+        }
+    }
+
+    // Starting again after the warning blocks the domains in use anyway
+    if !app.blocking_disabled {
+        let in_use = domains_in_use(&websites, &config);
+        if in_use.iter().any(|in_use| !app.in_use_warned.contains(&in_use.domain)) {
+            let domains: Vec<String> = in_use.iter().map(|in_use| in_use.domain.clone()).collect();
+            app.status_message = format!(
+                "In use right now: {}. Start again to block anyway, or remove them from the list",
+                domains.join(", ")
+            );
+            app.in_use_warned = domains;
+            return Ok(());
+        }
+    }
+    app.in_use_warned.clear();
+
+    let session_id = ulid::new();
+    let (enforcement, started) = if app.blocking_disabled {
+        (Enforcement::Unenforced, Ok(()))
+    } else {
+        let task = app.session_task();
+        let started = start_blocking_websites(&websites, &lists, duration, app.priority, task.as_deref(), &session_id);
+        (Enforcement::Blocked, started)
+    };
+    match started {
+        Ok(_) => {
+            app.start_blocking(duration)?;
+            app.session_handle.set_session_id(&session_id);
+            app.session_handle.set_lists(&lists);
+            if app.blocking_disabled {
+                app.status_message = "Timer running; nothing is blocked (no permissions)".to_string();
+            } else {
+                match blockpage::serve(app.session_handle.clone()) {
+                    Ok(block_page) => app.block_page = block_page,
+                    Err(e) => app.status_message = e.to_string(),
+                }
+            }
+
+            let task = app.session_task().unwrap_or_default();
+            let recorded = history::record(
+                &session_id,
+                &task,
+                &lists,
+                app.priority,
+                duration,
+                app.intent.as_deref(),
+                enforcement,
+            );
+            match recorded {
+                Ok(history_id) => app.history_id = Some(history_id),
+                Err(e) => {
+                    // Without a session nothing would ever release the claim
+                    if !app.blocking_disabled {
+                        let _ = hosts::release(&session_source("tui"));
+                    }
+                    app.stop_blocking()?;
+                    app.status_message = format!("Could not record the session: {}", e);
+                }
+            }
+        }
+        Err(e) => {
+            app.status_message = format!("Error blocking websites: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Run a command entered on the ':' command line
+fn run_tui_command(app: &mut App, command: &str) {
+    let mut words = command.split_whitespace();
+    app.mode = TuiMode::Normal;
+    match (words.next(), words.next()) {
+        (Some("docs") | Some("help"), page) => {
+            if let Some(page) = page
+                && !app.docs.open(page)
+            {
+                app.status_message = format!("No guide page '{}'", page);
+                return;
+            }
+            app.mode = TuiMode::Docs;
+        }
+        (Some("backend"), _) => app.mode = TuiMode::Backend,
+        (Some("blocked"), _) => {
+            let provenance = load_config().and_then(|config| {
+                diagnose::provenance(&app.website_lists, config.services.as_deref().unwrap_or_default())
+            });
+            match provenance {
+                Ok(provenance) => {
+                    app.provenance = provenance.iter().map(diagnose::Provenance::line).collect();
+                    app.provenance_scroll = 0;
+                    app.mode = TuiMode::Blocked;
+                }
+                Err(error) => app.status_message = format!("Could not read the blocks: {}", error),
+            }
+        }
+        (Some("q") | Some("quit"), _) => app.running = false,
+        (None, _) => {}
+        (Some(other), _) => app.status_message = format!("Unknown command: {}", other),
+    }
+}
+
+/// Select the list of a past session (the most recent one by default) and
+/// start it again
+fn repeat_tui_session(app: &mut App, from: Option<u64>) -> Result<()> {
+    let history = history::History::load()?;
+    let record = match from {
+        Some(id) => history.find(id)?,
+        None => match history.last() {
+            Some(record) => record,
+            None => {
+                app.status_message = "No previous session to repeat".to_string();
+                return Ok(());
+            }
+        },
+    };
+
+    // The TUI blocks a single list; sessions over all lists can't be repeated here
+    let index = record.lists.first().and_then(|name| {
+        app.website_lists.iter().position(|list| list.name.eq_ignore_ascii_case(name))
+    });
+    let Some(index) = index else {
+        app.status_message = format!("Session #{} can't be repeated here; use 'timeguardian again'", record.id);
+        return Ok(());
+    };
+
+    app.website_list_state.select(Some(index));
+    app.selected_list_index = Some(index);
+    app.priority = record.priority;
+    app.session_task = Some(record.task.clone());
+    start_tui_session(app, record.duration())
+}
+
+/// Close the summary of the ended TUI session and add the session to the daily
+/// note, now that its intent is rated; returns the history id of the session
+fn close_summary(app: &mut App) -> Option<u64> {
+    app.mode = TuiMode::Normal;
+    let history_id = app.summary.take()?.history_id?;
+    match append_daily_note(history_id) {
+        Ok(Some(path)) => app.status_message = format!("Session added to {}", path.display()),
+        Ok(None) => {}
+        Err(error) => app.status_message = format!("Could not update the daily note: {}", error),
+    }
+    Some(history_id)
+}
+
+/// Record the end of the TUI session and show its summary
+fn end_tui_session(app: &mut App, ended_by: EndReason) -> Result<()> {
+    let history_id = app.history_id.take();
+    if let Some(id) = history_id {
+        history::finish(id, ended_by)?;
+    }
+
+    let now = chrono::Utc::now();
+    let schedules = load_config()?.schedules.unwrap_or_default();
+    let next_block = schedule::upcoming(&schedules, now, now + chrono::Duration::days(7))
+        .unwrap_or_default()
+        .into_iter()
+        .find(|occurrence| occurrence.start > now)
+        .map(|occurrence| (occurrence.schedule.name.clone(), occurrence.start.with_timezone(&Local)));
+    let remaining = app.get_remaining_time().unwrap_or_default();
+    let summary = tui::app::SessionSummary {
+        history_id,
+        task: app.session_task().unwrap_or_default(),
+        list: app.current_website_list().map(|list| list.name.clone()),
+        planned: app.session_duration,
+        focused: app.session_duration.saturating_sub(remaining),
+        completed: ended_by == EndReason::Expired,
+        intent: app.intent.clone(),
+        achieved: None,
+        next_block,
+    };
+
+    app.stop_blocking()?;
+    app.show_summary(summary);
+    Ok(())
+}
+
+/// Stop the running TUI session early
+fn stop_tui_session(app: &mut App, ended_by: EndReason) -> Result<()> {
+    match hosts::release(&session_source("tui")) {
+        Ok(retained) => {
+            end_tui_session(app, ended_by)?;
+            if !retained.is_empty() {
+                app.status_message = format!(
+                    "Website blocking stopped ({} domains still blocked by a higher-priority session)",
+                    retained.len()
+                );
+            }
+        }
+        Err(e) => {
+            app.status_message = format!("Error stopping website blocking: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// The occurrence the TUI is warning about, if it has not started yet
+fn warned_occurrence<'a>(
+    app: &mut App,
+    schedules: &'a [schedule::Schedule],
+    decisions: &snooze::Decisions,
+) -> Result<Option<schedule::Occurrence<'a>>> {
+    let Some(warning) = app.schedule_warning.take() else {
+        return Ok(None);
+    };
+    let occurrence = snooze::pending_warning(schedules, decisions, chrono::Utc::now())?
+        .map(|(occurrence, _)| occurrence)
+        .filter(|occurrence| occurrence.schedule.name == warning.schedule);
+    if occurrence.is_none() {
+        app.status_message = format!("Schedule '{}' already started", warning.schedule);
+    }
+    Ok(occurrence)
+}
+
+/// Block the warned-about schedule right away
+fn start_scheduled_block_now(app: &mut App, schedules: &[schedule::Schedule], cli: &Cli) -> Result<()> {
+    let mut decisions = snooze::Decisions::load()?;
+    let Some(occurrence) = warned_occurrence(app, schedules, &decisions)? else {
+        return Ok(());
+    };
+
+    let config = load_config()?;
+    match snooze::apply_occurrence(&config, &safety_list(cli, &config), &occurrence, chrono::Utc::now()) {
+        Ok(message) => {
+            decisions.start_now(&occurrence);
+            decisions.save()?;
+            app.status_message = message;
+        }
+        Err(error) => app.status_message = format!("Error blocking websites: {}", error),
+    }
+    Ok(())
+}
+
+/// Postpone the warned-about schedule by the snooze length
+fn snooze_scheduled_block(app: &mut App, schedules: &[schedule::Schedule]) -> Result<()> {
+    let mut decisions = snooze::Decisions::load()?;
+    let Some(occurrence) = warned_occurrence(app, schedules, &decisions)? else {
+        return Ok(());
+    };
+
+    let limit = load_config()?.schedule_snooze_limit.unwrap_or(snooze::DEFAULT_SNOOZE_LIMIT);
+    match decisions.snooze(&occurrence, limit) {
+        Ok(until) => {
+            decisions.save()?;
+            app.status_message = format!(
+                "Schedule '{}' snoozed until {}",
+                occurrence.schedule.name,
+                time_of_day(&until.with_timezone(&Local))
+            );
+        }
+        Err(error) => app.status_message = error.to_string(),
+    }
+    Ok(())
+}
+
+/// Skip the warned-about schedule, logging the reason
+fn skip_scheduled_block(app: &mut App, schedules: &[schedule::Schedule], reason: &str) -> Result<()> {
+    let mut decisions = snooze::Decisions::load()?;
+    let Some(occurrence) = warned_occurrence(app, schedules, &decisions)? else {
+        return Ok(());
+    };
+
+    decisions.skip(&occurrence, reason);
+    decisions.save()?;
+    app.status_message = format!("Skipped schedule '{}'", occurrence.schedule.name);
+    Ok(())
+}
+
+/// Block websites using the TUI interface
+fn start_blocking_websites(
+    websites: &[String],
+    lists: &[String],
+    duration: Duration,
+    priority: Priority,
+    task: Option<&str>,
+    session_id: &str,
+) -> Result<()> {
+    // Check if we're running as root/admin (the demo hosts file is ours)
+    #[cfg(target_family = "unix")]
+    {
+        if !is_root() && demo::dir().is_none() {
+            return Err(color_eyre::eyre::eyre!(
+                "Insufficient permissions to modify hosts file. Please run the application with sudo or as administrator."
+            ));
+        }
+    }
+
+    hosts::claim(&session_source("tui"), priority, task, Some(session_id), lists, websites, duration)
+}
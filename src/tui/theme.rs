@@ -0,0 +1,163 @@
+/*
+* TimeGuardian TUI Theme Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module contains the color themes of the TUI. Besides the default
+* colors there are palettes that stay distinguishable with color vision
+* deficiencies (based on the Okabe-Ito palette) and a monochrome theme that
+* shows selection and blocking state with symbols and text modifiers only.
+*
+*   theme = "deuteranopia"   # or --theme monochrome
+*/
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+/// Selectable themes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    /// The original colors
+    #[default]
+    Default,
+    /// Red-green safe (blue/orange)
+    Deuteranopia,
+    /// Red-green safe (blue/orange), without dark reds
+    Protanopia,
+    /// Blue-yellow safe (red/teal)
+    Tritanopia,
+    /// No colors; symbols, bold, underline and reverse video only
+    Monochrome,
+}
+
+/// Styles used by the TUI
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// First part of the title ("Time")
+    pub title: Style,
+    /// Second part of the title ("Guardian")
+    pub title_accent: Style,
+    /// Shortcut letter of a tab
+    pub tab_key: Style,
+    /// The selected tab
+    pub tab_selected: Style,
+    /// Entries of the website lists
+    pub text: Style,
+    /// The selected entry of a list
+    pub selection: Style,
+    /// Symbol in front of the selected entry
+    pub selection_symbol: &'static str,
+    /// Key hints
+    pub hint: Style,
+    /// Text of the running session
+    pub session: Style,
+    /// Progress gauge of the running session
+    pub gauge: Style,
+    /// The status bar
+    pub status_bar: Style,
+    /// Popups and prompts
+    pub popup: Style,
+    /// Headings of the user guide
+    pub heading: Style,
+    /// Code in the user guide
+    pub code: Style,
+    /// Shown in the status bar while blocking
+    pub blocking_marker: &'static str,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        ThemeName::Default.theme()
+    }
+}
+
+impl ThemeName {
+    /// The styles of this theme
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeName::Default => Theme {
+                title: Style::default().fg(Color::Green),
+                title_accent: Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD),
+                tab_key: Style::default().fg(Color::Yellow),
+                tab_selected: Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                text: Style::default().fg(Color::White),
+                selection: Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD),
+                selection_symbol: ">> ",
+                hint: Style::default().fg(Color::Yellow),
+                session: Style::default().fg(Color::Red),
+                gauge: Style::default().fg(Color::Red),
+                status_bar: Style::default().fg(Color::White).bg(Color::Blue),
+                popup: Style::default().bg(Color::Black),
+                heading: Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                code: Style::default().fg(Color::Yellow),
+                blocking_marker: "",
+            },
+            ThemeName::Deuteranopia | ThemeName::Protanopia => {
+                // Okabe-Ito blue, orange, sky blue and yellow
+                let blue = Color::Rgb(0, 114, 178);
+                let orange = Color::Rgb(230, 159, 0);
+                let sky_blue = Color::Rgb(86, 180, 233);
+                let yellow = Color::Rgb(240, 228, 66);
+                // Protanopes see reds darker, so the session uses orange instead of vermillion
+                let session = if self == ThemeName::Protanopia { orange } else { Color::Rgb(213, 94, 0) };
+                Theme {
+                    title: Style::default().fg(sky_blue),
+                    title_accent: Style::default().fg(orange).add_modifier(Modifier::BOLD),
+                    tab_key: Style::default().fg(yellow),
+                    tab_selected: Style::default().fg(sky_blue).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                    text: Style::default().fg(Color::White),
+                    selection: Style::default().fg(Color::White).bg(blue).add_modifier(Modifier::BOLD),
+                    selection_symbol: "▶ ",
+                    hint: Style::default().fg(yellow),
+                    session: Style::default().fg(session).add_modifier(Modifier::BOLD),
+                    gauge: Style::default().fg(session),
+                    status_bar: Style::default().fg(Color::White).bg(blue),
+                    popup: Style::default().bg(Color::Black),
+                    heading: Style::default().fg(sky_blue).add_modifier(Modifier::BOLD),
+                    code: Style::default().fg(orange),
+                    blocking_marker: "● ",
+                }
+            }
+            ThemeName::Tritanopia => {
+                // Red and teal stay apart when blue and yellow merge
+                let red = Color::Rgb(213, 94, 0);
+                let teal = Color::Rgb(0, 158, 115);
+                let pink = Color::Rgb(204, 121, 167);
+                Theme {
+                    title: Style::default().fg(teal),
+                    title_accent: Style::default().fg(red).add_modifier(Modifier::BOLD),
+                    tab_key: Style::default().fg(pink),
+                    tab_selected: Style::default().fg(teal).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                    text: Style::default().fg(Color::White),
+                    selection: Style::default().fg(Color::White).bg(teal).add_modifier(Modifier::BOLD),
+                    selection_symbol: "▶ ",
+                    hint: Style::default().fg(pink),
+                    session: Style::default().fg(red).add_modifier(Modifier::BOLD),
+                    gauge: Style::default().fg(red),
+                    status_bar: Style::default().fg(Color::White).bg(Color::Rgb(60, 60, 60)),
+                    popup: Style::default().bg(Color::Black),
+                    heading: Style::default().fg(teal).add_modifier(Modifier::BOLD),
+                    code: Style::default().fg(pink),
+                    blocking_marker: "● ",
+                }
+            }
+            ThemeName::Monochrome => Theme {
+                title: Style::default(),
+                title_accent: Style::default().add_modifier(Modifier::BOLD),
+                tab_key: Style::default().add_modifier(Modifier::UNDERLINED),
+                tab_selected: Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                text: Style::default(),
+                selection: Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                selection_symbol: "▶ ",
+                hint: Style::default(),
+                session: Style::default().add_modifier(Modifier::BOLD),
+                gauge: Style::default(),
+                status_bar: Style::default().add_modifier(Modifier::REVERSED),
+                popup: Style::default(),
+                heading: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                code: Style::default().add_modifier(Modifier::ITALIC),
+                blocking_marker: "[BLOCKING] ",
+            },
+        }
+    }
+}
@@ -6,33 +6,33 @@
 * It defines layout, widgets, and drawing functions for the interface.
 */
 
+use chrono::Local;
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style, Modifier},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::{Style, Modifier},
     text::{Line, Span, Text},
     widgets::{
-        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap,
+        Block, BorderType, Borders, Clear, List, ListItem, Paragraph, Tabs, Wrap,
     },
     Frame,
 };
+use std::time::Duration;
 
-use crate::tui::{App, TuiMode};
-
-/// Time unit enum for the timer tab
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TimeUnit {
-    /// Minutes (default)
-    Minutes,
-    /// Hours
-    Hours,
-    /// Seconds
-    Seconds,
-}
+use timeguardian::{format, SessionState, SessionWidget};
+
+use crate::idn;
+use crate::ipc::SessionStatus;
+use crate::tui::{
+    custom::{PaneDirection, PaneWidget},
+    dialog::Field,
+    docs::DocLine,
+    App, TuiMode,
+};
 
 /// Tab state for managing tab navigation
 pub struct TabsState {
     /// List of tab titles
-    pub titles: Vec<&'static str>,
+    pub titles: Vec<String>,
     /// Index of the currently selected tab
     pub index: usize,
 }
@@ -40,7 +40,15 @@ pub struct TabsState {
 impl TabsState {
     /// Create a new tabs state with the given titles
     pub fn new(titles: Vec<&'static str>) -> Self {
-        Self { titles, index: 0 }
+        Self {
+            titles: titles.into_iter().map(str::to_string).collect(),
+            index: 0,
+        }
+    }
+    
+    /// Append a tab
+    pub fn push(&mut self, title: String) {
+        self.titles.push(title);
     }
     
     /// Select the next tab
@@ -56,16 +64,29 @@ impl TabsState {
             self.index = self.titles.len() - 1;
         }
     }
+    
+    /// Select the tab titled `title`; returns `false` if there is none
+    pub fn select(&mut self, title: &str) -> bool {
+        match self.titles.iter().position(|t| t.eq_ignore_ascii_case(title)) {
+            Some(index) => {
+                self.index = index;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 /// Main render function for the UI
 pub fn render(app: &mut App, frame: &mut Frame) {
     // Create the layout
+    let banner_height = if app.schedule_warning.is_some() { 1 } else { 0 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),  // Title bar and tabs (reduced from 5 to 3)
             Constraint::Min(0),     // Main area
+            Constraint::Length(banner_height),  // Schedule warning
             Constraint::Length(3),  // Status bar
         ])
         .split(frame.size());
@@ -77,24 +98,38 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     match app.tabs.index {
         0 => render_website_lists_tab(app, frame, chunks[1]),
         1 => render_timer_tab(app, frame, chunks[1]),
-        _ => {}
+        _ => render_custom_tab(app, frame, chunks[1]),
     }
     
     // Render the status bar
-    render_status_bar(app, frame, chunks[2]);
+    render_schedule_warning(app, frame, chunks[2]);
+    render_status_bar(app, frame, chunks[3]);
     
     // Render help popup if in help mode
     if app.mode == TuiMode::Help {
         render_help_popup(app, frame);
     }
+    
+    match app.mode {
+        TuiMode::Command => render_command_line(app, frame),
+        TuiMode::Intent => render_intent_prompt(app, frame),
+        TuiMode::SkipReason => render_skip_reason_prompt(app, frame),
+        TuiMode::Backend => render_backend(app, frame),
+        TuiMode::Blocked => render_blocked(app, frame),
+        TuiMode::Paste => render_paste(app, frame),
+        TuiMode::Dialog => render_dialog(app, frame),
+        TuiMode::Summary => render_summary(app, frame),
+        TuiMode::Docs => render_docs(app, frame, chunks[1]),
+        _ => {}
+    }
 }
 
 /// Render the title bar and tabs
 fn render_title_and_tabs(app: &App, frame: &mut Frame, area: Rect) {
     // Create title spans
     let title_spans = vec![
-        Span::styled("Time", Style::default().fg(Color::Green)),
-        Span::styled("Guardian", Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)),
+        Span::styled("Time", app.theme.title),
+        Span::styled("Guardian", app.theme.title_accent),
         Span::raw(" - Block distractions, stay focused"),
     ];
     
@@ -117,9 +152,9 @@ fn render_title_and_tabs(app: &App, frame: &mut Frame, area: Rect) {
         .titles
         .iter()
         .map(|t| {
-            let (first, rest) = t.split_at(1);
+            let (first, rest) = t.split_at(t.chars().next().map_or(0, char::len_utf8));
             Line::from(vec![
-                Span::styled(first, Style::default().fg(Color::Yellow)),
+                Span::styled(first, app.theme.tab_key),
                 Span::styled(rest, Style::default()),
             ])
         })
@@ -130,7 +165,7 @@ fn render_title_and_tabs(app: &App, frame: &mut Frame, area: Rect) {
             .borders(Borders::NONE)) // Entferne die Ränder für ein flacheres Design
         .select(app.tabs.index)
         .style(Style::default())
-        .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+        .highlight_style(app.theme.tab_selected);
     
     frame.render_widget(title, chunks[0]);
     frame.render_widget(tabs, chunks[1]);
@@ -157,18 +192,21 @@ fn render_website_lists_tab(app: &mut App, frame: &mut Frame, area: Rect) {
         .website_lists
         .iter()
         .map(|list| {
-            let lines = vec![Line::from(vec![Span::styled(
-                &list.name,
-                Style::default().fg(Color::White),
-            )])];
-            ListItem::new(lines)
+            let mut spans = vec![Span::styled(&list.name, app.theme.text)];
+            if list.system {
+                spans.push(Span::styled(" (system)", Style::default().add_modifier(Modifier::DIM)));
+            }
+            if !list.enabled {
+                spans.push(Span::styled(" (off)", Style::default().add_modifier(Modifier::DIM)));
+            }
+            ListItem::new(vec![Line::from(spans)])
         })
         .collect();
     
     let lists = List::new(list_items)
         .block(lists_block)
-        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
-        .highlight_symbol(">> ");
+        .highlight_style(app.theme.selection)
+        .highlight_symbol(app.theme.selection_symbol);
     
     frame.render_stateful_widget(lists, chunks[0], &mut app.website_list_state);
     
@@ -188,34 +226,23 @@ fn render_website_lists_tab(app: &mut App, frame: &mut Frame, area: Rect) {
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded);
     
-    // Get websites from selected list
-    let website_items: Vec<ListItem> = if let Some(index) = app.selected_list_index {
-        if index < app.website_lists.len() {
-            app.website_lists[index].websites
-                .iter()
-                .map(|website| {
-                    let lines = vec![Line::from(Span::raw(website))];
-                    ListItem::new(lines)
-                })
-                .collect()
-        } else {
-            Vec::new()
-        }
-    } else {
-        Vec::new()
+    // Get websites from selected list; only the visible rows are built
+    let all_websites: &[String] = match app.selected_list_index {
+        Some(index) if index < app.website_lists.len() => &app.website_lists[index].websites,
+        _ => &[],
     };
+    let (rows, mut window_state) = visible_rows(all_websites.len(), chunks[1], &mut app.website_state);
+    let website_items: Vec<ListItem> = all_websites[rows]
+        .iter()
+        .map(|website| ListItem::new(Line::from(Span::raw(idn::display(website)))))
+        .collect();
     
     let websites = List::new(website_items)
         .block(websites_block)
-        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
-        .highlight_symbol(">> ");
-    
-    frame.render_stateful_widget(websites, chunks[1], &mut app.website_state);
+        .highlight_style(app.theme.selection)
+        .highlight_symbol(app.theme.selection_symbol);
     
-    // Render input box if in editing mode
-    if app.mode == TuiMode::Editing && app.tabs.index == 0 {
-        render_input_box(app, frame);
-    }
+    frame.render_stateful_widget(websites, chunks[1], &mut window_state);
 }
 
 /// Render the timer tab
@@ -224,46 +251,34 @@ fn render_timer_tab(app: &mut App, frame: &mut Frame, area: Rect) {
         .direction(Direction::Vertical)
         .margin(2)
         .constraints([
-            Constraint::Length(3),  // Timer controls
+            Constraint::Length(if app.is_blocking || app.cli_session.is_some() { 5 } else { 3 }),  // Timer controls or session
             Constraint::Length(3),  // Selected list
-            Constraint::Min(0),     // Timer status
+            Constraint::Min(0),     // Active sessions
+            Constraint::Length(3),  // Timer status
         ])
         .split(area);
     
     // Timer display and controls
-    let unit_display = match app.time_unit {
-        TimeUnit::Minutes => "minutes",
-        TimeUnit::Hours => "hours",
-        TimeUnit::Seconds => "seconds",
-    };
-    
-    let timer_text = if app.is_blocking {
-        if let Some(remaining) = app.get_remaining_time() {
-            format!(
-                "Blocking websites... Time remaining: {}",
-                app.format_duration(remaining)
-            )
-        } else {
-            "Blocking websites...".to_string()
-        }
+    if app.is_blocking {
+        render_session(app, frame, chunks[0]);
+    } else if let Some(status) = &app.cli_session {
+        render_cli_session(app, status, frame, chunks[0]);
     } else {
-        format!("Block for {} {}", app.time_value, unit_display)
-    };
-    
-    let timer_block = Block::default()
-        .title("Timer Settings")
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded);
-    
-    let timer_paragraph = Paragraph::new(timer_text)
-        .block(timer_block)
-        .style(if app.is_blocking {
-            Style::default().fg(Color::Red)
-        } else {
-            Style::default()
-        });
-    
-    frame.render_widget(timer_paragraph, chunks[0]);
+        let timer_text = format!(
+            "Block for {} (priority: {}, on end: {})",
+            app.countdown.label(),
+            app.priority.label(),
+            app.end_behavior.label()
+        );
+        
+        let timer_block = Block::default()
+            .title("Timer Settings")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        
+        let timer_paragraph = Paragraph::new(timer_text).block(timer_block);
+        frame.render_widget(timer_paragraph, chunks[0]);
+    }
     
     // Selected list info
     let selected_list_info = if let Some(index) = app.selected_list_index {
@@ -289,11 +304,15 @@ fn render_timer_tab(app: &mut App, frame: &mut Frame, area: Rect) {
     let list_paragraph = Paragraph::new(selected_list_info).block(list_block);
     frame.render_widget(list_paragraph, chunks[1]);
     
+    render_sessions_pane(app, frame, chunks[2]);
+    
     // Help text
     let help_text = if app.is_blocking {
         "Press [Esc] to stop blocking"
+    } else if app.cli_session.is_some() {
+        "Press [Esc] to stop the CLI session"
     } else {
-        "Press [j/k] to adjust time | [t/u] to change unit | [Space/Enter] to start blocking"
+        "Press [j/k] to adjust time | [t/u] to change unit | [p] priority | [r] repeat last | [Space/Enter] to start blocking"
     };
     
     let instructions = Paragraph::new(help_text)
@@ -303,9 +322,209 @@ fn render_timer_tab(app: &mut App, frame: &mut Frame, area: Rect) {
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded),
         )
-        .style(Style::default().fg(Color::Yellow));
+        .style(app.theme.hint);
     
-    frame.render_widget(instructions, chunks[2]);
+    frame.render_widget(instructions, chunks[3]);
+}
+
+/// Render the countdown of the running session
+/// Render the session of a CLI process that was running when the TUI started
+fn render_cli_session(app: &App, status: &SessionStatus, frame: &mut Frame, area: Rect) {
+    let total = match (status.started_at, status.ends_at) {
+        (Some(started_at), Some(ends_at)) => (ends_at - started_at).to_std().unwrap_or_default(),
+        _ => Duration::ZERO,
+    };
+    let remaining = Duration::from_secs(status.remaining_secs.unwrap_or(0));
+    let mut session = SessionState::new(status.task.clone(), total, remaining);
+    
+    let session_widget = SessionWidget::new()
+        .block(
+            Block::default()
+                .title("CLI Session")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .style(app.theme.session)
+        .gauge_style(app.theme.gauge);
+    
+    frame.render_stateful_widget(session_widget, area, &mut session);
+}
+
+fn render_session(app: &App, frame: &mut Frame, area: Rect) {
+    let mut session = SessionState::new(
+        app.session_task(),
+        app.session_duration,
+        app.get_remaining_time().unwrap_or_default(),
+    );
+    
+    let session_widget = SessionWidget::new()
+        .block(
+            Block::default()
+                .title("Blocking Session")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .style(app.theme.session)
+        .gauge_style(app.theme.gauge);
+    
+    frame.render_stateful_widget(session_widget, area, &mut session);
+}
+
+/// Render all active sessions with their own remaining time
+fn render_sessions_pane(app: &App, frame: &mut Frame, area: Rect) {
+    let now = chrono::Utc::now();
+    let items: Vec<ListItem> = if app.sessions.is_empty() {
+        vec![ListItem::new("No active sessions")]
+    } else {
+        app.sessions
+            .iter()
+            .map(|session| {
+                let left = (session.expires_at - now).to_std().unwrap_or_default();
+                let remaining = if session.unreadable_expiry {
+                    format!("about {} left (expiry unreadable)", format::human(left))
+                } else {
+                    format!("{} left", format::human(left))
+                };
+                let owner = if session.source == app.claim_source { " (this TUI)" } else { "" };
+                ListItem::new(format!(
+                    "{}{} [{}]: {} - {}, {} domains",
+                    session.task.as_deref().unwrap_or("no task"),
+                    owner,
+                    session.priority.name(),
+                    session.source,
+                    remaining,
+                    session.domains.len()
+                ))
+            })
+            .collect()
+    };
+    
+    let list = List::new(items).block(
+        Block::default()
+            .title(format!("Active Sessions ({})", app.sessions.len()))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+    frame.render_widget(list, area);
+}
+
+/// Render a user-defined tab from its panes
+fn render_custom_tab(app: &mut App, frame: &mut Frame, area: Rect) {
+    let Some(tab) = app.current_custom_tab().cloned() else {
+        return;
+    };
+    
+    let direction = match tab.direction {
+        PaneDirection::Vertical => Direction::Vertical,
+        PaneDirection::Horizontal => Direction::Horizontal,
+    };
+    let constraints: Vec<Constraint> = tab.pane_sizes().into_iter().map(Constraint::Percentage).collect();
+    let chunks = Layout::default()
+        .direction(direction)
+        .constraints(constraints)
+        .split(area);
+    
+    for (pane, chunk) in tab.panes.iter().zip(chunks.iter()) {
+        match pane.widget {
+            PaneWidget::Lists => render_lists_pane(app, frame, *chunk),
+            PaneWidget::List => render_list_pane(app, frame, *chunk, pane.list.as_deref()),
+            PaneWidget::Timer => render_timer_tab(app, frame, *chunk),
+            PaneWidget::Sessions => render_sessions_pane(app, frame, *chunk),
+            PaneWidget::Session if app.is_blocking => render_session(app, frame, *chunk),
+            PaneWidget::Session => {
+                let idle = Paragraph::new("Not blocking. Press [Space/Enter] to start.")
+                    .block(
+                        Block::default()
+                            .title("Session")
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    );
+                frame.render_widget(idle, *chunk);
+            }
+        }
+    }
+}
+
+/// Render an overview of all website lists
+fn render_lists_pane(app: &mut App, frame: &mut Frame, area: Rect) {
+    let items: Vec<ListItem> = app
+        .website_lists
+        .iter()
+        .map(|list| {
+            let state = if list.enabled { "" } else { ", off" };
+            ListItem::new(format!("{} ({} websites{})", list.name, list.websites.len(), state))
+        })
+        .collect();
+    
+    let lists = List::new(items)
+        .block(
+            Block::default()
+                .title("Website Lists")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .highlight_style(app.theme.selection)
+        .highlight_symbol(app.theme.selection_symbol);
+    
+    frame.render_stateful_widget(lists, area, &mut app.website_list_state);
+}
+
+/// Render the websites of a single list
+fn render_list_pane(app: &App, frame: &mut Frame, area: Rect, name: Option<&str>) {
+    let list = name.and_then(|name| {
+        app.website_lists
+            .iter()
+            .find(|list| list.name.eq_ignore_ascii_case(name))
+    });
+    
+    let (title, items): (String, Vec<ListItem>) = match (list, name) {
+        (Some(list), _) => {
+            let (rows, _) = visible_rows(list.websites.len(), area, &mut ratatui::widgets::ListState::default());
+            (
+                list.name.clone(),
+                list.websites[rows].iter().map(|website| ListItem::new(idn::display(website))).collect(),
+            )
+        }
+        (None, Some(name)) => (format!("List '{}' not found", name), Vec::new()),
+        (None, None) => ("No list configured for this pane".to_string(), Vec::new()),
+    };
+    
+    let websites = List::new(items).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+    
+    frame.render_widget(websites, area);
+}
+
+/// Rows of a bordered list with `len` items that fit into `area`.
+///
+/// Imported lists can have tens of thousands of entries, and building a
+/// `ListItem` for each of them every frame makes the TUI crawl. The rows
+/// follow the selection the way ratatui scrolls, `state` keeps the offset
+/// between frames, and the returned state selects within the rows.
+fn visible_rows(
+    len: usize,
+    area: Rect,
+    state: &mut ratatui::widgets::ListState,
+) -> (std::ops::Range<usize>, ratatui::widgets::ListState) {
+    let height = usize::from(area.height.saturating_sub(2)).max(1);
+    let mut offset = state.offset();
+    if let Some(selected) = state.selected() {
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + height {
+            offset = selected + 1 - height;
+        }
+    }
+    offset = offset.min(len.saturating_sub(height));
+    *state.offset_mut() = offset;
+    
+    let rows = offset..len.min(offset + height);
+    let selected = state.selected().filter(|selected| rows.contains(selected));
+    (rows.clone(), ratatui::widgets::ListState::default().with_selected(selected.map(|selected| selected - offset)))
 }
 
 /// Render the status bar
@@ -313,13 +532,34 @@ fn render_status_bar(app: &App, frame: &mut Frame, area: Rect) {
     // Create the status message with mode indicator
     let mode_indicator = match app.mode {
         TuiMode::Normal => "[Normal]",
-        TuiMode::Editing => "[Editing]",
+        TuiMode::Dialog => "[Dialog]",
         TuiMode::Help => "[Help]",
+        TuiMode::Command => "[Command]",
+        TuiMode::Docs => "[Guide]",
+        TuiMode::Intent => "[Intent]",
+        TuiMode::Summary => "[Summary]",
+        TuiMode::SkipReason => "[Skip]",
+        TuiMode::Backend => "[Backend]",
+        TuiMode::Blocked => "[Blocked]",
+        TuiMode::Paste => "[Paste]",
     };
     
-    let status = format!("{} {}", mode_indicator, app.status_message);
+    let status = if app.accessible {
+        accessible_status(app)
+    } else {
+        // The marker keeps the blocking state visible without relying on color
+        let marker = if app.is_blocking { app.theme.blocking_marker } else { "" };
+        let backend = app.blocker.name();
+        match &app.active_profile {
+            Some(profile) => format!(
+                "{}{} [{}] [Profile: {}] {}",
+                marker, mode_indicator, backend, profile, app.status_message
+            ),
+            None => format!("{}{} [{}] {}", marker, mode_indicator, backend, app.status_message),
+        }
+    };
     let status_bar = Paragraph::new(status)
-        .style(Style::default().fg(Color::White).bg(Color::Blue))
+        .style(app.theme.status_bar)
         .block(Block::default()
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded));
@@ -327,33 +567,435 @@ fn render_status_bar(app: &App, frame: &mut Frame, area: Rect) {
     frame.render_widget(status_bar, area);
 }
 
-/// Render the input box for editing
-fn render_input_box(app: &App, frame: &mut Frame) {
-    // Create a centered popup for the input
+/// Linear status sentence for screen readers: tab, mode, session, message
+fn accessible_status(app: &App) -> String {
+    let mode = match app.mode {
+        TuiMode::Normal => "Normal mode".to_string(),
+        TuiMode::Dialog => app.dialog.as_ref().map(|(_, dialog)| dialog.describe()).unwrap_or_default(),
+        TuiMode::Help => "Help".to_string(),
+        TuiMode::Command => "Command line".to_string(),
+        TuiMode::Docs => format!("Guide, page {}", app.docs.current().title),
+        TuiMode::Intent => "Intent prompt: what will success look like? Enter to start, Escape to cancel".to_string(),
+        TuiMode::SkipReason => "Why skip the scheduled block? Enter to skip, Escape to cancel".to_string(),
+        TuiMode::Paste => {
+            let preview = app.paste_preview();
+            format!(
+                "Paste domains. {} new, {} duplicates, {} not domains, {} protected. Control S to add, Escape to cancel",
+                preview.added.len(),
+                preview.duplicates,
+                preview.invalid.len(),
+                preview.protected.len()
+            )
+        }
+        TuiMode::Backend => {
+            let capabilities = app.blocker.capabilities();
+            let (supported, unsupported): (Vec<_>, Vec<_>) =
+                capabilities.rows().into_iter().partition(|(_, supported)| *supported);
+            let list = |rows: Vec<(&str, bool)>| {
+                if rows.is_empty() {
+                    "none".to_string()
+                } else {
+                    rows.iter().map(|(label, _)| *label).collect::<Vec<_>>().join(", ")
+                }
+            };
+            format!(
+                "Backend {}: {}. Supported: {}. Not supported: {}. Escape to close",
+                app.blocker.name(),
+                app.blocker.description(),
+                list(supported),
+                list(unsupported)
+            )
+        }
+        TuiMode::Blocked => match app.provenance.get(app.provenance_scroll) {
+            Some(line) => format!(
+                "Blocked domain {} of {}: {}. Up and down to move, Escape to close",
+                app.provenance_scroll + 1,
+                app.provenance.len(),
+                line
+            ),
+            None => "No websites are blocked. Escape to close".to_string(),
+        },
+        TuiMode::Summary => match &app.summary {
+            Some(summary) => {
+                let mut text = format!(
+                    "Session {}: {}, focused {}",
+                    if summary.completed { "finished" } else { "stopped early" },
+                    summary.task,
+                    format::spoken(summary.focused)
+                );
+                if let (Some(intent), None) = (&summary.intent, summary.achieved) {
+                    text.push_str(&format!(". Your intent was: {}. Achieved? Press y, p for partly or n", intent));
+                }
+                text.push_str(". Press r to repeat, b for a break, Enter to return");
+                text
+            }
+            None => "Summary".to_string(),
+        },
+    };
+    let mut parts = vec![format!("{} tab", app.tabs.titles[app.tabs.index]), mode];
+    if let Some(warning) = &app.schedule_warning {
+        parts.push(format!(
+            "Schedule {} starts at {}. Press capital N to start now, Z to snooze, X to skip",
+            warning.schedule,
+            crate::time_of_day(&warning.starts_at)
+        ));
+    }
+    
+    if let Some(remaining) = app.get_remaining_time() {
+        parts.push(format!(
+            "{}, {} remaining",
+            app.session_task().unwrap_or_else(|| "Blocking".to_string()),
+            format::spoken(remaining)
+        ));
+    } else {
+        parts.push("Not blocking".to_string());
+    }
+    parts.push(format!("Backend {}", app.blocker.name()));
+    if let Some(profile) = &app.active_profile {
+        parts.push(format!("Profile {}", profile));
+    }
+    if !app.status_message.is_empty() {
+        parts.push(app.status_message.clone());
+    }
+    
+    parts.join(". ")
+}
+
+/// Render the open dialog with its fields below each other
+fn render_dialog(app: &App, frame: &mut Frame) {
+    let Some((_, dialog)) = &app.dialog else {
+        return;
+    };
+    
+    let mut lines: Vec<Line> = dialog.message.iter().map(|line| Line::from(line.as_str())).collect();
+    if !lines.is_empty() {
+        lines.push(Line::from(""));
+    }
+    let mut cursor = None;
+    for (index, field) in dialog.fields.iter().enumerate() {
+        let focused = index == dialog.focus;
+        let label_style = if focused { app.theme.heading } else { app.theme.text };
+        if index > 0 {
+            lines.push(Line::from(""));
+        }
+        match field {
+            Field::Confirm { label, value } => {
+                let (yes, no) = if *value { ("[Yes]", " No ") } else { (" Yes ", "[No]") };
+                lines.push(Line::from(Span::styled(label.clone(), label_style)));
+                lines.push(Line::from(format!("  {}  {}", yes, no)));
+            }
+            Field::Input { label, input, .. } => {
+                lines.push(Line::from(Span::styled(label.clone(), label_style)));
+                if focused {
+                    cursor = Some((input.visual_cursor() as u16 + 2, lines.len() as u16));
+                }
+                lines.push(Line::from(format!("> {}", input.value())));
+            }
+            Field::Select { label, options, selected } => {
+                lines.push(Line::from(Span::styled(label.clone(), label_style)));
+                for (option_index, option) in options.iter().enumerate() {
+                    let marker = if option_index == *selected { "(*)" } else { "( )" };
+                    let style = if focused && option_index == *selected { app.theme.selection } else { Style::default() };
+                    lines.push(Line::from(Span::styled(format!("  {} {}", marker, option), style)));
+                }
+            }
+            Field::MultiSelect { label, options, checked, cursor: at } => {
+                lines.push(Line::from(Span::styled(label.clone(), label_style)));
+                for (option_index, option) in options.iter().enumerate() {
+                    let marker = if checked.get(option_index) == Some(&true) { "[x]" } else { "[ ]" };
+                    let style = if focused && option_index == *at { app.theme.selection } else { Style::default() };
+                    lines.push(Line::from(Span::styled(format!("  {} {}", marker, option), style)));
+                }
+            }
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        dialog.error.clone().unwrap_or_else(|| dialog_hint(&dialog.fields)),
+        app.theme.hint,
+    )));
+    
+    let height = (lines.len() as u16 + 2).min(frame.size().height);
+    let area = centered_rect(60, height, frame.size());
+    let block = Block::default()
+        .title(dialog.title.as_str())
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(app.theme.popup);
+    
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+    if let Some((x, y)) = cursor {
+        frame.set_cursor(area.x + 1 + x, area.y + 1 + y);
+    }
+}
+
+/// Keys of a dialog, shown below its fields
+fn dialog_hint(fields: &[Field]) -> String {
+    match fields {
+        [Field::Confirm { .. }] => "[y] Yes, [n] No, [Enter] Confirm, [Esc] Cancel".to_string(),
+        [_] => "[Enter] Confirm, [Esc] Cancel".to_string(),
+        _ => "[Tab] Next field, [Enter] Confirm, [Esc] Cancel".to_string(),
+    }
+}
+
+/// Render the ':' command line
+fn render_command_line(app: &App, frame: &mut Frame) {
     let area = centered_rect(60, 3, frame.size());
+    let block = Block::default()
+        .title("Command (docs [page], backend, blocked, q)")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(app.theme.popup);
     
-    // Render the input popup
-    let input_block = Block::default()
-        .title("Input")
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(format!(":{}", app.input.value())).block(block), area);
+    frame.set_cursor(area.x + app.input.visual_cursor() as u16 + 2, area.y + 1);
+}
+
+/// Render the banner for a scheduled block that is about to start
+fn render_schedule_warning(app: &App, frame: &mut Frame, area: Rect) {
+    let Some(warning) = &app.schedule_warning else {
+        return;
+    };
+    let seconds = (warning.starts_at - Local::now()).num_seconds().max(0);
+    let text = format!(
+        " Schedule '{}' starts in {}s  [N] Start now  [Z] Snooze 10m  [X] Skip",
+        warning.schedule, seconds
+    );
+    frame.render_widget(Paragraph::new(text).style(app.theme.hint), area);
+}
+
+/// Render the prompt for the reason to skip a scheduled block
+fn render_skip_reason_prompt(app: &App, frame: &mut Frame) {
+    let area = centered_rect(60, 3, frame.size());
+    let block = Block::default()
+        .title("Why skip this block? (Enter to skip, Esc to cancel)")
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .style(Style::default().bg(Color::Black));
+        .style(app.theme.popup);
     
-    let input_widget = Paragraph::new(app.input.value())
-        .style(Style::default())
-        .block(input_block);
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(app.input.value()).block(block), area);
+    frame.set_cursor(area.x + app.input.visual_cursor() as u16 + 1, area.y + 1);
+}
+
+/// Render what the blocking backend can and cannot do
+fn render_backend(app: &App, frame: &mut Frame) {
+    let capabilities = app.blocker.capabilities();
+    let mut lines = vec![
+        Line::from(app.blocker.description()),
+        Line::from(if capabilities.needs_root {
+            "Needs root or administrator rights"
+        } else {
+            "Works without root"
+        }),
+        Line::from(""),
+    ];
+    for (label, supported) in capabilities.rows() {
+        if supported {
+            lines.push(Line::from(Span::styled(format!("  [x] {}", label), app.theme.text)));
+        } else {
+            lines.push(Line::from(Span::styled(format!("  [ ] {} (not available)", label), app.theme.hint)));
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from("[Esc] Close"));
+    
+    let area = centered_rect(70, lines.len() as u16 + 2, frame.size());
+    let block = Block::default()
+        .title(format!("Backend: {}", app.blocker.name()))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(app.theme.popup);
     
-    // Render a background to create a popup effect
     frame.render_widget(Clear, area);
-    frame.render_widget(input_widget, area);
+    frame.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), area);
+}
+
+/// Render the sessions and lists blocking each domain
+fn render_blocked(app: &App, frame: &mut Frame) {
+    let area = centered_rect(80, 20.min(frame.size().height), frame.size());
+    let block = Block::default()
+        .title(format!("Blocked domains: {} ([j/k] Scroll, [Esc] Close)", app.provenance.len()))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(app.theme.popup);
+    
+    let lines: Vec<Line> = if app.provenance.is_empty() {
+        vec![Line::from(Span::styled("No websites are blocked.", app.theme.hint))]
+    } else {
+        app.provenance
+            .iter()
+            .skip(app.provenance_scroll)
+            .map(|line| Line::from(Span::styled(line.as_str(), app.theme.text)))
+            .collect()
+    };
+    
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), area);
+}
+
+/// Render the paste area for the bulk import next to a preview of the result
+fn render_paste(app: &App, frame: &mut Frame) {
+    let area = centered_rect(80, 20.min(frame.size().height), frame.size());
+    let list = app.current_website_list().map(|list| list.name.as_str()).unwrap_or_default();
+    let block = Block::default()
+        .title(format!("Paste domains into '{}' ([Ctrl+S] Add, [Esc] Cancel)", list))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(app.theme.popup);
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+    
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(inner);
+    
+    // Keep the end of the text, where typing continues, in view
+    let lines: Vec<&str> = app.paste.split('\n').collect();
+    let hidden = lines.len().saturating_sub(columns[0].height as usize);
+    let text: Vec<Line> = lines[hidden..].iter().map(|line| Line::from(*line)).collect();
+    frame.render_widget(Paragraph::new(text).block(Block::default().borders(Borders::RIGHT)), columns[0]);
+    if let Some(last) = lines.last() {
+        frame.set_cursor(
+            columns[0].x + (last.chars().count() as u16).min(columns[0].width.saturating_sub(2)),
+            columns[0].y + (lines.len() - hidden - 1) as u16,
+        );
+    }
+    
+    let preview = app.paste_preview();
+    let mut summary = vec![Line::from(Span::styled(
+        format!("{} new, {} duplicates", preview.added.len(), preview.duplicates),
+        app.theme.heading,
+    ))];
+    if !preview.invalid.is_empty() {
+        summary.push(Line::from(Span::styled(format!("Not domains: {}", preview.invalid.join(" ")), app.theme.hint)));
+    }
+    if !preview.protected.is_empty() {
+        summary.push(Line::from(Span::styled(format!("Protected: {}", preview.protected.join(" ")), app.theme.hint)));
+    }
+    summary.push(Line::from(""));
+    summary.extend(preview.added.iter().map(|domain| Line::from(format!("+ {}", domain))));
+    let margin = Margin { horizontal: 1, vertical: 0 };
+    frame.render_widget(Paragraph::new(summary).wrap(Wrap { trim: false }), columns[1].inner(&margin));
+}
+
+/// Render the prompt for the intent of a new session
+fn render_intent_prompt(app: &App, frame: &mut Frame) {
+    let area = centered_rect(60, 3, frame.size());
+    let block = Block::default()
+        .title("What will success look like? (Enter to start, Esc to cancel)")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(app.theme.popup);
+    
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(app.input.value()).block(block), area);
+    frame.set_cursor(area.x + app.input.visual_cursor() as u16 + 1, area.y + 1);
+}
+
+/// Render the summary of the ended session over the whole screen
+fn render_summary(app: &App, frame: &mut Frame) {
+    let Some(summary) = &app.summary else {
+        return;
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let field = |name: &'static str, value: String| Line::from(vec![Span::styled(name, bold), Span::raw(value)]);
+    
+    let mut lines = vec![
+        field("Task: ", summary.task.clone()),
+        field("List: ", summary.list.clone().unwrap_or_else(|| "none".to_string())),
+        field(
+            "Focused: ",
+            format!("{} of {}", format::human(summary.focused), format::human(summary.planned)),
+        ),
+    ];
+    if let Some(intent) = &summary.intent {
+        lines.push(field("Intent: ", intent.clone()));
+        lines.push(match summary.achieved {
+            Some(achieved) => field("Rating: ", achieved.label().to_string()),
+            None => Line::from("Achieved? [y]es  [p]artial  [n]o"),
+        });
+    }
+    lines.push(field(
+        "Next scheduled block: ",
+        match &summary.next_block {
+            Some((name, start)) => format!("{} at {} {}", name, start.format("%a"), crate::time_of_day(start)),
+            None => "none in the next 7 days".to_string(),
+        },
+    ));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "[r] Repeat session  [b] 5-minute break  [Enter/Esc] Return",
+        app.theme.hint,
+    )));
+    
+    let block = Block::default()
+        .title(if summary.completed { "Session finished" } else { "Session stopped early" })
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
     
-    // Set cursor position
-    frame.set_cursor(
-        area.x + app.input.visual_cursor() as u16 + 1,
-        area.y + 1,
+    let area = frame.size();
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(Text::from(lines)).block(block).wrap(Wrap { trim: true }), area);
+}
+
+/// Render the user guide over the main area
+fn render_docs(app: &App, frame: &mut Frame, area: Rect) {
+    let page = app.docs.current();
+    let lines: Vec<Line> = page
+        .lines
+        .iter()
+        .map(|line| match line {
+            DocLine::Heading(text) => Line::from(Span::styled(
+                *text,
+                app.theme.heading,
+            )),
+            DocLine::Bullet(text) => {
+                let mut spans = vec![Span::raw("• ")];
+                spans.extend(inline_code(text, app.theme.code));
+                Line::from(spans)
+            }
+            DocLine::Code(text) => Line::from(Span::styled(*text, app.theme.code)),
+            DocLine::Text(text) => Line::from(inline_code(text, app.theme.code)),
+            DocLine::Blank => Line::from(""),
+        })
+        .collect();
+    
+    let titles: Vec<String> = crate::tui::docs::GUIDE
+        .iter()
+        .enumerate()
+        .map(|(index, doc)| if index == app.docs.page { format!("[{}]", doc.title) } else { doc.title.to_string() })
+        .collect();
+    let block = Block::default()
+        .title(format!("Guide: {} | [h/l] page, [j/k] scroll, [Esc] close", titles.join("  ")))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(Text::from(lines)).block(block).scroll((app.docs.scroll, 0)),
+        area,
     );
 }
 
+/// Split text at backticks and highlight the `code` parts
+fn inline_code(text: &'static str, code: Style) -> Vec<Span<'static>> {
+    text.split('`')
+        .enumerate()
+        .map(|(index, part)| {
+            if index % 2 == 1 {
+                Span::styled(part, code)
+            } else {
+                Span::raw(part)
+            }
+        })
+        .collect()
+}
+
 /// Render the help popup
 fn render_help_popup(app: &App, frame: &mut Frame) {
     let area = centered_rect(70, 20, frame.size());
@@ -364,8 +1006,7 @@ fn render_help_popup(app: &App, frame: &mut Frame) {
     // Create the help text based on the current tab
     let help_text = match app.tabs.index {
         0 => get_website_lists_tab_help(),
-        1 => get_timer_tab_help(),
-        _ => Vec::new(),
+        _ => get_timer_tab_help(),
     };
     
     let help_block = Block::default()
@@ -396,11 +1037,15 @@ fn get_website_lists_tab_help() -> Vec<Line<'static>> {
         Line::from("Actions:"),
         Line::from("  [o/n]: Create a new website list"),
         Line::from("  [a]: Add a website to the selected list"),
+        Line::from("  [P] or paste: Add many websites at once"),
         Line::from("  [d/x]: Delete selected website"),
         Line::from("  [D]: Delete selected list"),
+        Line::from("  [Space]: Enable/disable selected list"),
         Line::from(""),
         Line::from("Other:"),
         Line::from("  [?]: Toggle help"),
+        Line::from("  [F1] or [:docs]: User guide"),
+        Line::from("  [N/Z/X]: Start, snooze or skip an upcoming scheduled block"),
         Line::from("  [q]: Quit application"),
     ]
 }
@@ -416,7 +1061,11 @@ fn get_timer_tab_help() -> Vec<Line<'static>> {
         Line::from("  [k/j] or [↑/↓]: Increase/decrease time"),
         Line::from("  [+/-]: Quick increase/decrease by larger steps"),
         Line::from("  [t/u]: Change time unit (minutes, hours, seconds)"),
+        Line::from("  [p]: Cycle session priority (low, normal, high, critical)"),
+        Line::from("  [e]: Cycle end behavior (unblock, notify, lock screen)"),
+        Line::from("  [S]: Session settings (priority, end behavior, enabled lists)"),
         Line::from("  [Space/Enter]: Start blocking websites"),
+        Line::from("  [r]: Repeat the most recent session"),
         Line::from("  [Esc]: Stop active blocking session"),
         Line::from(""),
         Line::from("Navigation:"),
@@ -424,6 +1073,8 @@ fn get_timer_tab_help() -> Vec<Line<'static>> {
         Line::from(""),
         Line::from("Other:"),
         Line::from("  [?]: Toggle help"),
+        Line::from("  [F1] or [:docs]: User guide"),
+        Line::from("  [N/Z/X]: Start, snooze or skip an upcoming scheduled block"),
         Line::from("  [q]: Quit application"),
         Line::from(""),
         Line::from("Note: You must select a website list in the Website Lists tab first"),
@@ -0,0 +1,44 @@
+/*
+* TimeGuardian ULID Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module generates the session IDs: ULIDs, 26 characters of Crockford
+* base32 holding the start time in milliseconds followed by 80 random bits.
+* They sort by time and stay unique across machines, so one ID ties a
+* session together in the history, the hosts file, the block state, the
+* journal and the IPC status (`timeguardian stats show <id>`).
+*/
+
+use chrono::Utc;
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Crockford's base32 alphabet, without I, L, O and U
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Length of a ULID in characters
+pub const LENGTH: usize = 26;
+
+/// A new ULID for a session starting now
+pub fn new() -> String {
+    let millis = Utc::now().timestamp_millis().max(0) as u128;
+    let mut random = [0u8; 10];
+    // Without randomness the nanoseconds still keep IDs of one machine apart
+    if SystemRandom::new().fill(&mut random).is_err() {
+        let nanos = Utc::now().timestamp_subsec_nanos().to_be_bytes();
+        random[6..].copy_from_slice(&nanos);
+    }
+
+    let value = random
+        .iter()
+        .fold(millis & ((1 << 48) - 1), |value, byte| (value << 8) | *byte as u128);
+    (0..LENGTH)
+        .map(|index| ALPHABET[((value >> ((LENGTH - 1 - index) * 5)) & 31) as usize] as char)
+        .collect()
+}
+
+/// Whether `text` could be the start of a ULID (case-insensitive)
+pub fn is_prefix(text: &str) -> bool {
+    !text.is_empty()
+        && text.len() <= LENGTH
+        && text.bytes().all(|byte| ALPHABET.contains(&byte.to_ascii_uppercase()))
+}
@@ -0,0 +1,126 @@
+/*
+* TimeGuardian Session Widget
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* A ratatui widget showing the state of a focus session: the task, the
+* remaining time and a progress gauge. It is used by the Timer tab and can be
+* embedded by other terminal dashboards through the library crate.
+*/
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Gauge, Paragraph, StatefulWidget, Widget},
+};
+use std::time::Duration;
+
+use crate::format;
+
+/// State of the session shown by [`SessionWidget`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionState {
+    /// Task or reason of the session, if any
+    pub task: Option<String>,
+    /// Total length of the session
+    pub total: Duration,
+    /// Time left until the session ends
+    pub remaining: Duration,
+}
+
+impl SessionState {
+    /// Create a new session state
+    pub fn new(task: Option<String>, total: Duration, remaining: Duration) -> Self {
+        Self { task, total, remaining }
+    }
+
+    /// Fraction of the session that has elapsed (0.0 - 1.0)
+    pub fn progress(&self) -> f64 {
+        if self.total.is_zero() {
+            return 1.0;
+        }
+        let elapsed = self.total.saturating_sub(self.remaining);
+        (elapsed.as_secs_f64() / self.total.as_secs_f64()).clamp(0.0, 1.0)
+    }
+}
+
+/// Countdown, gauge and task display for a running session
+#[derive(Debug, Clone, Default)]
+pub struct SessionWidget<'a> {
+    block: Option<Block<'a>>,
+    style: Style,
+    gauge_style: Style,
+}
+
+impl<'a> SessionWidget<'a> {
+    /// Create a new session widget
+    pub fn new() -> Self {
+        Self {
+            block: None,
+            style: Style::default(),
+            gauge_style: Style::default().fg(Color::Red),
+        }
+    }
+
+    /// Surround the widget with a block
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Style of the text lines
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Style of the progress gauge
+    pub fn gauge_style(mut self, style: Style) -> Self {
+        self.gauge_style = style;
+        self
+    }
+}
+
+impl StatefulWidget for SessionWidget<'_> {
+    type State = SessionState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let inner = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1), // Task
+                Constraint::Length(1), // Remaining time
+                Constraint::Length(1), // Gauge
+            ])
+            .split(inner);
+
+        let task = state.task.as_deref().unwrap_or("Focus session");
+        Paragraph::new(Line::from(vec![
+            Span::raw("Task: "),
+            Span::styled(task.to_string(), Style::default().add_modifier(Modifier::BOLD)),
+        ]))
+        .style(self.style)
+        .render(chunks[0], buf);
+
+        Paragraph::new(format!("Time remaining: {}", format::clock(state.remaining)))
+            .style(self.style)
+            .render(chunks[1], buf);
+
+        let progress = state.progress();
+        Gauge::default()
+            .gauge_style(self.gauge_style)
+            .ratio(progress)
+            .label(format!("{:.0}%", progress * 100.0))
+            .render(chunks[2], buf);
+    }
+}
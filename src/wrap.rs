@@ -0,0 +1,232 @@
+/*
+* TimeGuardian Wrap Module
+* Author: Jannis Krija (https://github.com/cipher-shad0w)
+*
+* This module ties a session to the lifetime of a command:
+*
+*   timeguardian wrap -- nvim thesis.tex
+*
+* The block starts right before the command and is released as soon as it
+* exits, e.g. when the editor is closed or the tmux pane running it goes
+* away. Claims still need an expiry, so the block lasts at most `--max`; if
+* TimeGuardian itself is killed, the block is healed after that like any
+* expired one. Ctrl-C belongs to the command and doesn't end the session.
+* Without websites to block, the command still runs in a timer-only session.
+* When TimeGuardian was started with sudo, the command runs as the user who
+* called sudo again.
+*/
+
+use chrono::Local;
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use std::{
+    process::{Command, ExitStatus},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::history::{self, EndReason, Enforcement};
+use crate::hosts::{self, Priority};
+use crate::{
+    check_and_get_permissions, collect_websites, explain_no_websites, ipc, load_config, parse_duration, safety_list,
+    session_lists, session_source, ulid, Cli, SessionOptions,
+};
+
+/// How often the command and the IPC socket are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Set when the terminal went away or TimeGuardian was asked to terminate
+static HUNG_UP: AtomicBool = AtomicBool::new(false);
+
+/// The session wrapped around a command
+pub struct Wrap<'a> {
+    /// Task of the session; the command line by default
+    pub task: &'a str,
+    pub priority: Priority,
+    /// Website lists the websites come from
+    pub lists: &'a [String],
+    /// Websites to block
+    pub websites: &'a [String],
+    /// Longest time the block lasts
+    pub max: Duration,
+    pub enforcement: Enforcement,
+}
+
+/// Run `command` inside the session and return its exit status, or `None`
+/// if TimeGuardian was hung up before the command exited
+pub fn run(wrap: &Wrap, command: &[String]) -> Result<Option<ExitStatus>> {
+    let (program, args) = command.split_first().ok_or_else(|| eyre!("Pass the command to run after --"))?;
+    let blocked = wrap.enforcement == Enforcement::Blocked;
+
+    let source = session_source("wrap");
+    let session_id = ulid::new();
+    if blocked {
+        hosts::claim(&source, wrap.priority, Some(wrap.task), Some(&session_id), wrap.lists, wrap.websites, wrap.max)?;
+    }
+    let recorded = history::record(&session_id, wrap.task, wrap.lists, wrap.priority, wrap.max, None, wrap.enforcement);
+    let history_id = match recorded {
+        Ok(history_id) => history_id,
+        Err(error) => {
+            // Without a session nothing would ever release the claim
+            if blocked {
+                let _ = hosts::release(&source);
+            }
+            return Err(error);
+        }
+    };
+
+    let start_time = Local::now();
+    let end_time = start_time + chrono::Duration::from_std(wrap.max)?;
+    let session = ipc::SessionHandle::new(false);
+    session.set_session(Some(wrap.task.to_string()), wrap.priority, start_time, end_time);
+    session.set_session_id(&session_id);
+    session.set_lists(wrap.lists);
+    let _ipc_server = ipc::serve(session.clone());
+    let _host_socket = ipc::register(session.clone());
+
+    let started = Instant::now();
+    let end = |ended_by: EndReason| -> Result<()> {
+        if blocked {
+            hosts::release(&source)?;
+        }
+        history::finish_after(history_id, ended_by, started.elapsed())?;
+        session.clear_session();
+        Ok(())
+    };
+
+    catch_signals();
+    let mut child = match as_invoking_user(Command::new(program).args(args)).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            end(EndReason::Stopped)?;
+            return Err(e).wrap_err_with(|| format!("Could not run {}", program));
+        }
+    };
+
+    let mut ended = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if HUNG_UP.load(Ordering::Relaxed) {
+            break None;
+        }
+        if !ended {
+            let ended_by = if session.take_stop_request() {
+                Some(EndReason::StoppedRemotely)
+            } else if Local::now() >= end_time {
+                Some(EndReason::Expired)
+            } else {
+                None
+            };
+            if let Some(ended_by) = ended_by {
+                end(ended_by)?;
+                ended = true;
+                eprintln!("TimeGuardian: the session ended; {} keeps running.", program);
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    if !ended {
+        // The command exiting is the planned end of the session
+        end(if status.is_some() { EndReason::Expired } else { EndReason::Stopped })?;
+        if blocked {
+            eprintln!("TimeGuardian: blocking removed.");
+        }
+    }
+    Ok(status)
+}
+
+/// Keep Ctrl-C and Ctrl-\ for the command; a hang-up or SIGTERM ends the
+/// session. Handlers, unlike ignored signals, are reset for the command.
+#[cfg(unix)]
+fn catch_signals() {
+    extern "C" fn keep(_: libc::c_int) {}
+    extern "C" fn hang_up(_: libc::c_int) {
+        HUNG_UP.store(true, Ordering::Relaxed);
+    }
+    let keep = keep as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    let hang_up = hang_up as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, keep);
+        libc::signal(libc::SIGQUIT, keep);
+        libc::signal(libc::SIGHUP, hang_up);
+        libc::signal(libc::SIGTERM, hang_up);
+    }
+}
+
+#[cfg(not(unix))]
+fn catch_signals() {}
+
+/// Run the command as the user who called sudo, not as root
+#[cfg(unix)]
+fn as_invoking_user(command: &mut Command) -> &mut Command {
+    use std::os::unix::process::CommandExt;
+
+    let id = |name| std::env::var(name).ok().and_then(|id| id.parse::<u32>().ok());
+    if unsafe { libc::geteuid() } != 0 {
+        return command;
+    }
+    let (Some(uid), Some(gid)) = (id("SUDO_UID"), id("SUDO_GID")) else {
+        return command;
+    };
+    command.uid(uid).gid(gid);
+    if let Ok(user) = std::env::var("SUDO_USER") {
+        command.env("USER", &user).env("LOGNAME", &user);
+    }
+    let entry = unsafe { libc::getpwuid(uid) };
+    if !entry.is_null() {
+        let home = unsafe { std::ffi::CStr::from_ptr((*entry).pw_dir) };
+        command.env("HOME", home.to_string_lossy().as_ref());
+    }
+    command
+}
+
+#[cfg(not(unix))]
+fn as_invoking_user(command: &mut Command) -> &mut Command {
+    command
+}
+
+/// Block the websites of the session while `command` runs and exit like it
+pub fn run_command(cli: &Cli, session: &SessionOptions, max: &str, command: &[String]) -> Result<()> {
+    let config = load_config()?;
+    let lists = if session.timer_only { Vec::new() } else { session_lists(cli, &session.lists, &config) };
+    let websites = if session.timer_only { Vec::new() } else { collect_websites(&config, &lists)? };
+
+    safety_list(cli, &config).check(&websites)?;
+
+    // The command runs either way; without websites the session only times it
+    let enforcement = if session.timer_only {
+        Enforcement::TimerOnly
+    } else if websites.is_empty() {
+        explain_no_websites(&config);
+        println!("Running the command as a timer-only session.");
+        Enforcement::TimerOnly
+    } else if check_and_get_permissions()? {
+        Enforcement::Blocked
+    } else {
+        println!("Blocking disabled (no permissions): the session is only recorded.");
+        Enforcement::Unenforced
+    };
+    let task = session.task.clone().unwrap_or_else(|| command.join(" "));
+    let wrap = Wrap {
+        task: &task,
+        priority: session.priority.or(cli.session.priority).unwrap_or_default(),
+        lists: &lists,
+        websites: &websites,
+        max: Duration::from_millis(parse_duration(max)?),
+        enforcement,
+    };
+    let status = run(&wrap, command)?;
+
+    // A hang-up or a signal killing the command counts as a failure
+    let code = status.map_or(1, |status| status.code().unwrap_or(1));
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}